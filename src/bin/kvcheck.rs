@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::app_from_crate;
+use clap::crate_authors;
+use clap::crate_description;
+use clap::crate_name;
+use clap::crate_version;
+use kvdb::common::options::JournalMode;
+use kvdb::common::options::PagerOption;
+use kvdb::error::Result;
+use kvdb::storage::encryption::EncryptionKey;
+use kvdb::storage::sqlite::page::Pager;
+use kvdb::storage::sqlite::page::BUSY_TIMEOUT;
+use kvdb::storage::sqlite::page::PAGE_SIZE;
+
+/// checks the pages of a database directory, while no server uses it
+fn main() -> Result<()> {
+    let opts = app_from_crate!()
+        .arg(
+            clap::Arg::with_name("dir")
+                .help("Database directory to check")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            clap::Arg::with_name("key-file")
+                .long("key-file")
+                .help("File holding the key of an encrypted database")
+                .takes_value(true)
+                .conflicts_with("passphrase"),
+        )
+        .arg(
+            clap::Arg::with_name("passphrase")
+                .long("passphrase")
+                .help("Passphrase of an encrypted database")
+                .takes_value(true),
+        )
+        .get_matches();
+    let dir: &'static str = Box::leak(opts.value_of("dir").unwrap().to_owned().into_boxed_str());
+    if !Path::new(dir).join("kvdb.db").exists() {
+        eprintln!("{} has no database file", dir);
+        std::process::exit(2);
+    }
+
+    let encryption = match (opts.value_of("key-file"), opts.value_of("passphrase")) {
+        (Some(path), _) => Some(EncryptionKey::KeyFile(PathBuf::from(path))),
+        (_, Some(passphrase)) => Some(EncryptionKey::Passphrase(passphrase.to_owned())),
+        _ => None,
+    };
+
+    // the log is checked too, it is not checkpointed by the check
+    let journal_mode = if Path::new(dir).join("kvdb.wal").exists() {
+        JournalMode::Wal
+    } else {
+        JournalMode::Rollback
+    };
+    let mut pager = Pager::open(PagerOption {
+        path: Some(dir),
+        max_page: 10,
+        n_extra: 0,
+        read_only: true,
+        journal_mode,
+        page_size: PAGE_SIZE,
+        checksums: false,
+        busy_timeout: BUSY_TIMEOUT,
+        mmap_size: 0,
+        encryption,
+    })?;
+    if !pager.checksums() {
+        println!("warning: the pages of {} have no checksums", dir);
+    }
+
+    let problems = pager.integrity_check()?;
+    if problems.is_empty() {
+        println!("ok");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    std::process::exit(1);
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::app_from_crate;
 use clap::crate_authors;
 use clap::crate_description;
@@ -6,6 +8,7 @@ use clap::crate_version;
 use kvdb::error::*;
 use kvdb::server::tcp_server::Server;
 use kvdb::storage;
+use kvdb::storage::encryption::EncryptionKey;
 use serde_derive::Deserialize;
 
 #[tokio::main]
@@ -31,8 +34,14 @@ async fn main() -> Result<()> {
     // TODO. it should using a new sql_store
     let _path = std::path::Path::new(&cfg.data_dir);
     let _sync = &cfg.sync;
+    let encryption = cfg.encryption()?;
 
     let sql_store: Box<dyn storage::Store> = match cfg.storage_sql.as_str() {
+        "memory" | "" if encryption.is_some() => {
+            return Err(Error::Config(
+                "sql storage engine memory writes no data files to encrypt".into(),
+            ))
+        }
         "memory" | "" => Box::new(storage::b_tree::Memory::new()),
         // TODO. a new sql_store on disk
         name => {
@@ -42,12 +51,41 @@ async fn main() -> Result<()> {
             )))
         }
     };
-    Server::new(&cfg.id, sql_store)
+    let mut server = Server::new(&cfg.id, sql_store)
         .await?
         .listen(&cfg.listen_sql)
-        .await?
-        .server()
-        .await
+        .await?;
+    if !cfg.listen_pg.is_empty() {
+        server = server.listen_pg(&cfg.listen_pg).await?;
+    }
+    if !cfg.listen_mysql.is_empty() {
+        server = server.listen_mysql(&cfg.listen_mysql).await?;
+    }
+    if !cfg.listen_http.is_empty() {
+        server = server.listen_http(&cfg.listen_http).await?;
+    }
+    match (cfg.tls_cert_file.as_str(), cfg.tls_key_file.as_str()) {
+        ("", "") if cfg.tls_client_ca_file.is_empty() => {}
+        ("", _) | (_, "") => {
+            return Err(Error::Config(
+                "tls_cert_file and tls_key_file are required with TLS".into(),
+            ))
+        }
+        (cert_file, key_file) => {
+            let client_ca_file = Some(cfg.tls_client_ca_file.as_str()).filter(|f| !f.is_empty());
+            server = server.tls(cert_file, key_file, client_ca_file)?
+        }
+    }
+    match (cfg.superuser.as_str(), cfg.superuser_password.as_str()) {
+        ("", _) => {}
+        (_, "") => {
+            return Err(Error::Config(
+                "superuser_password is required with a superuser".into(),
+            ))
+        }
+        (user, password) => server = server.authenticate(user, password).await?,
+    }
+    server.server().await
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,8 +94,29 @@ struct Config {
     data_dir: String,
     sync: bool,
     listen_sql: String,
+    /// the address of the PostgreSQL wire protocol, empty to disable it
+    listen_pg: String,
+    /// the address of the MySQL wire protocol, empty to disable it
+    listen_mysql: String,
+    /// the address of the HTTP/JSON API, empty to disable it
+    listen_http: String,
     log_level: String,
     storage_sql: String,
+    encryption_passphrase: String,
+    encryption_key_file: String,
+    /// the superuser the clients authenticate with, empty to accept any
+    /// client without authentication. PostgreSQL clients authenticate with
+    /// SCRAM-SHA-256 and HTTP clients with basic authentication, the MySQL
+    /// listener can not authenticate and must be disabled.
+    superuser: String,
+    superuser_password: String,
+    /// the PEM certificate chain and key of the listeners, empty to serve
+    /// them without TLS. the clients of every protocol must use TLS
+    tls_cert_file: String,
+    tls_key_file: String,
+    /// the PEM bundle of the certificate authorities issuing the client
+    /// certificates, empty to accept clients without a certificate
+    tls_client_ca_file: String,
 }
 
 impl Config {
@@ -67,11 +126,36 @@ impl Config {
         c.set_default("data_dir", "/var/lib/kvdb")?;
         c.set_default("sync", true)?;
         c.set_default("listen_sql", "0.0.0.0:9605")?;
+        c.set_default("listen_pg", "")?;
+        c.set_default("listen_mysql", "")?;
+        c.set_default("listen_http", "")?;
         c.set_default("log_level", "info")?;
         c.set_default("storage_sql", "memory")?;
+        c.set_default("encryption_passphrase", "")?;
+        c.set_default("encryption_key_file", "")?;
+        c.set_default("superuser", "")?;
+        c.set_default("superuser_password", "")?;
+        c.set_default("tls_cert_file", "")?;
+        c.set_default("tls_key_file", "")?;
+        c.set_default("tls_client_ca_file", "")?;
 
         c.merge(config::File::with_name(file))?;
         c.merge(config::Environment::with_prefix("KVDB"))?;
         Ok(c.try_into()?)
     }
+
+    /// the key of the data files, None if they are not encrypted
+    fn encryption(&self) -> Result<Option<EncryptionKey>> {
+        match (
+            self.encryption_passphrase.as_str(),
+            self.encryption_key_file.as_str(),
+        ) {
+            ("", "") => Ok(None),
+            (passphrase, "") => Ok(Some(EncryptionKey::Passphrase(passphrase.to_owned()))),
+            ("", path) => Ok(Some(EncryptionKey::KeyFile(PathBuf::from(path)))),
+            _ => Err(Error::Config(
+                "set either encryption_passphrase or encryption_key_file".into(),
+            )),
+        }
+    }
 }
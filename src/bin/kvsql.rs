@@ -4,7 +4,9 @@ use clap::crate_description;
 use clap::crate_name;
 use clap::crate_version;
 use kvdb::client::Client;
+use kvdb::client::Credentials;
 use kvdb::common::result::ResultSet;
+use kvdb::common::tls::TlsOptions;
 use kvdb::error::Result;
 use kvdb::storage::mvcc::TransactionMode;
 use rustyline::validate::Validator;
@@ -36,11 +38,68 @@ async fn main() -> Result<()> {
                 .required(true)
                 .default_value("9601"),
         )
+        .arg(
+            clap::Arg::with_name("user")
+                .short("u")
+                .long("user")
+                .help("User to authenticate as")
+                .takes_value(true)
+                .requires("password"),
+        )
+        .arg(
+            clap::Arg::with_name("password")
+                .long("password")
+                .help("Password of the user")
+                .takes_value(true)
+                .env("KVSQL_PASSWORD"),
+        )
+        .arg(
+            clap::Arg::with_name("tls-ca")
+                .long("tls-ca")
+                .help("Connect with TLS, verifying the server with this PEM CA bundle")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("tls-server-name")
+                .long("tls-server-name")
+                .help("Name of the server certificate, the host by default")
+                .takes_value(true)
+                .requires("tls-ca"),
+        )
+        .arg(
+            clap::Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .help("PEM client certificate chain")
+                .takes_value(true)
+                .requires_all(&["tls-ca", "tls-key"]),
+        )
+        .arg(
+            clap::Arg::with_name("tls-key")
+                .long("tls-key")
+                .help("PEM client private key")
+                .takes_value(true)
+                .requires("tls-cert"),
+        )
         .get_matches();
 
+    let credentials = opts.value_of("user").map(|user| Credentials {
+        user: user.into(),
+        password: opts.value_of("password").unwrap_or_default().into(),
+    });
+    let host = opts.value_of("host").unwrap();
+    let tls = opts.value_of("tls-ca").map(|ca_file| TlsOptions {
+        ca_file: ca_file.into(),
+        server_name: opts.value_of("tls-server-name").unwrap_or(host).into(),
+        identity: opts
+            .value_of("tls-cert")
+            .zip(opts.value_of("tls-key"))
+            .map(|(cert, key)| (cert.into(), key.into())),
+    });
     let mut kvsql = KVSQL::new(
-        opts.value_of("host").unwrap(),
+        host,
         opts.value_of("port").unwrap().parse()?,
+        tls,
+        credentials,
     )
     .await?;
 
@@ -56,9 +115,14 @@ struct KVSQL {
 }
 
 impl KVSQL {
-    async fn new(host: &str, port: u16) -> Result<Self> {
+    async fn new(
+        host: &str,
+        port: u16,
+        tls: Option<TlsOptions>,
+        credentials: Option<Credentials>,
+    ) -> Result<Self> {
         Ok(Self {
-            client: Client::new((host, port)).await?,
+            client: Client::connect((host, port), tls.as_ref(), credentials.as_ref()).await?,
             editor: Editor::new(),
             history_path: std::env::var_os("HOME")
                 .map(|home| std::path::Path::new(&home).join(".kvsql.history")),
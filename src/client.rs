@@ -1,96 +1,482 @@
-use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
 
 use futures::SinkExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::net::TcpStream;
 use tokio::net::ToSocketAddrs;
 use tokio::sync::Mutex;
-use tokio::sync::MutexGuard;
+use tokio_stream::Stream;
 use tokio_stream::StreamExt;
 use tokio_util::codec::Framed;
 use tokio_util::codec::LengthDelimitedCodec;
 
+use crate::common::result::DataColumns;
+use crate::common::result::DataRow;
 use crate::common::result::ResultSet;
+use crate::common::scram;
+use crate::common::tls::TlsOptions;
 use crate::error::Error;
 use crate::error::Result;
 use crate::server::servlet::Request;
 use crate::server::servlet::Response;
+use crate::server::servlet::Status;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
 use crate::storage::mvcc::TransactionMode;
 
-type Connection = tokio_serde::Framed<
-    Framed<TcpStream, LengthDelimitedCodec>,
+/// the stream of a connection, plain or TLS
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Socket for S {}
+
+type Transport = tokio_serde::Framed<
+    Framed<Box<dyn Socket>, LengthDelimitedCodec>,
     Result<Response>,
     Request,
     tokio_serde::formats::Bincode<Result<Response>, Request>,
 >;
 
-/// client
+/// the number of rows fetched at a time by a cursor
+const FETCH_SIZE: usize = 100;
+
+/// the user and the password a client authenticates with
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+/// client, its clones share its connection
 #[derive(Clone)]
 pub struct Client {
-    conn: Arc<Mutex<Connection>>,
-    txn: Cell<Option<(u64, TransactionMode)>>,
+    conn: Arc<Connection>,
 }
 
 impl Client {
     pub async fn new<A: ToSocketAddrs>(addr: A) -> Result<Self> {
-        Ok(Self {
-            conn: Arc::new(Mutex::new(tokio_serde::Framed::new(
-                Framed::new(TcpStream::connect(addr).await?, LengthDelimitedCodec::new()),
-                tokio_serde::formats::Bincode::default(),
-            ))),
-            txn: Cell::new(None),
-        })
+        Self::connect(addr, None, None).await
+    }
+
+    /// connect as a user, the password is not sent to the server. the
+    /// server proves it knows the password too
+    pub async fn with_credentials<A: ToSocketAddrs>(
+        addr: A,
+        credentials: &Credentials,
+    ) -> Result<Self> {
+        Self::connect(addr, None, Some(credentials)).await
+    }
+
+    /// connect over TLS if tls is given, as a user if credentials are given
+    pub async fn connect<A: ToSocketAddrs>(
+        addr: A,
+        tls: Option<&TlsOptions>,
+        credentials: Option<&Credentials>,
+    ) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        let addr = stream.peer_addr()?;
+        let socket: Box<dyn Socket> = match tls {
+            Some(tls) => {
+                let (connector, server_name) = tls.connector()?;
+                Box::new(connector.connect(server_name, stream).await?)
+            }
+            None => Box::new(stream),
+        };
+        let client = Self {
+            conn: Arc::new(Connection {
+                transport: Mutex::new((
+                    tokio_serde::Framed::new(
+                        Framed::new(socket, LengthDelimitedCodec::new()),
+                        tokio_serde::formats::Bincode::default(),
+                    ),
+                    0,
+                )),
+                abandoned: std::sync::Mutex::new(Vec::new()),
+                txn: std::sync::Mutex::new(None),
+                broken: AtomicBool::new(false),
+                addr,
+                tls: tls.cloned(),
+            }),
+        };
+        match credentials {
+            Some(credentials) => client.authenticate(credentials).await,
+            None => Ok(client),
+        }
+    }
+
+    /// run the SCRAM exchange of a user
+    async fn authenticate(self, credentials: &Credentials) -> Result<Self> {
+        let nonce = scram::nonce();
+        let request = Request::Authenticate {
+            user: credentials.user.clone(),
+            nonce: nonce.clone(),
+        };
+        let (server_nonce, salt, iterations) = match self.conn.call(request).await? {
+            Response::Challenge {
+                nonce,
+                salt,
+                iterations,
+            } => (nonce, salt, iterations),
+            resp => return Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        };
+        let auth_message =
+            scram::auth_message(&credentials.user, &nonce, &server_nonce, &salt, iterations);
+        let proof = scram::client_proof(&credentials.password, &salt, iterations, &auth_message);
+        match self.conn.call(Request::Proof(proof)).await? {
+            Response::Authenticated { signature }
+                if scram::verify_server_signature(
+                    &credentials.password,
+                    &salt,
+                    iterations,
+                    &auth_message,
+                    &signature,
+                ) =>
+            {
+                Ok(self)
+            }
+            Response::Authenticated { .. } => Err(Error::Unauthorized(
+                "The server did not prove it knows the password".into(),
+            )),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
     }
 
     /// return the transaction status of the client
     pub fn txn(&self) -> Option<(u64, TransactionMode)> {
-        self.txn.get()
+        self.conn.txn.lock().map_or(None, |txn| *txn)
     }
 
+    /// whether the connection has failed, its calls can not succeed
+    pub fn is_broken(&self) -> bool {
+        self.conn.broken.load(Ordering::Relaxed)
+    }
+
+    /// execute a statement, all the rows of a query are fetched before it
+    /// returns
     pub async fn execute(&self, query: &str) -> Result<ResultSet> {
-        let mut conn = self.conn.lock().await;
-        let mut result_set = match self
-            .call_locked(&mut conn, Request::Execute(query.into()))
-            .await?
-        {
-            Response::Execute(rs) => rs,
-            resp => return Err(Error::Internal(format!("Unecpected response: {:?}", resp))),
-        };
+        self.execute_request(Request::Execute(query.into())).await
+    }
 
-        if let ResultSet::Query { columns, .. } = result_set {
-            // the ResultSet::Query, column and rows are separated
-            let mut rows = Vec::new();
-            while let Some(result) = conn.try_next().await? {
-                match result? {
-                    Response::Row(Some(row)) => rows.push(row),
-                    Response::Row(None) => break,
-                    response => {
-                        return Err(Error::Internal(format!(
-                            "Unexcepted response: {:?}",
-                            response
-                        )))
-                    }
+    /// execute a query, its rows are fetched in batches as the cursor is read
+    pub async fn query(&self, query: &str) -> Result<Cursor> {
+        self.query_request(Request::Execute(query.into())).await
+    }
+
+    /// prepare a statement, returns its handle and the types of its
+    /// parameters
+    pub async fn prepare(&self, query: &str) -> Result<(u64, Vec<Option<DataType>>)> {
+        match self.conn.call(Request::Prepare(query.into())).await? {
+            Response::Prepare { id, parameters } => Ok((id, parameters)),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+
+    /// execute a prepared statement with the values of its parameters
+    pub async fn execute_prepared(&self, id: u64, params: Vec<DataValue>) -> Result<ResultSet> {
+        self.execute_request(Request::ExecutePrepared(id, params))
+            .await
+    }
+
+    /// execute a prepared query with the values of its parameters, its rows
+    /// are fetched in batches as the cursor is read
+    pub async fn query_prepared(&self, id: u64, params: Vec<DataValue>) -> Result<Cursor> {
+        self.query_request(Request::ExecutePrepared(id, params))
+            .await
+    }
+
+    /// drop a prepared statement
+    pub async fn deallocate(&self, id: u64) -> Result<()> {
+        match self.conn.call(Request::Deallocate(id)).await? {
+            Response::Deallocate => Ok(()),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+
+    /// send a request answered by a result set, the rows of a query are
+    /// fetched from its cursor
+    async fn execute_request(&self, request: Request) -> Result<ResultSet> {
+        match self.conn.call(request).await? {
+            Response::Execute(result_set) => Ok(result_set),
+            Response::Cursor { id, columns } => {
+                let mut cursor = Cursor::new(self.conn.clone(), id, columns.clone());
+                let mut rows = Vec::new();
+                while let Some(row) = cursor.next().await {
+                    rows.push(row?);
                 }
+                Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(rows.into_iter().map(Ok)),
+                })
             }
-            result_set = ResultSet::Query {
-                columns,
-                rows: Box::new(rows.into_iter().map(Ok)),
-            }
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
         }
+    }
+
+    /// send a request answered by the cursor of a query
+    async fn query_request(&self, request: Request) -> Result<Cursor> {
+        match self.conn.call(request).await? {
+            Response::Cursor { id, columns } => Ok(Cursor::new(self.conn.clone(), id, columns)),
+            Response::Execute(result_set) => Err(Error::Value(format!(
+                "The statement returned no rows: {}",
+                result_set
+            ))),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+
+    /// return the schema of a table
+    pub async fn get_table(&self, table: &str) -> Result<Table> {
+        match self.conn.call(Request::GetTable(table.into())).await? {
+            Response::Table(table) => Ok(table),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
 
-        Ok(result_set)
+    /// return the tables and views of the database, with their kind
+    pub async fn list_tables(&self) -> Result<Vec<(String, TableKind)>> {
+        match self.conn.call(Request::ListTables).await? {
+            Response::ListTable(tables) => Ok(tables),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
     }
 
-    /// call a server method while holding the mutex lock
-    async fn call_locked(
-        &self,
-        conn: &mut MutexGuard<'_, Connection>,
+    /// return the status of the server
+    pub async fn status(&self) -> Result<Status> {
+        match self.conn.call(Request::Status).await? {
+            Response::Status(status) => Ok(status),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+
+    /// return the token cancelling the statements of the client, it is
+    /// taken before the statement to cancel since the calls of a client wait
+    /// for each other
+    pub async fn cancel_token(&self) -> Result<CancelToken> {
+        match self.conn.call(Request::CancelKey).await? {
+            Response::CancelKey { id, key } => Ok(CancelToken {
+                addr: self.conn.addr,
+                tls: self.conn.tls.clone(),
+                id,
+                key,
+            }),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+}
+
+/// cancels the statements of a client from a connection of its own
+#[derive(Clone)]
+pub struct CancelToken {
+    addr: SocketAddr,
+    tls: Option<TlsOptions>,
+    id: u64,
+    key: u64,
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("addr", &self.addr)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CancelToken {
+    /// cancel the statement running on the client, or the rows of its query
+    /// being fetched. the statement fails, and its transaction is rolled back
+    pub async fn cancel(&self) -> Result<()> {
+        let client = Client::connect(self.addr, self.tls.as_ref(), None).await?;
+        let request = Request::Cancel {
+            id: self.id,
+            key: self.key,
+        };
+        match client.conn.call(request).await? {
+            Response::Cancel => Ok(()),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+}
+
+/// the connection of a client, shared by its clones and its cursors
+struct Connection {
+    /// the transport, with the number of responses left unread by the calls
+    /// dropped before their response
+    transport: Mutex<(Transport, usize)>,
+    /// the cursors dropped before their last row, closed by the next call
+    abandoned: std::sync::Mutex<Vec<u64>>,
+    /// the transaction of the server session
+    txn: std::sync::Mutex<Option<(u64, TransactionMode)>>,
+    /// whether the transport has failed
+    broken: AtomicBool,
+    /// the address and the TLS of the server, for the cancel requests
+    addr: SocketAddr,
+    tls: Option<TlsOptions>,
+}
+
+impl Connection {
+    /// call a server method
+    async fn call(&self, request: Request) -> Result<Response> {
+        let mut guard = self.transport.lock().await;
+        let (transport, unread) = &mut *guard;
+        let abandoned = std::mem::take(&mut *self.abandoned.lock()?);
+        let response = Self::exchange(transport, unread, abandoned, request).await;
+        match response {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                self.broken.store(true, Ordering::Relaxed);
+                Err(Error::Internal("Server disconnected".into()))
+            }
+            Err(err) => {
+                self.broken.store(true, Ordering::Relaxed);
+                Err(err)
+            }
+        }
+    }
+
+    /// send a request and read its response, None if the server has closed
+    /// the connection
+    async fn exchange(
+        transport: &mut Transport,
+        unread: &mut usize,
+        abandoned: Vec<u64>,
         request: Request,
-    ) -> Result<Response> {
-        conn.send(request).await?;
-        match conn.try_next().await? {
-            Some(result) => result,
-            None => Err(Error::Internal("Server disconnected".into())),
+    ) -> Result<Option<Result<Response>>> {
+        // the responses to the closing of the abandoned cursors are not read
+        for id in abandoned {
+            transport.send(Request::CloseCursor(id)).await?;
+            *unread += 1;
+        }
+        while *unread > 0 {
+            if transport.try_next().await?.is_none() {
+                return Ok(None);
+            }
+            *unread -= 1;
+        }
+
+        transport.send(request).await?;
+        *unread += 1;
+        let response = transport.try_next().await?;
+        *unread -= 1;
+        Ok(response)
+    }
+}
+
+type Fetch = Pin<Box<dyn Future<Output = Result<(Vec<DataRow>, bool)>> + Send>>;
+
+/// the rows of a query, fetched from the server in batches as they are read.
+/// a cursor dropped before its last row is closed by the next call of the
+/// client
+pub struct Cursor {
+    conn: Arc<Connection>,
+    id: u64,
+    columns: DataColumns,
+    fetch_size: usize,
+    rows: VecDeque<DataRow>,
+    /// whether the server has no more rows, or the cursor failed
+    done: bool,
+    fetch: Option<Fetch>,
+}
+
+impl Cursor {
+    fn new(conn: Arc<Connection>, id: u64, columns: DataColumns) -> Self {
+        Self {
+            conn,
+            id,
+            columns,
+            fetch_size: FETCH_SIZE,
+            rows: VecDeque::new(),
+            done: false,
+            fetch: None,
+        }
+    }
+
+    pub fn columns(&self) -> &DataColumns {
+        &self.columns
+    }
+
+    /// set the number of rows fetched at a time
+    pub fn fetch_size(mut self, fetch_size: usize) -> Self {
+        self.fetch_size = fetch_size.max(1);
+        self
+    }
+
+    /// close the cursor, its remaining rows are not fetched
+    pub async fn close(mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+        match self.conn.call(Request::CloseCursor(self.id)).await? {
+            Response::CloseCursor => Ok(()),
+            resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+        }
+    }
+}
+
+impl Stream for Cursor {
+    type Item = Result<DataRow>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let cursor = self.get_mut();
+        loop {
+            if let Some(row) = cursor.rows.pop_front() {
+                return Poll::Ready(Some(Ok(row)));
+            }
+            if cursor.done {
+                return Poll::Ready(None);
+            }
+            let fetch = cursor.fetch.get_or_insert_with(|| {
+                let (conn, id, n) = (cursor.conn.clone(), cursor.id, cursor.fetch_size);
+                Box::pin(async move {
+                    match conn.call(Request::Fetch(id, n)).await? {
+                        Response::Rows { rows, done } => Ok((rows, done)),
+                        resp => Err(Error::Internal(format!("Unexpected response: {:?}", resp))),
+                    }
+                })
+            });
+            let result = futures::ready!(fetch.as_mut().poll(cx));
+            cursor.fetch = None;
+            match result {
+                Ok((rows, done)) => {
+                    cursor.rows.extend(rows);
+                    cursor.done = done;
+                }
+                // the server closes a cursor on an error
+                Err(err) => {
+                    cursor.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Cursor {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Ok(mut abandoned) = self.conn.abandoned.lock() {
+                abandoned.push(self.id);
+            }
         }
     }
 }
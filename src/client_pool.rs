@@ -0,0 +1,395 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::warn;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio_stream::Stream;
+
+use crate::client::CancelToken;
+use crate::client::Client;
+use crate::client::Credentials;
+use crate::client::Cursor;
+use crate::common::result::DataColumns;
+use crate::common::result::DataRow;
+use crate::common::result::ResultSet;
+use crate::common::tls::TlsOptions;
+use crate::error::Error;
+use crate::error::Result;
+use crate::server::servlet::Status;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
+use crate::storage::mvcc::TransactionMode;
+
+/// the settings of a client pool
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// the connections kept open, even when idle
+    pub min_connections: usize,
+    /// the connections open at most, checked out or idle
+    pub max_connections: usize,
+    /// how long a checkout waits for a connection
+    pub acquire_timeout: Duration,
+    /// how long a connection above the minimum stays idle before it is closed
+    pub idle_timeout: Duration,
+    /// a connection idle for longer is checked before it is checked out, the
+    /// pool is maintained at this interval
+    pub health_check_interval: Duration,
+    /// the user the connections authenticate as, if the server requires it
+    pub credentials: Option<Credentials>,
+    /// the TLS of the connections, if the server requires it
+    pub tls: Option<TlsOptions>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_connections: 1,
+            max_connections: 16,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(600),
+            health_check_interval: Duration::from_secs(30),
+            credentials: None,
+            tls: None,
+        }
+    }
+}
+
+/// a pool of clients shared by concurrent tasks. a checked out client is not
+/// used by any other task, so the statements of a transaction stay on its
+/// connection
+#[derive(Clone)]
+pub struct ClientPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    addr: String,
+    config: PoolConfig,
+    /// a permit for each checked out client
+    semaphore: Arc<Semaphore>,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// the idle clients, with the time they were returned
+    idle: VecDeque<(Client, Instant)>,
+    /// the connections open, checked out or idle
+    open: usize,
+}
+
+impl ClientPool {
+    /// open the minimum connections of a pool, they are maintained by a
+    /// background task until the pool is dropped
+    pub async fn new(addr: &str, config: PoolConfig) -> Result<Self> {
+        if config.max_connections == 0 || config.min_connections > config.max_connections {
+            return Err(Error::Config(format!(
+                "Invalid pool size, min {} max {}",
+                config.min_connections, config.max_connections
+            )));
+        }
+        let pool = Self {
+            inner: Arc::new(Inner {
+                addr: addr.to_string(),
+                semaphore: Arc::new(Semaphore::new(config.max_connections)),
+                config,
+                state: Mutex::new(State {
+                    idle: VecDeque::new(),
+                    open: 0,
+                }),
+            }),
+        };
+        pool.inner.maintain().await?;
+        tokio::spawn(Inner::run(Arc::downgrade(&pool.inner)));
+        Ok(pool)
+    }
+
+    /// check out a client, waiting for one when all the connections are
+    /// checked out. a client returned with an open transaction, or whose
+    /// connection failed, is closed
+    pub async fn get(&self) -> Result<PooledClient> {
+        let inner = &self.inner;
+        let permit = tokio::time::timeout(
+            inner.config.acquire_timeout,
+            inner.semaphore.clone().acquire_owned(),
+        )
+        .await
+        .map_err(|_| Error::Internal("Timed out waiting for a connection".into()))?
+        .map_err(|err| Error::Internal(err.to_string()))?;
+
+        loop {
+            let idle = {
+                let mut state = inner.state.lock()?;
+                let idle = state.idle.pop_back();
+                if idle.is_none() {
+                    state.open += 1;
+                }
+                idle
+            };
+            let client = match idle {
+                Some((client, returned))
+                    if returned.elapsed() >= inner.config.health_check_interval =>
+                {
+                    match client.status().await {
+                        Ok(_) => client,
+                        Err(err) => {
+                            warn!("closing a pooled connection: {}", err);
+                            inner.state.lock()?.open -= 1;
+                            continue;
+                        }
+                    }
+                }
+                Some((client, _)) => client,
+                None => inner.connect().await?,
+            };
+            return Ok(PooledClient {
+                checkout: Arc::new(Checkout {
+                    client: Some(client),
+                    pool: inner.clone(),
+                    _permit: permit,
+                }),
+            });
+        }
+    }
+
+    /// execute a statement on a checked out client
+    pub async fn execute(&self, query: &str) -> Result<ResultSet> {
+        self.get().await?.execute(query).await
+    }
+
+    /// the connections open, checked out or idle
+    pub fn open_connections(&self) -> Result<usize> {
+        Ok(self.inner.state.lock()?.open)
+    }
+
+    /// the connections waiting to be checked out
+    pub fn idle_connections(&self) -> Result<usize> {
+        Ok(self.inner.state.lock()?.idle.len())
+    }
+}
+
+impl Inner {
+    /// open a connection counted as open by the caller
+    async fn connect(&self) -> Result<Client> {
+        let client = Client::connect(
+            self.addr.as_str(),
+            self.config.tls.as_ref(),
+            self.config.credentials.as_ref(),
+        )
+        .await;
+        match client {
+            Ok(client) => Ok(client),
+            Err(err) => {
+                self.state.lock()?.open -= 1;
+                Err(err)
+            }
+        }
+    }
+
+    /// close the connections idle for too long, then open the minimum
+    /// connections
+    async fn maintain(&self) -> Result<()> {
+        {
+            let mut state = self.state.lock()?;
+            let min = self.config.min_connections;
+            let idle_timeout = self.config.idle_timeout;
+            while state.open > min
+                && state
+                    .idle
+                    .front()
+                    .is_some_and(|(_, returned)| returned.elapsed() >= idle_timeout)
+            {
+                state.idle.pop_front();
+                state.open -= 1;
+            }
+        }
+
+        loop {
+            // the connection holds a permit while it is opened, so that the
+            // connections never exceed the maximum
+            let permit = match self.semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => return Ok(()),
+            };
+            {
+                let mut state = self.state.lock()?;
+                if state.open >= self.config.min_connections {
+                    return Ok(());
+                }
+                state.open += 1;
+            }
+            let client = self.connect().await?;
+            self.state.lock()?.idle.push_back((client, Instant::now()));
+            drop(permit);
+        }
+    }
+
+    /// maintain a pool until it is dropped
+    async fn run(pool: Weak<Inner>) {
+        loop {
+            let interval = match pool.upgrade() {
+                Some(inner) => inner.config.health_check_interval,
+                None => return,
+            };
+            tokio::time::sleep(interval).await;
+            let inner = match pool.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            if let Err(err) = inner.maintain().await {
+                warn!("maintaining the client pool: {}", err);
+            }
+        }
+    }
+
+    /// take back a checked out client
+    fn release(&self, client: Client) {
+        if let Ok(mut state) = self.state.lock() {
+            // the server rolls back the transaction of a closed connection
+            if client.is_broken() || client.txn().is_some() {
+                state.open -= 1;
+            } else {
+                state.idle.push_back((client, Instant::now()));
+            }
+        }
+    }
+}
+
+/// a checked out client, returned to its pool when dropped with the cursors
+/// of its queries. it forwards the calls of its Client, which is not exposed:
+/// a clone of it would share the connection with its next borrower
+pub struct PooledClient {
+    checkout: Arc<Checkout>,
+}
+
+/// the client checked out by a PooledClient and its cursors, returned to the
+/// pool when the last of them is dropped
+struct Checkout {
+    client: Option<Client>,
+    pool: Arc<Inner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledClient {
+    fn client(&self) -> &Client {
+        self.checkout
+            .client
+            .as_ref()
+            .expect("client taken before drop")
+    }
+
+    /// see Client::txn
+    pub fn txn(&self) -> Option<(u64, TransactionMode)> {
+        self.client().txn()
+    }
+
+    /// see Client::execute
+    pub async fn execute(&self, query: &str) -> Result<ResultSet> {
+        self.client().execute(query).await
+    }
+
+    /// see Client::query, the cursor keeps the client checked out
+    pub async fn query(&self, query: &str) -> Result<PooledCursor> {
+        let cursor = self.client().query(query).await?;
+        Ok(self.cursor(cursor))
+    }
+
+    /// see Client::prepare
+    pub async fn prepare(&self, query: &str) -> Result<(u64, Vec<Option<DataType>>)> {
+        self.client().prepare(query).await
+    }
+
+    /// see Client::execute_prepared
+    pub async fn execute_prepared(&self, id: u64, params: Vec<DataValue>) -> Result<ResultSet> {
+        self.client().execute_prepared(id, params).await
+    }
+
+    /// see Client::query_prepared, the cursor keeps the client checked out
+    pub async fn query_prepared(&self, id: u64, params: Vec<DataValue>) -> Result<PooledCursor> {
+        let cursor = self.client().query_prepared(id, params).await?;
+        Ok(self.cursor(cursor))
+    }
+
+    /// see Client::deallocate
+    pub async fn deallocate(&self, id: u64) -> Result<()> {
+        self.client().deallocate(id).await
+    }
+
+    /// see Client::get_table
+    pub async fn get_table(&self, table: &str) -> Result<Table> {
+        self.client().get_table(table).await
+    }
+
+    /// see Client::list_tables
+    pub async fn list_tables(&self) -> Result<Vec<(String, TableKind)>> {
+        self.client().list_tables().await
+    }
+
+    /// see Client::status
+    pub async fn status(&self) -> Result<Status> {
+        self.client().status().await
+    }
+
+    /// see Client::cancel_token
+    pub async fn cancel_token(&self) -> Result<CancelToken> {
+        self.client().cancel_token().await
+    }
+
+    fn cursor(&self, cursor: Cursor) -> PooledCursor {
+        PooledCursor {
+            cursor,
+            _checkout: self.checkout.clone(),
+        }
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.release(client);
+        }
+    }
+}
+
+/// the cursor of a query on a checked out client. the client is not returned
+/// to the pool while the cursor is alive, so that its rows are not fetched
+/// on the connection of the next borrower
+pub struct PooledCursor {
+    cursor: Cursor,
+    _checkout: Arc<Checkout>,
+}
+
+impl PooledCursor {
+    /// see Cursor::columns
+    pub fn columns(&self) -> &DataColumns {
+        self.cursor.columns()
+    }
+
+    /// see Cursor::fetch_size
+    pub fn fetch_size(mut self, fetch_size: usize) -> Self {
+        self.cursor = self.cursor.fetch_size(fetch_size);
+        self
+    }
+
+    /// see Cursor::close
+    pub async fn close(self) -> Result<()> {
+        self.cursor.close().await
+    }
+}
+
+impl Stream for PooledCursor {
+    type Item = Result<DataRow>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.cursor).poll_next(cx)
+    }
+}
@@ -4,6 +4,7 @@ use std::fmt::Display;
 use crate::error::Error;
 use crate::error::Result;
 use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::format_key;
 
 /// MVCC keys
 #[derive(Debug)]
@@ -29,8 +30,13 @@ pub enum SQLKey<'a> {
     Table(Option<Cow<'a, str>>),
     /// A key for an index entry
     Index(Cow<'a, str>, Cow<'a, str>, Option<Cow<'a, DataValue>>),
-    /// A key for a row identified by table name and row primary key
-    Row(Cow<'a, str>, Option<Cow<'a, DataValue>>),
+    /// A key for a row identified by table name and row primary key, the
+    /// values of a composite primary key are encoded one after another
+    Row(Cow<'a, str>, Option<Cow<'a, [DataValue]>>),
+    /// A view definition key for the given view name
+    View(Option<Cow<'a, str>>),
+    /// A user key for the given user name
+    User(Option<Cow<'a, str>>),
 }
 
 impl<'a> TransactionKey<'a> {
@@ -89,6 +95,8 @@ impl<'a> Display for TransactionKey<'a> {
 /// Table: 0x01
 /// Index: 0x02
 /// Row  : 0x03
+/// View : 0x04
+/// User : 0x05
 impl<'a> SQLKey<'a> {
     pub fn encode(self) -> Vec<u8> {
         use super::encoding::*;
@@ -106,9 +114,16 @@ impl<'a> SQLKey<'a> {
             ]
             .concat(),
             Self::Row(table, None) => [&[0x03][..], &encode_string(&table)].concat(),
-            Self::Row(table, Some(pk)) => {
-                [&[0x03][..], &encode_string(&table), &encode_data_value(&pk)].concat()
-            }
+            Self::Row(table, Some(pk)) => [
+                &[0x03][..],
+                &encode_string(&table),
+                &pk.iter().flat_map(encode_data_value).collect::<Vec<_>>(),
+            ]
+            .concat(),
+            Self::View(None) => vec![0x04],
+            Self::View(Some(name)) => [&[0x04][..], &encode_string(&name)].concat(),
+            Self::User(None) => vec![0x05],
+            Self::User(Some(name)) => [&[0x05][..], &encode_string(&name)].concat(),
         }
     }
 
@@ -122,10 +137,16 @@ impl<'a> SQLKey<'a> {
                 take_string(bytes)?.into(),
                 Some(take_data_value(bytes)?.into()),
             ),
-            0x03 => Self::Row(
-                take_string(bytes)?.into(),
-                Some(take_data_value(bytes)?.into()),
-            ),
+            0x03 => {
+                let table = take_string(bytes)?;
+                let mut pk = vec![take_data_value(bytes)?];
+                while !bytes.is_empty() {
+                    pk.push(take_data_value(bytes)?);
+                }
+                Self::Row(table.into(), Some(pk.into()))
+            }
+            0x04 => Self::View(Some(take_string(bytes)?.into())),
+            0x05 => Self::User(Some(take_string(bytes)?.into())),
             b => return Err(Error::Value(format!("Unknow SQL key prefix {}", b))),
         };
         if !bytes.is_empty() {
@@ -147,7 +168,11 @@ impl<'a> Display for SQLKey<'a> {
                 write!(f, "SQLKey:Index({}, {}, {})", table, row, value)
             }
             Self::Row(table, None) => write!(f, "SQLKey:Row({}, None)", table),
-            Self::Row(table, Some(pk)) => write!(f, "SQLKey:Row({}, {})", table, pk),
+            Self::Row(table, Some(pk)) => write!(f, "SQLKey:Row({}, {})", table, format_key(pk)),
+            Self::View(None) => write!(f, "SQLKey::View(None)"),
+            Self::View(Some(view)) => write!(f, "SQLKey::View({})", view),
+            Self::User(None) => write!(f, "SQLKey::User(None)"),
+            Self::User(Some(user)) => write!(f, "SQLKey::User({})", user),
         }
     }
 }
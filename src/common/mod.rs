@@ -6,3 +6,5 @@ pub mod range;
 pub mod result;
 pub mod scan;
 pub mod scope;
+pub mod scram;
+pub mod tls;
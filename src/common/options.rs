@@ -1,8 +1,22 @@
 use std::env::temp_dir;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::error::Error;
 use crate::error::Result;
+use crate::storage::encryption::EncryptionKey;
+
+/// how the pager makes a commit atomic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalMode {
+    /// the original pages are saved in a rollback journal, and the changed
+    /// pages are written into the database file
+    #[default]
+    Rollback,
+    /// the changed pages are appended to a write-ahead log, and copied into
+    /// the database file by a checkpoint
+    Wal,
+}
 
 pub struct PagerOption {
     /// db dir
@@ -13,6 +27,23 @@ pub struct PagerOption {
     pub n_extra: u64,
     /// the db is read_only
     pub read_only: bool,
+    /// rollback journal or write-ahead log
+    pub journal_mode: JournalMode,
+    /// the page size of a new database, an existing database keeps the page
+    /// size recorded in its file header
+    pub page_size: usize,
+    /// end every page of a new database with a checksum, an existing
+    /// database keeps the setting recorded in its file header
+    pub checksums: bool,
+    /// how long to wait for a lock held by another connection before the
+    /// database is reported busy
+    pub busy_timeout: Duration,
+    /// the most bytes of the database file to map into memory, the clean
+    /// pages inside the mapping are not copied. 0 reads every page.
+    pub mmap_size: u64,
+    /// the key of an encrypted database. a new database is encrypted if a
+    /// key is given, an existing database must be opened with its key.
+    pub encryption: Option<EncryptionKey>,
 }
 
 impl PagerOption {
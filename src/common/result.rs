@@ -39,6 +39,19 @@ pub enum ResultSet {
     DropTable {
         name: String,
     },
+    // view created
+    CreateView {
+        name: String,
+    },
+    // views dropped
+    DropView {
+        names: Vec<String>,
+    },
+    // materialized view refreshed, with the number of rows
+    RefreshView {
+        name: String,
+        count: u64,
+    },
     // query result
     Query {
         columns: DataColumns,
@@ -55,6 +68,27 @@ pub enum ResultSet {
     },
     // Explain result
     Explain(PlanNode),
+    // user created
+    CreateUser {
+        name: String,
+    },
+    // users dropped
+    DropUser {
+        names: Vec<String>,
+    },
+    // privileges granted to a user
+    Grant {
+        user: String,
+    },
+    // privileges revoked from a user
+    Revoke {
+        user: String,
+    },
+    // setting of the session changed
+    SetVariable {
+        name: String,
+        value: DataValue,
+    },
 }
 
 impl ResultSet {
@@ -86,12 +120,32 @@ impl Display for ResultSet {
             Self::Create { count } => write!(f, "ResultSet::Create{{count:{}}}", count),
             Self::CreateTable { name } => write!(f, "ResultSet::CreateTable{{name: {}}}", name),
             Self::DropTable { name } => write!(f, "ResultSet::DropTable{{name: {}}}", name),
+            Self::CreateView { name } => write!(f, "ResultSet::CreateView{{name: {}}}", name),
+            Self::DropView { names } => write!(f, "ResultSet::DropView{{names: {:?}}}", names),
+            Self::RefreshView { name, count } => {
+                write!(
+                    f,
+                    "ResultSet::RefreshView{{name: {}, count: {}}}",
+                    name, count
+                )
+            }
             Self::Query { columns, rows: _ } => {
                 write!(f, "ResultSet::Query:\r\n columns:{:?}", columns)
             }
             Self::Update { count } => write!(f, "ResultSet::Update{{count: {}}}", count),
             Self::Delete { count } => write!(f, "ResultSet::Delete{{count: {}}}", count),
             Self::Explain(plan) => write!(f, "ResultSet::Explain({})", plan),
+            Self::CreateUser { name } => write!(f, "ResultSet::CreateUser{{name: {}}}", name),
+            Self::DropUser { names } => write!(f, "ResultSet::DropUser{{names: {:?}}}", names),
+            Self::Grant { user } => write!(f, "ResultSet::Grant{{user: {}}}", user),
+            Self::Revoke { user } => write!(f, "ResultSet::Revoke{{user: {}}}", user),
+            Self::SetVariable { name, value } => {
+                write!(
+                    f,
+                    "ResultSet::SetVariable{{name: {}, value: {}}}",
+                    name, value
+                )
+            }
         }
     }
 }
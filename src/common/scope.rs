@@ -4,6 +4,7 @@ use std::collections::HashSet;
 use crate::error::Error;
 use crate::error::Result;
 use crate::sql::plan::plan_expression::Expression;
+use crate::sql::schema::data_type::DataType;
 use crate::sql::schema::table::Table;
 
 /// Manages names available to expressions and executors, and maps them onto columns/fields.
@@ -12,9 +13,11 @@ pub struct Scope {
     // If true, the scope is constant and cannot contain any variables.
     constant: bool,
     // Currently visible tables, by query name (i.e. alias or actual name).
-    tables: HashMap<String, Table>,
+    tables: HashSet<String>,
     // Column labels, if any (qualified by table name when available)
     columns: Vec<(Option<String>, Option<String>)>,
+    // Column datatypes, None if not known before the rows are read.
+    types: Vec<Option<DataType>>,
     // Qualified names to column indexes.
     qualified: HashMap<(String, String), usize>,
     // Unqualified names to column indexes, if unique.
@@ -28,8 +31,9 @@ impl Scope {
     pub fn new() -> Self {
         Self {
             constant: false,
-            tables: HashMap::new(),
+            tables: HashSet::new(),
             columns: Vec::new(),
+            types: Vec::new(),
             qualified: HashMap::new(),
             unqualified: HashMap::new(),
             ambiguous: HashSet::new(),
@@ -50,9 +54,14 @@ impl Scope {
         Ok(scope)
     }
 
-    /// Adds a column to the scope.
+    /// Adds a column to the scope, with its datatype if known.
     #[allow(clippy::map_entry)]
-    pub fn add_column(&mut self, table: Option<String>, label: Option<String>) {
+    pub fn add_column(
+        &mut self,
+        table: Option<String>,
+        label: Option<String>,
+        datatype: Option<DataType>,
+    ) {
         if let Some(l) = label.clone() {
             if let Some(t) = table.clone() {
                 // 通过表名和列名构建的元组作为key,value则是这次要添加的列的columns下标
@@ -74,6 +83,7 @@ impl Scope {
             }
         }
         self.columns.push((table, label));
+        self.types.push(datatype);
     }
 
     /// Adds a table to the scope.
@@ -81,13 +91,33 @@ impl Scope {
         if self.constant {
             return Err(Error::Internal("Can't modify constant scope".into()));
         }
-        if self.tables.contains_key(&label) {
+        if self.tables.contains(&label) {
             return Err(Error::Value(format!("Duplicate table name {}", label)));
         }
         for column in &table.columns {
-            self.add_column(Some(label.clone()), Some(column.name.clone()));
+            self.add_column(
+                Some(label.clone()),
+                Some(column.name.clone()),
+                Some(column.datatype.clone()),
+            );
         }
-        self.tables.insert(label, table);
+        self.tables.insert(label);
+        Ok(())
+    }
+
+    /// Adds a derived table to the scope, e.g. a common table expression,
+    /// its columns are the output columns of the given query scope.
+    pub fn add_derived(&mut self, label: String, scope: &Scope) -> Result<()> {
+        if self.constant {
+            return Err(Error::Internal("Can't modify constant scope".into()));
+        }
+        if self.tables.contains(&label) {
+            return Err(Error::Value(format!("Duplicate table name {}", label)));
+        }
+        for ((_, name), datatype) in scope.columns.iter().zip(&scope.types) {
+            self.add_column(Some(label.clone()), name.clone(), datatype.clone());
+        }
+        self.tables.insert(label);
         Ok(())
     }
 
@@ -105,6 +135,11 @@ impl Scope {
             .ok_or_else(|| Error::Value(format!("Column index {} not found", index)))
     }
 
+    /// Fetches the datatype of a column by index, None if it is not known.
+    pub fn get_type(&self, index: usize) -> Option<DataType> {
+        self.types.get(index).cloned().flatten()
+    }
+
     /// Fetches a column label by index, if any.
     pub fn get_label(&self, index: usize) -> Result<Option<(Option<String>, String)>> {
         Ok(match self.get_column(index)? {
@@ -118,14 +153,14 @@ impl Scope {
         if self.constant {
             return Err(Error::Internal("Can't modify constant scope".into()));
         }
-        for (label, table) in scope.tables {
-            if self.tables.contains_key(&label) {
+        for label in scope.tables {
+            if self.tables.contains(&label) {
                 return Err(Error::Value(format!("Duplicate table name {}", label)));
             }
-            self.tables.insert(label, table);
+            self.tables.insert(label);
         }
-        for (table, label) in scope.columns {
-            self.add_column(table, label);
+        for ((table, label), datatype) in scope.columns.into_iter().zip(scope.types) {
+            self.add_column(table, label, datatype);
         }
         Ok(())
     }
@@ -143,7 +178,7 @@ impl Scope {
             )));
         }
         if let Some(table) = table {
-            if !self.tables.contains_key(table) {
+            if !self.tables.contains(table) {
                 return Err(Error::Value(format!("Unknown table {}", table)));
             }
             self.qualified
@@ -174,22 +209,23 @@ impl Scope {
         let mut new = Self::new();
         new.tables = self.tables.clone();
         for (expr, label) in projection {
+            let datatype = expr.data_type(self);
             match (expr, label) {
-                (_, Some(label)) => new.add_column(None, Some(label.clone())),
+                (_, Some(label)) => new.add_column(None, Some(label.clone()), datatype),
                 (Expression::Field(_, Some((Some(table), name))), _) => {
-                    new.add_column(Some(table.clone()), Some(name.clone()))
+                    new.add_column(Some(table.clone()), Some(name.clone()), datatype)
                 }
                 (Expression::Field(_, Some((None, name))), _) => {
                     if let Some(i) = self.unqualified.get(name) {
                         let (table, name) = self.columns[*i].clone();
-                        new.add_column(table, name);
+                        new.add_column(table, name, datatype);
                     }
                 }
                 (Expression::Field(i, None), _) => {
                     let (table, label) = self.columns.get(*i).cloned().unwrap_or((None, None));
-                    new.add_column(table, label)
+                    new.add_column(table, label, datatype)
                 }
-                _ => new.add_column(None, None),
+                _ => new.add_column(None, None, datatype),
             }
         }
         *self = new;
@@ -0,0 +1,133 @@
+//! SCRAM-SHA-256 (RFC 5802, RFC 7677) challenge-response authentication.
+//! the server stores a salted key of a password, the client proves it knows
+//! the password without sending it, and the server proves it knows the key
+
+use std::sync::OnceLock;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// the PBKDF2-HMAC-SHA256 rounds salting a password
+pub const ITERATIONS: u32 = 4096;
+
+/// the size of a salt and of a nonce
+pub const NONCE_SIZE: usize = 18;
+
+/// random bytes, for a salt or a nonce
+pub fn nonce() -> Vec<u8> {
+    let mut nonce = vec![0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+}
+
+/// the salt of a user who does not exist, the same on every exchange so
+/// that it can not be told from the salt of a user. it is keyed by a secret
+/// of the server process
+pub fn unknown_user_salt(user: &str) -> Vec<u8> {
+    static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+    let secret = SECRET.get_or_init(nonce);
+    hmac(secret, user.as_bytes())[..NONCE_SIZE].to_vec()
+}
+
+/// the stored key and the server key of a password
+pub fn keys(password: &str, salt: &[u8], iterations: u32) -> (Vec<u8>, Vec<u8>) {
+    let salted = salted_password(password, salt, iterations);
+    let client_key = hmac(&salted, b"Client Key");
+    (
+        Sha256::digest(client_key).to_vec(),
+        hmac(&salted, b"Server Key"),
+    )
+}
+
+/// the message both sides sign, it binds the proofs to the user, the nonces
+/// and the salt of the exchange
+pub fn auth_message(
+    user: &str,
+    client_nonce: &[u8],
+    server_nonce: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Vec<u8> {
+    format!(
+        "n={},r={},r={}{},s={},i={},c=biws,r={}{}",
+        user,
+        hex(client_nonce),
+        hex(client_nonce),
+        hex(server_nonce),
+        hex(salt),
+        iterations,
+        hex(client_nonce),
+        hex(server_nonce)
+    )
+    .into_bytes()
+}
+
+/// the proof of the client that it knows the password
+pub fn client_proof(password: &str, salt: &[u8], iterations: u32, auth_message: &[u8]) -> Vec<u8> {
+    let salted = salted_password(password, salt, iterations);
+    let client_key = hmac(&salted, b"Client Key");
+    let signature = hmac(&Sha256::digest(&client_key), auth_message);
+    xor(&client_key, &signature)
+}
+
+/// whether a client proof matches the stored key
+pub fn verify_client_proof(stored_key: &[u8], auth_message: &[u8], proof: &[u8]) -> bool {
+    let signature = hmac(stored_key, auth_message);
+    if proof.len() != signature.len() {
+        return false;
+    }
+    let client_key = xor(proof, &signature);
+    constant_time_eq(&Sha256::digest(client_key), stored_key)
+}
+
+/// whether a password given in clear matches the stored key, for the
+/// protocols without a challenge
+pub fn verify_password(password: &str, salt: &[u8], iterations: u32, stored_key: &[u8]) -> bool {
+    let (key, _) = keys(password, salt, iterations);
+    constant_time_eq(&key, stored_key)
+}
+
+/// the proof of the server that it knows the key of the password
+pub fn server_signature(server_key: &[u8], auth_message: &[u8]) -> Vec<u8> {
+    hmac(server_key, auth_message)
+}
+
+/// whether a server signature is the one of a password
+pub fn verify_server_signature(
+    password: &str,
+    salt: &[u8],
+    iterations: u32,
+    auth_message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let (_, server_key) = keys(password, salt, iterations);
+    constant_time_eq(&server_signature(&server_key, auth_message), signature)
+}
+
+fn salted_password(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut salted = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut salted);
+    salted
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes a key of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,110 @@
+//! TLS of the server protocols. the certificates and keys are read from PEM
+//! files, a server may require the clients to present a certificate issued
+//! by its certificate authorities
+
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::Certificate;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::rustls::PrivateKey;
+use tokio_rustls::rustls::RootCertStore;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::ServerName;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::TlsConnector;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// the TLS settings of a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// the PEM bundle of the certificate authorities the certificate of the
+    /// server is verified with
+    pub ca_file: String,
+    /// the name the certificate of the server is issued to
+    pub server_name: String,
+    /// the PEM files of the certificate chain and the key of the client,
+    /// for a server verifying the client certificates
+    pub identity: Option<(String, String)>,
+}
+
+impl TlsOptions {
+    /// the connector of the options, and the name of the server
+    pub fn connector(&self) -> Result<(TlsConnector, ServerName)> {
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots(&self.ca_file)?);
+        let config = match &self.identity {
+            Some((cert_file, key_file)) => builder
+                .with_client_auth_cert(certs(cert_file)?, key(key_file)?)
+                .map_err(|err| Error::Config(format!("Invalid client certificate: {}", err)))?,
+            None => builder.with_no_client_auth(),
+        };
+        let server_name = ServerName::try_from(self.server_name.as_str())
+            .map_err(|_| Error::Config(format!("Invalid server name {}", self.server_name)))?;
+        Ok((TlsConnector::from(Arc::new(config)), server_name))
+    }
+}
+
+/// the acceptor of a server, the clients present a certificate issued by the
+/// certificate authorities of client_ca_file if it is given
+pub fn acceptor(
+    cert_file: &str,
+    key_file: &str,
+    client_ca_file: Option<&str>,
+) -> Result<TlsAcceptor> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca_file {
+        Some(ca_file) => builder
+            .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots(ca_file)?).boxed()),
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(certs(cert_file)?, key(key_file)?)
+        .map_err(|err| Error::Config(format!("Invalid server certificate: {}", err)))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// the certificates of a PEM file
+fn certs(path: &str) -> Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut reader(path)?)?;
+    if certs.is_empty() {
+        return Err(Error::Config(format!("No certificate in {}", path)));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// the first private key of a PEM file
+fn key(path: &str) -> Result<PrivateKey> {
+    let mut reader = reader(path)?;
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    Err(Error::Config(format!("No private key in {}", path)))
+}
+
+/// the certificate authorities of a PEM bundle
+fn roots(path: &str) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in certs(path)? {
+        roots
+            .add(&cert)
+            .map_err(|err| Error::Config(format!("Invalid certificate in {}: {}", path, err)))?;
+    }
+    Ok(roots)
+}
+
+fn reader(path: &str) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| Error::Config(format!("Can not read {}: {}", path, err)))
+}
@@ -16,12 +16,21 @@ pub enum Error {
     Parse(String),
     Serialization,
     ReadOnly,
+    /// the user lacks a privilege, or could not be authenticated
+    Unauthorized(String),
+    /// the statement was cancelled, or ran out of time
+    Cancelled(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Config(s) | Error::Value(s) | Error::Internal(s) | Error::Parse(s) => {
+            Error::Config(s)
+            | Error::Value(s)
+            | Error::Internal(s)
+            | Error::Parse(s)
+            | Error::Unauthorized(s)
+            | Error::Cancelled(s) => {
                 write!(f, "{}", s)
             }
             Error::Serialization => write!(f, "Serialization failure, retry transaction"),
@@ -122,6 +131,18 @@ impl From<tokio::task::JoinError> for Error {
     }
 }
 
+impl From<hyper::Error> for Error {
+    fn from(err: hyper::Error) -> Self {
+        Error::Internal(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
 impl From<log::ParseLevelError> for Error {
     fn from(err: log::ParseLevelError) -> Self {
         Error::Config(err.to_string())
@@ -3,6 +3,7 @@
 #![allow(clippy::unneeded_field_pattern)]
 
 pub mod client;
+pub mod client_pool;
 pub mod common;
 pub mod error;
 pub mod server;
@@ -0,0 +1,399 @@
+use std::convert::Infallible;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use hyper::body::HttpBody;
+use hyper::header;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::Body;
+use hyper::Method;
+use hyper::Request;
+use hyper::Response;
+use hyper::StatusCode;
+use log::info;
+use serde_derive::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::sessions::Sessions;
+use crate::common::result::ResultSet;
+use crate::common::scram;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::engine::KVEngine;
+use crate::sql::engine::SQLEngine;
+use crate::sql::engine::SQLSession;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::storage::mvcc::TransactionMode;
+
+const JSON: &str = "application/json";
+const NDJSON: &str = "application/x-ndjson";
+
+/// a request body larger than this is refused, it is a broken or hostile
+/// client
+const MAX_BODY_SIZE: usize = 16 << 20;
+
+/// the body of POST /query
+#[derive(Debug, Deserialize)]
+struct QueryRequest {
+    sql: String,
+    /// the values of the $1, $2, ... parameters of the statement
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+/// an HTTP client connection, its requests are served by the JSON API
+pub struct HTTPSession {
+    engine: KVEngine,
+    /// the sessions of the server, for its status
+    sessions: Sessions,
+    /// whether every request carries the Basic credentials of a user
+    authentication: bool,
+}
+
+impl HTTPSession {
+    pub fn new(engine: KVEngine, sessions: Sessions, authentication: bool) -> Result<Self> {
+        Ok(Self {
+            engine,
+            sessions,
+            authentication,
+        })
+    }
+
+    pub async fn handle<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+        self,
+        socket: S,
+    ) -> Result<()> {
+        let (engine, sessions, authentication) = (self.engine, self.sessions, self.authentication);
+        let service = service_fn(move |request| {
+            let (engine, sessions) = (engine.clone(), sessions.clone());
+            async move { Ok::<_, Infallible>(route(engine, sessions, authentication, request).await) }
+        });
+        Http::new()
+            .http1_only(true)
+            .serve_connection(socket, service)
+            .await?;
+        Ok(())
+    }
+}
+
+/// serves a request, an error is answered with its message as JSON
+async fn route(
+    engine: KVEngine,
+    sessions: Sessions,
+    authentication: bool,
+    request: Request<Body>,
+) -> Response<Body> {
+    info!("http request {} {}", request.method(), request.uri());
+    // the statements run as the user of the credentials
+    let user = match authentication {
+        true => match authenticate(&engine, &request).await {
+            Ok(Some(user)) => Some(user),
+            Ok(None) => {
+                let mut response =
+                    error_response(StatusCode::UNAUTHORIZED, "Authentication required");
+                response.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    header::HeaderValue::from_static("Basic realm=\"kvdb\""),
+                );
+                return response;
+            }
+            Err(err) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+        },
+        false => None,
+    };
+    let path = request.uri().path().to_string();
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .map(percent_decode)
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let result = match (request.method(), segments.as_slice()) {
+        (&Method::POST, ["query"]) => query(engine, user, request).await,
+        (&Method::GET, ["tables"]) => tables(engine, user),
+        (&Method::GET, ["tables", name]) => table(engine, user, name),
+        (&Method::GET, ["status"]) => status(engine, user, &sessions),
+        (_, ["query"] | ["tables"] | ["tables", _] | ["status"]) => Ok(error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            &format!("Method {} not allowed on {}", request.method(), path),
+        )),
+        _ => Ok(error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Path {} not found", path),
+        )),
+    };
+    result.unwrap_or_else(|err| {
+        let status = match err {
+            Error::Parse(_) | Error::Value(_) | Error::ReadOnly => StatusCode::BAD_REQUEST,
+            Error::Serialization => StatusCode::CONFLICT,
+            Error::Unauthorized(_) => StatusCode::FORBIDDEN,
+            Error::Cancelled(_) => StatusCode::REQUEST_TIMEOUT,
+            Error::Config(_) | Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        error_response(status, &err.to_string())
+    })
+}
+
+/// POST /query, executes a statement. the result is the JSON of the result
+/// set, the rows of a query are added to it. a client accepting NDJSON gets
+/// the result set on the first line and every row on a line of its own
+async fn query(
+    engine: KVEngine,
+    user: Option<String>,
+    request: Request<Body>,
+) -> Result<Response<Body>> {
+    let ndjson = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains(NDJSON));
+    let length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    let body = match length {
+        Some(length) if length > MAX_BODY_SIZE as u64 => None,
+        _ => read_body(request.into_body()).await?,
+    };
+    let body = match body {
+        Some(body) => body,
+        None => {
+            return Ok(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!("Request body larger than {} bytes", MAX_BODY_SIZE),
+            ))
+        }
+    };
+    let request: QueryRequest = serde_json::from_slice(&body)?;
+
+    // the statement and its rows read the store, they are run on the
+    // blocking threads and not on the threads serving the connections
+    let mut result = tokio::task::spawn_blocking(move || execute(engine, user, request)).await??;
+    let rows = match &mut result {
+        ResultSet::Query { rows, .. } => std::mem::replace(rows, ResultSet::empty_rows()),
+        _ => return json_response(&result),
+    };
+    let rows =
+        rows.map(|row| row.map(|row| Value::Array(row.into_iter().map(json_value).collect())));
+
+    if !ndjson {
+        let rows = tokio::task::spawn_blocking(move || rows.collect::<Result<_>>()).await??;
+        let mut value = serde_json::to_value(&result)?;
+        value["Query"]["rows"] = Value::Array(rows);
+        return json_response(&value);
+    }
+
+    // an error ends the rows, it is sent on the last line
+    let lines = std::iter::once(Ok(serde_json::to_value(&result)?))
+        .chain(rows)
+        .scan(false, |failed, row| match (*failed, row) {
+            (true, _) => None,
+            (_, Ok(value)) => Some(value),
+            (_, Err(err)) => {
+                *failed = true;
+                Some(json!({ "error": err.to_string() }))
+            }
+        })
+        .map(|value| Ok::<_, Infallible>(format!("{}\n", value)));
+    // the rows are fetched as the client reads them, the fetch stops when
+    // the client goes away
+    let (sender, receiver) = mpsc::channel(64);
+    tokio::task::spawn_blocking(move || {
+        for line in lines {
+            if sender.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, NDJSON)
+        .body(Body::wrap_stream(ReceiverStream::new(receiver)))
+        .map_err(|e| Error::Internal(e.to_string()))
+}
+
+/// reads a request body of at most MAX_BODY_SIZE bytes, None if it is larger
+async fn read_body(mut body: Body) -> Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if data.len() + chunk.len() > MAX_BODY_SIZE {
+            return Ok(None);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(Some(data))
+}
+
+/// executes the statement of POST /query with its parameters
+fn execute(engine: KVEngine, user: Option<String>, request: QueryRequest) -> Result<ResultSet> {
+    let session = session(&engine, user)?;
+    let mut prepared = session.prepare(&request.sql)?;
+    let types = prepared.parameters();
+    if request.params.len() < types.len() {
+        return Err(Error::Value(format!(
+            "Parameter ${} is not bound",
+            request.params.len() + 1
+        )));
+    }
+    let params = request
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, value)| json_parameter(i, value, types.get(i).cloned().flatten()))
+        .collect::<Result<Vec<_>>>()?;
+    session.execute_prepared(&mut prepared, &params)
+}
+
+/// GET /tables, the names and kinds of the tables and views
+fn tables(engine: KVEngine, user: Option<String>) -> Result<Response<Body>> {
+    let tables = session(&engine, user)?.with_txn(TransactionMode::ReadOnly, |txn| {
+        let mut tables = Vec::new();
+        for table in txn.scan_table()? {
+            if txn.can_see(&table.name)? {
+                tables.push(json!({ "name": table.name, "kind": table.kind.to_string() }));
+            }
+        }
+        Ok(tables)
+    })?;
+    json_response(&tables)
+}
+
+/// GET /tables/{name}, the schema of a table
+fn table(engine: KVEngine, user: Option<String>, name: &str) -> Result<Response<Body>> {
+    match session(&engine, user)?.with_txn(TransactionMode::ReadOnly, |txn| {
+        txn.authorize_schema(name)?;
+        txn.read_table(name)
+    })? {
+        Some(table) => json_response(&table),
+        None => Ok(error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Table {} does not exist", name),
+        )),
+    }
+}
+
+/// GET /status, the status of the server, for superusers only
+fn status(engine: KVEngine, user: Option<String>, sessions: &Sessions) -> Result<Response<Body>> {
+    session(&engine, user)?.with_txn(TransactionMode::ReadOnly, |txn| txn.authorize_superuser())?;
+    json_response(&sessions.status(&engine.mvcc)?)
+}
+
+/// the user of the Basic credentials of a request, None if there are none or
+/// if they are wrong
+async fn authenticate(engine: &KVEngine, request: &Request<Body>) -> Result<Option<String>> {
+    let credentials = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok()?.strip_prefix("Basic "))
+        .and_then(|credentials| BASE64.decode(credentials.trim()).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok());
+    let (name, password) = match credentials.as_ref().and_then(|c| c.split_once(':')) {
+        Some((name, password)) => (name.to_string(), password.to_string()),
+        None => return Ok(None),
+    };
+    // the password is salted by many rounds, it is checked on a blocking thread
+    let engine = engine.clone();
+    tokio::task::spawn_blocking(move || {
+        let user = engine
+            .session()?
+            .with_txn(TransactionMode::ReadOnly, |txn| txn.read_user(&name))?;
+        Ok(user
+            .filter(|user| {
+                scram::verify_password(&password, &user.salt, user.iterations, &user.stored_key)
+            })
+            .map(|user| user.name))
+    })
+    .await?
+}
+
+/// a SQL session of the user of a request
+fn session(engine: &KVEngine, user: Option<String>) -> Result<SQLSession<KVEngine>> {
+    let mut session = engine.session()?;
+    session.user = user;
+    Ok(session)
+}
+
+fn json_response<T: serde::Serialize>(value: &T) -> Result<Response<Body>> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, JSON)
+        .body(Body::from(serde_json::to_vec(value)?))
+        .map_err(|e| Error::Internal(e.to_string()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(json!({ "error": message }).to_string()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static(JSON));
+    response
+}
+
+/// the JSON of a value, a float that is not a number is null
+fn json_value(value: DataValue) -> Value {
+    match value {
+        DataValue::Null => Value::Null,
+        DataValue::Boolean(b) => Value::Bool(b),
+        DataValue::Integer(i) => Value::from(i),
+        DataValue::Float(f) => Value::from(f),
+        DataValue::String(s) => Value::String(s),
+    }
+}
+
+/// the value of the JSON parameter $i+1, a number given to a float
+/// parameter is a float
+fn json_parameter(i: usize, value: &Value, datatype: Option<DataType>) -> Result<DataValue> {
+    Ok(match value {
+        Value::Null => DataValue::Null,
+        Value::Bool(b) => DataValue::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_f64(), datatype) {
+            (Some(i), _, Some(DataType::Float)) => DataValue::Float(i as f64),
+            (Some(i), _, _) => DataValue::Integer(i),
+            (None, Some(f), _) if n.is_f64() => DataValue::Float(f),
+            _ => {
+                return Err(Error::Value(format!(
+                    "Parameter ${} is out of range: {}",
+                    i + 1,
+                    n
+                )))
+            }
+        },
+        Value::String(s) => DataValue::String(s.clone()),
+        Value::Array(_) | Value::Object(_) => {
+            return Err(Error::Value(format!(
+                "Parameter ${} is not a scalar: {}",
+                i + 1,
+                value
+            )))
+        }
+    })
+}
+
+/// decodes the %XX escapes of a path segment
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], segment.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
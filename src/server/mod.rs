@@ -1,3 +1,18 @@
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+mod http_session;
+mod mysql_codec;
+mod mysql_session;
+mod pg_codec;
+mod pg_session;
 pub mod servlet;
+mod sessions;
+mod sql_text;
 pub mod tcp_server;
 mod tcp_session;
+
+/// the connection of a client, plaintext or TLS
+trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Socket for S {}
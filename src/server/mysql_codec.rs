@@ -0,0 +1,405 @@
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+use tokio_util::codec::Encoder;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// a payload this long continues in the next packet
+const MAX_PAYLOAD: usize = 0xff_ffff;
+
+/// a message larger than this is refused, it is a broken or hostile client
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// the capabilities of the server
+const CLIENT_LONG_PASSWORD: u32 = 0x0000_0001;
+const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+const CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+const CLIENT_SSL: u32 = 0x0000_0800;
+const CLIENT_TRANSACTIONS: u32 = 0x0000_2000;
+const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x0008_0000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x0020_0000;
+
+/// the status flags sent with OK and EOF packets
+const SERVER_STATUS_AUTOCOMMIT: u16 = 0x0002;
+
+/// the utf8_general_ci and binary collations
+const UTF8_COLLATION: u8 = 33;
+const BINARY_COLLATION: u8 = 63;
+
+/// the length of the scramble of the handshake
+pub const SCRAMBLE_SIZE: usize = 20;
+
+const COM_QUIT: u8 = 0x01;
+const COM_INIT_DB: u8 = 0x02;
+const COM_QUERY: u8 = 0x03;
+const COM_PING: u8 = 0x0e;
+
+/// the type of a result column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Tiny = 0x01,
+    Double = 0x05,
+    LongLong = 0x08,
+    VarString = 0xfd,
+}
+
+/// a packet sent by a MySQL client
+#[derive(Debug, PartialEq)]
+pub enum ClientPacket {
+    /// the answer to the handshake, the user and the default database
+    HandshakeResponse {
+        capabilities: u32,
+        user: String,
+        database: Option<String>,
+    },
+    /// the client asks for TLS, its handshake response follows encrypted
+    SslRequest,
+    Quit,
+    InitDb(String),
+    Query(String),
+    Ping,
+    /// a command that is not supported
+    Command(u8),
+}
+
+impl ClientPacket {
+    /// the name of the packet, which is logged: a query may carry a
+    /// password
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::HandshakeResponse { .. } => "HandshakeResponse",
+            Self::SslRequest => "SslRequest",
+            Self::Quit => "Quit",
+            Self::InitDb(_) => "InitDb",
+            Self::Query(_) => "Query",
+            Self::Ping => "Ping",
+            Self::Command(_) => "Command",
+        }
+    }
+}
+
+/// a packet sent to a MySQL client
+#[derive(Debug, PartialEq)]
+pub enum ServerPacket {
+    /// the first packet of a connection, tls offers TLS to the client
+    Handshake {
+        connection_id: u32,
+        scramble: [u8; SCRAMBLE_SIZE],
+        tls: bool,
+    },
+    Ok {
+        affected_rows: u64,
+    },
+    Err {
+        code: u16,
+        state: &'static str,
+        message: String,
+    },
+    Eof,
+    /// the number of columns of a result set
+    ColumnCount(usize),
+    Column {
+        name: String,
+        column_type: ColumnType,
+    },
+    /// the values of a row in text format, None is NULL
+    Row(Vec<Option<String>>),
+}
+
+/// frames the packets of the MySQL client/server protocol. the codec numbers
+/// the packets: a reply continues the sequence of the packet it answers
+pub struct MySQLCodec {
+    /// true until the handshake response, which is not a command, an
+    /// SSLRequest may precede it
+    handshake: bool,
+    /// the sequence id of the next packet sent
+    seq: u8,
+}
+
+impl MySQLCodec {
+    pub fn new() -> Self {
+        Self {
+            handshake: true,
+            seq: 0,
+        }
+    }
+}
+
+impl Decoder for MySQLCodec {
+    type Item = ClientPacket;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<ClientPacket>> {
+        // a payload of MAX_PAYLOAD bytes continues in the next packet, the
+        // message is decoded once all its packets arrived
+        let mut size = 0;
+        let mut len = 0;
+        loop {
+            if src.len() < size + 4 {
+                return Ok(None);
+            }
+            let packet = u32::from_le_bytes([src[size], src[size + 1], src[size + 2], 0]) as usize;
+            if len + packet > MAX_MESSAGE_SIZE {
+                return Err(Error::Parse(format!(
+                    "Message larger than {} bytes",
+                    MAX_MESSAGE_SIZE
+                )));
+            }
+            size += 4 + packet;
+            len += packet;
+            if packet < MAX_PAYLOAD {
+                break;
+            }
+        }
+        if src.len() < size {
+            src.reserve(size - src.len());
+            return Ok(None);
+        }
+
+        let mut payload = BytesMut::with_capacity(len);
+        loop {
+            let packet = u32::from_le_bytes([src[0], src[1], src[2], 0]) as usize;
+            self.seq = src[3].wrapping_add(1);
+            src.advance(4);
+            payload.extend_from_slice(&src.split_to(packet));
+            if packet < MAX_PAYLOAD {
+                break;
+            }
+        }
+
+        if self.handshake {
+            let capabilities = get_u32(&mut payload)?;
+            if capabilities & CLIENT_PROTOCOL_41 == 0 {
+                return Err(Error::Parse("Client does not speak protocol 4.1".into()));
+            }
+            // an SSLRequest is the start of a handshake response, up to the
+            // filler
+            if capabilities & CLIENT_SSL != 0 && payload.len() == 4 + 1 + 23 {
+                return Ok(Some(ClientPacket::SslRequest));
+            }
+            self.handshake = false;
+            // max packet size, character set and filler
+            skip(&mut payload, 4 + 1 + 23)?;
+            let user = get_cstr(&mut payload)?;
+            let auth = if capabilities & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA != 0 {
+                get_lenenc_int(&mut payload)? as usize
+            } else {
+                get_u8(&mut payload)? as usize
+            };
+            skip(&mut payload, auth)?;
+            let database = match capabilities & CLIENT_CONNECT_WITH_DB {
+                0 => None,
+                _ => Some(get_cstr(&mut payload)?).filter(|db| !db.is_empty()),
+            };
+            return Ok(Some(ClientPacket::HandshakeResponse {
+                capabilities,
+                user,
+                database,
+            }));
+        }
+
+        let command = get_u8(&mut payload)?;
+        Ok(Some(match command {
+            COM_QUIT => ClientPacket::Quit,
+            COM_INIT_DB => ClientPacket::InitDb(String::from_utf8(payload.to_vec())?),
+            COM_QUERY => ClientPacket::Query(String::from_utf8(payload.to_vec())?),
+            COM_PING => ClientPacket::Ping,
+            command => ClientPacket::Command(command),
+        }))
+    }
+}
+
+impl Encoder<ServerPacket> for MySQLCodec {
+    type Error = Error;
+
+    fn encode(&mut self, packet: ServerPacket, dst: &mut BytesMut) -> Result<()> {
+        let mut payload = BytesMut::new();
+        match packet {
+            ServerPacket::Handshake {
+                connection_id,
+                scramble,
+                tls,
+            } => {
+                let ssl = if tls { CLIENT_SSL } else { 0 };
+                let capabilities = ssl
+                    | CLIENT_LONG_PASSWORD
+                    | CLIENT_CONNECT_WITH_DB
+                    | CLIENT_PROTOCOL_41
+                    | CLIENT_TRANSACTIONS
+                    | CLIENT_SECURE_CONNECTION
+                    | CLIENT_PLUGIN_AUTH
+                    | CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA;
+                payload.put_u8(10);
+                put_cstr(
+                    &mut payload,
+                    concat!("8.0.0-kvdb-", env!("CARGO_PKG_VERSION")),
+                );
+                payload.put_u32_le(connection_id);
+                payload.put_slice(&scramble[..8]);
+                payload.put_u8(0);
+                payload.put_u16_le(capabilities as u16);
+                payload.put_u8(UTF8_COLLATION);
+                payload.put_u16_le(SERVER_STATUS_AUTOCOMMIT);
+                payload.put_u16_le((capabilities >> 16) as u16);
+                payload.put_u8(SCRAMBLE_SIZE as u8 + 1);
+                payload.put_slice(&[0; 10]);
+                payload.put_slice(&scramble[8..]);
+                payload.put_u8(0);
+                put_cstr(&mut payload, "mysql_native_password");
+            }
+            ServerPacket::Ok { affected_rows } => {
+                payload.put_u8(0x00);
+                put_lenenc_int(&mut payload, affected_rows);
+                // no last insert id
+                put_lenenc_int(&mut payload, 0);
+                payload.put_u16_le(SERVER_STATUS_AUTOCOMMIT);
+                payload.put_u16_le(0);
+            }
+            ServerPacket::Err {
+                code,
+                state,
+                message,
+            } => {
+                payload.put_u8(0xff);
+                payload.put_u16_le(code);
+                payload.put_u8(b'#');
+                payload.put_slice(state.as_bytes());
+                payload.put_slice(message.as_bytes());
+            }
+            ServerPacket::Eof => {
+                payload.put_u8(0xfe);
+                payload.put_u16_le(0);
+                payload.put_u16_le(SERVER_STATUS_AUTOCOMMIT);
+            }
+            ServerPacket::ColumnCount(count) => put_lenenc_int(&mut payload, count as u64),
+            ServerPacket::Column { name, column_type } => {
+                // catalog, schema, table and original table
+                put_lenenc_str(&mut payload, "def");
+                put_lenenc_str(&mut payload, "");
+                put_lenenc_str(&mut payload, "");
+                put_lenenc_str(&mut payload, "");
+                put_lenenc_str(&mut payload, &name);
+                put_lenenc_str(&mut payload, &name);
+                // the length of the fixed fields
+                payload.put_u8(0x0c);
+                let (collation, length, decimals) = match column_type {
+                    ColumnType::Tiny => (BINARY_COLLATION, 1, 0),
+                    ColumnType::Double => (BINARY_COLLATION, 22, 31),
+                    ColumnType::LongLong => (BINARY_COLLATION, 20, 0),
+                    ColumnType::VarString => (UTF8_COLLATION, 0xffff, 0),
+                };
+                payload.put_u16_le(collation as u16);
+                payload.put_u32_le(length);
+                payload.put_u8(column_type as u8);
+                // no flags
+                payload.put_u16_le(0);
+                payload.put_u8(decimals);
+                payload.put_u16_le(0);
+            }
+            ServerPacket::Row(values) => {
+                for value in values {
+                    match value {
+                        Some(value) => put_lenenc_str(&mut payload, &value),
+                        None => payload.put_u8(0xfb),
+                    }
+                }
+            }
+        }
+
+        // a payload of MAX_PAYLOAD bytes or more is split, the last packet is
+        // shorter, it may be empty
+        let mut chunks = payload.chunks(MAX_PAYLOAD).peekable();
+        if chunks.peek().is_none() {
+            dst.put_slice(&[0, 0, 0, self.seq]);
+            self.seq = self.seq.wrapping_add(1);
+        }
+        while let Some(chunk) = chunks.next() {
+            dst.put_slice(&(chunk.len() as u32).to_le_bytes()[..3]);
+            dst.put_u8(self.seq);
+            dst.put_slice(chunk);
+            self.seq = self.seq.wrapping_add(1);
+            if chunks.peek().is_none() && chunk.len() == MAX_PAYLOAD {
+                dst.put_slice(&[0, 0, 0, self.seq]);
+                self.seq = self.seq.wrapping_add(1);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn skip(buf: &mut BytesMut, n: usize) -> Result<()> {
+    if buf.remaining() < n {
+        return Err(Error::Parse("Packet too short".into()));
+    }
+    buf.advance(n);
+    Ok(())
+}
+
+fn get_u8(buf: &mut BytesMut) -> Result<u8> {
+    if buf.remaining() < 1 {
+        return Err(Error::Parse("Packet too short".into()));
+    }
+    Ok(buf.get_u8())
+}
+
+fn get_u32(buf: &mut BytesMut) -> Result<u32> {
+    if buf.remaining() < 4 {
+        return Err(Error::Parse("Packet too short".into()));
+    }
+    Ok(buf.get_u32_le())
+}
+
+fn get_lenenc_int(buf: &mut BytesMut) -> Result<u64> {
+    let size = match get_u8(buf)? {
+        0xfc => 2,
+        0xfd => 3,
+        0xfe => 8,
+        n => return Ok(n as u64),
+    };
+    if buf.remaining() < size {
+        return Err(Error::Parse("Packet too short".into()));
+    }
+    Ok(buf.get_uint_le(size))
+}
+
+/// a NUL terminated string, or the rest of the packet
+fn get_cstr(buf: &mut BytesMut) -> Result<String> {
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    let s = String::from_utf8(buf.split_to(end).to_vec())?;
+    if buf.has_remaining() {
+        buf.advance(1);
+    }
+    Ok(s)
+}
+
+fn put_cstr(buf: &mut BytesMut, s: &str) {
+    buf.put_slice(s.as_bytes());
+    buf.put_u8(0);
+}
+
+fn put_lenenc_int(buf: &mut BytesMut, n: u64) {
+    match n {
+        0..=0xfa => buf.put_u8(n as u8),
+        0xfb..=0xffff => {
+            buf.put_u8(0xfc);
+            buf.put_u16_le(n as u16);
+        }
+        0x1_0000..=0xff_ffff => {
+            buf.put_u8(0xfd);
+            buf.put_uint_le(n, 3);
+        }
+        _ => {
+            buf.put_u8(0xfe);
+            buf.put_u64_le(n);
+        }
+    }
+}
+
+fn put_lenenc_str(buf: &mut BytesMut, s: &str) {
+    put_lenenc_int(buf, s.len() as u64);
+    buf.put_slice(s.as_bytes());
+}
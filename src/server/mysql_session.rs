@@ -0,0 +1,240 @@
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use bytes::BytesMut;
+use futures::sink::SinkExt as _;
+use log::debug;
+use log::info;
+use tokio::io::AsyncReadExt as _;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::StreamExt as _;
+use tokio_util::codec::Decoder as _;
+use tokio_util::codec::Framed;
+use tokio_util::codec::FramedParts;
+
+use super::mysql_codec::ClientPacket;
+use super::mysql_codec::ColumnType;
+use super::mysql_codec::MySQLCodec;
+use super::mysql_codec::ServerPacket;
+use super::mysql_codec::SCRAMBLE_SIZE;
+use super::Socket;
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::KVEngine;
+use crate::sql::engine::SQLEngine;
+use crate::sql::engine::SQLSession;
+use crate::sql::schema::data_value::DataValue;
+
+type Connection = Framed<Box<dyn Socket>, MySQLCodec>;
+
+/// the id of the next connection, it is sent in the handshake
+static NEXT_CONNECTION_ID: AtomicU32 = AtomicU32::new(1);
+
+/// a MySQL client session coupled to a SQL session
+pub struct MySQLSession {
+    session: SQLSession<KVEngine>,
+    connection_id: u32,
+}
+
+impl MySQLSession {
+    pub fn new(engine: KVEngine) -> Result<Self> {
+        Ok(Self {
+            session: engine.session()?,
+            connection_id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
+    pub async fn handle(self, socket: TcpStream, tls: Option<TlsAcceptor>) -> Result<()> {
+        let mut conn: Connection = Framed::new(Box::new(socket), MySQLCodec::new());
+        // the scramble of a password, printable and without NUL bytes
+        let mut scramble = [0u8; SCRAMBLE_SIZE];
+        OsRng.fill_bytes(&mut scramble);
+        scramble.iter_mut().for_each(|b| *b = *b % 94 + 33);
+        conn.send(ServerPacket::Handshake {
+            connection_id: self.connection_id,
+            scramble,
+            tls: tls.is_some(),
+        })
+        .await?;
+        if let Some(acceptor) = tls {
+            conn = match Self::encrypt(conn, acceptor).await? {
+                Some(conn) => conn,
+                None => return Ok(()),
+            };
+        }
+
+        while let Some(packet) = conn.try_next().await? {
+            debug!("mysql packet {}", packet.kind());
+            if packet == ClientPacket::Quit {
+                break;
+            }
+            if let Err(err) = self.packet(&mut conn, packet).await {
+                conn.feed(error_packet(&err)).await?;
+            }
+            conn.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// the TLS handshake requested by the SSLRequest of a client, None if
+    /// the client leaves or does not ask for TLS
+    async fn encrypt(conn: Connection, acceptor: TlsAcceptor) -> Result<Option<Connection>> {
+        // the client starts the TLS handshake right after its SSLRequest,
+        // the packet is read alone so that the handshake is not buffered
+        let FramedParts {
+            mut io, mut codec, ..
+        } = conn.into_parts();
+        let mut packet = BytesMut::from(&[0u8; 4][..]);
+        if io.read_exact(&mut packet).await.is_err() {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes([packet[0], packet[1], packet[2], 0]) as usize;
+        packet.resize(4 + len, 0);
+        io.read_exact(&mut packet[4..]).await?;
+        match codec.decode(&mut packet)? {
+            Some(ClientPacket::SslRequest) => {}
+            Some(ClientPacket::HandshakeResponse { .. }) => {
+                let err = Error::Unauthorized("TLS is required".into());
+                Framed::new(io, codec).send(error_packet(&err)).await?;
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        }
+        let stream = acceptor.accept(io).await?;
+        Ok(Some(Framed::new(Box::new(stream), codec)))
+    }
+
+    /// handles a packet, the replies are flushed by the caller
+    async fn packet(&self, conn: &mut Connection, packet: ClientPacket) -> Result<()> {
+        match packet {
+            ClientPacket::HandshakeResponse { user, database, .. } => {
+                info!("mysql connection {} user {}", self.connection_id, user);
+                if let Some(database) = database {
+                    info!(
+                        "mysql connection {} database {}",
+                        self.connection_id, database
+                    );
+                }
+                conn.feed(ServerPacket::Ok { affected_rows: 0 }).await
+            }
+            // there is a single database, any name selects it
+            ClientPacket::InitDb(_) | ClientPacket::Ping => {
+                conn.feed(ServerPacket::Ok { affected_rows: 0 }).await
+            }
+            ClientPacket::Query(query) => self.query(conn, &query).await,
+            ClientPacket::Command(command) => Err(Error::Value(format!(
+                "Unsupported command 0x{:02x}",
+                command
+            ))),
+            ClientPacket::SslRequest => Err(Error::Value("TLS is not enabled".into())),
+            ClientPacket::Quit => unreachable!(),
+        }
+    }
+
+    /// executes a query, a result set is sent as text rows
+    async fn query(&self, conn: &mut Connection, query: &str) -> Result<()> {
+        let (columns, rows) = match self.session.execute(query)? {
+            ResultSet::Query { columns, rows } => (
+                columns
+                    .into_iter()
+                    .map(|c| c.name.unwrap_or_else(|| "?column?".into()))
+                    .collect::<Vec<_>>(),
+                rows,
+            ),
+            ResultSet::Explain(plan) => (
+                vec!["plan".into()],
+                Box::new(
+                    plan.to_string()
+                        .lines()
+                        .map(|line| Ok(vec![DataValue::String(line.to_string())]))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ) as _,
+            ),
+            ResultSet::Create { count }
+            | ResultSet::Update { count }
+            | ResultSet::Delete { count }
+            | ResultSet::RefreshView { count, .. } => {
+                return conn
+                    .feed(ServerPacket::Ok {
+                        affected_rows: count,
+                    })
+                    .await
+            }
+            ResultSet::CreateTable { .. }
+            | ResultSet::DropTable { .. }
+            | ResultSet::CreateView { .. }
+            | ResultSet::DropView { .. }
+            | ResultSet::CreateUser { .. }
+            | ResultSet::DropUser { .. }
+            | ResultSet::Grant { .. }
+            | ResultSet::Revoke { .. }
+            | ResultSet::SetVariable { .. } => {
+                return conn.feed(ServerPacket::Ok { affected_rows: 0 }).await
+            }
+        };
+
+        // the types are taken from the first row, strings if there is none
+        let mut rows = rows.peekable();
+        conn.feed(ServerPacket::ColumnCount(columns.len())).await?;
+        for (i, name) in columns.into_iter().enumerate() {
+            let column_type = match rows.peek() {
+                Some(Ok(row)) => match row.get(i) {
+                    Some(DataValue::Boolean(_)) => ColumnType::Tiny,
+                    Some(DataValue::Integer(_)) => ColumnType::LongLong,
+                    Some(DataValue::Float(_)) => ColumnType::Double,
+                    _ => ColumnType::VarString,
+                },
+                _ => ColumnType::VarString,
+            };
+            conn.feed(ServerPacket::Column { name, column_type })
+                .await?;
+        }
+        conn.feed(ServerPacket::Eof).await?;
+        for row in rows {
+            // an error packet ends the result set in place of a row
+            conn.feed(ServerPacket::Row(row_text(row?))).await?;
+        }
+        conn.feed(ServerPacket::Eof).await
+    }
+}
+
+impl Drop for MySQLSession {
+    fn drop(&mut self) {
+        self.session.execute("ROLLBACK").ok();
+    }
+}
+
+/// the error packet of an error, with a MySQL error code and SQLSTATE
+fn error_packet(err: &Error) -> ServerPacket {
+    let (code, state) = match err {
+        Error::Parse(_) => (1064, "42000"),
+        Error::Serialization => (1213, "40001"),
+        Error::ReadOnly => (1792, "25006"),
+        Error::Unauthorized(_) => (1142, "42000"),
+        Error::Cancelled(_) => (1317, "70100"),
+        Error::Config(_) | Error::Value(_) | Error::Internal(_) => (1105, "HY000"),
+    };
+    ServerPacket::Err {
+        code,
+        state,
+        message: err.to_string(),
+    }
+}
+
+/// the text format of the values of a row
+fn row_text(row: Vec<DataValue>) -> Vec<Option<String>> {
+    row.into_iter()
+        .map(|value| match value {
+            DataValue::Null => None,
+            DataValue::Boolean(b) => Some(if b { "1" } else { "0" }.into()),
+            DataValue::Integer(i) => Some(i.to_string()),
+            DataValue::Float(f) => Some(f.to_string()),
+            DataValue::String(s) => Some(s),
+        })
+        .collect()
+}
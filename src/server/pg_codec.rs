@@ -0,0 +1,410 @@
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+use tokio_util::codec::Encoder;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// the version 3.0 of the protocol, the only one spoken
+const PROTOCOL_VERSION: i32 = 196608;
+const SSL_REQUEST: i32 = 80877103;
+const GSSENC_REQUEST: i32 = 80877104;
+const CANCEL_REQUEST: i32 = 80877102;
+
+/// a message larger than this is refused, it is a broken or hostile client
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// the type oids of the result columns
+pub const BOOL_OID: u32 = 16;
+pub const INT8_OID: u32 = 20;
+pub const TEXT_OID: u32 = 25;
+pub const FLOAT8_OID: u32 = 701;
+
+/// a message sent by a PostgreSQL client
+#[derive(Debug, PartialEq)]
+pub enum FrontendMessage {
+    /// the first message of a connection, the user, database and settings
+    Startup(Vec<(String, String)>),
+    /// the client asks for TLS (SSLRequest) or GSSAPI encryption before the
+    /// startup
+    EncryptionRequest {
+        tls: bool,
+    },
+    CancelRequest {
+        process_id: i32,
+        secret_key: i32,
+    },
+    Query(String),
+    Parse {
+        name: String,
+        query: String,
+        param_types: Vec<u32>,
+    },
+    Bind {
+        portal: String,
+        statement: String,
+        param_formats: Vec<i16>,
+        params: Vec<Option<Vec<u8>>>,
+        result_formats: Vec<i16>,
+    },
+    /// describe a prepared statement (S) or a portal (P)
+    Describe {
+        kind: u8,
+        name: String,
+    },
+    Execute {
+        portal: String,
+        max_rows: i32,
+    },
+    /// close a prepared statement (S) or a portal (P)
+    Close {
+        kind: u8,
+        name: String,
+    },
+    Sync,
+    Flush,
+    Terminate,
+    /// a password, or a SASL response, the session knows which one it
+    /// waits for
+    Password(Vec<u8>),
+}
+
+/// the SASLInitialResponse in a Password message, the mechanism and the
+/// client-first-message
+pub fn sasl_initial_response(data: &[u8]) -> Result<(String, Vec<u8>)> {
+    let mut buf = BytesMut::from(data);
+    let mechanism = get_cstr(&mut buf)?;
+    let len = get_i32(&mut buf)?;
+    if len < 0 || len as usize != buf.remaining() {
+        return Err(Error::Parse("Invalid SASL response length".into()));
+    }
+    Ok((mechanism, buf.to_vec()))
+}
+
+/// a column of a row description
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescription {
+    pub name: String,
+    pub type_oid: u32,
+}
+
+/// a message sent to a PostgreSQL client
+#[derive(Debug, PartialEq)]
+pub enum BackendMessage {
+    AuthenticationOk,
+    /// the SASL mechanisms the client may authenticate with
+    AuthenticationSasl(Vec<String>),
+    /// a SASL challenge, the server-first-message of SCRAM
+    AuthenticationSaslContinue(Vec<u8>),
+    /// the SASL outcome, the server-final-message of SCRAM
+    AuthenticationSaslFinal(Vec<u8>),
+    ParameterStatus(String, String),
+    /// the transaction status: idle (I), in a transaction (T) or in a failed
+    /// transaction (E)
+    ReadyForQuery(u8),
+    RowDescription(Vec<FieldDescription>),
+    /// the values of a row in text format, None is NULL
+    DataRow(Vec<Option<String>>),
+    CommandComplete(String),
+    EmptyQueryResponse,
+    ErrorResponse {
+        code: &'static str,
+        message: String,
+    },
+    ParseComplete,
+    BindComplete,
+    CloseComplete,
+    NoData,
+    ParameterDescription(Vec<u32>),
+    PortalSuspended,
+    /// the single byte answer refusing an encryption request
+    EncryptionRefused,
+    /// the single byte answer accepting an SSLRequest, the TLS handshake
+    /// follows
+    EncryptionAccepted,
+}
+
+/// frames the messages of the PostgreSQL v3 protocol
+pub struct PGCodec {
+    /// true until the startup message, which has no type byte
+    startup: bool,
+}
+
+impl PGCodec {
+    pub fn new() -> Self {
+        Self { startup: true }
+    }
+}
+
+impl Decoder for PGCodec {
+    type Item = FrontendMessage;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<FrontendMessage>> {
+        let header = if self.startup { 4 } else { 5 };
+        if src.len() < header {
+            return Ok(None);
+        }
+        let len = i32::from_be_bytes(src[header - 4..header].try_into()?);
+        if len < 4 || len as usize > MAX_MESSAGE_SIZE {
+            return Err(Error::Parse(format!("Invalid message length {}", len)));
+        }
+        let size = header - 4 + len as usize;
+        if src.len() < size {
+            src.reserve(size - src.len());
+            return Ok(None);
+        }
+
+        let mut frame = src.split_to(size);
+        let tag = if self.startup { 0 } else { frame.get_u8() };
+        frame.advance(4);
+        let message = match tag {
+            0 => match get_i32(&mut frame)? {
+                PROTOCOL_VERSION => {
+                    let mut params = Vec::new();
+                    loop {
+                        let name = get_cstr(&mut frame)?;
+                        if name.is_empty() {
+                            break;
+                        }
+                        params.push((name, get_cstr(&mut frame)?));
+                    }
+                    self.startup = false;
+                    FrontendMessage::Startup(params)
+                }
+                SSL_REQUEST => FrontendMessage::EncryptionRequest { tls: true },
+                GSSENC_REQUEST => FrontendMessage::EncryptionRequest { tls: false },
+                CANCEL_REQUEST => FrontendMessage::CancelRequest {
+                    process_id: get_i32(&mut frame)?,
+                    secret_key: get_i32(&mut frame)?,
+                },
+                version => {
+                    return Err(Error::Parse(format!(
+                        "Unsupported protocol version {}.{}",
+                        version >> 16,
+                        version & 0xffff
+                    )))
+                }
+            },
+            b'Q' => FrontendMessage::Query(get_cstr(&mut frame)?),
+            b'P' => FrontendMessage::Parse {
+                name: get_cstr(&mut frame)?,
+                query: get_cstr(&mut frame)?,
+                param_types: (0..get_u16(&mut frame)?)
+                    .map(|_| Ok(get_i32(&mut frame)? as u32))
+                    .collect::<Result<_>>()?,
+            },
+            b'B' => FrontendMessage::Bind {
+                portal: get_cstr(&mut frame)?,
+                statement: get_cstr(&mut frame)?,
+                param_formats: get_i16_array(&mut frame)?,
+                params: (0..get_u16(&mut frame)?)
+                    .map(|_| match get_i32(&mut frame)? {
+                        -1 => Ok(None),
+                        len if len < 0 || len as usize > frame.remaining() => {
+                            Err(Error::Parse("Invalid parameter length".into()))
+                        }
+                        len => Ok(Some(frame.split_to(len as usize).to_vec())),
+                    })
+                    .collect::<Result<_>>()?,
+                result_formats: get_i16_array(&mut frame)?,
+            },
+            b'D' => FrontendMessage::Describe {
+                kind: get_u8(&mut frame)?,
+                name: get_cstr(&mut frame)?,
+            },
+            b'E' => FrontendMessage::Execute {
+                portal: get_cstr(&mut frame)?,
+                max_rows: get_i32(&mut frame)?,
+            },
+            b'C' => FrontendMessage::Close {
+                kind: get_u8(&mut frame)?,
+                name: get_cstr(&mut frame)?,
+            },
+            b'S' => FrontendMessage::Sync,
+            b'H' => FrontendMessage::Flush,
+            b'X' => FrontendMessage::Terminate,
+            b'p' => FrontendMessage::Password(frame.to_vec()),
+            tag => {
+                return Err(Error::Parse(format!(
+                    "Unknown message type {}",
+                    tag as char
+                )))
+            }
+        };
+        Ok(Some(message))
+    }
+}
+
+impl Encoder<BackendMessage> for PGCodec {
+    type Error = Error;
+
+    fn encode(&mut self, message: BackendMessage, dst: &mut BytesMut) -> Result<()> {
+        let tag = match &message {
+            BackendMessage::EncryptionRefused => {
+                dst.put_u8(b'N');
+                return Ok(());
+            }
+            BackendMessage::EncryptionAccepted => {
+                dst.put_u8(b'S');
+                return Ok(());
+            }
+            BackendMessage::AuthenticationOk
+            | BackendMessage::AuthenticationSasl(_)
+            | BackendMessage::AuthenticationSaslContinue(_)
+            | BackendMessage::AuthenticationSaslFinal(_) => b'R',
+            BackendMessage::ParameterStatus(..) => b'S',
+            BackendMessage::ReadyForQuery(_) => b'Z',
+            BackendMessage::RowDescription(_) => b'T',
+            BackendMessage::DataRow(_) => b'D',
+            BackendMessage::CommandComplete(_) => b'C',
+            BackendMessage::EmptyQueryResponse => b'I',
+            BackendMessage::ErrorResponse { .. } => b'E',
+            BackendMessage::ParseComplete => b'1',
+            BackendMessage::BindComplete => b'2',
+            BackendMessage::CloseComplete => b'3',
+            BackendMessage::NoData => b'n',
+            BackendMessage::ParameterDescription(_) => b't',
+            BackendMessage::PortalSuspended => b's',
+        };
+        dst.put_u8(tag);
+        // the length is patched once the body is written
+        let start = dst.len();
+        dst.put_i32(0);
+
+        match message {
+            BackendMessage::AuthenticationOk => dst.put_i32(0),
+            BackendMessage::AuthenticationSasl(mechanisms) => {
+                dst.put_i32(10);
+                for mechanism in mechanisms {
+                    put_cstr(dst, &mechanism);
+                }
+                dst.put_u8(0);
+            }
+            BackendMessage::AuthenticationSaslContinue(data) => {
+                dst.put_i32(11);
+                dst.put_slice(&data);
+            }
+            BackendMessage::AuthenticationSaslFinal(data) => {
+                dst.put_i32(12);
+                dst.put_slice(&data);
+            }
+            BackendMessage::ParameterStatus(name, value) => {
+                put_cstr(dst, &name);
+                put_cstr(dst, &value);
+            }
+            BackendMessage::ReadyForQuery(status) => dst.put_u8(status),
+            BackendMessage::RowDescription(fields) => {
+                dst.put_i16(fields.len() as i16);
+                for field in fields {
+                    put_cstr(dst, &field.name);
+                    // no table oid and column number
+                    dst.put_i32(0);
+                    dst.put_i16(0);
+                    dst.put_u32(field.type_oid);
+                    dst.put_i16(match field.type_oid {
+                        BOOL_OID => 1,
+                        INT8_OID | FLOAT8_OID => 8,
+                        _ => -1,
+                    });
+                    // no type modifier, text format
+                    dst.put_i32(-1);
+                    dst.put_i16(0);
+                }
+            }
+            BackendMessage::DataRow(values) => {
+                dst.put_i16(values.len() as i16);
+                for value in values {
+                    match value {
+                        Some(value) => {
+                            dst.put_i32(value.len() as i32);
+                            dst.put_slice(value.as_bytes());
+                        }
+                        None => dst.put_i32(-1),
+                    }
+                }
+            }
+            BackendMessage::CommandComplete(tag) => put_cstr(dst, &tag),
+            BackendMessage::ErrorResponse { code, message } => {
+                for (field, value) in [
+                    (b'S', "ERROR"),
+                    (b'V', "ERROR"),
+                    (b'C', code),
+                    (b'M', message.as_str()),
+                ] {
+                    dst.put_u8(field);
+                    put_cstr(dst, value);
+                }
+                dst.put_u8(0);
+            }
+            BackendMessage::ParameterDescription(types) => {
+                dst.put_i16(types.len() as i16);
+                for oid in types {
+                    dst.put_u32(oid);
+                }
+            }
+            BackendMessage::EmptyQueryResponse
+            | BackendMessage::ParseComplete
+            | BackendMessage::BindComplete
+            | BackendMessage::CloseComplete
+            | BackendMessage::NoData
+            | BackendMessage::PortalSuspended
+            | BackendMessage::EncryptionRefused
+            | BackendMessage::EncryptionAccepted => {}
+        }
+
+        let len = (dst.len() - start) as i32;
+        dst[start..start + 4].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+}
+
+fn get_u8(buf: &mut BytesMut) -> Result<u8> {
+    if buf.remaining() < 1 {
+        return Err(Error::Parse("Message too short".into()));
+    }
+    Ok(buf.get_u8())
+}
+
+fn get_i16(buf: &mut BytesMut) -> Result<i16> {
+    if buf.remaining() < 2 {
+        return Err(Error::Parse("Message too short".into()));
+    }
+    Ok(buf.get_i16())
+}
+
+/// a count of the items following it, an Int16 to be read unsigned
+fn get_u16(buf: &mut BytesMut) -> Result<u16> {
+    if buf.remaining() < 2 {
+        return Err(Error::Parse("Message too short".into()));
+    }
+    Ok(buf.get_u16())
+}
+
+fn get_i32(buf: &mut BytesMut) -> Result<i32> {
+    if buf.remaining() < 4 {
+        return Err(Error::Parse("Message too short".into()));
+    }
+    Ok(buf.get_i32())
+}
+
+fn get_i16_array(buf: &mut BytesMut) -> Result<Vec<i16>> {
+    (0..get_u16(buf)?).map(|_| get_i16(buf)).collect()
+}
+
+fn get_cstr(buf: &mut BytesMut) -> Result<String> {
+    let end = buf
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| Error::Parse("Unterminated string in message".into()))?;
+    let s = String::from_utf8(buf.split_to(end).to_vec())?;
+    buf.advance(1);
+    Ok(s)
+}
+
+fn put_cstr(buf: &mut BytesMut, s: &str) {
+    buf.put_slice(s.as_bytes());
+    buf.put_u8(0);
+}
@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use futures::sink::SinkExt as _;
+use log::info;
+use log::trace;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::StreamExt as _;
+use tokio_util::codec::Framed;
+
+use super::pg_codec::sasl_initial_response;
+use super::pg_codec::BackendMessage;
+use super::pg_codec::FieldDescription;
+use super::pg_codec::FrontendMessage;
+use super::pg_codec::PGCodec;
+use super::pg_codec::BOOL_OID;
+use super::pg_codec::FLOAT8_OID;
+use super::pg_codec::INT8_OID;
+use super::pg_codec::TEXT_OID;
+use super::sql_text;
+use super::Socket;
+use crate::common::result::DataRows;
+use crate::common::result::ResultSet;
+use crate::common::scram;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::engine::KVEngine;
+use crate::sql::engine::Prepared;
+use crate::sql::engine::SQLEngine;
+use crate::sql::engine::SQLSession;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::user::User;
+use crate::storage::mvcc::TransactionMode;
+
+type Connection = Framed<Box<dyn Socket>, PGCodec>;
+
+/// the settings reported to a client after the startup
+const PARAMETERS: [(&str, &str); 6] = [
+    ("server_version", "14.0"),
+    ("server_encoding", "UTF8"),
+    ("client_encoding", "UTF8"),
+    ("DateStyle", "ISO, MDY"),
+    ("integer_datetimes", "on"),
+    ("standard_conforming_strings", "on"),
+];
+
+/// the only SASL mechanism offered
+const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+/// the SCRAM-SHA-256 exchange of a client which has to authenticate
+enum Authentication {
+    /// waiting for the startup message
+    Startup,
+    /// AuthenticationSASL was sent for the user of the startup message
+    Sasl { name: String },
+    /// the server-first-message was sent
+    Continue {
+        name: String,
+        /// None for an unknown user, the exchange fails at the proof
+        user: Option<User>,
+        /// the nonces of the client and of the server
+        nonce: String,
+        /// the client-first-message-bare and the server-first-message, the
+        /// start of the message both sides sign
+        messages: String,
+    },
+}
+
+/// a statement prepared by Parse
+struct Statement {
+    prepared: Prepared,
+    /// the declared types of the parameters, 0 is unspecified
+    param_types: Vec<u32>,
+}
+
+/// a statement bound to its parameters by Bind, it is executed by the first
+/// Describe or Execute
+struct Portal {
+    prepared: Prepared,
+    params: Vec<DataValue>,
+    result: Option<Pending>,
+}
+
+/// the result of a statement, its rows are sent as the client fetches them
+struct Pending {
+    /// the columns, None if the statement returns no rows
+    fields: Option<Vec<FieldDescription>>,
+    rows: Peekable<DataRows>,
+    /// the command tag, the row count is added to SELECT
+    tag: String,
+}
+
+impl Pending {
+    fn new(result: ResultSet) -> Self {
+        let (fields, rows, tag): (_, DataRows, _) = match result {
+            ResultSet::Query { columns, rows } => {
+                let mut rows = rows.peekable();
+                // the types are taken from the first row, text if there is none
+                let fields = columns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, column)| FieldDescription {
+                        name: column.name.unwrap_or_else(|| "?column?".into()),
+                        type_oid: match rows.peek() {
+                            Some(Ok(row)) => match row.get(i) {
+                                Some(DataValue::Boolean(_)) => BOOL_OID,
+                                Some(DataValue::Integer(_)) => INT8_OID,
+                                Some(DataValue::Float(_)) => FLOAT8_OID,
+                                _ => TEXT_OID,
+                            },
+                            _ => TEXT_OID,
+                        },
+                    })
+                    .collect();
+                return Self {
+                    fields: Some(fields),
+                    rows,
+                    tag: "SELECT".into(),
+                };
+            }
+            ResultSet::Explain(plan) => (
+                Some(vec![FieldDescription {
+                    name: "QUERY PLAN".into(),
+                    type_oid: TEXT_OID,
+                }]),
+                Box::new(
+                    plan.to_string()
+                        .lines()
+                        .map(|line| Ok(vec![DataValue::String(line.to_string())]))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ),
+                "EXPLAIN".into(),
+            ),
+            result => (
+                None,
+                ResultSet::empty_rows(),
+                match result {
+                    ResultSet::Create { count } => format!("INSERT 0 {}", count),
+                    ResultSet::CreateTable { .. } => "CREATE TABLE".into(),
+                    ResultSet::DropTable { .. } => "DROP TABLE".into(),
+                    ResultSet::CreateView { .. } => "CREATE VIEW".into(),
+                    ResultSet::DropView { .. } => "DROP VIEW".into(),
+                    ResultSet::RefreshView { .. } => "REFRESH MATERIALIZED VIEW".into(),
+                    ResultSet::Update { count } => format!("UPDATE {}", count),
+                    ResultSet::Delete { count } => format!("DELETE {}", count),
+                    ResultSet::CreateUser { .. } => "CREATE ROLE".into(),
+                    ResultSet::DropUser { .. } => "DROP ROLE".into(),
+                    ResultSet::Grant { .. } => "GRANT".into(),
+                    ResultSet::Revoke { .. } => "REVOKE".into(),
+                    ResultSet::SetVariable { .. } => "SET".into(),
+                    ResultSet::Query { .. } | ResultSet::Explain(_) => unreachable!(),
+                },
+            ),
+        };
+        Self {
+            fields,
+            rows: rows.peekable(),
+            tag,
+        }
+    }
+
+    /// the row description, or NoData for a statement without rows
+    fn describe(&self) -> BackendMessage {
+        match &self.fields {
+            Some(fields) => BackendMessage::RowDescription(fields.clone()),
+            None => BackendMessage::NoData,
+        }
+    }
+
+    fn complete(&self, count: usize) -> BackendMessage {
+        BackendMessage::CommandComplete(match self.fields {
+            Some(_) if self.tag == "SELECT" => format!("SELECT {}", count),
+            _ => self.tag.clone(),
+        })
+    }
+
+    /// sends at most max_rows rows, all of them if it is 0. the portal is
+    /// suspended if rows are left, the client fetches them with a later
+    /// Execute
+    async fn send(&mut self, conn: &mut Connection, max_rows: usize) -> Result<()> {
+        let mut count = 0;
+        while max_rows == 0 || count < max_rows {
+            match self.rows.next().transpose()? {
+                Some(row) => conn.feed(BackendMessage::DataRow(row_text(row))).await?,
+                None => {
+                    return conn.feed(self.complete(count)).await;
+                }
+            }
+            count += 1;
+        }
+        if self.rows.peek().is_none() {
+            return conn.feed(self.complete(count)).await;
+        }
+        conn.feed(BackendMessage::PortalSuspended).await
+    }
+}
+
+/// a PostgreSQL client session coupled to a SQL session
+pub struct PGSession {
+    session: SQLSession<KVEngine>,
+    statements: HashMap<String, Statement>,
+    portals: HashMap<String, Portal>,
+    /// an extended query failed, its messages are skipped until Sync
+    failed: bool,
+    /// the exchange of a client which has not authenticated yet, None once
+    /// it has or if no authentication is required
+    authentication: Option<Authentication>,
+}
+
+impl PGSession {
+    pub fn new(engine: KVEngine, authentication: bool) -> Result<Self> {
+        Ok(Self {
+            session: engine.session()?,
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            failed: false,
+            authentication: authentication.then_some(Authentication::Startup),
+        })
+    }
+
+    pub async fn handle(self, socket: TcpStream, tls: Option<TlsAcceptor>) -> Result<()> {
+        let conn = match tls {
+            Some(acceptor) => match Self::encrypt(socket, acceptor).await? {
+                Some(conn) => conn,
+                None => return Ok(()),
+            },
+            None => Framed::new(Box::new(socket) as Box<dyn Socket>, PGCodec::new()),
+        };
+        self.serve(conn).await
+    }
+
+    /// the TLS handshake requested by the SSLRequest of a client, None if
+    /// the client leaves or does not ask for TLS
+    async fn encrypt(socket: TcpStream, acceptor: TlsAcceptor) -> Result<Option<Connection>> {
+        let mut conn = Framed::new(socket, PGCodec::new());
+        loop {
+            match conn.try_next().await? {
+                Some(FrontendMessage::EncryptionRequest { tls: true }) => break,
+                // the client may ask for TLS after GSSAPI encryption is refused
+                Some(FrontendMessage::EncryptionRequest { tls: false }) => {
+                    conn.send(BackendMessage::EncryptionRefused).await?
+                }
+                Some(FrontendMessage::Startup(_)) => {
+                    let err = Error::Unauthorized("TLS is required".into());
+                    conn.send(error_response(&err)).await?;
+                    return Ok(None);
+                }
+                _ => return Ok(None),
+            }
+        }
+        conn.send(BackendMessage::EncryptionAccepted).await?;
+        // the client waits for the answer, it sent nothing else in plaintext
+        let parts = conn.into_parts();
+        if !parts.read_buf.is_empty() {
+            return Err(Error::Parse(
+                "Data received before the TLS handshake".into(),
+            ));
+        }
+        let stream = acceptor.accept(parts.io).await?;
+        Ok(Some(Framed::new(Box::new(stream), parts.codec)))
+    }
+
+    /// serve the messages of a connection, its encryption is settled
+    async fn serve(mut self, mut conn: Connection) -> Result<()> {
+        while let Some(message) = conn.try_next().await? {
+            // the password of a client is never logged, nor the statements
+            // and parameters which may carry one
+            match &message {
+                FrontendMessage::Password(_) => trace!("pg message Password"),
+                FrontendMessage::Query(_) => trace!("pg message Query"),
+                FrontendMessage::Parse { name, .. } => trace!("pg message Parse {:?}", name),
+                FrontendMessage::Bind {
+                    portal, statement, ..
+                } => trace!("pg message Bind {:?} {:?}", portal, statement),
+                message => trace!("pg message {:?}", message),
+            }
+            if let Some(authentication) = self.authentication.take() {
+                match message {
+                    FrontendMessage::Terminate | FrontendMessage::CancelRequest { .. } => break,
+                    FrontendMessage::EncryptionRequest { .. } => {
+                        self.authentication = Some(authentication);
+                        conn.send(BackendMessage::EncryptionRefused).await?;
+                    }
+                    message => {
+                        // a failed authentication closes the connection
+                        if let Err(err) =
+                            self.authenticate(&mut conn, authentication, message).await
+                        {
+                            conn.send(error_response(&err)).await?;
+                            break;
+                        }
+                        conn.flush().await?;
+                    }
+                }
+                continue;
+            }
+            match message {
+                FrontendMessage::Terminate | FrontendMessage::CancelRequest { .. } => break,
+                FrontendMessage::Sync => {
+                    self.failed = false;
+                    self.portals.clear();
+                    conn.send(self.ready()).await?;
+                }
+                FrontendMessage::Flush => conn.flush().await?,
+                _ if self.failed => {}
+                message => {
+                    let simple = matches!(
+                        message,
+                        FrontendMessage::Query(_)
+                            | FrontendMessage::Startup(_)
+                            | FrontendMessage::EncryptionRequest { .. }
+                    );
+                    if let Err(err) = self.message(&mut conn, message).await {
+                        conn.feed(error_response(&err)).await?;
+                        if simple {
+                            conn.feed(self.ready()).await?;
+                        } else {
+                            self.failed = true;
+                        }
+                    }
+                    if simple {
+                        conn.flush().await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the end of the startup, the session is ready for queries
+    async fn start(conn: &mut Connection, ready: BackendMessage) -> Result<()> {
+        conn.feed(BackendMessage::AuthenticationOk).await?;
+        for (name, value) in PARAMETERS {
+            conn.feed(BackendMessage::ParameterStatus(name.into(), value.into()))
+                .await?;
+        }
+        conn.feed(ready).await
+    }
+
+    /// handles a message of the SCRAM-SHA-256 exchange (RFC 5802), the user
+    /// is the one of the startup message
+    async fn authenticate(
+        &mut self,
+        conn: &mut Connection,
+        authentication: Authentication,
+        message: FrontendMessage,
+    ) -> Result<()> {
+        match (authentication, message) {
+            (Authentication::Startup, FrontendMessage::Startup(params)) => {
+                info!("pg startup {:?}", params);
+                let name = params
+                    .into_iter()
+                    .find(|(name, _)| name == "user")
+                    .map(|(_, user)| user)
+                    .ok_or_else(|| Error::Unauthorized("The startup has no user".into()))?;
+                conn.feed(BackendMessage::AuthenticationSasl(vec![
+                    SCRAM_SHA_256.into()
+                ]))
+                .await?;
+                self.authentication = Some(Authentication::Sasl { name });
+            }
+            (Authentication::Sasl { name }, FrontendMessage::Password(data)) => {
+                let (mechanism, client_first) = sasl_initial_response(&data)?;
+                if mechanism != SCRAM_SHA_256 {
+                    return Err(Error::Unauthorized(format!(
+                        "Unsupported SASL mechanism {}",
+                        mechanism
+                    )));
+                }
+                // no channel binding, the gs2 header is n,, or y,,
+                let client_first = String::from_utf8(client_first)?;
+                let bare = client_first
+                    .strip_prefix("n,,")
+                    .or_else(|| client_first.strip_prefix("y,,"))
+                    .ok_or_else(|| {
+                        Error::Unauthorized("Channel binding is not supported".into())
+                    })?;
+                let user = self
+                    .session
+                    .with_txn(TransactionMode::ReadOnly, |txn| txn.read_user(&name))?;
+                // an unknown user is challenged like any other
+                let (salt, iterations) = match &user {
+                    Some(user) => (user.salt.clone(), user.iterations),
+                    None => (scram::unknown_user_salt(&name), scram::ITERATIONS),
+                };
+                let nonce = format!("{}{}", attribute(bare, 'r')?, BASE64.encode(scram::nonce()));
+                let server_first =
+                    format!("r={},s={},i={}", nonce, BASE64.encode(salt), iterations);
+                conn.feed(BackendMessage::AuthenticationSaslContinue(
+                    server_first.clone().into_bytes(),
+                ))
+                .await?;
+                self.authentication = Some(Authentication::Continue {
+                    name,
+                    user,
+                    nonce,
+                    messages: format!("{},{}", bare, server_first),
+                });
+            }
+            (
+                Authentication::Continue {
+                    name,
+                    user,
+                    nonce,
+                    messages,
+                },
+                FrontendMessage::Password(data),
+            ) => {
+                let client_final = String::from_utf8(data)?;
+                let (without_proof, proof) = client_final
+                    .rsplit_once(",p=")
+                    .ok_or_else(|| Error::Unauthorized("The SCRAM proof is missing".into()))?;
+                let proof = BASE64
+                    .decode(proof)
+                    .map_err(|_| Error::Unauthorized("Invalid SCRAM proof".into()))?;
+                let auth_message = format!("{},{}", messages, without_proof);
+                match user {
+                    Some(user)
+                        if attribute(without_proof, 'r')? == nonce
+                            && scram::verify_client_proof(
+                                &user.stored_key,
+                                auth_message.as_bytes(),
+                                &proof,
+                            ) =>
+                    {
+                        let signature =
+                            scram::server_signature(&user.server_key, auth_message.as_bytes());
+                        conn.feed(BackendMessage::AuthenticationSaslFinal(
+                            format!("v={}", BASE64.encode(signature)).into_bytes(),
+                        ))
+                        .await?;
+                        self.session.user = Some(user.name);
+                        Self::start(conn, self.ready()).await?;
+                    }
+                    _ => {
+                        return Err(Error::Unauthorized(format!(
+                            "Authentication failed for user {}",
+                            name
+                        )))
+                    }
+                }
+            }
+            _ => return Err(Error::Unauthorized("Authentication required".into())),
+        }
+        Ok(())
+    }
+
+    /// the ReadyForQuery of the session transaction status
+    fn ready(&self) -> BackendMessage {
+        BackendMessage::ReadyForQuery(if self.session.txn.is_some() {
+            b'T'
+        } else {
+            b'I'
+        })
+    }
+
+    /// handles a message, the replies are flushed by the caller
+    async fn message(&mut self, conn: &mut Connection, message: FrontendMessage) -> Result<()> {
+        match message {
+            FrontendMessage::EncryptionRequest { .. } => {
+                conn.feed(BackendMessage::EncryptionRefused).await?
+            }
+            FrontendMessage::Startup(params) => {
+                info!("pg startup {:?}", params);
+                Self::start(conn, self.ready()).await?;
+            }
+            FrontendMessage::Query(query) => {
+                let statements = sql_text::split_statements(&query);
+                if statements.is_empty() {
+                    conn.feed(BackendMessage::EmptyQueryResponse).await?;
+                }
+                for statement in statements {
+                    let mut pending = Pending::new(self.session.execute(statement)?);
+                    if pending.fields.is_some() {
+                        conn.feed(pending.describe()).await?;
+                    }
+                    pending.send(conn, 0).await?;
+                }
+                conn.feed(self.ready()).await?;
+            }
+            FrontendMessage::Parse {
+                name,
+                query,
+                param_types,
+            } => {
+                let prepared = self.session.prepare(&query)?;
+                if param_types.len() > prepared.parameters().len() {
+                    return Err(Error::Value(format!(
+                        "Parse declares {} parameter types, the query has {} parameters",
+                        param_types.len(),
+                        prepared.parameters().len()
+                    )));
+                }
+                self.statements.insert(
+                    name,
+                    Statement {
+                        prepared,
+                        param_types,
+                    },
+                );
+                conn.feed(BackendMessage::ParseComplete).await?;
+            }
+            FrontendMessage::Bind {
+                portal,
+                statement,
+                param_formats,
+                params,
+                result_formats,
+            } => {
+                let statement = self.statement(&statement)?;
+                // one format for all the parameters, or one for each
+                if param_formats.len() > 1 && param_formats.len() != params.len() {
+                    return Err(Error::Value(format!(
+                        "Bind has {} parameter formats for {} parameters",
+                        param_formats.len(),
+                        params.len()
+                    )));
+                }
+                if param_formats.iter().any(|f| *f != 0) {
+                    return Err(Error::Value("Binary parameters are not supported".into()));
+                }
+                if result_formats.iter().any(|f| *f != 0) {
+                    return Err(Error::Value("Binary results are not supported".into()));
+                }
+                let params = bind_parameters(statement, &params)?;
+                let prepared = statement.prepared.clone();
+                self.portals.insert(
+                    portal,
+                    Portal {
+                        prepared,
+                        params,
+                        result: None,
+                    },
+                );
+                conn.feed(BackendMessage::BindComplete).await?;
+            }
+            FrontendMessage::Describe { kind: b'S', name } => {
+                let statement = self.statements.get_mut(&name).ok_or_else(|| {
+                    Error::Value(format!("Prepared statement {} does not exist", name))
+                })?;
+                // the columns come from the plan, the statement is not run
+                let columns = self.session.describe(&mut statement.prepared)?;
+                let types = statement
+                    .prepared
+                    .parameters()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, datatype)| match statement.param_types.get(i) {
+                        Some(oid) if *oid != 0 => *oid,
+                        _ => datatype.as_ref().map_or(TEXT_OID, type_oid),
+                    })
+                    .collect();
+                let description = match columns {
+                    Some(columns) => BackendMessage::RowDescription(
+                        columns
+                            .into_iter()
+                            .map(|(name, datatype)| FieldDescription {
+                                name: name.unwrap_or_else(|| "?column?".into()),
+                                type_oid: datatype.as_ref().map_or(TEXT_OID, type_oid),
+                            })
+                            .collect(),
+                    ),
+                    None => BackendMessage::NoData,
+                };
+                conn.feed(BackendMessage::ParameterDescription(types))
+                    .await?;
+                conn.feed(description).await?;
+            }
+            FrontendMessage::Describe { kind: b'P', name } => {
+                let message = self.portal(&name)?.describe();
+                conn.feed(message).await?;
+            }
+            FrontendMessage::Execute { portal, max_rows } => {
+                let pending = self.portal(&portal)?;
+                pending.send(conn, max_rows.max(0) as usize).await?;
+            }
+            FrontendMessage::Close { kind, name } => {
+                match kind {
+                    b'S' => self.statements.remove(&name).map(|_| ()),
+                    _ => self.portals.remove(&name).map(|_| ()),
+                };
+                conn.feed(BackendMessage::CloseComplete).await?;
+            }
+            FrontendMessage::Describe { kind, .. } => {
+                return Err(Error::Parse(format!(
+                    "Invalid describe kind {}",
+                    kind as char
+                )))
+            }
+            FrontendMessage::Password(_) => {
+                return Err(Error::Value("No password was requested".into()))
+            }
+            FrontendMessage::Sync
+            | FrontendMessage::Flush
+            | FrontendMessage::Terminate
+            | FrontendMessage::CancelRequest { .. } => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn statement(&self, name: &str) -> Result<&Statement> {
+        self.statements
+            .get(name)
+            .ok_or_else(|| Error::Value(format!("Prepared statement {} does not exist", name)))
+    }
+
+    /// the result of a portal, the portal is executed the first time
+    fn portal(&mut self, name: &str) -> Result<&mut Pending> {
+        let portal = self
+            .portals
+            .get_mut(name)
+            .ok_or_else(|| Error::Value(format!("Portal {} does not exist", name)))?;
+        if portal.result.is_none() {
+            let result = self
+                .session
+                .execute_prepared(&mut portal.prepared, &portal.params)?;
+            portal.result = Some(Pending::new(result));
+        }
+        Ok(portal.result.as_mut().unwrap())
+    }
+}
+
+impl Drop for PGSession {
+    fn drop(&mut self) {
+        self.session.execute("ROLLBACK").ok();
+    }
+}
+
+/// the SQLSTATE code of an error
+fn sqlstate(err: &Error) -> &'static str {
+    match err {
+        Error::Config(_) => "F0000",
+        Error::Value(_) => "22000",
+        Error::Internal(_) => "XX000",
+        Error::Parse(_) => "42601",
+        Error::Serialization => "40001",
+        Error::ReadOnly => "25006",
+        Error::Unauthorized(_) => "42501",
+        Error::Cancelled(_) => "57014",
+    }
+}
+
+/// the value of an attribute of a SCRAM message, e.g. r of r=nonce
+fn attribute(message: &str, name: char) -> Result<&str> {
+    message
+        .split(',')
+        .find_map(|attribute| attribute.strip_prefix(name)?.strip_prefix('='))
+        .ok_or_else(|| Error::Unauthorized(format!("The SCRAM message has no {} attribute", name)))
+}
+
+fn error_response(err: &Error) -> BackendMessage {
+    BackendMessage::ErrorResponse {
+        code: sqlstate(err),
+        message: err.to_string(),
+    }
+}
+
+/// the text format of the values of a row
+fn row_text(row: Vec<DataValue>) -> Vec<Option<String>> {
+    row.into_iter()
+        .map(|value| match value {
+            DataValue::Null => None,
+            DataValue::Boolean(b) => Some(if b { "t" } else { "f" }.into()),
+            DataValue::Integer(i) => Some(i.to_string()),
+            DataValue::Float(f) => Some(f.to_string()),
+            DataValue::String(s) => Some(s),
+        })
+        .collect()
+}
+
+/// the type of a column or a parameter
+fn type_oid(datatype: &DataType) -> u32 {
+    match datatype {
+        DataType::Boolean => BOOL_OID,
+        DataType::Integer => INT8_OID,
+        DataType::Float => FLOAT8_OID,
+        DataType::String => TEXT_OID,
+    }
+}
+
+/// the values of the parameters of a statement, from their text format. a
+/// parameter takes its declared type, or the type the statement gives it,
+/// or is a string
+fn bind_parameters(statement: &Statement, values: &[Option<Vec<u8>>]) -> Result<Vec<DataValue>> {
+    let types = statement.prepared.parameters();
+    if values.len() != types.len() {
+        return Err(Error::Value(format!(
+            "Bind supplies {} parameters, the statement requires {}",
+            values.len(),
+            types.len()
+        )));
+    }
+    let mut params = Vec::with_capacity(values.len());
+    for (i, value) in values.iter().enumerate() {
+        let value = match value {
+            Some(value) => String::from_utf8(value.clone())?,
+            None => {
+                params.push(DataValue::Null);
+                continue;
+            }
+        };
+        let datatype = match statement.param_types.get(i).copied().unwrap_or(0) {
+            0 => types.get(i).cloned().flatten(),
+            BOOL_OID => Some(DataType::Boolean),
+            // int2, int4 and int8
+            21 | 23 | INT8_OID => Some(DataType::Integer),
+            // float4, float8 and numeric
+            700 | FLOAT8_OID | 1700 => Some(DataType::Float),
+            _ => Some(DataType::String),
+        };
+        params.push(match datatype {
+            Some(DataType::Boolean) => match value.trim().to_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" | "on" | "1" => DataValue::Boolean(true),
+                "f" | "false" | "n" | "no" | "off" | "0" => DataValue::Boolean(false),
+                _ => return Err(Error::Value(format!("Invalid boolean {}", value))),
+            },
+            Some(DataType::Integer) => DataValue::Integer(value.trim().parse()?),
+            Some(DataType::Float) => DataValue::Float(value.trim().parse()?),
+            Some(DataType::String) | None => DataValue::String(value),
+        });
+    }
+    Ok(params)
+}
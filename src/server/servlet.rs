@@ -1,20 +1,141 @@
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
+use crate::common::result::DataColumns;
 use crate::common::result::DataRow;
 use crate::common::result::ResultSet;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
+use crate::storage::mvcc;
+use crate::storage::mvcc::TransactionMode;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
+    /// start a SCRAM-SHA-256 exchange for a user, with the nonce of the
+    /// client
+    Authenticate {
+        user: String,
+        nonce: Vec<u8>,
+    },
+    /// the proof of the client that it knows the password of the user
+    Proof(Vec<u8>),
     Execute(String),
+    /// plan a statement for the session, its $n or ? placeholders are bound
+    /// by each execution
+    Prepare(String),
+    /// execute a prepared statement with the values of its parameters
+    ExecutePrepared(u64, Vec<DataValue>),
+    /// drop a prepared statement
+    Deallocate(u64),
+    /// fetch at most the given number of rows of a cursor
+    Fetch(u64, usize),
+    /// close a cursor before its last row
+    CloseCursor(u64),
     GetTable(String),
     ListTables,
     Status,
+    /// the id and the secret key cancelling the statements of the session
+    CancelKey,
+    /// cancel the statement running on another session, sent on a
+    /// connection of its own
+    Cancel {
+        id: u64,
+        key: u64,
+    },
+}
+
+impl Request {
+    /// the name of the request, which is logged: its statements, parameters
+    /// and proofs may carry passwords
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Authenticate { .. } => "Authenticate",
+            Self::Proof(_) => "Proof",
+            Self::Execute(_) => "Execute",
+            Self::Prepare(_) => "Prepare",
+            Self::ExecutePrepared(..) => "ExecutePrepared",
+            Self::Deallocate(_) => "Deallocate",
+            Self::Fetch(..) => "Fetch",
+            Self::CloseCursor(_) => "CloseCursor",
+            Self::GetTable(_) => "GetTable",
+            Self::ListTables => "ListTables",
+            Self::Status => "Status",
+            Self::CancelKey => "CancelKey",
+            Self::Cancel { .. } => "Cancel",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
+    /// the nonce of the server, with the salt and the iterations of the
+    /// password of the user
+    Challenge {
+        nonce: Vec<u8>,
+        salt: Vec<u8>,
+        iterations: u32,
+    },
+    /// the signature of the server, proving it knows the password
+    Authenticated {
+        signature: Vec<u8>,
+    },
+    /// the result of a statement, other than a query
     Execute(ResultSet),
-    Row(Option<DataRow>),
-    ListTable(Vec<String>),
+    /// the columns of a query, its rows are fetched from the cursor
+    Cursor {
+        id: u64,
+        columns: DataColumns,
+    },
+    /// rows of a cursor, done after its last row
+    Rows {
+        rows: Vec<DataRow>,
+        done: bool,
+    },
+    CloseCursor,
+    /// the handle of a prepared statement and the types of its parameters,
+    /// None where any type is taken
+    Prepare {
+        id: u64,
+        parameters: Vec<Option<DataType>>,
+    },
+    Deallocate,
+    /// the tables and views of the database, with their kind
+    ListTable(Vec<(String, TableKind)>),
+    Table(Table),
+    Status(Status),
+    CancelKey {
+        id: u64,
+        key: u64,
+    },
+    Cancel,
+}
+
+/// the status of a server
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub id: String,
+    /// seconds since the server started
+    pub uptime: u64,
+    /// the open client connections, of all protocols
+    pub connections: u64,
+    /// the connections accepted since the server started
+    pub connections_accepted: u64,
+    pub sessions: Vec<SessionStatus>,
+    /// the transactions and the storage engine
+    pub mvcc: mvcc::Status,
+}
+
+/// the status of a client connection
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionStatus {
+    pub id: u64,
+    /// sql, postgresql, mysql or http
+    pub protocol: String,
+    pub peer: String,
+    /// seconds since the client connected
+    pub connected: u64,
+    /// the transaction held by the session, if any
+    pub txn: Option<(u64, TransactionMode)>,
 }
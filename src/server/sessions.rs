@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+
+use super::servlet::SessionStatus;
+use super::servlet::Status;
+use crate::error::Error;
+use crate::error::Result;
+use crate::storage::mvcc::TransactionMode;
+use crate::storage::mvcc::MVCC;
+
+/// the client sessions of a server, for its status
+#[derive(Clone)]
+pub struct Sessions {
+    id: String,
+    started: Instant,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    /// the connections accepted since the server started
+    accepted: u64,
+    sessions: BTreeMap<u64, (SessionStatus, Instant)>,
+    /// the secret key and the cancel flag of the sessions whose statements
+    /// can be cancelled
+    cancels: HashMap<u64, (u64, Arc<AtomicBool>)>,
+}
+
+impl Sessions {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            started: Instant::now(),
+            inner: Arc::new(Mutex::new(Inner {
+                accepted: 0,
+                sessions: BTreeMap::new(),
+                cancels: HashMap::new(),
+            })),
+        }
+    }
+
+    /// registers a connection, it is removed when the handle is dropped
+    pub fn register(&self, protocol: &str, peer: SocketAddr) -> Result<SessionHandle> {
+        let mut inner = self.inner.lock()?;
+        inner.accepted += 1;
+        let id = inner.accepted;
+        let status = SessionStatus {
+            id,
+            protocol: protocol.to_string(),
+            peer: peer.to_string(),
+            connected: 0,
+            txn: None,
+        };
+        inner.sessions.insert(id, (status, Instant::now()));
+        Ok(SessionHandle {
+            id,
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// cancels the statement running on a session, from another connection
+    /// knowing the key of the session
+    pub fn cancel(&self, id: u64, key: u64) -> Result<()> {
+        match self.inner.lock()?.cancels.get(&id) {
+            Some((secret, cancel)) if *secret == key => {
+                cancel.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            _ => Err(Error::Value("Invalid cancel key".into())),
+        }
+    }
+
+    /// the status of the server and of its storage
+    pub fn status(&self, mvcc: &MVCC) -> Result<Status> {
+        let mvcc = mvcc.status()?;
+        let inner = self.inner.lock()?;
+        Ok(Status {
+            id: self.id.clone(),
+            uptime: self.started.elapsed().as_secs(),
+            connections: inner.sessions.len() as u64,
+            connections_accepted: inner.accepted,
+            sessions: inner
+                .sessions
+                .values()
+                .map(|(status, connected)| SessionStatus {
+                    connected: connected.elapsed().as_secs(),
+                    ..status.clone()
+                })
+                .collect(),
+            mvcc,
+        })
+    }
+}
+
+/// a registered connection
+pub struct SessionHandle {
+    id: u64,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl SessionHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// lets the statements of the session be cancelled by setting the flag,
+    /// returns the secret key of the cancel requests
+    pub fn set_cancel(&self, cancel: Arc<AtomicBool>) -> Result<u64> {
+        let key = OsRng.next_u64();
+        self.inner.lock()?.cancels.insert(self.id, (key, cancel));
+        Ok(key)
+    }
+
+    /// records the transaction of the session
+    pub fn set_txn(&self, txn: Option<(u64, TransactionMode)>) -> Result<()> {
+        if let Some((status, _)) = self.inner.lock()?.sessions.get_mut(&self.id) {
+            status.txn = txn;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SessionHandle {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.sessions.remove(&self.id);
+            inner.cancels.remove(&self.id);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/// calls f with the position of every character of the SQL outside of
+/// string literals, quoted identifiers and comments
+fn scan_sql(sql: &str, mut f: impl FnMut(usize, char)) {
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // a doubled quote inside the literal closes and reopens it
+                for (_, q) in chars.by_ref() {
+                    if q == c {
+                        break;
+                    }
+                }
+            }
+            '-' if matches!(chars.peek(), Some((_, '-'))) => {
+                for (_, q) in chars.by_ref() {
+                    if q == '\n' {
+                        break;
+                    }
+                }
+            }
+            c => f(i, c),
+        }
+    }
+}
+
+/// the statements of a simple query, separated by semicolons
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let mut ends = Vec::new();
+    scan_sql(sql, |i, c| {
+        if c == ';' {
+            ends.push(i);
+        }
+    });
+    ends.push(sql.len());
+    let mut start = 0;
+    let mut statements = Vec::new();
+    for end in ends {
+        let statement = sql[start..end].trim();
+        if !statement.is_empty() {
+            statements.push(statement);
+        }
+        start = end + 1;
+    }
+    statements
+}
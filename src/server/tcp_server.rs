@@ -1,20 +1,43 @@
 use log::error;
 use log::info;
 use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
 use tokio_stream::wrappers::TcpListenerStream;
 use tokio_stream::StreamExt;
 
+use crate::common::tls;
 use crate::error::Error;
 use crate::error::Result;
+use crate::server::http_session::HTTPSession;
+use crate::server::mysql_session::MySQLSession;
+use crate::server::pg_session::PGSession;
+use crate::server::sessions::Sessions;
 use crate::server::tcp_session::TCPSession;
+use crate::sql::engine::Catalog;
 use crate::sql::engine::KVEngine;
+use crate::sql::engine::SQLEngine;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::schema::user::User;
+use crate::storage::mvcc::TransactionMode;
 use crate::storage::mvcc::MVCC;
 use crate::storage::Store;
 
 pub struct Server {
     id: String,
     engine: KVEngine,
+    /// the client connections, for the status of the server
+    sessions: Sessions,
     sql_listener: Option<TcpListener>,
+    /// the listener of the PostgreSQL wire protocol, if enabled
+    pg_listener: Option<TcpListener>,
+    /// the listener of the MySQL wire protocol, if enabled
+    mysql_listener: Option<TcpListener>,
+    /// the listener of the HTTP/JSON API, if enabled
+    http_listener: Option<TcpListener>,
+    /// whether the SQL clients must authenticate
+    authentication: bool,
+    /// the TLS of the connections of every protocol, if enabled
+    tls: Option<TlsAcceptor>,
 }
 
 impl Server {
@@ -25,10 +48,43 @@ impl Server {
             engine: KVEngine {
                 mvcc: MVCC::new(sql_store),
             },
+            sessions: Sessions::new(id),
             sql_listener: None,
+            pg_listener: None,
+            mysql_listener: None,
+            http_listener: None,
+            authentication: false,
+            tls: None,
         })
     }
 
+    /// require the SQL clients to authenticate, the superuser is created
+    /// with the given password if it does not exist
+    pub async fn authenticate(mut self, superuser: &str, password: &str) -> Result<Self> {
+        let mut txn = self.engine.begin(TransactionMode::ReadWrite)?;
+        if txn.read_user(superuser)?.is_none() {
+            txn.create_user(User::new(superuser, password, true))?;
+            info!("SQL Server id {} created superuser {}", self.id, superuser);
+        }
+        txn.commit()?;
+        self.authentication = true;
+        Ok(self)
+    }
+
+    /// encrypt the connections of every protocol with TLS, the PostgreSQL
+    /// and MySQL clients ask for it before the startup and HTTP is served as
+    /// HTTPS. the clients present a certificate issued by the certificate
+    /// authorities of client_ca_file if it is given
+    pub fn tls(
+        mut self,
+        cert_file: &str,
+        key_file: &str,
+        client_ca_file: Option<&str>,
+    ) -> Result<Self> {
+        self.tls = Some(tls::acceptor(cert_file, key_file, client_ca_file)?);
+        Ok(self)
+    }
+
     /// start listening on the given ports, must be call before serve
     pub async fn listen(mut self, sql_addr: &str) -> Result<Self> {
         let sql = TcpListener::bind(sql_addr).await?;
@@ -41,25 +97,116 @@ impl Server {
         Ok(self)
     }
 
+    /// also listen for PostgreSQL clients on the given address, must be call
+    /// before serve
+    pub async fn listen_pg(mut self, pg_addr: &str) -> Result<Self> {
+        let pg = TcpListener::bind(pg_addr).await?;
+        info!(
+            "SQL Server id {} Listening on {} (PostgreSQL)",
+            self.id,
+            pg.local_addr()?
+        );
+        self.pg_listener = Some(pg);
+        Ok(self)
+    }
+
+    /// also listen for MySQL clients on the given address, must be call
+    /// before serve
+    pub async fn listen_mysql(mut self, mysql_addr: &str) -> Result<Self> {
+        let mysql = TcpListener::bind(mysql_addr).await?;
+        info!(
+            "SQL Server id {} Listening on {} (MySQL)",
+            self.id,
+            mysql.local_addr()?
+        );
+        self.mysql_listener = Some(mysql);
+        Ok(self)
+    }
+
+    /// also serve the HTTP/JSON API on the given address, must be call
+    /// before serve
+    pub async fn listen_http(mut self, http_addr: &str) -> Result<Self> {
+        let http = TcpListener::bind(http_addr).await?;
+        info!(
+            "SQL Server id {} Listening on {} (HTTP)",
+            self.id,
+            http.local_addr()?
+        );
+        self.http_listener = Some(http);
+        Ok(self)
+    }
+
     /// serve SQL request until the returned future is dropped. Consumes the server
     pub async fn server(self) -> Result<()> {
         let sql_listener = self
             .sql_listener
             .ok_or_else(|| Error::Internal("Must listen before serving".into()))?;
-        tokio::try_join!(Self::sql_serve(sql_listener, self.engine),)?;
+        // the MySQL protocol has no authentication: mysql_native_password
+        // needs the SHA-1 of the password, and only its SCRAM keys are stored
+        if self.authentication && self.mysql_listener.is_some() {
+            return Err(Error::Config(
+                "Authentication is not supported by the MySQL protocol, disable the MySQL listener"
+                    .into(),
+            ));
+        }
+        tokio::try_join!(
+            Self::sql_serve(
+                sql_listener,
+                self.engine.clone(),
+                self.sessions.clone(),
+                self.authentication,
+                self.tls.clone()
+            ),
+            Self::pg_serve(
+                self.pg_listener,
+                self.engine.clone(),
+                self.sessions.clone(),
+                self.authentication,
+                self.tls.clone()
+            ),
+            Self::mysql_serve(
+                self.mysql_listener,
+                self.engine.clone(),
+                self.sessions.clone(),
+                self.tls.clone()
+            ),
+            Self::http_serve(
+                self.http_listener,
+                self.engine,
+                self.sessions,
+                self.authentication,
+                self.tls
+            ),
+        )?;
         Ok(())
     }
 
     /// server sql
-    async fn sql_serve(listener: TcpListener, engine: KVEngine) -> Result<()> {
+    async fn sql_serve(
+        listener: TcpListener,
+        engine: KVEngine,
+        sessions: Sessions,
+        authentication: bool,
+        tls: Option<TlsAcceptor>,
+    ) -> Result<()> {
         let mut listener = TcpListenerStream::new(listener);
         // a client connectioned
         while let Some(socket) = listener.try_next().await? {
             let peer = socket.peer_addr()?;
-            let session = TCPSession::new(engine.clone())?;
+            let handle = sessions.register("sql", peer)?;
+            let session =
+                TCPSession::new(engine.clone(), sessions.clone(), handle, authentication)?;
+            let tls = tls.clone();
             tokio::spawn(async move {
                 info!("Client {} connected", peer);
-                match session.handle(socket).await {
+                let result = match tls {
+                    Some(acceptor) => match acceptor.accept(socket).await {
+                        Ok(stream) => session.handle(stream).await,
+                        Err(err) => Err(err.into()),
+                    },
+                    None => session.handle(socket).await,
+                };
+                match result {
                     Ok(()) => info!("Client {} disconnected", peer),
                     Err(e) => error!("Client {} error: {}", peer, e),
                 }
@@ -67,4 +214,101 @@ impl Server {
         }
         todo!()
     }
+
+    /// serve the PostgreSQL clients, if the listener is enabled
+    async fn pg_serve(
+        listener: Option<TcpListener>,
+        engine: KVEngine,
+        sessions: Sessions,
+        authentication: bool,
+        tls: Option<TlsAcceptor>,
+    ) -> Result<()> {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => return Ok(()),
+        };
+        let mut listener = TcpListenerStream::new(listener);
+        while let Some(socket) = listener.try_next().await? {
+            let peer = socket.peer_addr()?;
+            let handle = sessions.register("postgresql", peer)?;
+            let session = PGSession::new(engine.clone(), authentication)?;
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                let _handle = handle;
+                info!("PostgreSQL client {} connected", peer);
+                match session.handle(socket, tls).await {
+                    Ok(()) => info!("PostgreSQL client {} disconnected", peer),
+                    Err(e) => error!("PostgreSQL client {} error: {}", peer, e),
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// serve the MySQL clients, if the listener is enabled
+    async fn mysql_serve(
+        listener: Option<TcpListener>,
+        engine: KVEngine,
+        sessions: Sessions,
+        tls: Option<TlsAcceptor>,
+    ) -> Result<()> {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => return Ok(()),
+        };
+        let mut listener = TcpListenerStream::new(listener);
+        while let Some(socket) = listener.try_next().await? {
+            let peer = socket.peer_addr()?;
+            let handle = sessions.register("mysql", peer)?;
+            let session = MySQLSession::new(engine.clone())?;
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                let _handle = handle;
+                info!("MySQL client {} connected", peer);
+                match session.handle(socket, tls).await {
+                    Ok(()) => info!("MySQL client {} disconnected", peer),
+                    Err(e) => error!("MySQL client {} error: {}", peer, e),
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// serve the HTTP clients, if the listener is enabled
+    async fn http_serve(
+        listener: Option<TcpListener>,
+        engine: KVEngine,
+        sessions: Sessions,
+        authentication: bool,
+        tls: Option<TlsAcceptor>,
+    ) -> Result<()> {
+        let listener = match listener {
+            Some(listener) => listener,
+            None => return Ok(()),
+        };
+        let mut listener = TcpListenerStream::new(listener);
+        while let Some(socket) = listener.try_next().await? {
+            let peer = socket.peer_addr()?;
+            let handle = sessions.register("http", peer)?;
+            let session = HTTPSession::new(engine.clone(), sessions.clone(), authentication)?;
+            let tls = tls.clone();
+            tokio::spawn(async move {
+                let _handle = handle;
+                info!("HTTP client {} connected", peer);
+                // HTTPS if TLS is enabled
+                let result = match tls {
+                    Some(acceptor) => match acceptor.accept(socket).await {
+                        Ok(stream) => session.handle(stream).await,
+                        Err(err) => Err(err.into()),
+                    },
+                    None => session.handle(socket).await,
+                };
+                match result {
+                    Ok(()) => info!("HTTP client {} disconnected", peer),
+                    Err(e) => error!("HTTP client {} error: {}", peer, e),
+                }
+            });
+        }
+        Ok(())
+    }
 }
@@ -1,76 +1,106 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+
 use futures::sink::SinkExt as _;
-use log::info;
-use tokio::net::TcpStream;
+use log::debug;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio_stream::StreamExt as _;
 use tokio_util::codec::Framed;
 use tokio_util::codec::LengthDelimitedCodec;
 
 use super::servlet::Request;
 use super::servlet::Response;
+use super::sessions::SessionHandle;
+use super::sessions::Sessions;
+use crate::common::result::DataRows;
 use crate::common::result::ResultSet;
+use crate::common::scram;
+use crate::error::Error;
 use crate::error::Result;
 use crate::sql::engine::Catalog;
 use crate::sql::engine::KVEngine;
+use crate::sql::engine::Prepared;
 use crate::sql::engine::SQLEngine;
 use crate::sql::engine::SQLSession;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::schema::user::User;
 use crate::storage::mvcc::TransactionMode;
 
 /// a client session coupled to a SQL session
 pub struct TCPSession {
-    _engine: KVEngine,
+    engine: KVEngine,
     session: SQLSession<KVEngine>,
+    /// the sessions of the server, for its status
+    sessions: Sessions,
+    handle: SessionHandle,
+    /// the prepared statements of the session, by handle
+    statements: HashMap<u64, Prepared>,
+    next_statement: u64,
+    /// the rows of the open queries, pulled from the executor as they are
+    /// fetched
+    cursors: HashMap<u64, Peekable<DataRows>>,
+    next_cursor: u64,
+    /// whether a client must authenticate before its requests
+    authentication: bool,
+    /// the exchange started by the last authenticate request
+    challenge: Option<Challenge>,
+    /// the secret key cancelling the statements of the session
+    cancel_key: u64,
+}
+
+/// a SCRAM exchange waiting for the proof of the client
+struct Challenge {
+    name: String,
+    /// None for an unknown user, the exchange fails at the proof
+    user: Option<User>,
+    auth_message: Vec<u8>,
 }
 
 impl TCPSession {
-    pub fn new(engine: KVEngine) -> Result<Self> {
+    pub fn new(
+        engine: KVEngine,
+        sessions: Sessions,
+        handle: SessionHandle,
+        authentication: bool,
+    ) -> Result<Self> {
+        let session = engine.session()?;
+        let cancel_key = handle.set_cancel(session.cancel.clone())?;
         Ok(Self {
-            session: engine.session()?,
-            _engine: engine,
+            session,
+            engine,
+            sessions,
+            handle,
+            statements: HashMap::new(),
+            next_statement: 1,
+            cursors: HashMap::new(),
+            next_cursor: 1,
+            authentication,
+            challenge: None,
+            cancel_key,
         })
     }
 
-    pub async fn handle(mut self, socket: TcpStream) -> Result<()> {
+    /// serve the requests of a connection, plain or TLS
+    pub async fn handle<S: AsyncRead + AsyncWrite + Unpin>(mut self, socket: S) -> Result<()> {
         let mut stream = tokio_serde::Framed::new(
             Framed::new(socket, LengthDelimitedCodec::new()),
             tokio_serde::formats::Bincode::default(),
         );
 
         while let Some(request) = stream.try_next().await? {
-            info!("request info {:?}", request);
+            debug!("request {}", Request::kind(&request));
+            let is_proof = matches!(request, Request::Proof(_));
             // execute request
-            let mut response = self.request(request);
-
-            // separate columns from rows
-            let mut rows: Box<dyn Iterator<Item = Result<Response>> + Send> =
-                Box::new(std::iter::empty());
-            if let Ok(Response::Execute(ResultSet::Query {
-                rows: ref mut resultrows,
-                ..
-            })) = &mut response
-            {
-                rows = Box::new(
-                    std::mem::replace(resultrows, Box::new(std::iter::empty()))
-                        .map(|result| result.map(|row| Response::Row(Some(row))))
-                        // package with Response::Row
-                        .chain(std::iter::once(Ok(Response::Row(None))))
-                        .scan(false, |err_sent, response| match (&err_sent, &response) {
-                            (true, _) => None,
-                            (_, Err(error)) => {
-                                *err_sent = true;
-                                Some(Err(error.clone()))
-                            }
-                            _ => Some(response),
-                        })
-                        .fuse(),
-                );
-            }
-
-            // send response type
+            let response = self.request(request);
+            self.handle
+                .set_txn(self.session.txn.as_ref().map(|txn| (txn.id(), txn.mode())))?;
+            // a failed authentication closes the connection
+            let failed = is_proof && response.is_err();
             stream.send(response).await?;
-            // send response rows
-            stream
-                .send_all(&mut tokio_stream::iter(rows.map(Ok)))
-                .await?;
+            if failed {
+                break;
+            }
         }
 
         Ok(())
@@ -78,16 +108,171 @@ impl TCPSession {
 
     /// execute a request
     pub fn request(&mut self, request: Request) -> Result<Response> {
+        if self.authentication
+            && self.session.user.is_none()
+            && !matches!(
+                request,
+                Request::Authenticate { .. } | Request::Proof(_) | Request::Cancel { .. }
+            )
+        {
+            return Err(Error::Unauthorized("Authentication required".into()));
+        }
         Ok(match request {
-            Request::Execute(query) => Response::Execute(self.session.execute(&query)?),
+            Request::Authenticate { user: name, nonce } => {
+                let user = self
+                    .session
+                    .with_txn(TransactionMode::ReadOnly, |txn| txn.read_user(&name))?;
+                // an unknown user is challenged like any other
+                let (salt, iterations) = match &user {
+                    Some(user) => (user.salt.clone(), user.iterations),
+                    None => (scram::unknown_user_salt(&name), scram::ITERATIONS),
+                };
+                let server_nonce = scram::nonce();
+                let auth_message =
+                    scram::auth_message(&name, &nonce, &server_nonce, &salt, iterations);
+                self.challenge = Some(Challenge {
+                    name,
+                    user,
+                    auth_message,
+                });
+                Response::Challenge {
+                    nonce: server_nonce,
+                    salt,
+                    iterations,
+                }
+            }
+            Request::Proof(proof) => {
+                let challenge = self.challenge.take().ok_or_else(|| {
+                    Error::Value("The proof is sent after an authenticate request".into())
+                })?;
+                match challenge.user {
+                    Some(user)
+                        if scram::verify_client_proof(
+                            &user.stored_key,
+                            &challenge.auth_message,
+                            &proof,
+                        ) =>
+                    {
+                        self.session.user = Some(user.name);
+                        Response::Authenticated {
+                            signature: scram::server_signature(
+                                &user.server_key,
+                                &challenge.auth_message,
+                            ),
+                        }
+                    }
+                    _ => {
+                        return Err(Error::Unauthorized(format!(
+                            "Authentication failed for user {}",
+                            challenge.name
+                        )))
+                    }
+                }
+            }
+            Request::Execute(query) => {
+                let result = self.session.execute(&query)?;
+                self.result(result)
+            }
+            Request::Prepare(query) => {
+                let prepared = self.session.prepare(&query)?;
+                let id = self.next_statement;
+                self.next_statement += 1;
+                let parameters = prepared.parameters().to_vec();
+                self.statements.insert(id, prepared);
+                Response::Prepare { id, parameters }
+            }
+            Request::ExecutePrepared(id, params) => {
+                let prepared = self.statements.get_mut(&id).ok_or_else(|| {
+                    Error::Value(format!("Prepared statement {} does not exist", id))
+                })?;
+                let result = self.session.execute_prepared(prepared, &params)?;
+                self.result(result)
+            }
+            Request::Deallocate(id) => {
+                self.statements.remove(&id).ok_or_else(|| {
+                    Error::Value(format!("Prepared statement {} does not exist", id))
+                })?;
+                Response::Deallocate
+            }
+            Request::Fetch(id, n) => {
+                let cursor = self
+                    .cursors
+                    .get_mut(&id)
+                    .ok_or_else(|| Error::Value(format!("Cursor {} does not exist", id)))?;
+                let mut rows = Vec::new();
+                while rows.len() < n {
+                    // the rows before an error are sent first, the error by
+                    // the next fetch
+                    if !rows.is_empty() && matches!(cursor.peek(), Some(Err(_))) {
+                        break;
+                    }
+                    match cursor.next() {
+                        Some(Ok(row)) => rows.push(row),
+                        // the cursor is closed by an error
+                        Some(Err(err)) => {
+                            self.cursors.remove(&id);
+                            return Err(err);
+                        }
+                        None => break,
+                    }
+                }
+                let done = cursor.peek().is_none();
+                if done {
+                    self.cursors.remove(&id);
+                }
+                Response::Rows { rows, done }
+            }
+            Request::CloseCursor(id) => match self.cursors.remove(&id) {
+                Some(_) => Response::CloseCursor,
+                None => return Err(Error::Value(format!("Cursor {} does not exist", id))),
+            },
             Request::ListTables => {
                 Response::ListTable(self.session.with_txn(TransactionMode::ReadOnly, |txn| {
-                    Ok(txn.scan_table()?.map(|t| t.name).collect())
+                    let mut tables = Vec::new();
+                    for table in txn.scan_table()? {
+                        if txn.can_see(&table.name)? {
+                            tables.push((table.name, table.kind));
+                        }
+                    }
+                    Ok(tables)
                 })?)
             }
-            _ => Response::ListTable(vec!["unsupport".into()]),
+            Request::GetTable(table) => {
+                Response::Table(self.session.with_txn(TransactionMode::ReadOnly, |txn| {
+                    txn.authorize_schema(&table)?;
+                    txn.must_read_table(&table)
+                })?)
+            }
+            Request::Status => {
+                self.session
+                    .with_txn(TransactionMode::ReadOnly, |txn| txn.authorize_superuser())?;
+                Response::Status(self.sessions.status(&self.engine.mvcc)?)
+            }
+            Request::CancelKey => Response::CancelKey {
+                id: self.handle.id(),
+                key: self.cancel_key,
+            },
+            // the key is the secret of the session, no authentication is
+            // needed
+            Request::Cancel { id, key } => {
+                self.sessions.cancel(id, key)?;
+                Response::Cancel
+            }
         })
     }
+
+    /// the response of a result set, the rows of a query are left to a cursor
+    fn result(&mut self, result: ResultSet) -> Response {
+        match result {
+            ResultSet::Query { columns, rows } => {
+                let id = self.next_cursor;
+                self.next_cursor += 1;
+                self.cursors.insert(id, rows.peekable());
+                Response::Cursor { id, columns }
+            }
+            result => Response::Execute(result),
+        }
+    }
 }
 
 impl Drop for TCPSession {
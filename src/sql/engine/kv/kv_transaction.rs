@@ -1,8 +1,10 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
 
 use bincode::deserialize;
 use bincode::serialize;
+use serde::de::DeserializeOwned;
 
 use crate::common::keys::SQLKey;
 use crate::common::result::DataRow;
@@ -12,19 +14,36 @@ use crate::sql::engine::sql_transaction::IndexScan;
 use crate::sql::engine::sql_transaction::SQLTransaction;
 use crate::sql::engine::sql_transaction::Scan;
 use crate::sql::engine::Catalog;
+use crate::sql::engine::Interrupt;
 use crate::sql::plan::plan_expression::Expression;
 use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::format_key;
 use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
 use crate::sql::schema::table::Tables;
+use crate::sql::schema::table_constraint::ForeignKey;
+use crate::sql::schema::table_constraint::ReferentialAction;
+use crate::sql::schema::user::User;
+use crate::sql::schema::user::Users;
+use crate::sql::schema::view::View;
+use crate::sql::schema::view::Views;
+use crate::storage::compression::Compression;
 use crate::storage::mvcc::MVCCTransaction;
 
 pub struct KVTransaction {
     txn: MVCCTransaction,
+    /// the user whose privileges are checked, None for every privilege
+    user: Option<String>,
+    interrupt: Interrupt,
 }
 
 impl KVTransaction {
     pub fn new(txn: MVCCTransaction) -> Self {
-        Self { txn }
+        Self {
+            txn,
+            user: None,
+            interrupt: Interrupt::default(),
+        }
     }
 
     // Loads an index entry
@@ -33,12 +52,12 @@ impl KVTransaction {
         table: &str,
         column: &str,
         value: &DataValue,
-    ) -> Result<HashSet<DataValue>> {
+    ) -> Result<HashSet<Vec<DataValue>>> {
         let key = SQLKey::Index(table.into(), column.into(), Some(value.into())).encode();
         let result = self
             .txn
             .get(&key)?
-            .map(|v| deserialize(&v))
+            .map(|v| decode(&v))
             .transpose()?
             .unwrap_or_else(HashSet::new);
         Ok(result)
@@ -47,19 +66,98 @@ impl KVTransaction {
     // saves an index entry
     pub fn index_save(
         &mut self,
-        table: &str,
+        table: &Table,
         column: &str,
         value: &DataValue,
-        index: HashSet<DataValue>,
+        index: HashSet<Vec<DataValue>>,
     ) -> Result<()> {
-        let key = SQLKey::Index(table.into(), column.into(), Some(value.into())).encode();
+        let key = SQLKey::Index((&table.name).into(), column.into(), Some(value.into())).encode();
         if index.is_empty() {
             self.txn.delete(&key)
         } else {
-            let value = serialize(&index)?;
+            let value = table.compression.compress(&serialize(&index)?);
             self.txn.set(&key, value)
         }
     }
+
+    // the keys of every row of a table
+    fn row_keys(&self, table: &str) -> Result<HashSet<Vec<u8>>> {
+        self.txn
+            .scan_prefix(&SQLKey::Row(table.into(), None).encode())?
+            .map(|r| r.map(|(k, _)| k))
+            .collect()
+    }
+
+    // the rows referencing a row through a foreign key, except the rows in
+    // skip, i.e. the row itself and the rows already deleted by a cascade
+    fn referencing_rows(
+        &self,
+        table: &str,
+        id: &[DataValue],
+        skip: &HashSet<(String, Vec<DataValue>)>,
+    ) -> Result<Vec<(Table, ForeignKey, DataRow)>> {
+        let mut rows = Vec::new();
+        for (t, foreign_key) in self.table_references(table, true)? {
+            let mut scan = self.scan(&t.name, None)?;
+            while let Some(row) = scan.next().transpose()? {
+                if t.get_values(&row, &foreign_key.columns)? == id
+                    && !skip.contains(&(t.name.clone(), t.get_row_key(&row)?))
+                {
+                    rows.push((t.clone(), foreign_key.clone(), row));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    // deletes a row and applies the ON DELETE actions, the rows deleted by
+    // cascades are remembered so that reference cycles terminate
+    fn delete_row(
+        &mut self,
+        table: &Table,
+        id: &[DataValue],
+        deleted: &mut HashSet<(String, Vec<DataValue>)>,
+    ) -> Result<()> {
+        let row = match self.read(&table.name, id)? {
+            Some(row) => row,
+            None => return Ok(()),
+        };
+        if !deleted.insert((table.name.clone(), id.to_vec())) {
+            return Ok(());
+        }
+
+        for (t, foreign_key, referencing) in self.referencing_rows(&table.name, id, deleted)? {
+            let key = t.get_row_key(&referencing)?;
+            match foreign_key.on_delete {
+                ReferentialAction::Restrict => {
+                    return Err(Error::Value(format!(
+                        "Primary key {} is referenced by table {} column {}",
+                        format_key(id),
+                        t.name,
+                        foreign_key.columns.join(", ")
+                    )))
+                }
+                ReferentialAction::Cascade => self.delete_row(&t, &key, deleted)?,
+                ReferentialAction::SetNull => {
+                    // the row may have been changed by a previous action
+                    if let Some(mut row) = self.read(&t.name, &key)? {
+                        for column in &foreign_key.columns {
+                            row[t.get_column_index(column)?] = DataValue::Null;
+                        }
+                        self.update(&t.name, &key, row)?;
+                    }
+                }
+            }
+        }
+
+        for (i, column) in table.columns.iter().enumerate().filter(|(_, c)| c.index) {
+            let mut index = self.index_load(&table.name, &column.name, &row[i])?;
+            index.remove(id);
+            self.index_save(table, &column.name, &row[i], index)?;
+        }
+        self.txn
+            .delete(&SQLKey::Row((&table.name).into(), Some(id.into())).encode())
+    }
 }
 
 impl SQLTransaction for KVTransaction {
@@ -79,76 +177,58 @@ impl SQLTransaction for KVTransaction {
         self.txn.rollback()
     }
 
+    fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    fn set_user(&mut self, user: Option<String>) {
+        self.user = user;
+    }
+
+    fn interrupt(&self) -> &Interrupt {
+        &self.interrupt
+    }
+
+    fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = interrupt;
+    }
+
     fn create(&mut self, table: &str, row: DataRow) -> Result<()> {
         let table = self.must_read_table(table)?;
+        table.check_writable()?;
         table.validate_row(&row, self)?;
         let primary_key = table.get_row_key(&row)?;
         if self.read(&table.name, &primary_key)?.is_some() {
             return Err(Error::Value(format!(
                 "Primary key {} already exist in table {}",
-                primary_key, table.name
+                format_key(&primary_key),
+                table.name
             )));
         }
         let key = SQLKey::Row((&table.name).into(), Some(Cow::Borrowed(&primary_key)));
-        self.txn.set(&key.encode(), serialize(&row)?)?;
+        self.txn
+            .set(&key.encode(), table.compression.compress(&serialize(&row)?))?;
 
         for (i, column) in table.columns.iter().enumerate().filter(|(_, c)| c.index) {
             let mut index = self.index_load(&table.name, &column.name, &row[i])?;
             index.insert(primary_key.clone());
-            self.index_save(&table.name, &column.name, &row[i], index)?;
+            self.index_save(&table, &column.name, &row[i], index)?;
         }
 
         Ok(())
     }
 
-    fn delete(&mut self, table: &str, id: &DataValue) -> crate::error::Result<()> {
+    fn delete(&mut self, table: &str, id: &[DataValue]) -> crate::error::Result<()> {
         let table = self.must_read_table(table)?;
-
-        // check reference
-        for (t, cs) in self.table_references(&table.name, true)? {
-            let t = self.must_read_table(&t)?;
-            let cs = cs
-                .into_iter()
-                .map(|c| Ok((t.get_column_index(&c)?, c)))
-                .collect::<Result<Vec<_>>>()?;
-            let mut scan = self.scan(&t.name, None)?;
-            while let Some(row) = scan.next().transpose()? {
-                for (i, c) in &cs {
-                    if &row[*i] == id && (table.name != t.name || id != &table.get_row_key(&row)?) {
-                        return Err(Error::Value(format!(
-                            "Primary key {} is referenced by table {} column {}",
-                            id, t.name, c
-                        )));
-                    }
-                }
-            }
-        }
-
-        let indexes: Vec<_> = table
-            .columns
-            .iter()
-            .enumerate()
-            .filter(|(_, c)| c.index)
-            .collect();
-        if !indexes.is_empty() {
-            // read row by table id
-            if let Some(row) = self.read(&table.name, id)? {
-                for (i, column) in indexes {
-                    let mut index = self.index_load(&table.name, &column.name, &row[i])?;
-                    index.remove(id);
-                    self.index_save(&table.name, &column.name, &row[i], index)?;
-                }
-            }
-        }
-        self.txn
-            .delete(&SQLKey::Row(table.name.into(), Some(id.into())).encode())
+        table.check_writable()?;
+        self.delete_row(&table, id, &mut HashSet::new())
     }
 
-    fn read(&self, table: &str, id: &DataValue) -> Result<Option<DataRow>> {
+    fn read(&self, table: &str, id: &[DataValue]) -> Result<Option<DataRow>> {
         let result = self
             .txn
             .get(&SQLKey::Row(table.into(), Some(id.into())).encode())?
-            .map(|v| deserialize::<DataRow>(&v))
+            .map(|v| decode::<DataRow>(&v))
             .transpose()?;
         Ok(result)
     }
@@ -158,7 +238,7 @@ impl SQLTransaction for KVTransaction {
         table: &str,
         column: &str,
         value: &DataValue,
-    ) -> Result<HashSet<DataValue>> {
+    ) -> Result<HashSet<Vec<DataValue>>> {
         // check the column is index
         if !self.must_read_table(table)?.get_column(column)?.index {
             return Err(Error::Value(format!(
@@ -171,10 +251,14 @@ impl SQLTransaction for KVTransaction {
 
     fn scan(&self, table: &str, filter: Option<Expression>) -> Result<Scan> {
         let table = self.must_read_table(table)?;
+        let interrupt = self.interrupt.clone();
         let scan = self
             .txn
             .scan_prefix(&SQLKey::Row((&table.name).into(), None).encode())?
-            .map(|r| r.and_then(|(_, v)| Ok(deserialize(&v)?)))
+            .map(move |r| {
+                interrupt.check()?;
+                r.and_then(|(_, v)| decode(&v))
+            })
             .filter_map(move |r| match r {
                 Ok(row) => match &filter {
                     Some(filter) => match filter.evaluate(Some(&row)) {
@@ -188,6 +272,7 @@ impl SQLTransaction for KVTransaction {
                     },
                     None => Some(Ok(row)),
                 },
+                Err(err @ Error::Cancelled(_)) => Some(Err(err)),
                 Err(err) => Some(Err(Error::Internal(format!("scan error {}", err)))),
             });
         Ok(Box::new(scan))
@@ -202,27 +287,42 @@ impl SQLTransaction for KVTransaction {
                 table.name, column.name
             )));
         }
+        let interrupt = self.interrupt.clone();
         let scan = self
             .txn
             .scan_prefix(
                 &SQLKey::Index((&table.name).into(), (&column.name).into(), None).encode(),
             )?
-            .map(|r| -> Result<(DataValue, HashSet<DataValue>)> {
+            .map(move |r| -> Result<(DataValue, HashSet<Vec<DataValue>>)> {
+                interrupt.check()?;
                 let (k, v) = r?;
                 let value = match SQLKey::decode(&k)? {
                     SQLKey::Index(_, _, Some(pk)) => pk.into_owned(),
                     _ => return Err(Error::Internal("Invalid index key".into())),
                 };
-                Ok((value, deserialize(&v)?))
+                Ok((value, decode(&v)?))
             });
 
         Ok(Box::new(scan))
     }
 
-    fn update(&mut self, table: &str, id: &DataValue, row: DataRow) -> Result<()> {
+    fn update(&mut self, table: &str, id: &[DataValue], row: DataRow) -> Result<()> {
         let table = self.must_read_table(table)?;
+        table.check_writable()?;
         // if primary key changed, we do delete and create, otherwise we replaced
-        if &table.get_row_key(&row)? != id {
+        if table.get_row_key(&row)? != id {
+            // the ON DELETE actions don't apply, a referenced key can't change
+            let skip = HashSet::from([(table.name.clone(), id.to_vec())]);
+            if let Some((t, foreign_key, _)) =
+                self.referencing_rows(&table.name, id, &skip)?.first()
+            {
+                return Err(Error::Value(format!(
+                    "Primary key {} is referenced by table {} column {}",
+                    format_key(id),
+                    t.name,
+                    foreign_key.columns.join(", ")
+                )));
+            }
             self.delete(&table.name, id)?;
             self.create(&table.name, row)?;
             return Ok(());
@@ -237,7 +337,8 @@ impl SQLTransaction for KVTransaction {
         if !indexes.is_empty() {
             let old = self.read(&table.name, id)?.ok_or(Error::Value(format!(
                 "Table {} id {} not exists.",
-                table.name, id
+                table.name,
+                format_key(id)
             )))?;
 
             for (i, column) in indexes {
@@ -249,19 +350,150 @@ impl SQLTransaction for KVTransaction {
                 // table_name-column_name-column_value -> row_id
                 let mut index = self.index_load(&table.name, &column.name, &old[i])?;
                 index.remove(id);
-                self.index_save(&table.name, &column.name, &old[i], index)?;
+                self.index_save(&table, &column.name, &old[i], index)?;
 
                 let mut index = self.index_load(&table.name, &column.name, &row[i])?;
-                index.insert(id.clone());
-                self.index_save(&table.name, &column.name, &row[i], index)?;
+                index.insert(id.to_vec());
+                self.index_save(&table, &column.name, &row[i], index)?;
             }
         }
 
         table.validate_row(&row, self)?;
         let key = SQLKey::Row((&table.name).into(), Some(id.into())).encode();
-        let value = serialize(&row)?;
+        let value = table.compression.compress(&serialize(&row)?);
         self.txn.set(&key, value)
     }
+
+    fn materialize(&mut self, table: Table, rows: Vec<DataRow>) -> Result<()> {
+        if table.kind != TableKind::MaterializedView {
+            return Err(Error::Internal(format!(
+                "Table {} is not a materialized view",
+                table.name
+            )));
+        }
+        // the rows have no primary key, they are keyed by their row number,
+        // the rows are overwritten in place, so only the rows beyond the new
+        // row count are deleted
+        let mut stale = self.row_keys(&table.name)?;
+        for (i, row) in rows.iter().enumerate() {
+            let id = [DataValue::Integer(i as i64)];
+            let key = SQLKey::Row((&table.name).into(), Some(id[..].into())).encode();
+            stale.remove(&key);
+            self.txn
+                .set(&key, table.compression.compress(&serialize(row)?))?;
+        }
+        for key in stale {
+            self.txn.delete(&key)?;
+        }
+        let key = SQLKey::Table(Some((&table.name).into())).encode();
+        self.txn.set(&key, serialize(&table)?)
+    }
+
+    fn integrity_check(&mut self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        for table in self.scan_table()?.filter(|t| t.kind != TableKind::View) {
+            // the rows are scanned in key order, a row is stored under the
+            // key of its primary key
+            let mut rows = HashMap::new();
+            let mut last_key: Option<Vec<u8>> = None;
+            for r in self
+                .txn
+                .scan_prefix(&SQLKey::Row((&table.name).into(), None).encode())?
+            {
+                let (key, value) = r?;
+                if last_key.as_ref().is_some_and(|last| *last >= key) {
+                    problems.push(format!("Table {} has rows out of key order", table.name));
+                }
+                last_key = Some(key.clone());
+                let id = match SQLKey::decode(&key) {
+                    Ok(SQLKey::Row(_, Some(id))) => id.into_owned(),
+                    _ => {
+                        problems.push(format!("Table {} has an invalid row key", table.name));
+                        continue;
+                    }
+                };
+                let row = match decode::<DataRow>(&value) {
+                    Ok(row) if row.len() == table.columns.len() => row,
+                    _ => {
+                        problems.push(format!(
+                            "Row {} of table {} can not be decoded",
+                            format_key(&id),
+                            table.name
+                        ));
+                        continue;
+                    }
+                };
+                if table.kind == TableKind::Table && table.get_row_key(&row)? != id {
+                    problems.push(format!(
+                        "Row {} of table {} is stored under the key {}",
+                        format_key(&table.get_row_key(&row)?),
+                        table.name,
+                        format_key(&id)
+                    ));
+                }
+                rows.insert(id, row);
+            }
+            if table.kind != TableKind::Table {
+                continue;
+            }
+
+            for row in rows.values() {
+                if let Err(err) = table.validate_row(row, self) {
+                    problems.push(format!(
+                        "Row {} of table {}: {}",
+                        format_key(&table.get_row_key(row)?),
+                        table.name,
+                        err
+                    ));
+                }
+            }
+
+            // every row is in each index of the table exactly once, under the
+            // value of its column
+            for (i, column) in table.columns.iter().enumerate().filter(|(_, c)| c.index) {
+                let mut indexed = HashSet::new();
+                for entry in self.scan_index(&table.name, &column.name)? {
+                    let (value, ids) = entry?;
+                    for id in ids {
+                        match rows.get(&id) {
+                            None => problems.push(format!(
+                                "Index {}.{} references the missing row {}",
+                                table.name,
+                                column.name,
+                                format_key(&id)
+                            )),
+                            Some(row) if row[i] != value => problems.push(format!(
+                                "Index {}.{} references row {} under the value {}",
+                                table.name,
+                                column.name,
+                                format_key(&id),
+                                value
+                            )),
+                            Some(_) => {}
+                        }
+                        if !indexed.insert(id.clone()) {
+                            problems.push(format!(
+                                "Index {}.{} references row {} more than once",
+                                table.name,
+                                column.name,
+                                format_key(&id)
+                            ));
+                        }
+                    }
+                }
+                for id in rows.keys().filter(|id| !indexed.contains(*id)) {
+                    problems.push(format!(
+                        "Row {} of table {} is missing from index {}.{}",
+                        format_key(id),
+                        table.name,
+                        table.name,
+                        column.name
+                    ));
+                }
+            }
+        }
+        Ok(problems)
+    }
 }
 
 impl Catalog for KVTransaction {
@@ -275,7 +507,7 @@ impl Catalog for KVTransaction {
     }
 
     fn create_table(&mut self, table: Table) -> crate::error::Result<()> {
-        if self.read_table(&table.name)?.is_some() {
+        if self.read_table(&table.name)?.is_some() || self.read_view(&table.name)?.is_some() {
             return Err(Error::Value(format!(
                 "Create table name {} already exists.",
                 table.name
@@ -290,10 +522,24 @@ impl Catalog for KVTransaction {
 
     fn delete_table(&mut self, table: &str) -> crate::error::Result<()> {
         let table = self.must_read_table(table)?;
-        if let Some((t, cs)) = self.table_references(&table.name, false)?.first() {
+        if table.kind != TableKind::Table {
+            return Err(Error::Value(format!(
+                "{} is a {}, use DROP MATERIALIZED VIEW",
+                table.name, table.kind
+            )));
+        }
+        if let Some((t, foreign_key)) = self.table_references(&table.name, false)?.first() {
             return Err(Error::Value(format!(
                 "Table {} is referenced by table {} column {}",
-                table.name, t, cs[0]
+                table.name,
+                t.name,
+                foreign_key.columns.join(", ")
+            )));
+        }
+        if let Some(view) = self.view_references(&table.name)?.first() {
+            return Err(Error::Value(format!(
+                "Table {} is referenced by view {}",
+                table.name, view.name
             )));
         }
         let mut scan = self.scan(&table.name, None)?;
@@ -305,16 +551,124 @@ impl Catalog for KVTransaction {
     }
 
     fn scan_table(&self) -> Result<Tables> {
+        let mut tables = self
+            .txn
+            .scan_prefix(&SQLKey::Table(None).encode())?
+            .map(|r| {
+                r.and_then(|(_, v)| {
+                    deserialize(&v).or(Err(Error::Internal("Excepted scan".into())))
+                })
+            })
+            .collect::<Result<Vec<Table>>>()?;
+        // a materialized view is listed by its table
+        tables.extend(
+            self.scan_view()?
+                .filter(|v| !v.materialized)
+                .map(|v| Table {
+                    name: v.name,
+                    columns: vec![],
+                    kind: TableKind::View,
+                    checks: vec![],
+                    unique_keys: vec![],
+                    foreign_keys: vec![],
+                    compression: Compression::None,
+                }),
+        );
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Box::new(tables.into_iter()))
+    }
+
+    fn create_view(&mut self, view: View) -> Result<()> {
+        if self.read_table(&view.name)?.is_some() || self.read_view(&view.name)?.is_some() {
+            return Err(Error::Value(format!(
+                "Create view name {} already exists.",
+                view.name
+            )));
+        }
+        let key = &SQLKey::View(Some((&view.name).into())).encode();
+        self.txn.set(key, serialize(&view)?)
+    }
+
+    fn delete_view(&mut self, view: &str) -> Result<()> {
+        let view = self
+            .read_view(view)?
+            .ok_or_else(|| Error::Value(format!("View {} does not exist.", view)))?;
+        if let Some(v) = self.view_references(&view.name)?.first() {
+            return Err(Error::Value(format!(
+                "View {} is referenced by view {}",
+                view.name, v.name
+            )));
+        }
+        if view.materialized {
+            for key in self.row_keys(&view.name)? {
+                self.txn.delete(&key)?;
+            }
+            self.txn
+                .delete(&SQLKey::Table(Some((&view.name).into())).encode())?;
+        }
+        self.txn
+            .delete(&SQLKey::View(Some((&view.name).into())).encode())
+    }
+
+    fn read_view(&self, view: &str) -> Result<Option<View>> {
+        self.txn
+            .get(&SQLKey::View(Some(view.into())).encode())?
+            .map(|v| deserialize(&v))
+            .transpose()
+            .map_err(|e| e.into())
+    }
+
+    fn scan_view(&self) -> Result<Views> {
         Ok(Box::new(
             self.txn
-                .scan_prefix(&SQLKey::Table(None).encode())?
-                .map(|r| {
-                    r.and_then(|(_, v)| {
-                        deserialize(&v).or(Err(Error::Internal("Excepted scan".into())))
-                    })
-                })
+                .scan_prefix(&SQLKey::View(None).encode())?
+                .map(|r| r.and_then(|(_, v)| Ok(deserialize(&v)?)))
                 .collect::<Result<Vec<_>>>()?
                 .into_iter(),
         ))
     }
+
+    fn create_user(&mut self, user: User) -> Result<()> {
+        if self.read_user(&user.name)?.is_some() {
+            return Err(Error::Value(format!(
+                "Create user name {} already exists.",
+                user.name
+            )));
+        }
+        self.update_user(user)
+    }
+
+    fn update_user(&mut self, user: User) -> Result<()> {
+        let key = &SQLKey::User(Some((&user.name).into())).encode();
+        self.txn.set(key, serialize(&user)?)
+    }
+
+    fn delete_user(&mut self, user: &str) -> Result<()> {
+        let user = self.must_read_user(user)?;
+        self.txn
+            .delete(&SQLKey::User(Some((&user.name).into())).encode())
+    }
+
+    fn read_user(&self, user: &str) -> Result<Option<User>> {
+        self.txn
+            .get(&SQLKey::User(Some(user.into())).encode())?
+            .map(|v| deserialize(&v))
+            .transpose()
+            .map_err(|e| e.into())
+    }
+
+    fn scan_user(&self) -> Result<Users> {
+        Ok(Box::new(
+            self.txn
+                .scan_prefix(&SQLKey::User(None).encode())?
+                .map(|r| r.and_then(|(_, v)| Ok(deserialize(&v)?)))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter(),
+        ))
+    }
+}
+
+/// decodes a stored row or index entry, whatever codec compressed it
+fn decode<T: DeserializeOwned>(value: &[u8]) -> Result<T> {
+    Ok(deserialize(&Compression::decompress(value)?)?)
 }
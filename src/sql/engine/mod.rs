@@ -1,11 +1,16 @@
 mod kv;
 mod sql_catalog;
 mod sql_engine;
+mod sql_interrupt;
+mod sql_prepared;
 mod sql_session;
 mod sql_transaction;
 
 pub use kv::KVEngine;
 pub use sql_catalog::Catalog;
 pub use sql_engine::SQLEngine;
+pub use sql_interrupt::Interrupt;
+pub use sql_prepared::ColumnTypes;
+pub use sql_prepared::Prepared;
 pub use sql_session::SQLSession;
 pub use sql_transaction::SQLTransaction;
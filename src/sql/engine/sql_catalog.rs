@@ -2,6 +2,11 @@ use crate::error::Error;
 use crate::error::Result;
 use crate::sql::schema::table::Table;
 use crate::sql::schema::table::Tables;
+use crate::sql::schema::table_constraint::ForeignKey;
+use crate::sql::schema::user::User;
+use crate::sql::schema::user::Users;
+use crate::sql::schema::view::View;
+use crate::sql::schema::view::Views;
 
 pub trait Catalog {
     /// create table
@@ -13,31 +18,68 @@ pub trait Catalog {
     /// Read a table, if it exists
     fn read_table(&self, table: &str) -> Result<Option<Table>>;
 
-    /// iterator over all tables
+    /// iterator over all tables, including views and materialized views
     fn scan_table(&self) -> Result<Tables>;
 
+    /// create view
+    fn create_view(&mut self, view: View) -> Result<()>;
+
+    /// delete view, and the rows of a materialized view
+    fn delete_view(&mut self, view: &str) -> Result<()>;
+
+    /// Read a view, if it exists
+    fn read_view(&self, view: &str) -> Result<Option<View>>;
+
+    /// iterator over all views
+    fn scan_view(&self) -> Result<Views>;
+
+    /// create user
+    fn create_user(&mut self, user: User) -> Result<()>;
+
+    /// replace a user, e.g. with its privileges changed
+    fn update_user(&mut self, user: User) -> Result<()>;
+
+    /// delete user
+    fn delete_user(&mut self, user: &str) -> Result<()>;
+
+    /// Read a user, if it exists
+    fn read_user(&self, user: &str) -> Result<Option<User>>;
+
+    /// iterator over all users
+    fn scan_user(&self) -> Result<Users>;
+
     /// Read a table, and error if it does not exists
     fn must_read_table(&self, table: &str) -> Result<Table> {
         self.read_table(table)?
             .ok_or_else(|| Error::Value(format!("Table {} does not exist.", table)))
     }
 
-    /// return all references to a table, as table,column pairs
-    fn table_references(&self, table: &str, with_self: bool) -> Result<Vec<(String, Vec<String>)>> {
+    /// Read a user, and error if it does not exists
+    fn must_read_user(&self, user: &str) -> Result<User> {
+        self.read_user(user)?
+            .ok_or_else(|| Error::Value(format!("User {} does not exist.", user)))
+    }
+
+    /// return all foreign keys referencing a table, with their tables
+    fn table_references(&self, table: &str, with_self: bool) -> Result<Vec<(Table, ForeignKey)>> {
         Ok(self
             .scan_table()?
             .filter(|t| with_self || t.name != table)
-            .map(|t| {
-                (
-                    t.name,
-                    t.columns
-                        .iter()
-                        .filter(|c| c.references.as_deref() == Some(table))
-                        .map(|c| c.name.clone())
-                        .collect::<Vec<_>>(),
-                )
+            .flat_map(|t| {
+                t.foreign_keys
+                    .iter()
+                    .filter(|fk| fk.table == table)
+                    .map(|fk| (t.clone(), fk.clone()))
+                    .collect::<Vec<_>>()
             })
-            .filter(|(_, cs)| !cs.is_empty())
-            .collect::<Vec<_>>())
+            .collect())
+    }
+
+    /// return all views reading a table or a view
+    fn view_references(&self, name: &str) -> Result<Vec<View>> {
+        Ok(self
+            .scan_view()?
+            .filter(|v| v.name != name && v.dependencies.iter().any(|d| d == name))
+            .collect())
     }
 }
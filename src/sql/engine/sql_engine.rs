@@ -1,6 +1,11 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
 use super::sql_session::SQLSession;
 use super::sql_transaction::SQLTransaction;
 use crate::error::Result;
+use crate::sql::plan::planners::DEFAULT_MAX_RECURSIVE_ROWS;
 use crate::storage::mvcc::TransactionMode;
 
 /// A SQL-Engine
@@ -19,6 +24,10 @@ pub trait SQLEngine: Clone {
         Ok(SQLSession {
             engine: self.clone(),
             txn: None,
+            user: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            statement_timeout: AtomicU64::new(0),
+            max_recursive_rows: AtomicU64::new(DEFAULT_MAX_RECURSIVE_ROWS),
         })
     }
 }
@@ -0,0 +1,71 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// interrupts a statement, by a cancel request of its session or by its
+/// statement timeout. it is checked for every row scanned, so it also
+/// interrupts the rows of a query being fetched
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    /// the cancel flag of the session
+    cancelled: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+    /// the max_recursive_rows of the session, None for no limit
+    max_recursive_rows: Option<u64>,
+}
+
+impl Interrupt {
+    /// the interrupt of a statement starting now, no timeout if None
+    pub fn new(cancelled: Arc<AtomicBool>, timeout: Option<Duration>) -> Self {
+        Self {
+            cancelled: Some(cancelled),
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            max_recursive_rows: None,
+        }
+    }
+
+    /// also interrupts a recursive query producing more than
+    /// max_recursive_rows rows
+    pub fn with_max_recursive_rows(mut self, max_recursive_rows: Option<u64>) -> Self {
+        self.max_recursive_rows = max_recursive_rows;
+        self
+    }
+
+    /// an error if the recursive query has produced more rows than allowed
+    pub fn check_recursive_rows(&self, name: &str, rows: usize) -> Result<()> {
+        match self.max_recursive_rows {
+            Some(max) if rows as u64 > max => Err(Error::Value(format!(
+                "Recursive query {} exceeded max_recursive_rows of {} rows",
+                name, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// an error if the statement was cancelled or ran out of time
+    pub fn check(&self) -> Result<()> {
+        if self
+            .cancelled
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+        {
+            return Err(Error::Cancelled(
+                "Canceling statement due to user request".into(),
+            ));
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Error::Cancelled(
+                "Canceling statement due to statement timeout".into(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use std::cell::RefCell;
+
+use super::Catalog;
+use super::SQLTransaction;
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan_parser::PlanParser;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::table::Tables;
+use crate::sql::schema::user::Privilege;
+use crate::sql::schema::user::User;
+use crate::sql::schema::user::Users;
+use crate::sql::schema::view::View;
+use crate::sql::schema::view::Views;
+use crate::sql::sql_executor::KVExecutor;
+
+/// the names and the types of the columns of a query
+pub type ColumnTypes = Vec<(Option<String>, Option<DataType>)>;
+
+/// a statement planned once, executed with the values of its parameters
+#[derive(Debug, Clone)]
+pub struct Prepared {
+    sql: String,
+    plan: PlanNode,
+    /// the types of the parameters $1, $2, ..., None where any type is taken
+    parameters: Vec<Option<DataType>>,
+    /// the names and the types of the columns of a query, None for a
+    /// statement without rows
+    columns: Option<ColumnTypes>,
+    /// the tables and views read by the planning, the plan is built again
+    /// when one of them has changed
+    tables: Vec<(String, Option<Table>)>,
+    views: Vec<(String, Option<View>)>,
+}
+
+impl Prepared {
+    /// parse and plan a statement
+    pub fn new<C: Catalog>(sql: &str, catalog: &mut C) -> Result<Self> {
+        let mut recorder = Recorder {
+            catalog,
+            tables: RefCell::new(Vec::new()),
+            views: RefCell::new(Vec::new()),
+        };
+        let (mut plan, scope) = PlanParser::parser_scope(sql, &mut recorder)?;
+        let parameters = parameter_types(&mut plan, &recorder)?;
+        let columns = match scope {
+            Some(scope) => Some(
+                (0..scope.len())
+                    .map(|i| Ok((scope.get_column(i)?.1, scope.get_type(i))))
+                    .collect::<Result<_>>()?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            sql: sql.to_string(),
+            plan,
+            parameters,
+            columns,
+            tables: recorder.tables.into_inner(),
+            views: recorder.views.into_inner(),
+        })
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn parameters(&self) -> &[Option<DataType>] {
+        &self.parameters
+    }
+
+    /// the columns of a query, None for a statement without rows. the
+    /// statement is planned again if a table it
+    /// reads has changed, it is not executed: the user must hold the SELECT
+    /// privilege on the tables a query reads
+    pub fn describe<T: SQLTransaction>(&mut self, txn: &mut T) -> Result<Option<&ColumnTypes>> {
+        if !self.is_current(txn)? {
+            *self = Self::new(&self.sql, txn)?;
+        }
+        if self.columns.is_some() {
+            let mut tables = Vec::new();
+            scanned_tables(&self.plan, &mut tables);
+            for table in tables {
+                txn.authorize(Privilege::Select, table)?;
+            }
+        }
+        Ok(self.columns.as_ref())
+    }
+
+    /// executes the statement with the values of its parameters
+    pub fn execute<T: SQLTransaction + 'static>(
+        &mut self,
+        params: &[DataValue],
+        txn: &mut T,
+    ) -> Result<ResultSet> {
+        if !self.is_current(txn)? {
+            *self = Self::new(&self.sql, txn)?;
+        }
+        if params.len() != self.parameters.len() {
+            return Err(Error::Value(format!(
+                "Expected {} parameters, got {}",
+                self.parameters.len(),
+                params.len()
+            )));
+        }
+        for (i, (value, datatype)) in params.iter().zip(&self.parameters).enumerate() {
+            match (value.data_type(), datatype) {
+                (Some(t), Some(datatype)) if &t != datatype => {
+                    return Err(Error::Value(format!(
+                        "Invalid datatype {} for parameter ${}, expected {}",
+                        t,
+                        i + 1,
+                        datatype
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        let mut plan = self.plan.clone();
+        plan.try_for_each_expression(&mut |expr| {
+            if let Expression::Parameter(i) = expr {
+                *expr = Expression::Constant(params[*i].clone());
+            }
+            Ok(())
+        })?;
+        <dyn KVExecutor<T>>::build(plan).execute(txn)
+    }
+
+    /// whether the tables and views read by the planning are unchanged
+    fn is_current<C: Catalog>(&self, catalog: &C) -> Result<bool> {
+        for (name, table) in &self.tables {
+            if &catalog.read_table(name)? != table {
+                return Ok(false);
+            }
+        }
+        for (name, view) in &self.views {
+            if &catalog.read_view(name)? != view {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// the types of the parameters of a plan. a parameter given as the value of
+/// a column by an INSERT or UPDATE takes the type of the column
+fn parameter_types<C: Catalog>(plan: &mut PlanNode, catalog: &C) -> Result<Vec<Option<DataType>>> {
+    let mut count = 0;
+    plan.try_for_each_expression(&mut |expr| {
+        if let Expression::Parameter(i) = expr {
+            count = count.max(*i + 1);
+        }
+        Ok(())
+    })?;
+    let mut types = vec![None; count];
+
+    let mut column_value = |table: &Table, column: Option<usize>, expr: &Expression| {
+        if let (Some(column), Expression::Parameter(i)) = (column, expr) {
+            types[*i] = table.columns.get(column).map(|c| c.datatype.clone());
+        }
+    };
+    match plan {
+        PlanNode::Insert(plan) => {
+            let table = catalog.must_read_table(&plan.table_name)?;
+            for row in &plan.rows {
+                for (i, expr) in row.iter().enumerate() {
+                    let column = match plan.columns.get(i) {
+                        Some(name) => table.get_column_index(name).ok(),
+                        None if plan.columns.is_empty() => Some(i),
+                        None => None,
+                    };
+                    column_value(&table, column, expr);
+                }
+            }
+        }
+        PlanNode::Update(plan) => {
+            let table = catalog.must_read_table(&plan.table_name)?;
+            for (column, _, expr) in &plan.expressions {
+                column_value(&table, Some(*column), expr);
+            }
+        }
+        _ => {}
+    }
+    Ok(types)
+}
+
+/// the tables scanned by a plan
+fn scanned_tables<'a>(plan: &'a PlanNode, tables: &mut Vec<&'a str>) {
+    if let PlanNode::Scan(scan) = plan {
+        tables.push(&scan.table_name);
+    }
+    for source in plan.sources() {
+        scanned_tables(source, tables);
+    }
+}
+
+/// a catalog recording the tables and views read through it
+struct Recorder<'a, C: Catalog> {
+    catalog: &'a mut C,
+    tables: RefCell<Vec<(String, Option<Table>)>>,
+    views: RefCell<Vec<(String, Option<View>)>>,
+}
+
+impl<'a, C: Catalog> Catalog for Recorder<'a, C> {
+    fn create_table(&mut self, table: Table) -> Result<()> {
+        self.catalog.create_table(table)
+    }
+
+    fn delete_table(&mut self, table: &str) -> Result<()> {
+        self.catalog.delete_table(table)
+    }
+
+    fn read_table(&self, table: &str) -> Result<Option<Table>> {
+        let result = self.catalog.read_table(table)?;
+        self.tables
+            .borrow_mut()
+            .push((table.to_string(), result.clone()));
+        Ok(result)
+    }
+
+    fn scan_table(&self) -> Result<Tables> {
+        self.catalog.scan_table()
+    }
+
+    fn create_view(&mut self, view: View) -> Result<()> {
+        self.catalog.create_view(view)
+    }
+
+    fn delete_view(&mut self, view: &str) -> Result<()> {
+        self.catalog.delete_view(view)
+    }
+
+    fn read_view(&self, view: &str) -> Result<Option<View>> {
+        let result = self.catalog.read_view(view)?;
+        self.views
+            .borrow_mut()
+            .push((view.to_string(), result.clone()));
+        Ok(result)
+    }
+
+    fn scan_view(&self) -> Result<Views> {
+        self.catalog.scan_view()
+    }
+
+    fn create_user(&mut self, user: User) -> Result<()> {
+        self.catalog.create_user(user)
+    }
+
+    fn update_user(&mut self, user: User) -> Result<()> {
+        self.catalog.update_user(user)
+    }
+
+    fn delete_user(&mut self, user: &str) -> Result<()> {
+        self.catalog.delete_user(user)
+    }
+
+    fn read_user(&self, user: &str) -> Result<Option<User>> {
+        self.catalog.read_user(user)
+    }
+
+    fn scan_user(&self) -> Result<Users> {
+        self.catalog.scan_user()
+    }
+}
@@ -1,9 +1,21 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
 use super::sql_engine::SQLEngine;
+use super::ColumnTypes;
+use super::Interrupt;
+use super::Prepared;
 use super::SQLTransaction;
 use crate::common::result::ResultSet;
 use crate::error::Error;
 use crate::error::Result;
+use crate::sql::plan::planners::MAX_RECURSIVE_ROWS;
+use crate::sql::plan::planners::STATEMENT_TIMEOUT;
 use crate::sql::plan_parser::PlanParser;
+use crate::sql::schema::data_value::DataValue;
 use crate::storage::mvcc::TransactionMode;
 
 /// A SQL-Session
@@ -12,15 +24,64 @@ pub struct SQLSession<E: SQLEngine> {
     pub engine: E,
     /// the current session transaction
     pub txn: Option<E::Transaction>,
+    /// the authenticated user, whose privileges are checked by the
+    /// executors. None if every statement is allowed
+    pub user: Option<String>,
+    /// set to cancel the statement running, or the rows of its query being
+    /// fetched. it is cleared when a statement starts
+    pub cancel: Arc<AtomicBool>,
+    /// the time a statement may run, in milliseconds, 0 for no limit
+    pub statement_timeout: AtomicU64,
+    /// the most rows a recursive query may produce, 0 for no limit
+    pub max_recursive_rows: AtomicU64,
 }
 
 impl<E: SQLEngine + 'static> SQLSession<E> {
     /// execute a query, managing transaction status for the session
     pub fn execute(&self, query: &str) -> Result<ResultSet> {
-        let mut txn = self.engine.begin(TransactionMode::ReadWrite)?;
+        self.cancel.store(false, Ordering::Relaxed);
+        let mut txn = self.begin(TransactionMode::ReadWrite)?;
         match PlanParser::parser(query, &mut txn)?.execute(&mut txn) {
             Ok(result) => {
                 txn.commit()?;
+                self.apply(&result);
+                Ok(result)
+            }
+            Err(e) => {
+                txn.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// prepare a statement, it is planned in a read-only transaction
+    pub fn prepare(&self, query: &str) -> Result<Prepared> {
+        let mut txn = self.begin(TransactionMode::ReadOnly)?;
+        let result = Prepared::new(query, &mut txn);
+        txn.rollback()?;
+        result
+    }
+
+    /// the columns of a prepared statement, see Prepared::describe
+    pub fn describe(&self, prepared: &mut Prepared) -> Result<Option<ColumnTypes>> {
+        let mut txn = self.begin(TransactionMode::ReadOnly)?;
+        let result = prepared.describe(&mut txn).map(|columns| columns.cloned());
+        txn.rollback()?;
+        result
+    }
+
+    /// execute a prepared statement with the values of its parameters
+    pub fn execute_prepared(
+        &self,
+        prepared: &mut Prepared,
+        params: &[DataValue],
+    ) -> Result<ResultSet> {
+        self.cancel.store(false, Ordering::Relaxed);
+        let mut txn = self.begin(TransactionMode::ReadWrite)?;
+        match prepared.execute(params, &mut txn) {
+            Ok(result) => {
+                txn.commit()?;
+                self.apply(&result);
                 Ok(result)
             }
             Err(e) => {
@@ -32,7 +93,9 @@ impl<E: SQLEngine + 'static> SQLSession<E> {
 
     /// runs a closure in the session's transaction, or a new transaction if none is active
     pub fn with_txn<R, F>(&mut self, mode: TransactionMode, f: F) -> Result<R>
-    where F: FnOnce(&mut E::Transaction) -> Result<R> {
+    where
+        F: FnOnce(&mut E::Transaction) -> Result<R>,
+    {
         if let Some(ref mut txn) = self.txn {
             if !txn.mode().satisfies(&mode) {
                 return Err(Error::Value(
@@ -43,9 +106,45 @@ impl<E: SQLEngine + 'static> SQLSession<E> {
         }
 
         // a new transaction
-        let mut txn = self.engine.begin(mode)?;
+        let mut txn = self.begin(mode)?;
         let result = f(&mut txn);
         txn.rollback()?;
         result
     }
+
+    /// begins a transaction of the session's user, interrupted by a cancel
+    /// or by the statement timeout
+    fn begin(&self, mode: TransactionMode) -> Result<E::Transaction> {
+        let mut txn = self.engine.begin(mode)?;
+        txn.set_user(self.user.clone());
+        let timeout = match self.statement_timeout.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        let max_recursive_rows = match self.max_recursive_rows.load(Ordering::Relaxed) {
+            0 => None,
+            rows => Some(rows),
+        };
+        txn.set_interrupt(
+            Interrupt::new(self.cancel.clone(), timeout)
+                .with_max_recursive_rows(max_recursive_rows),
+        );
+        Ok(txn)
+    }
+
+    /// applies the settings changed by a statement
+    fn apply(&self, result: &ResultSet) {
+        if let ResultSet::SetVariable {
+            name,
+            value: DataValue::Integer(value),
+        } = result
+        {
+            let setting = match name.as_str() {
+                STATEMENT_TIMEOUT => &self.statement_timeout,
+                MAX_RECURSIVE_ROWS => &self.max_recursive_rows,
+                _ => return,
+            };
+            setting.store((*value).max(0) as u64, Ordering::Relaxed);
+        }
+    }
 }
@@ -1,10 +1,14 @@
 use std::collections::HashSet;
 
 use super::Catalog;
+use super::Interrupt;
 use crate::common::result::DataRow;
+use crate::error::Error;
 use crate::error::Result;
 use crate::sql::plan::plan_expression::Expression;
 use crate::sql::schema::data_value::DataValue;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::user::Privilege;
 use crate::storage::mvcc::TransactionMode;
 
 /// a row scan iterator
@@ -12,7 +16,7 @@ pub type Scan = Box<dyn DoubleEndedIterator<Item = Result<DataRow>> + Send>;
 
 /// an index scan iterator
 pub type IndexScan =
-    Box<dyn DoubleEndedIterator<Item = Result<(DataValue, HashSet<DataValue>)>> + Send>;
+    Box<dyn DoubleEndedIterator<Item = Result<(DataValue, HashSet<Vec<DataValue>>)>> + Send>;
 
 /// A SQL-Transaction interface
 /// All implementations of this trait need to implement Catalog at the same time
@@ -25,24 +29,85 @@ pub trait SQLTransaction: Catalog {
     fn commit(self) -> Result<()>;
     /// Rolls back the transaction
     fn rollback(self) -> Result<()>;
+    /// The user whose privileges are checked, None if every statement is
+    /// allowed
+    fn user(&self) -> Option<&str>;
+    /// Sets the user whose privileges are checked
+    fn set_user(&mut self, user: Option<String>);
+    /// The interrupt of the statement, checked by the scans and the
+    /// executors
+    fn interrupt(&self) -> &Interrupt;
+    /// Sets the interrupt of the statement
+    fn set_interrupt(&mut self, interrupt: Interrupt);
+
+    /// Checks that the user holds a privilege on a table, before an executor
+    /// reads or changes it
+    fn authorize(&self, privilege: Privilege, table: &str) -> Result<()> {
+        match self.user() {
+            Some(user) if !self.must_read_user(user)?.has_privilege(privilege, table) => {
+                Err(Error::Unauthorized(format!(
+                    "User {} has no {} privilege on table {}",
+                    user, privilege, table
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Whether the user holds any privilege on a table, and so may see its
+    /// schema
+    fn can_see(&self, table: &str) -> Result<bool> {
+        match self.user() {
+            Some(user) => {
+                let user = self.must_read_user(user)?;
+                Ok(Privilege::ALL.iter().any(|p| user.has_privilege(*p, table)))
+            }
+            None => Ok(true),
+        }
+    }
+    /// Checks that the user holds any privilege on a table, before its schema
+    /// is described
+    fn authorize_schema(&self, table: &str) -> Result<()> {
+        match self.user() {
+            Some(user) if !self.can_see(table)? => Err(Error::Unauthorized(format!(
+                "User {} has no privilege on table {}",
+                user, table
+            ))),
+            _ => Ok(()),
+        }
+    }
+    /// Checks that the user is a superuser, before the users are managed
+    fn authorize_superuser(&self) -> Result<()> {
+        match self.user() {
+            Some(user) if !self.must_read_user(user)?.superuser => Err(Error::Unauthorized(
+                format!("User {} is not a superuser", user),
+            )),
+            _ => Ok(()),
+        }
+    }
 
     /// Creates a new table row
     fn create(&mut self, table: &str, row: DataRow) -> Result<()>;
-    /// Deletes a table row
-    fn delete(&mut self, table: &str, id: &DataValue) -> Result<()>;
-    /// Reads a table row, if it exists
-    fn read(&self, table: &str, id: &DataValue) -> Result<Option<DataRow>>;
+    /// Deletes a table row, and applies the ON DELETE actions of the foreign
+    /// keys referencing it
+    fn delete(&mut self, table: &str, id: &[DataValue]) -> Result<()>;
+    /// Reads a table row by its primary key, if it exists
+    fn read(&self, table: &str, id: &[DataValue]) -> Result<Option<DataRow>>;
     /// Reads an index entry, if it exists
     fn read_index(
         &self,
         table: &str,
         column: &str,
         value: &DataValue,
-    ) -> Result<HashSet<DataValue>>;
+    ) -> Result<HashSet<Vec<DataValue>>>;
     /// Scans a table's rows
     fn scan(&self, table: &str, filter: Option<Expression>) -> Result<Scan>;
     /// Scans a column's index entries
     fn scan_index(&self, table: &str, column: &str) -> Result<IndexScan>;
     /// Updates a table row
-    fn update(&mut self, table: &str, id: &DataValue, row: DataRow) -> Result<()>;
+    fn update(&mut self, table: &str, id: &[DataValue], row: DataRow) -> Result<()>;
+    /// Replaces the table and the rows of a materialized view
+    fn materialize(&mut self, table: Table, rows: Vec<DataRow>) -> Result<()>;
+    /// Checks the rows and the indexes of every table, and describes each
+    /// problem found
+    fn integrity_check(&mut self) -> Result<Vec<String>>;
 }
@@ -2,6 +2,7 @@ use crate::common::result::ResultSet;
 use crate::error::Result;
 use crate::sql::engine::SQLTransaction;
 use crate::sql::plan::planners::CreateTablePlan;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct CreateTableExec {
@@ -18,6 +19,7 @@ impl<T: SQLTransaction + 'static> KVExecutor<T> for CreateTableExec {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
         let table = self.plan.to_table();
         let name = table.name.clone();
+        txn.authorize(Privilege::Ddl, &name)?;
         txn.create_table(table)?;
         Ok(ResultSet::CreateTable { name })
     }
@@ -0,0 +1,24 @@
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::CreateUserPlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct CreateUserExec {
+    plan: CreateUserPlan,
+}
+
+impl CreateUserExec {
+    pub fn new(plan: CreateUserPlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for CreateUserExec {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize_superuser()?;
+        let name = self.plan.user.name.clone();
+        txn.create_user(self.plan.user)?;
+        Ok(ResultSet::CreateUser { name })
+    }
+}
@@ -0,0 +1,82 @@
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::CreateViewPlan;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
+use crate::sql::schema::table_column::TableColumn;
+use crate::sql::schema::user::Privilege;
+use crate::sql::schema::view::View;
+use crate::sql::sql_executor::KVExecutor;
+use crate::storage::compression::Compression;
+
+pub struct CreateViewExec<T: SQLTransaction> {
+    view: View,
+    source: Option<Box<dyn KVExecutor<T>>>,
+}
+
+impl<T: SQLTransaction + 'static> CreateViewExec<T> {
+    pub fn new(plan: CreateViewPlan) -> Box<Self> {
+        Box::new(Self {
+            view: plan.view,
+            source: plan.source.map(|s| <dyn KVExecutor<T>>::build(*s)),
+        })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for CreateViewExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let name = self.view.name.clone();
+        txn.authorize(Privilege::Ddl, &name)?;
+        txn.create_view(self.view)?;
+        if let Some(source) = self.source {
+            materialize(txn, &name, source)?;
+        }
+        Ok(ResultSet::CreateView { name })
+    }
+}
+
+/// stores the rows of the query of a materialized view, the datatype of a
+/// column is the datatype of its first value which is not NULL
+pub(super) fn materialize<T: SQLTransaction + 'static>(
+    txn: &mut T,
+    name: &str,
+    source: Box<dyn KVExecutor<T>>,
+) -> Result<u64> {
+    let (columns, rows) = match source.execute(txn)? {
+        ResultSet::Query { columns, rows } => (columns, rows.collect::<Result<Vec<_>>>()?),
+        r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+    };
+    let columns = columns
+        .into_iter()
+        .enumerate()
+        .map(|(i, column)| TableColumn {
+            name: column.name.unwrap_or_else(|| format!("column{}", i + 1)),
+            datatype: rows
+                .iter()
+                .find_map(|row| row.get(i).and_then(|v| v.data_type()))
+                .unwrap_or(DataType::String),
+            primary_key: false,
+            nullable: true,
+            default: None,
+            unique: false,
+            index: false,
+        })
+        .collect();
+    let count = rows.len() as u64;
+    txn.materialize(
+        Table {
+            name: name.into(),
+            columns,
+            kind: TableKind::MaterializedView,
+            checks: vec![],
+            unique_keys: vec![],
+            foreign_keys: vec![],
+            compression: Compression::None,
+        },
+        rows,
+    )?;
+    Ok(count)
+}
@@ -1,7 +1,10 @@
+use super::ScanExec;
 use crate::common::result::ResultSet;
 use crate::error::Error;
 use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::plan_node::PlanNode;
 use crate::sql::plan::planners::DeletePlan;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct DeleteExec<T: SQLTransaction> {
@@ -13,13 +16,18 @@ impl<T: SQLTransaction + 'static> DeleteExec<T> {
     pub fn new(plan: DeletePlan) -> Box<Self> {
         Box::new(Self {
             table_name: plan.table_name,
-            source: <dyn KVExecutor<T>>::build(*plan.source),
+            source: match *plan.source {
+                // the rows are read under the privilege of the statement
+                PlanNode::Scan(scan) => ScanExec::with_privilege(scan, Privilege::Delete),
+                source => <dyn KVExecutor<T>>::build(source),
+            },
         })
     }
 }
 
 impl<T: SQLTransaction + 'static> KVExecutor<T> for DeleteExec<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
+        txn.authorize(Privilege::Delete, &self.table_name)?;
         let table = txn.must_read_table(&self.table_name)?;
         let mut count = 0;
         match self.source.execute(txn)? {
@@ -1,6 +1,7 @@
 use crate::common::result::ResultSet;
 use crate::sql::engine::SQLTransaction;
 use crate::sql::plan::planners::DropTablePlan;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct DropTableExec {
@@ -16,6 +17,7 @@ impl DropTableExec {
 impl<T: SQLTransaction + 'static> KVExecutor<T> for DropTableExec {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
         let table_name = self.plan.table_name;
+        txn.authorize(Privilege::Ddl, &table_name)?;
         txn.delete_table(&table_name)?;
         Ok(ResultSet::DropTable { name: table_name })
     }
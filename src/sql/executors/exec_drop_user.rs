@@ -0,0 +1,35 @@
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::DropUserPlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct DropUserExec {
+    plan: DropUserPlan,
+}
+
+impl DropUserExec {
+    pub fn new(plan: DropUserPlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for DropUserExec {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize_superuser()?;
+        let mut names = Vec::new();
+        for name in self.plan.names {
+            if txn.user() == Some(name.as_str()) {
+                return Err(Error::Value(format!("User {} can not drop itself", name)));
+            }
+            match txn.read_user(&name)? {
+                Some(_) => txn.delete_user(&name)?,
+                None if self.plan.if_exists => continue,
+                None => return Err(Error::Value(format!("User {} does not exist.", name))),
+            }
+            names.push(name);
+        }
+        Ok(ResultSet::DropUser { names })
+    }
+}
@@ -0,0 +1,46 @@
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::DropViewPlan;
+use crate::sql::schema::user::Privilege;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct DropViewExec {
+    plan: DropViewPlan,
+}
+
+impl DropViewExec {
+    pub fn new(plan: DropViewPlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for DropViewExec {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let mut names = Vec::new();
+        for name in self.plan.names {
+            txn.authorize(Privilege::Ddl, &name)?;
+            match txn.read_view(&name)? {
+                Some(view) if view.materialized == self.plan.materialized => {}
+                Some(view) if view.materialized => {
+                    return Err(Error::Value(format!(
+                        "{} is a materialized view, use DROP MATERIALIZED VIEW",
+                        name
+                    )))
+                }
+                Some(_) => {
+                    return Err(Error::Value(format!(
+                        "{} is not a materialized view, use DROP VIEW",
+                        name
+                    )))
+                }
+                None if self.plan.if_exists => continue,
+                None => return Err(Error::Value(format!("View {} does not exist.", name))),
+            }
+            txn.delete_view(&name)?;
+            names.push(name);
+        }
+        Ok(ResultSet::DropView { names })
+    }
+}
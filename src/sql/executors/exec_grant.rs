@@ -0,0 +1,33 @@
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::GrantPlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct GrantExec {
+    plan: GrantPlan,
+}
+
+impl GrantExec {
+    pub fn new(plan: GrantPlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for GrantExec {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize_superuser()?;
+        let plan = self.plan;
+        let mut user = txn.must_read_user(&plan.user)?;
+        if plan.revoke {
+            user.revoke(&plan.privileges, &plan.table);
+        } else {
+            user.grant(&plan.privileges, &plan.table);
+        }
+        txn.update_user(user)?;
+        Ok(match plan.revoke {
+            false => ResultSet::Grant { user: plan.user },
+            true => ResultSet::Revoke { user: plan.user },
+        })
+    }
+}
@@ -7,6 +7,7 @@ use crate::error::Result;
 use crate::sql::engine::SQLTransaction;
 use crate::sql::plan::planners::InsertPlan;
 use crate::sql::schema::table::Table;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct InsertExec {
@@ -73,6 +74,7 @@ impl InsertExec {
 impl<T: SQLTransaction + 'static> KVExecutor<T> for InsertExec {
     fn execute(self: Box<Self>, txn: &mut T) -> crate::error::Result<ResultSet> {
         let plan = self.plan;
+        txn.authorize(Privilege::Insert, &plan.table_name)?;
         let table = txn.must_read_table(&plan.table_name)?;
         let mut count = 0;
         for expression in plan.rows {
@@ -0,0 +1,35 @@
+use crate::common::result::DataColumn;
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_executor::KVExecutor;
+
+/// returns a row for each problem found, or a single row "ok"
+pub struct IntegrityCheckExec;
+
+impl IntegrityCheckExec {
+    pub fn new() -> Box<Self> {
+        Box::new(Self)
+    }
+}
+
+impl<T: SQLTransaction> KVExecutor<T> for IntegrityCheckExec {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize_superuser()?;
+        let mut problems = txn.integrity_check()?;
+        if problems.is_empty() {
+            problems.push("ok".into());
+        }
+        Ok(ResultSet::Query {
+            columns: vec![DataColumn {
+                name: Some("integrity_check".into()),
+            }],
+            rows: Box::new(
+                problems
+                    .into_iter()
+                    .map(|problem| Ok(vec![DataValue::String(problem)])),
+            ),
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::planners::NestedLoopJoinPlan;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct NestedLoopJoinExec<T: SQLTransaction> {
+    left: Box<dyn KVExecutor<T>>,
+    right: Box<dyn KVExecutor<T>>,
+    predicate: Option<Expression>,
+    outer: bool,
+}
+
+impl<T: SQLTransaction + 'static> NestedLoopJoinExec<T> {
+    pub fn new(plan: NestedLoopJoinPlan) -> Box<Self> {
+        Box::new(Self {
+            left: <dyn KVExecutor<T>>::build(*plan.left),
+            right: <dyn KVExecutor<T>>::build(*plan.right),
+            predicate: plan.predicate,
+            outer: plan.outer,
+        })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for NestedLoopJoinExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let (mut columns, left) = match self.left.execute(txn)? {
+            ResultSet::Query { columns, rows } => (columns, rows),
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+        // the right rows are read once, and iterated for every left row
+        let (right_columns, right) = match self.right.execute(txn)? {
+            ResultSet::Query { columns, rows } => (columns, rows.collect::<Result<Vec<_>>>()?),
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+        let right_width = right_columns.len();
+        columns.extend(right_columns);
+
+        let predicate = self.predicate;
+        let outer = self.outer;
+        let interrupt = txn.interrupt().clone();
+        let rows = left.flat_map(move |row| {
+            let row = match row.and_then(|row| interrupt.check().map(|_| row)) {
+                Ok(row) => row,
+                Err(err) => return vec![Err(err)],
+            };
+            let mut joined = Vec::new();
+            for right_row in &right {
+                let mut candidate = row.clone();
+                candidate.extend(right_row.iter().cloned());
+                match predicate
+                    .as_ref()
+                    .map(|p| p.evaluate(Some(&candidate)))
+                    .transpose()
+                {
+                    Ok(None) | Ok(Some(DataValue::Boolean(true))) => joined.push(Ok(candidate)),
+                    Ok(Some(DataValue::Boolean(false))) | Ok(Some(DataValue::Null)) => {}
+                    Ok(Some(value)) => {
+                        return vec![Err(Error::Value(format!(
+                            "Join predicate returned {}, expected boolean",
+                            value
+                        )))]
+                    }
+                    Err(err) => return vec![Err(err)],
+                }
+            }
+            if outer && joined.is_empty() {
+                let mut candidate = row;
+                candidate.extend(std::iter::repeat_n(DataValue::Null, right_width));
+                joined.push(Ok(candidate));
+            }
+            joined
+        });
+
+        Ok(ResultSet::Query {
+            columns,
+            rows: Box::new(rows),
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use crate::common::result::DataRow;
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::RecursiveCtePlan;
+use crate::sql::plan::planners::ValuesPlan;
+use crate::sql::plan::planners::WorkTablePlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct RecursiveCteExec<T: SQLTransaction> {
+    name: String,
+    all: bool,
+    anchor: Box<dyn KVExecutor<T>>,
+    recursive: PlanNode,
+}
+
+impl<T: SQLTransaction + 'static> RecursiveCteExec<T> {
+    pub fn new(plan: RecursiveCtePlan) -> Box<Self> {
+        Box::new(Self {
+            name: plan.name,
+            all: plan.all,
+            anchor: <dyn KVExecutor<T>>::build(*plan.anchor),
+            recursive: *plan.recursive,
+        })
+    }
+
+    /// replaces the work table of this query with the rows of the last iteration
+    fn bind_work_table(name: &str, node: &mut PlanNode, rows: &[DataRow]) {
+        match node {
+            PlanNode::WorkTable(plan) if plan.name == name => {
+                *node = PlanNode::Values(ValuesPlan {
+                    columns: plan.columns.clone(),
+                    rows: rows
+                        .iter()
+                        .map(|row| row.iter().cloned().map(Expression::Constant).collect())
+                        .collect(),
+                })
+            }
+            node => {
+                for source in node.sources_mut() {
+                    Self::bind_work_table(name, source, rows);
+                }
+            }
+        }
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for RecursiveCteExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let (columns, rows) = match self.anchor.execute(txn)? {
+            ResultSet::Query { columns, rows } => (columns, rows),
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+
+        // UNION only keeps the rows which have not been seen yet,
+        // the iteration stops when no new rows are produced
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        let mut working = Vec::new();
+        for row in rows {
+            let row = row?;
+            if self.all || seen.insert(row.clone()) {
+                working.push(row);
+            }
+        }
+
+        while !working.is_empty() {
+            // a recursive query may never end, e.g. a UNION ALL walking a
+            // cyclic hierarchy, it is stopped by its interrupt or by the
+            // max_recursive_rows of the session
+            txn.interrupt().check()?;
+            txn.interrupt()
+                .check_recursive_rows(&self.name, result.len() + working.len())?;
+            let mut node = self.recursive.clone();
+            Self::bind_work_table(&self.name, &mut node, &working);
+            result.append(&mut working);
+
+            match <dyn KVExecutor<T>>::build(node).execute(txn)? {
+                ResultSet::Query { rows, .. } => {
+                    for row in rows {
+                        let row = row?;
+                        if row.len() != columns.len() {
+                            return Err(Error::Value(format!(
+                                "Recursive query {} returned {} columns, expected {}",
+                                self.name,
+                                row.len(),
+                                columns.len()
+                            )));
+                        }
+                        if self.all || seen.insert(row.clone()) {
+                            working.push(row);
+                        }
+                    }
+                }
+                r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+            }
+        }
+
+        Ok(ResultSet::Query {
+            columns,
+            rows: Box::new(result.into_iter().map(Ok)),
+        })
+    }
+}
+
+pub struct WorkTableExec {
+    plan: WorkTablePlan,
+}
+
+impl WorkTableExec {
+    pub fn new(plan: WorkTablePlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction> KVExecutor<T> for WorkTableExec {
+    fn execute(self: Box<Self>, _txn: &mut T) -> Result<ResultSet> {
+        // the work table is replaced by the rows of an iteration before executing
+        Err(Error::Internal(format!(
+            "Work table {} read outside of its recursive query",
+            self.plan.name
+        )))
+    }
+}
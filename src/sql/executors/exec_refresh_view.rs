@@ -0,0 +1,32 @@
+use super::exec_create_view::materialize;
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::RefreshViewPlan;
+use crate::sql::schema::user::Privilege;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct RefreshViewExec<T: SQLTransaction> {
+    name: String,
+    source: Box<dyn KVExecutor<T>>,
+}
+
+impl<T: SQLTransaction + 'static> RefreshViewExec<T> {
+    pub fn new(plan: RefreshViewPlan) -> Box<Self> {
+        Box::new(Self {
+            name: plan.name,
+            source: <dyn KVExecutor<T>>::build(*plan.source),
+        })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for RefreshViewExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize(Privilege::Ddl, &self.name)?;
+        let count = materialize(txn, &self.name, self.source)?;
+        Ok(ResultSet::RefreshView {
+            name: self.name,
+            count,
+        })
+    }
+}
@@ -2,15 +2,23 @@ use crate::common::result::DataColumn;
 use crate::common::result::ResultSet;
 use crate::sql::engine::SQLTransaction;
 use crate::sql::plan::planners::ScanPlan;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct ScanExec {
     plan: ScanPlan,
+    /// the privilege the rows are read under
+    privilege: Privilege,
 }
 
 impl ScanExec {
     pub fn new(plan: ScanPlan) -> Box<Self> {
-        Box::new(Self { plan })
+        Self::with_privilege(plan, Privilege::Select)
+    }
+
+    /// a scan of the rows of an UPDATE or a DELETE, read under its privilege
+    pub fn with_privilege(plan: ScanPlan, privilege: Privilege) -> Box<Self> {
+        Box::new(Self { plan, privilege })
     }
 }
 
@@ -19,6 +27,7 @@ impl<T: SQLTransaction + 'static> KVExecutor<T> for ScanExec {
         self: Box<Self>,
         txn: &mut T,
     ) -> crate::error::Result<crate::common::result::ResultSet> {
+        txn.authorize(self.privilege, &self.plan.table_name)?;
         let table = txn.must_read_table(&self.plan.table_name)?;
         Ok(ResultSet::Query {
             columns: table
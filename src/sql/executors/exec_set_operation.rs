@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::exec_window::compare_keys;
+use crate::common::result::DataRow;
+use crate::common::result::DataRows;
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::SetOperationPlan;
+use crate::sql::plan::planners::SetOperator;
+use crate::sql::plan::planners::WindowOrder;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct SetOperationExec<T: SQLTransaction> {
+    op: SetOperator,
+    all: bool,
+    left: Box<dyn KVExecutor<T>>,
+    right: Box<dyn KVExecutor<T>>,
+    floats: Vec<usize>,
+    order_by: Vec<WindowOrder>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<T: SQLTransaction + 'static> SetOperationExec<T> {
+    pub fn new(plan: SetOperationPlan) -> Box<Self> {
+        Box::new(Self {
+            op: plan.op,
+            all: plan.all,
+            left: <dyn KVExecutor<T>>::build(*plan.left),
+            right: <dyn KVExecutor<T>>::build(*plan.right),
+            floats: plan.floats,
+            order_by: plan.order_by,
+            limit: plan.limit,
+            offset: plan.offset,
+        })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for SetOperationExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let (columns, left) = match self.left.execute(txn)? {
+            ResultSet::Query { columns, rows } => (columns, rows),
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+        let right = match self.right.execute(txn)? {
+            ResultSet::Query {
+                columns: right,
+                rows,
+            } if right.len() == columns.len() => rows,
+            ResultSet::Query { .. } => {
+                return Err(Error::Value(format!(
+                    "each {} query must have the same number of columns",
+                    self.op
+                )))
+            }
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+
+        // the integers matched to floats are compared as floats
+        let (left, right) = (
+            to_floats(left, self.floats.clone()),
+            to_floats(right, self.floats),
+        );
+
+        let op = self.op;
+        let rows: DataRows = match (op, self.all) {
+            (SetOperator::Union, all) => {
+                // keep the first occurrence of every row, preserving the order
+                let mut seen = HashSet::new();
+                Box::new(left.chain(right).filter(move |row| match row {
+                    Ok(row) if !all => seen.insert(row.clone()),
+                    _ => true,
+                }))
+            }
+            (_, all) => {
+                // the right side has to be read completely before any left
+                // row can be returned, the counts let ALL match rows one by one
+                let mut counts: HashMap<DataRow, usize> = HashMap::new();
+                for row in right {
+                    *counts.entry(row?).or_insert(0) += 1;
+                }
+                let mut seen = HashSet::new();
+                Box::new(left.filter(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(_) => return true,
+                    };
+                    if !all && !seen.insert(row.clone()) {
+                        return false;
+                    }
+                    let matched = match counts.get_mut(row) {
+                        Some(count) if *count > 0 => {
+                            if all {
+                                *count -= 1;
+                            }
+                            true
+                        }
+                        _ => false,
+                    };
+                    match op {
+                        SetOperator::Intersect => matched,
+                        _ => !matched,
+                    }
+                }))
+            }
+        };
+
+        let rows = match self.order_by.is_empty() {
+            true => rows,
+            false => sort(rows, &self.order_by)?,
+        };
+        let (offset, limit) = (self.offset, self.limit.unwrap_or(usize::MAX));
+        let mut skipped = 0;
+        let rows = rows
+            .filter(move |row| {
+                // an error is returned, not skipped
+                if row.is_ok() && skipped < offset {
+                    skipped += 1;
+                    return false;
+                }
+                true
+            })
+            .take(limit);
+
+        Ok(ResultSet::Query {
+            columns,
+            rows: Box::new(rows),
+        })
+    }
+}
+
+/// converts the integers of the given columns to floats
+fn to_floats(rows: DataRows, floats: Vec<usize>) -> DataRows {
+    if floats.is_empty() {
+        return rows;
+    }
+    Box::new(rows.map(move |row| {
+        let mut row = row?;
+        for i in &floats {
+            if let DataValue::Integer(n) = row[*i] {
+                row[*i] = DataValue::Float(n as f64);
+            }
+        }
+        Ok(row)
+    }))
+}
+
+/// sorts the rows by the ORDER BY keys, rows with equal keys keep their order
+fn sort(rows: DataRows, order_by: &[WindowOrder]) -> Result<DataRows> {
+    let mut rows = rows
+        .map(|row| {
+            let row = row?;
+            let keys = order_by
+                .iter()
+                .map(|order| order.expr.evaluate(Some(&row)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((keys, row))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    rows.sort_by(|(a, _), (b, _)| compare_keys(order_by, a, b));
+    Ok(Box::new(rows.into_iter().map(|(_, row)| Ok(row))))
+}
@@ -0,0 +1,25 @@
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::SetVariablePlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct SetVariableExec {
+    plan: SetVariablePlan,
+}
+
+impl SetVariableExec {
+    pub fn new(plan: SetVariablePlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction> KVExecutor<T> for SetVariableExec {
+    fn execute(self: Box<Self>, _txn: &mut T) -> Result<ResultSet> {
+        // the setting belongs to the session, which applies the result
+        Ok(ResultSet::SetVariable {
+            name: self.plan.name,
+            value: self.plan.value,
+        })
+    }
+}
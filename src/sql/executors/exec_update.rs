@@ -1,11 +1,14 @@
 use std::collections::HashSet;
 
+use super::ScanExec;
 use crate::common::result::ResultSet;
 use crate::error::Error;
 use crate::error::Result;
 use crate::sql::engine::SQLTransaction;
 use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
 use crate::sql::plan::planners::UpdatePlan;
+use crate::sql::schema::user::Privilege;
 use crate::sql::sql_executor::KVExecutor;
 
 pub struct UpdateExec<T: SQLTransaction> {
@@ -18,7 +21,11 @@ impl<T: SQLTransaction + 'static> UpdateExec<T> {
     pub fn new(plan: UpdatePlan) -> Box<Self> {
         Box::new(Self {
             table_name: plan.table_name,
-            source: <dyn KVExecutor<T>>::build(*plan.source),
+            source: match *plan.source {
+                // the rows are read under the privilege of the statement
+                PlanNode::Scan(scan) => ScanExec::with_privilege(scan, Privilege::Update),
+                source => <dyn KVExecutor<T>>::build(source),
+            },
             expressions: plan.expressions,
         })
     }
@@ -26,6 +33,7 @@ impl<T: SQLTransaction + 'static> UpdateExec<T> {
 
 impl<T: SQLTransaction + 'static> KVExecutor<T> for UpdateExec<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.authorize(Privilege::Update, &self.table_name)?;
         match self.source.execute(txn)? {
             ResultSet::Query { mut rows, .. } => {
                 let table = txn.must_read_table(&self.table_name)?;
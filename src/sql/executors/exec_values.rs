@@ -0,0 +1,37 @@
+use crate::common::result::DataColumn;
+use crate::common::result::ResultSet;
+use crate::error::Result;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::ValuesPlan;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct ValuesExec {
+    plan: ValuesPlan,
+}
+
+impl ValuesExec {
+    pub fn new(plan: ValuesPlan) -> Box<Self> {
+        Box::new(Self { plan })
+    }
+}
+
+impl<T: SQLTransaction> KVExecutor<T> for ValuesExec {
+    fn execute(self: Box<Self>, _txn: &mut T) -> Result<ResultSet> {
+        let columns = self
+            .plan
+            .columns
+            .into_iter()
+            .map(|name| DataColumn { name: Some(name) })
+            .collect();
+        let rows = self.plan.rows.into_iter().map(|exprs| {
+            exprs
+                .iter()
+                .map(|expr| expr.evaluate(None))
+                .collect::<Result<_>>()
+        });
+        Ok(ResultSet::Query {
+            columns,
+            rows: Box::new(rows),
+        })
+    }
+}
@@ -0,0 +1,307 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::common::result::DataColumn;
+use crate::common::result::DataRow;
+use crate::common::result::ResultSet;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Interrupt;
+use crate::sql::engine::SQLTransaction;
+use crate::sql::plan::planners::WindowExpression;
+use crate::sql::plan::planners::WindowFrame;
+use crate::sql::plan::planners::WindowFrameBound;
+use crate::sql::plan::planners::WindowFunction;
+use crate::sql::plan::planners::WindowOrder;
+use crate::sql::plan::planners::WindowPlan;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_executor::KVExecutor;
+
+pub struct WindowExec<T: SQLTransaction> {
+    source: Box<dyn KVExecutor<T>>,
+    functions: Vec<WindowExpression>,
+}
+
+impl<T: SQLTransaction + 'static> WindowExec<T> {
+    pub fn new(plan: WindowPlan) -> Box<Self> {
+        Box::new(Self {
+            source: <dyn KVExecutor<T>>::build(*plan.source),
+            functions: plan.functions,
+        })
+    }
+}
+
+impl<T: SQLTransaction + 'static> KVExecutor<T> for WindowExec<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let (mut columns, rows) = match self.source.execute(txn)? {
+            ResultSet::Query { columns, rows } => (columns, rows),
+            r => return Err(Error::Internal(format!("Unexpected result {}", r))),
+        };
+        // every function may see the whole partition of a row, so the
+        // source has to be read completely
+        let mut rows = rows.collect::<Result<Vec<_>>>()?;
+        let results = self
+            .functions
+            .iter()
+            .map(|function| evaluate(function, &rows, txn.interrupt()))
+            .collect::<Result<Vec<_>>>()?;
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.extend(results.iter().map(|values| values[i].clone()));
+        }
+        columns.extend(self.functions.iter().map(|function| DataColumn {
+            name: Some(function.function.to_string().to_lowercase()),
+        }));
+
+        Ok(ResultSet::Query {
+            columns,
+            rows: Box::new(rows.into_iter().map(Ok)),
+        })
+    }
+}
+
+/// computes a window function for every row, in the order of the rows. a
+/// frame may span the whole partition, the interrupt is checked for every row
+fn evaluate(
+    window: &WindowExpression,
+    rows: &[DataRow],
+    interrupt: &Interrupt,
+) -> Result<Vec<DataValue>> {
+    // partitions in the order of their first row
+    let mut partitions: Vec<Vec<usize>> = Vec::new();
+    let mut index: HashMap<Vec<DataValue>, usize> = HashMap::new();
+    let mut keys = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        interrupt.check()?;
+        let partition = window
+            .partition_by
+            .iter()
+            .map(|expr| expr.evaluate(Some(row)))
+            .collect::<Result<Vec<_>>>()?;
+        let next = partitions.len();
+        let p = *index.entry(partition).or_insert(next);
+        if p == next {
+            partitions.push(Vec::new());
+        }
+        partitions[p].push(i);
+        keys.push(
+            window
+                .order_by
+                .iter()
+                .map(|order| order.expr.evaluate(Some(row)))
+                .collect::<Result<Vec<_>>>()?,
+        );
+    }
+
+    let mut values = vec![DataValue::Null; rows.len()];
+    for mut partition in partitions {
+        interrupt.check()?;
+        // a stable sort keeps rows with equal keys in their source order
+        partition.sort_by(|a, b| compare_keys(&window.order_by, &keys[*a], &keys[*b]));
+        let peers = Peers::new(&partition, |a, b| {
+            compare_keys(&window.order_by, &keys[a], &keys[b]) == Ordering::Equal
+        });
+        for p in 0..partition.len() {
+            interrupt.check()?;
+            values[partition[p]] = evaluate_row(window, rows, &partition, &peers, p)?;
+        }
+    }
+    Ok(values)
+}
+
+/// computes a window function for the row at position p of a sorted partition
+fn evaluate_row(
+    window: &WindowExpression,
+    rows: &[DataRow],
+    partition: &[usize],
+    peers: &Peers,
+    p: usize,
+) -> Result<DataValue> {
+    use WindowFunction::*;
+    let row = &rows[partition[p]];
+    Ok(match window.function {
+        RowNumber => DataValue::Integer(p as i64 + 1),
+        Rank => DataValue::Integer(peers.start[p] as i64 + 1),
+        DenseRank => DataValue::Integer(peers.group[p] as i64),
+        Lag | Lead => {
+            let offset = match window.args.get(1) {
+                Some(offset) => match offset.evaluate(Some(row))? {
+                    DataValue::Integer(n) if n >= 0 => n as usize,
+                    v => {
+                        return Err(Error::Value(format!(
+                            "{} offset must be a non-negative integer, got {}",
+                            window.function, v
+                        )))
+                    }
+                },
+                None => 1,
+            };
+            let target = match window.function {
+                Lag => p.checked_sub(offset),
+                _ => p.checked_add(offset).filter(|t| *t < partition.len()),
+            };
+            match target {
+                Some(t) => window.args[0].evaluate(Some(&rows[partition[t]]))?,
+                None => match window.args.get(2) {
+                    Some(default) => default.evaluate(Some(row))?,
+                    None => DataValue::Null,
+                },
+            }
+        }
+        _ => {
+            let (start, end) = frame(&window.frame, peers, p, partition.len());
+            aggregate(window, partition[start..end].iter().map(|i| &rows[*i]))?
+        }
+    })
+}
+
+/// computes an aggregate window function over the rows of a frame
+fn aggregate<'a>(
+    window: &WindowExpression,
+    frame: impl Iterator<Item = &'a DataRow>,
+) -> Result<DataValue> {
+    use WindowFunction::*;
+    let arg = match window.args.first() {
+        Some(arg) => arg,
+        // COUNT(*)
+        None => return Ok(DataValue::Integer(frame.count() as i64)),
+    };
+    let values = frame
+        .map(|row| arg.evaluate(Some(row)))
+        .collect::<Result<Vec<_>>>()?;
+    if let FirstValue | LastValue = window.function {
+        let value = match window.function {
+            FirstValue => values.first(),
+            _ => values.last(),
+        };
+        return Ok(value.cloned().unwrap_or(DataValue::Null));
+    }
+
+    // the other aggregates ignore NULL
+    let values = values
+        .into_iter()
+        .filter(|v| v != &DataValue::Null)
+        .collect::<Vec<_>>();
+    Ok(match window.function {
+        Count => DataValue::Integer(values.len() as i64),
+        Min => values
+            .into_iter()
+            .min_by(|a, b| a.compare(b))
+            .unwrap_or(DataValue::Null),
+        Max => values
+            .into_iter()
+            .max_by(|a, b| a.compare(b))
+            .unwrap_or(DataValue::Null),
+        Sum | Avg => {
+            let count = values.len();
+            let mut sum = None;
+            for value in values {
+                sum = Some(match (sum, value) {
+                    (None, v @ DataValue::Integer(_)) | (None, v @ DataValue::Float(_)) => v,
+                    (Some(DataValue::Integer(l)), DataValue::Integer(r)) => DataValue::Integer(
+                        l.checked_add(r)
+                            .ok_or_else(|| Error::Value("Integer overflow".into()))?,
+                    ),
+                    (Some(DataValue::Integer(l)), DataValue::Float(r)) => {
+                        DataValue::Float(l as f64 + r)
+                    }
+                    (Some(DataValue::Float(l)), DataValue::Integer(r)) => {
+                        DataValue::Float(l + r as f64)
+                    }
+                    (Some(DataValue::Float(l)), DataValue::Float(r)) => DataValue::Float(l + r),
+                    (_, v) => {
+                        return Err(Error::Value(format!(
+                            "Can't compute {} of {}",
+                            window.function, v
+                        )))
+                    }
+                });
+            }
+            match (window.function, sum) {
+                (_, None) => DataValue::Null,
+                (Avg, Some(DataValue::Integer(sum))) => DataValue::Float(sum as f64 / count as f64),
+                (Avg, Some(DataValue::Float(sum))) => DataValue::Float(sum / count as f64),
+                (_, Some(sum)) => sum,
+            }
+        }
+        f => return Err(Error::Internal(format!("{} is not an aggregate", f))),
+    })
+}
+
+/// compares the ORDER BY values of two rows
+pub(super) fn compare_keys(order_by: &[WindowOrder], a: &[DataValue], b: &[DataValue]) -> Ordering {
+    for (i, order) in order_by.iter().enumerate() {
+        let ordering = match (&a[i], &b[i]) {
+            (DataValue::Null, DataValue::Null) => Ordering::Equal,
+            (DataValue::Null, _) if order.nulls_first => Ordering::Less,
+            (DataValue::Null, _) => Ordering::Greater,
+            (_, DataValue::Null) if order.nulls_first => Ordering::Greater,
+            (_, DataValue::Null) => Ordering::Less,
+            (l, r) if order.asc => l.compare(r),
+            (l, r) => r.compare(l),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// the peer groups of a sorted partition, i.e. rows with equal ORDER BY values
+struct Peers {
+    /// the position of the first peer of each row
+    start: Vec<usize>,
+    /// the position of the last peer of each row
+    end: Vec<usize>,
+    /// the number of the peer group of each row, starting at 1
+    group: Vec<usize>,
+}
+
+impl Peers {
+    fn new(partition: &[usize], equal: impl Fn(usize, usize) -> bool) -> Self {
+        let len = partition.len();
+        let mut peers = Self {
+            start: vec![0; len],
+            end: vec![0; len],
+            group: vec![1; len],
+        };
+        for p in 1..len {
+            if equal(partition[p - 1], partition[p]) {
+                peers.start[p] = peers.start[p - 1];
+                peers.group[p] = peers.group[p - 1];
+            } else {
+                peers.start[p] = p;
+                peers.group[p] = peers.group[p - 1] + 1;
+            }
+        }
+        for p in (0..len).rev() {
+            peers.end[p] = if p + 1 < len && peers.start[p + 1] == peers.start[p] {
+                peers.end[p + 1]
+            } else {
+                p
+            };
+        }
+        peers
+    }
+}
+
+/// the positions [start, end) of the frame of the row at position p
+fn frame(frame: &WindowFrame, peers: &Peers, p: usize, len: usize) -> (usize, usize) {
+    let start = match frame.start {
+        WindowFrameBound::UnboundedPreceding => 0,
+        WindowFrameBound::Preceding(n) => p.saturating_sub(n as usize),
+        WindowFrameBound::CurrentRow if frame.rows => p,
+        WindowFrameBound::CurrentRow => peers.start[p],
+        WindowFrameBound::Following(n) => p.saturating_add(n as usize),
+        WindowFrameBound::UnboundedFollowing => len,
+    };
+    let end = match frame.end {
+        WindowFrameBound::UnboundedPreceding => 0,
+        WindowFrameBound::Preceding(n) => (p + 1).saturating_sub(n as usize),
+        WindowFrameBound::CurrentRow if frame.rows => p + 1,
+        WindowFrameBound::CurrentRow => peers.end[p] + 1,
+        WindowFrameBound::Following(n) => p.saturating_add(n as usize).saturating_add(1),
+        WindowFrameBound::UnboundedFollowing => len,
+    };
+    let start = start.min(len);
+    (start, end.min(len).max(start))
+}
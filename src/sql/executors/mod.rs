@@ -1,21 +1,48 @@
 mod exec_create_table;
+mod exec_create_user;
+mod exec_create_view;
 mod exec_delete;
 mod exec_drop_table;
+mod exec_drop_user;
+mod exec_drop_view;
 mod exec_filter;
+mod exec_grant;
 mod exec_group_by;
 mod exec_insert;
+mod exec_integrity_check;
+mod exec_join;
 mod exec_nothing;
 mod exec_projection;
+mod exec_recursive_cte;
+mod exec_refresh_view;
 mod exec_scan;
+mod exec_set_operation;
+mod exec_set_variable;
 mod exec_update;
+mod exec_values;
+mod exec_window;
 
 pub use exec_create_table::CreateTableExec;
+pub use exec_create_user::CreateUserExec;
+pub use exec_create_view::CreateViewExec;
 pub use exec_delete::DeleteExec;
 pub use exec_drop_table::DropTableExec;
+pub use exec_drop_user::DropUserExec;
+pub use exec_drop_view::DropViewExec;
 pub use exec_filter::FilterExec;
+pub use exec_grant::GrantExec;
 pub use exec_group_by::GroupByExec;
 pub use exec_insert::InsertExec;
+pub use exec_integrity_check::IntegrityCheckExec;
+pub use exec_join::NestedLoopJoinExec;
 pub use exec_nothing::NothingExec;
 pub use exec_projection::ProjectionExec;
+pub use exec_recursive_cte::RecursiveCteExec;
+pub use exec_recursive_cte::WorkTableExec;
+pub use exec_refresh_view::RefreshViewExec;
 pub use exec_scan::ScanExec;
+pub use exec_set_operation::SetOperationExec;
+pub use exec_set_variable::SetVariableExec;
 pub use exec_update::UpdateExec;
+pub use exec_values::ValuesExec;
+pub use exec_window::WindowExec;
@@ -16,13 +16,18 @@ use crate::common::result::DataRow;
 use crate::common::scope::Scope;
 use crate::error::Error;
 use crate::error::Result;
+use crate::sql::schema::data_type::DataType;
 use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_parser::MAX_PARAMETER;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
 pub enum Expression {
     // Values
     Constant(DataValue),
     Field(usize, Option<(Option<String>, String)>),
+    /// the parameter $n of a prepared statement, by its index from 0. it is
+    /// replaced by a constant when the statement is executed
+    Parameter(usize),
 
     // Logical operations
     And(Box<Expression>, Box<Expression>),
@@ -57,6 +62,7 @@ impl Display for Expression {
             Self::Field(i, None) => format!("#{}", i),
             Self::Field(_, Some((None, name))) => name.to_string(),
             Self::Field(_, Some((Some(table), name))) => format!("{}.{}", table, name),
+            Self::Parameter(i) => format!("${}", i + 1),
 
             Self::And(lhs, rhs) => format!("{} AND {}", lhs, rhs),
             Self::Or(lhs, rhs) => format!("{} OR {}", lhs, rhs),
@@ -200,9 +206,26 @@ impl Expression {
             },
             Expr::UnaryOp { expr, op } => match op {
                 UnaryOperator::Not => Not(Expression::from_expr(expr, scope)?.into()),
+                UnaryOperator::Minus => Negate(Expression::from_expr(expr, scope)?.into()),
+                UnaryOperator::Plus => Assert(Expression::from_expr(expr, scope)?.into()),
                 _ => todo!(),
             },
             Expr::IsNull(expr) => IsNull(Expression::from_expr(expr, scope)?.into()),
+            // the placeholders are made identifiers by the parser, their
+            // numbers size the parameters of a plan
+            Expr::Identifier(ident)
+                if ident.quote_style.is_none() && ident.value.starts_with('$') =>
+            {
+                match ident.value[1..].parse::<usize>() {
+                    Ok(i) if i > 0 && i <= MAX_PARAMETER => Parameter(i - 1),
+                    _ => {
+                        return Err(Error::Value(format!(
+                            "Invalid parameter {}, parameters are numbered from $1 to ${}",
+                            ident.value, MAX_PARAMETER
+                        )))
+                    }
+                }
+            }
             Expr::Identifier(ident) => Field(
                 scope.resolve(None, &ident.to_string())?,
                 Some((None, ident.to_string())),
@@ -226,10 +249,81 @@ impl Expression {
                     return Err(Error::Value(format!("Unsupported SQL statement. {}", expr)));
                 }
             }
+            // the result of a window function is a column added by the window plan
+            Expr::Function(function) if function.over.is_some() => {
+                let label = expr.to_string();
+                let index = scope.resolve(None, &label).map_err(|_| {
+                    Error::Value(format!(
+                        "Window function {} is only allowed in the SELECT list",
+                        label
+                    ))
+                })?;
+                Field(index, Some((None, label)))
+            }
             _ => todo!(),
         })
     }
 
+    /// the datatype of the values of this expression, None if it is not
+    /// known before the expression is evaluated, e.g. NULL or a parameter
+    pub fn data_type(&self, scope: &Scope) -> Option<DataType> {
+        use Expression::*;
+        match self {
+            Constant(value) => value.data_type(),
+            Field(i, _) => scope.get_type(*i),
+            Parameter(_) => None,
+            And(..) | Not(_) | Or(..) | Equal(..) | GreaterThan(..) | IsNull(_) | LessThan(..)
+            | Like(..) => Some(DataType::Boolean),
+            Assert(expr) | Factorial(expr) | Negate(expr) => expr.data_type(scope),
+            Divide(..) => Some(DataType::Float),
+            Exponentiate(..) => Some(DataType::Integer),
+            Add(lhs, rhs) | Modulo(lhs, rhs) | Multiply(lhs, rhs) | Subtract(lhs, rhs) => {
+                match (lhs.data_type(scope)?, rhs.data_type(scope)?) {
+                    (DataType::Integer, DataType::Integer) => Some(DataType::Integer),
+                    (DataType::Integer | DataType::Float, DataType::Integer | DataType::Float) => {
+                        Some(DataType::Float)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// the operands of this expression, mutable
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Constant(_) | Self::Field(..) | Self::Parameter(_) => vec![],
+            Self::Not(expr)
+            | Self::IsNull(expr)
+            | Self::Assert(expr)
+            | Self::Factorial(expr)
+            | Self::Negate(expr) => vec![expr],
+            Self::And(lhs, rhs)
+            | Self::Or(lhs, rhs)
+            | Self::Equal(lhs, rhs)
+            | Self::GreaterThan(lhs, rhs)
+            | Self::LessThan(lhs, rhs)
+            | Self::Add(lhs, rhs)
+            | Self::Divide(lhs, rhs)
+            | Self::Exponentiate(lhs, rhs)
+            | Self::Modulo(lhs, rhs)
+            | Self::Multiply(lhs, rhs)
+            | Self::Subtract(lhs, rhs)
+            | Self::Like(lhs, rhs) => vec![lhs, rhs],
+        }
+    }
+
+    /// calls a closure on this expression and then on its operands
+    pub fn try_for_each(
+        &mut self,
+        f: &mut impl FnMut(&mut Expression) -> Result<()>,
+    ) -> Result<()> {
+        f(self)?;
+        self.children_mut()
+            .into_iter()
+            .try_for_each(|expr| expr.try_for_each(f))
+    }
+
     /// evaluate an expression to a value
     pub fn evaluate(&self, row: Option<&DataRow>) -> Result<DataValue> {
         use DataValue::*;
@@ -237,6 +331,9 @@ impl Expression {
             // constant value
             Self::Constant(c) => c.clone(),
             Self::Field(i, _) => row.and_then(|row| row.get(*i).cloned()).unwrap_or(Null),
+            Self::Parameter(i) => {
+                return Err(Error::Value(format!("Parameter ${} is not bound", i + 1)))
+            }
             Self::Equal(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 (Boolean(lhs), Boolean(rhs)) => Boolean(lhs == rhs),
                 (Integer(lhs), Integer(rhs)) => Boolean(lhs == rhs),
@@ -3,17 +3,32 @@ use std::fmt::Display;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
+use super::plan_expression::Expression;
 use super::planners::CreateTablePlan;
+use super::planners::CreateUserPlan;
+use super::planners::CreateViewPlan;
 use super::planners::DeletePlan;
 use super::planners::DropTablePlan;
+use super::planners::DropUserPlan;
+use super::planners::DropViewPlan;
 use super::planners::FilterPlan;
+use super::planners::GrantPlan;
 use super::planners::GroupByPlan;
 use super::planners::InsertPlan;
+use super::planners::NestedLoopJoinPlan;
 use super::planners::ProjectionPlan;
+use super::planners::RecursiveCtePlan;
+use super::planners::RefreshViewPlan;
 use super::planners::ScanPlan;
+use super::planners::SetOperationPlan;
+use super::planners::SetVariablePlan;
 use super::planners::UpdatePlan;
+use super::planners::ValuesPlan;
+use super::planners::WindowPlan;
+use super::planners::WorkTablePlan;
+use crate::error::Result;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Eq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
 pub enum PlanNode {
     CreateTable(CreateTablePlan),
     DropTable(DropTablePlan),
@@ -24,6 +39,20 @@ pub enum PlanNode {
     GroupBy(GroupByPlan),
     Update(UpdatePlan),
     Delete(DeletePlan),
+    SetOperation(SetOperationPlan),
+    Values(ValuesPlan),
+    NestedLoopJoin(NestedLoopJoinPlan),
+    RecursiveCte(RecursiveCtePlan),
+    WorkTable(WorkTablePlan),
+    Window(WindowPlan),
+    CreateView(CreateViewPlan),
+    DropView(DropViewPlan),
+    RefreshView(RefreshViewPlan),
+    IntegrityCheck,
+    CreateUser(CreateUserPlan),
+    DropUser(DropUserPlan),
+    Grant(GrantPlan),
+    SetVariable(SetVariablePlan),
     Nothing,
 }
 
@@ -39,7 +68,101 @@ impl Display for PlanNode {
             Self::GroupBy(plan) => write!(f, "PlanNode::GrouBy({:?})", plan),
             Self::Update(plan) => write!(f, "PlanNode::Update({:?})", plan),
             Self::Delete(plan) => write!(f, "PlanNode::Delete({:?})", plan),
+            Self::SetOperation(plan) => write!(f, "PlanNode::SetOperation({:?})", plan),
+            Self::Values(plan) => write!(f, "PlanNode::Values({:?})", plan),
+            Self::NestedLoopJoin(plan) => write!(f, "PlanNode::NestedLoopJoin({:?})", plan),
+            Self::RecursiveCte(plan) => write!(f, "PlanNode::RecursiveCte({:?})", plan),
+            Self::WorkTable(plan) => write!(f, "PlanNode::WorkTable({:?})", plan),
+            Self::Window(plan) => write!(f, "PlanNode::Window({:?})", plan),
+            Self::CreateView(plan) => write!(f, "PlanNode::CreateView({:?})", plan),
+            Self::DropView(plan) => write!(f, "PlanNode::DropView({:?})", plan),
+            Self::RefreshView(plan) => write!(f, "PlanNode::RefreshView({:?})", plan),
+            Self::IntegrityCheck => write!(f, "PlanNode::IntegrityCheck"),
+            Self::CreateUser(plan) => write!(f, "PlanNode::CreateUser({})", plan.user.name),
+            Self::DropUser(plan) => write!(f, "PlanNode::DropUser({:?})", plan),
+            Self::Grant(plan) => write!(f, "PlanNode::Grant({:?})", plan),
+            Self::SetVariable(plan) => write!(f, "PlanNode::SetVariable({:?})", plan),
             Self::Nothing => write!(f, "PlanNode::Nothin"),
         }
     }
 }
+
+impl PlanNode {
+    /// the source nodes of this node
+    pub fn sources(&self) -> Vec<&PlanNode> {
+        match self {
+            Self::Filter(plan) => vec![&plan.source],
+            Self::Projection(plan) => vec![&plan.source],
+            Self::GroupBy(plan) => vec![&plan.source],
+            Self::Update(plan) => vec![&plan.source],
+            Self::Delete(plan) => vec![&plan.source],
+            Self::SetOperation(plan) => vec![&plan.left, &plan.right],
+            Self::NestedLoopJoin(plan) => vec![&plan.left, &plan.right],
+            Self::RecursiveCte(plan) => vec![&plan.anchor, &plan.recursive],
+            Self::Window(plan) => vec![&plan.source],
+            _ => vec![],
+        }
+    }
+
+    /// the source nodes of this node, mutable
+    pub fn sources_mut(&mut self) -> Vec<&mut PlanNode> {
+        match self {
+            Self::Filter(plan) => vec![&mut plan.source],
+            Self::Projection(plan) => vec![&mut plan.source],
+            Self::GroupBy(plan) => vec![&mut plan.source],
+            Self::Update(plan) => vec![&mut plan.source],
+            Self::Delete(plan) => vec![&mut plan.source],
+            Self::SetOperation(plan) => vec![&mut plan.left, &mut plan.right],
+            Self::NestedLoopJoin(plan) => vec![&mut plan.left, &mut plan.right],
+            Self::RecursiveCte(plan) => vec![&mut plan.anchor, &mut plan.recursive],
+            Self::Window(plan) => vec![&mut plan.source],
+            _ => vec![],
+        }
+    }
+
+    /// the expressions of this node, mutable, without the ones of its sources
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Insert(plan) => plan.rows.iter_mut().flatten().collect(),
+            Self::Scan(plan) => plan.filter.iter_mut().collect(),
+            Self::Filter(plan) => vec![&mut plan.predicate],
+            Self::Projection(plan) => plan.expressions.iter_mut().map(|(e, _)| e).collect(),
+            Self::GroupBy(plan) => plan.expressions.iter_mut().collect(),
+            Self::Update(plan) => plan.expressions.iter_mut().map(|(_, _, e)| e).collect(),
+            Self::Values(plan) => plan.rows.iter_mut().flatten().collect(),
+            Self::NestedLoopJoin(plan) => plan.predicate.iter_mut().collect(),
+            Self::Window(plan) => plan
+                .functions
+                .iter_mut()
+                .flat_map(|f| {
+                    f.args
+                        .iter_mut()
+                        .chain(f.partition_by.iter_mut())
+                        .chain(f.order_by.iter_mut().map(|o| &mut o.expr))
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// calls a closure on every expression of this node and of its sources
+    pub fn try_for_each_expression(
+        &mut self,
+        f: &mut impl FnMut(&mut Expression) -> Result<()>,
+    ) -> Result<()> {
+        for expr in self.expressions_mut() {
+            expr.try_for_each(f)?;
+        }
+        self.sources_mut()
+            .into_iter()
+            .try_for_each(|node| node.try_for_each_expression(f))
+    }
+
+    /// whether the work table of the given recursive query is read by this plan
+    pub fn reads_work_table(&self, name: &str) -> bool {
+        match self {
+            Self::WorkTable(plan) => plan.name == name,
+            node => node.sources().iter().any(|n| n.reads_work_table(name)),
+        }
+    }
+}
@@ -1,19 +1,53 @@
 mod plan_delete;
 mod plan_filter;
+mod plan_grant;
 mod plan_group_by;
 mod plan_insert;
+mod plan_join;
 mod plan_projection;
+mod plan_recursive_cte;
 mod plan_scan;
+mod plan_set_operation;
+mod plan_set_variable;
 mod plan_table_create;
 mod plan_table_drop;
 mod plan_update;
+mod plan_user_create;
+mod plan_user_drop;
+mod plan_values;
+mod plan_view_create;
+mod plan_view_drop;
+mod plan_view_refresh;
+mod plan_window;
 
 pub use plan_delete::DeletePlan;
 pub use plan_filter::FilterPlan;
+pub use plan_grant::GrantPlan;
 pub use plan_group_by::GroupByPlan;
 pub use plan_insert::InsertPlan;
+pub use plan_join::NestedLoopJoinPlan;
 pub use plan_projection::ProjectionPlan;
+pub use plan_recursive_cte::RecursiveCtePlan;
+pub use plan_recursive_cte::WorkTablePlan;
 pub use plan_scan::ScanPlan;
+pub use plan_set_operation::SetOperationPlan;
+pub use plan_set_operation::SetOperator;
+pub use plan_set_variable::SetVariablePlan;
+pub use plan_set_variable::DEFAULT_MAX_RECURSIVE_ROWS;
+pub use plan_set_variable::MAX_RECURSIVE_ROWS;
+pub use plan_set_variable::STATEMENT_TIMEOUT;
 pub use plan_table_create::CreateTablePlan;
 pub use plan_table_drop::DropTablePlan;
 pub use plan_update::UpdatePlan;
+pub use plan_user_create::CreateUserPlan;
+pub use plan_user_drop::DropUserPlan;
+pub use plan_values::ValuesPlan;
+pub use plan_view_create::CreateViewPlan;
+pub use plan_view_drop::DropViewPlan;
+pub use plan_view_refresh::RefreshViewPlan;
+pub use plan_window::WindowExpression;
+pub use plan_window::WindowFrame;
+pub use plan_window::WindowFrameBound;
+pub use plan_window::WindowFunction;
+pub use plan_window::WindowOrder;
+pub use plan_window::WindowPlan;
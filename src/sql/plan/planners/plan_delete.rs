@@ -3,7 +3,7 @@ use serde_derive::Serialize;
 
 use crate::sql::plan::plan_node::PlanNode;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Eq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
 pub struct DeletePlan {
     pub table_name: String,
     pub source: Box<PlanNode>,
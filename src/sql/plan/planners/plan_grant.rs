@@ -0,0 +1,13 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::schema::user::Privilege;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct GrantPlan {
+    /// REVOKE, the privileges are taken from the user
+    pub revoke: bool,
+    pub privileges: Vec<Privilege>,
+    pub table: String,
+    pub user: String,
+}
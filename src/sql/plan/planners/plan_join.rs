@@ -0,0 +1,15 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct NestedLoopJoinPlan {
+    pub left: Box<PlanNode>,
+    pub right: Box<PlanNode>,
+    /// the join condition, evaluated on the left row followed by the right row
+    pub predicate: Option<Expression>,
+    /// LEFT JOIN, a left row without matches is returned with NULLs
+    pub outer: bool,
+}
@@ -0,0 +1,22 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::plan::plan_node::PlanNode;
+
+/// a WITH RECURSIVE common table expression, the recursive plan is executed
+/// on the rows of the previous iteration until it returns no new rows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct RecursiveCtePlan {
+    pub name: String,
+    /// UNION ALL, keep duplicate rows
+    pub all: bool,
+    pub anchor: Box<PlanNode>,
+    pub recursive: Box<PlanNode>,
+}
+
+/// the rows of the previous iteration of a recursive common table expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct WorkTablePlan {
+    pub name: String,
+    pub columns: Vec<String>,
+}
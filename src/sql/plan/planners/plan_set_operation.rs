@@ -0,0 +1,41 @@
+use std::fmt::Display;
+
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use super::plan_window::WindowOrder;
+use crate::sql::plan::plan_node::PlanNode;
+
+/// the kind of a set operation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl Display for SetOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SetOperator::Union => "UNION",
+            SetOperator::Intersect => "INTERSECT",
+            SetOperator::Except => "EXCEPT",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct SetOperationPlan {
+    pub op: SetOperator,
+    /// keep duplicate rows, e.g. UNION ALL
+    pub all: bool,
+    pub left: Box<PlanNode>,
+    pub right: Box<PlanNode>,
+    /// the columns whose integers are converted to floats, to match the
+    /// floats of the other query
+    pub floats: Vec<usize>,
+    /// ORDER BY, LIMIT and OFFSET of the result
+    pub order_by: Vec<WindowOrder>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
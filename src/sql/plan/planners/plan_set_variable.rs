@@ -0,0 +1,23 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::schema::data_value::DataValue;
+
+/// the time a statement of the session may run, in milliseconds, 0 for no
+/// limit
+pub const STATEMENT_TIMEOUT: &str = "statement_timeout";
+
+/// the most rows a recursive query of the session may produce, 0 for no
+/// limit. the rows are kept in memory, and every iteration adds some, so it
+/// also stops a recursion which never ends
+pub const MAX_RECURSIVE_ROWS: &str = "max_recursive_rows";
+
+/// the default of max_recursive_rows
+pub const DEFAULT_MAX_RECURSIVE_ROWS: u64 = 1_000_000;
+
+/// a setting of the session, applied by the session once executed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct SetVariablePlan {
+    pub name: String,
+    pub value: DataValue,
+}
@@ -4,12 +4,20 @@ use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use crate::sql::schema::table::Table;
+use crate::sql::schema::table::TableKind;
 use crate::sql::schema::table_column::TableColumn;
+use crate::sql::schema::table_constraint::Check;
+use crate::sql::schema::table_constraint::ForeignKey;
+use crate::storage::compression::Compression;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CreateTablePlan {
     pub name: String,
     pub columns: Vec<TableColumn>,
+    pub checks: Vec<Check>,
+    pub unique_keys: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub compression: Compression,
 }
 
 impl CreateTablePlan {
@@ -17,6 +25,11 @@ impl CreateTablePlan {
         Table {
             name: self.name,
             columns: self.columns,
+            kind: TableKind::Table,
+            checks: self.checks,
+            unique_keys: self.unique_keys,
+            foreign_keys: self.foreign_keys,
+            compression: self.compression,
         }
     }
 }
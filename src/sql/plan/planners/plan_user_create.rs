@@ -0,0 +1,9 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::schema::user::User;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct CreateUserPlan {
+    pub user: User,
+}
@@ -0,0 +1,8 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct DropUserPlan {
+    pub names: Vec<String>,
+    pub if_exists: bool,
+}
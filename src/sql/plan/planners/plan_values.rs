@@ -0,0 +1,10 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::plan::plan_expression::Expression;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValuesPlan {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Expression>>,
+}
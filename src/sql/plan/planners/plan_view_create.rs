@@ -0,0 +1,12 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::schema::view::View;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct CreateViewPlan {
+    pub view: View,
+    /// the query of a materialized view, its rows are stored at creation
+    pub source: Option<Box<PlanNode>>,
+}
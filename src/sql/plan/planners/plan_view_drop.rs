@@ -0,0 +1,10 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct DropViewPlan {
+    pub names: Vec<String>,
+    pub if_exists: bool,
+    /// DROP MATERIALIZED VIEW, only drops materialized views
+    pub materialized: bool,
+}
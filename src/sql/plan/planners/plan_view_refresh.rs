@@ -0,0 +1,11 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::sql::plan::plan_node::PlanNode;
+
+/// replaces the rows of a materialized view with the rows of its query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct RefreshViewPlan {
+    pub name: String,
+    pub source: Box<PlanNode>,
+}
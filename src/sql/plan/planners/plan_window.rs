@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use sqlparser::ast;
+use sqlparser::ast::Expr;
+use sqlparser::ast::Function;
+use sqlparser::ast::FunctionArg;
+use sqlparser::ast::WindowFrameUnits;
+
+use crate::common::scope::Scope;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+
+/// the functions that can be used with an OVER clause
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq)]
+pub enum WindowFunction {
+    // Ranking functions
+    RowNumber,
+    Rank,
+    DenseRank,
+
+    // Offset functions
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+
+    // Aggregate functions, computed over the frame of each row
+    Sum,
+    Count,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Display for WindowFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WindowFunction::RowNumber => "ROW_NUMBER",
+            WindowFunction::Rank => "RANK",
+            WindowFunction::DenseRank => "DENSE_RANK",
+            WindowFunction::Lag => "LAG",
+            WindowFunction::Lead => "LEAD",
+            WindowFunction::FirstValue => "FIRST_VALUE",
+            WindowFunction::LastValue => "LAST_VALUE",
+            WindowFunction::Sum => "SUM",
+            WindowFunction::Count => "COUNT",
+            WindowFunction::Avg => "AVG",
+            WindowFunction::Min => "MIN",
+            WindowFunction::Max => "MAX",
+        })
+    }
+}
+
+impl WindowFunction {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name.to_uppercase().as_str() {
+            "ROW_NUMBER" => WindowFunction::RowNumber,
+            "RANK" => WindowFunction::Rank,
+            "DENSE_RANK" => WindowFunction::DenseRank,
+            "LAG" => WindowFunction::Lag,
+            "LEAD" => WindowFunction::Lead,
+            "FIRST_VALUE" => WindowFunction::FirstValue,
+            "LAST_VALUE" => WindowFunction::LastValue,
+            "SUM" => WindowFunction::Sum,
+            "COUNT" => WindowFunction::Count,
+            "AVG" => WindowFunction::Avg,
+            "MIN" => WindowFunction::Min,
+            "MAX" => WindowFunction::Max,
+            _ => return Err(Error::Value(format!("Unknown window function {}", name))),
+        })
+    }
+
+    /// the allowed number of arguments
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => (0, 0),
+            WindowFunction::Lag | WindowFunction::Lead => (1, 3),
+            // COUNT(*) has no arguments
+            WindowFunction::Count => (0, 1),
+            _ => (1, 1),
+        }
+    }
+}
+
+/// a bound of a window frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Eq)]
+pub enum WindowFrameBound {
+    UnboundedPreceding,
+    Preceding(u64),
+    CurrentRow,
+    Following(u64),
+    UnboundedFollowing,
+}
+
+/// the rows of a partition an aggregate window function is computed over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct WindowFrame {
+    /// ROWS counts single rows, RANGE treats rows with equal ORDER BY values
+    /// (i.e. peers) as the same CURRENT ROW
+    pub rows: bool,
+    pub start: WindowFrameBound,
+    pub end: WindowFrameBound,
+}
+
+impl WindowFrame {
+    fn from_frame(frame: Option<&ast::WindowFrame>, ordered: bool) -> Result<Self> {
+        let frame = match frame {
+            Some(frame) => frame,
+            // with ORDER BY the frame ends with the last peer of the row
+            None if ordered => {
+                return Ok(Self {
+                    rows: false,
+                    start: WindowFrameBound::UnboundedPreceding,
+                    end: WindowFrameBound::CurrentRow,
+                })
+            }
+            // without ORDER BY every row of the partition is a peer
+            None => {
+                return Ok(Self {
+                    rows: true,
+                    start: WindowFrameBound::UnboundedPreceding,
+                    end: WindowFrameBound::UnboundedFollowing,
+                })
+            }
+        };
+        let rows = match frame.units {
+            WindowFrameUnits::Rows => true,
+            WindowFrameUnits::Range => false,
+            WindowFrameUnits::Groups => {
+                return Err(Error::Value("GROUPS window frame is not supported".into()))
+            }
+        };
+        let bound = |bound: &ast::WindowFrameBound, start: bool| -> Result<WindowFrameBound> {
+            let bound = match bound {
+                ast::WindowFrameBound::CurrentRow => WindowFrameBound::CurrentRow,
+                ast::WindowFrameBound::Preceding(None) => WindowFrameBound::UnboundedPreceding,
+                ast::WindowFrameBound::Following(None) => WindowFrameBound::UnboundedFollowing,
+                _ if !rows => {
+                    return Err(Error::Value(
+                        "RANGE window frame with an offset is not supported".into(),
+                    ))
+                }
+                ast::WindowFrameBound::Preceding(Some(n)) => WindowFrameBound::Preceding(*n),
+                ast::WindowFrameBound::Following(Some(n)) => WindowFrameBound::Following(*n),
+            };
+            match bound {
+                WindowFrameBound::UnboundedFollowing if start => Err(Error::Value(
+                    "window frame can not start at UNBOUNDED FOLLOWING".into(),
+                )),
+                WindowFrameBound::UnboundedPreceding if !start => Err(Error::Value(
+                    "window frame can not end at UNBOUNDED PRECEDING".into(),
+                )),
+                bound => Ok(bound),
+            }
+        };
+        Ok(Self {
+            rows,
+            start: bound(&frame.start_bound, true)?,
+            end: match &frame.end_bound {
+                Some(end) => bound(end, false)?,
+                None => WindowFrameBound::CurrentRow,
+            },
+        })
+    }
+}
+
+/// an ORDER BY item of a window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct WindowOrder {
+    pub expr: Expression,
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+/// a window function call, e.g. RANK() OVER (PARTITION BY a ORDER BY b)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct WindowExpression {
+    pub function: WindowFunction,
+    pub args: Vec<Expression>,
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<WindowOrder>,
+    pub frame: WindowFrame,
+}
+
+impl WindowExpression {
+    /// builds a window function call resolved against the given scope
+    pub fn from_function(function: &Function, scope: &mut Scope) -> Result<Self> {
+        let window = function.over.as_ref().ok_or_else(|| {
+            Error::Internal(format!("{} is not a window function call", function))
+        })?;
+        if function.distinct {
+            return Err(Error::Value(format!(
+                "DISTINCT is not supported in window function {}",
+                function
+            )));
+        }
+        let kind = WindowFunction::from_name(&function.name.to_string())?;
+        let mut args = Vec::new();
+        for arg in &function.args {
+            match arg {
+                FunctionArg::Unnamed(Expr::Wildcard) if kind == WindowFunction::Count => {}
+                FunctionArg::Unnamed(expr) => args.push(Expression::from_expr(expr, scope)?),
+                FunctionArg::Named { name, .. } => {
+                    return Err(Error::Value(format!(
+                        "Named argument {} is not supported in {}",
+                        name, kind
+                    )))
+                }
+            }
+        }
+        let (min, max) = kind.arity();
+        if args.len() < min || args.len() > max {
+            return Err(Error::Value(format!(
+                "Wrong number of arguments for window function {}",
+                function
+            )));
+        }
+
+        Ok(Self {
+            function: kind,
+            args,
+            partition_by: window
+                .partition_by
+                .iter()
+                .map(|expr| Expression::from_expr(expr, scope))
+                .collect::<Result<_>>()?,
+            order_by: window
+                .order_by
+                .iter()
+                .map(|order| {
+                    let asc = order.asc.unwrap_or(true);
+                    Ok(WindowOrder {
+                        expr: Expression::from_expr(&order.expr, scope)?,
+                        asc,
+                        // NULL is the largest value by default
+                        nulls_first: order.nulls_first.unwrap_or(!asc),
+                    })
+                })
+                .collect::<Result<_>>()?,
+            frame: WindowFrame::from_frame(
+                window.window_frame.as_ref(),
+                !window.order_by.is_empty(),
+            )?,
+        })
+    }
+
+    /// collects the window function calls of an expression
+    pub fn collect(expr: &Expr, functions: &mut Vec<Function>) {
+        match expr {
+            Expr::Function(function) if function.over.is_none() => {
+                for arg in &function.args {
+                    match arg {
+                        FunctionArg::Named { arg, .. } | FunctionArg::Unnamed(arg) => {
+                            Self::collect(arg, functions)
+                        }
+                    }
+                }
+            }
+            Expr::Function(function) if !functions.contains(function) => {
+                functions.push(function.clone())
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                Self::collect(left, functions);
+                Self::collect(right, functions);
+            }
+            Expr::UnaryOp { expr, .. }
+            | Expr::Nested(expr)
+            | Expr::IsNull(expr)
+            | Expr::IsNotNull(expr)
+            | Expr::Cast { expr, .. } => Self::collect(expr, functions),
+            Expr::Between {
+                expr, low, high, ..
+            } => {
+                Self::collect(expr, functions);
+                Self::collect(low, functions);
+                Self::collect(high, functions);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// computes window functions over the rows of the source, the result of every
+/// function is appended to each row as an extra column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct WindowPlan {
+    pub source: Box<PlanNode>,
+    pub functions: Vec<WindowExpression>,
+}
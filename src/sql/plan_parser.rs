@@ -3,9 +3,12 @@ use super::engine::SQLTransaction;
 use super::plan::plan_node::PlanNode;
 use super::sql_parser::KVParser;
 use super::sql_statement::KVStatement;
+use super::statements::AnalyzerQuery;
 use super::statements::AnalyzerResult;
 use super::statements::AnalyzerStatement;
+use super::statements::CommonTables;
 use crate::common::result::ResultSet;
+use crate::common::scope::Scope;
 use crate::error::Error;
 use crate::error::Result;
 use crate::sql::sql_executor::KVExecutor;
@@ -30,6 +33,33 @@ impl PlanParser {
         }
     }
 
+    /// plan a single statement, with the scope of the output columns of a
+    /// query, None for the other statements
+    pub fn parser_scope<C: Catalog>(
+        sql: &str,
+        catalog: &mut C,
+    ) -> Result<(PlanNode, Option<Scope>)> {
+        let mut stmts = KVParser::parser_sql(sql)?;
+        if stmts.len() != 1 {
+            return Err(Error::Internal("Only support single query".into()));
+        }
+        match stmts.remove(0) {
+            stmt @ (KVStatement::Query(_)
+            | KVStatement::SetOperation(_)
+            | KVStatement::Values(_)
+            | KVStatement::With(_)) => {
+                let (plan, scope) = stmt.analyze_query(catalog, &CommonTables::new())?;
+                Ok((plan, Some(scope)))
+            }
+            stmt => Ok((PlanParser::build_plan(vec![stmt], catalog)?.plan, None)),
+        }
+    }
+
+    /// the plan, consuming the parser
+    pub fn into_plan(self) -> PlanNode {
+        self.plan
+    }
+
     /// optimize the plan, consuming it.
     pub fn optimize<C: Catalog>(self, _catalog: &mut C) -> Result<Self> {
         // FIXME implement it
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -49,7 +50,7 @@ impl Hash for DataValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.data_type().hash(state);
         match self {
-            DataValue::Null => self.hash(state),
+            DataValue::Null => {}
             DataValue::Boolean(v) => v.hash(state),
             DataValue::Integer(v) => v.hash(state),
             DataValue::Float(v) => v.to_be_bytes().hash(state),
@@ -111,6 +112,33 @@ impl DataValue {
             DataValue::Null => None,
         }
     }
+
+    /// compares two values for sorting, NULL is larger than any other value
+    /// and values of different datatypes are ordered by their datatype
+    pub fn compare(&self, other: &DataValue) -> Ordering {
+        use DataValue::*;
+        match (self, other) {
+            (Null, Null) => Ordering::Equal,
+            (Null, _) => Ordering::Greater,
+            (_, Null) => Ordering::Less,
+            (Boolean(l), Boolean(r)) => l.cmp(r),
+            (Integer(l), Integer(r)) => l.cmp(r),
+            (Integer(l), Float(r)) => (*l as f64).total_cmp(r),
+            (Float(l), Integer(r)) => l.total_cmp(&(*r as f64)),
+            (Float(l), Float(r)) => l.total_cmp(r),
+            (String(l), String(r)) => l.cmp(r),
+            (l, r) => l.type_order().cmp(&r.type_order()),
+        }
+    }
+
+    fn type_order(&self) -> u8 {
+        match self {
+            DataValue::Boolean(_) => 0,
+            DataValue::Integer(_) | DataValue::Float(_) => 1,
+            DataValue::String(_) => 2,
+            DataValue::Null => 3,
+        }
+    }
 }
 
 impl Display for DataValue {
@@ -2,3 +2,6 @@ pub mod data_type;
 pub mod data_value;
 pub mod table;
 pub mod table_column;
+pub mod table_constraint;
+pub mod user;
+pub mod view;
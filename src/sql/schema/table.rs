@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use sqlparser::ast::ColumnDef;
@@ -5,17 +7,64 @@ use sqlparser::ast::ObjectName;
 
 use super::data_value::DataValue;
 use super::table_column::TableColumn;
+use super::table_constraint::Check;
+use super::table_constraint::ForeignKey;
+use super::table_constraint::ReferentialAction;
 use crate::error::Error;
 use crate::error::Result;
 use crate::sql::engine::SQLTransaction;
+use crate::storage::compression::Compression;
 
 pub type Tables = Box<dyn DoubleEndedIterator<Item = Table> + Send>;
 
+/// the kind of a relation listed by the catalog
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TableKind {
+    Table,
+    View,
+    MaterializedView,
+}
+
+impl Display for TableKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TableKind::Table => "table",
+            TableKind::View => "view",
+            TableKind::MaterializedView => "materialized view",
+        })
+    }
+}
+
 /// a table schema
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<TableColumn>,
+    /// a view has no columns, the rows of a materialized view are keyed by
+    /// their row number and can only be changed by a refresh
+    pub kind: TableKind,
+    /// the CHECK constraints of the table and its columns
+    pub checks: Vec<Check>,
+    /// the UNIQUE constraints over more than one column
+    pub unique_keys: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    /// the codec of the stored rows and index entries
+    pub compression: Compression,
+}
+
+/// formats a primary key, a composite key as a tuple
+pub fn format_key(key: &[DataValue]) -> String {
+    match key {
+        [value] => value.to_string(),
+        values => format!(
+            "({})",
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
 }
 
 impl Table {
@@ -28,6 +77,11 @@ impl Table {
         Ok(Table {
             name: table_name,
             columns,
+            kind: TableKind::Table,
+            checks: vec![],
+            unique_keys: vec![],
+            foreign_keys: vec![],
+            compression: Compression::None,
         })
     }
 
@@ -53,24 +107,59 @@ impl Table {
         })
     }
 
-    /// return the primary key value of a row
-    pub fn get_row_key(&self, row: &[DataValue]) -> Result<DataValue> {
-        row.get(
-            self.columns
-                .iter()
-                .position(|c| c.primary_key)
-                .ok_or_else(|| Error::Value("Table can not found primary key".into()))?,
-        )
-        .cloned()
-        .ok_or_else(|| Error::Value("primary key can not found for row".into()))
+    /// return the primary key of a row, the values of the primary key
+    /// columns in the order of the columns
+    pub fn get_row_key(&self, row: &[DataValue]) -> Result<Vec<DataValue>> {
+        let key = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.primary_key)
+            .map(|(i, _)| {
+                row.get(i)
+                    .cloned()
+                    .ok_or_else(|| Error::Value("primary key can not found for row".into()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if key.is_empty() {
+            return Err(Error::Value("Table can not found primary key".into()));
+        }
+        Ok(key)
     }
 
-    /// return the primaryt key of this table
-    pub fn get_primary_key(&self) -> Result<&TableColumn> {
-        self.columns
+    /// return the primary key columns of this table
+    pub fn get_primary_key(&self) -> Result<Vec<&TableColumn>> {
+        let columns = self
+            .columns
+            .iter()
+            .filter(|c| c.primary_key)
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            return Err(Error::Value(format!(
+                "Primary key not found in Table {}",
+                self.name
+            )));
+        }
+        Ok(columns)
+    }
+
+    /// return the values of the given columns of a row
+    pub fn get_values(&self, row: &[DataValue], columns: &[String]) -> Result<Vec<DataValue>> {
+        columns
             .iter()
-            .find(|c| c.primary_key)
-            .ok_or_else(|| Error::Value(format!("Primary key not found in Table {}", self.name)))
+            .map(|c| Ok(row[self.get_column_index(c)?].clone()))
+            .collect()
+    }
+
+    /// check that the rows of the table can be changed
+    pub fn check_writable(&self) -> Result<()> {
+        match self.kind {
+            TableKind::Table => Ok(()),
+            kind => Err(Error::Value(format!(
+                "Cannot change {} {}",
+                kind, self.name
+            ))),
+        }
     }
 
     /// validate the table row schema
@@ -86,6 +175,61 @@ impl Table {
             column.validate_value(self, &primary_key, value, txn)?;
         }
 
+        // a key containing NULL never conflicts and never references
+        for columns in &self.unique_keys {
+            let values = self.get_values(row, columns)?;
+            if values.contains(&DataValue::Null) {
+                continue;
+            }
+            let mut scan = txn.scan(&self.name, None)?;
+            while let Some(other) = scan.next().transpose()? {
+                if values == self.get_values(&other, columns)?
+                    && primary_key != self.get_row_key(&other)?
+                {
+                    return Err(Error::Value(format!(
+                        "Unique key ({}), the value {} has exist",
+                        columns.join(", "),
+                        format_key(&values)
+                    )));
+                }
+            }
+        }
+
+        for foreign_key in &self.foreign_keys {
+            let values = self.get_values(row, &foreign_key.columns)?;
+            if values.contains(&DataValue::Null)
+                || (foreign_key.table == self.name && values == primary_key)
+            {
+                continue;
+            }
+            if txn.read(&foreign_key.table, &values)?.is_none() {
+                return Err(Error::Value(format!(
+                    "Reference primary key {} in table {} does not exist",
+                    format_key(&values),
+                    foreign_key.table
+                )));
+            }
+        }
+
+        // like in a WHERE clause NULL is not FALSE, so the row passes
+        for check in &self.checks {
+            match check.expr.evaluate(Some(&row.to_vec()))? {
+                DataValue::Boolean(true) | DataValue::Null => {}
+                DataValue::Boolean(false) => {
+                    return Err(Error::Value(format!(
+                        "Row violates check constraint {} of table {}",
+                        check.sql, self.name
+                    )))
+                }
+                v => {
+                    return Err(Error::Value(format!(
+                        "Check constraint {} returned {}, expected boolean",
+                        check.sql, v
+                    )))
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -96,26 +240,90 @@ impl Table {
             return Err(Error::Value(format!("Table {} has no columns.", self.name)));
         }
 
-        // check primary key number, should 1
-        match self.columns.iter().filter(|c| c.primary_key).count() {
-            1 => {}
-            0 => {
+        // the primary key may span several columns
+        if !self.columns.iter().any(|c| c.primary_key) {
+            return Err(Error::Value(format!(
+                "Table {} has no primary_key.",
+                self.name
+            )));
+        }
+
+        for column in &self.columns {
+            column.validate(self)?;
+        }
+
+        for columns in &self.unique_keys {
+            for column in columns {
+                self.get_column(column)?;
+            }
+        }
+
+        for foreign_key in &self.foreign_keys {
+            self.validate_foreign_key(foreign_key, txn)?;
+        }
+        Ok(())
+    }
+
+    /// validate that a foreign key matches the primary key of its table
+    fn validate_foreign_key(
+        &self,
+        foreign_key: &ForeignKey,
+        txn: &mut dyn SQLTransaction,
+    ) -> Result<()> {
+        let target = if foreign_key.table == self.name {
+            // reference self
+            self.clone()
+        } else if let Some(table) = txn.read_table(&foreign_key.table)? {
+            table
+        } else {
+            return Err(Error::Value(format!(
+                "Table {} reference by {} does not exist",
+                foreign_key.table, foreign_key
+            )));
+        };
+        if target.kind != TableKind::Table {
+            return Err(Error::Value(format!(
+                "Can't reference {} {}",
+                target.kind, target.name
+            )));
+        }
+
+        let primary_key = target.get_primary_key()?;
+        if !foreign_key.referred_columns.is_empty()
+            && !foreign_key
+                .referred_columns
+                .iter()
+                .eq(primary_key.iter().map(|c| &c.name))
+        {
+            return Err(Error::Value(format!(
+                "{} must reference the primary key of table {}",
+                foreign_key, target.name
+            )));
+        }
+        if foreign_key.columns.len() != primary_key.len() {
+            return Err(Error::Value(format!(
+                "{} has {} columns, the primary key of table {} has {}",
+                foreign_key,
+                foreign_key.columns.len(),
+                target.name,
+                primary_key.len()
+            )));
+        }
+        for (name, referred) in foreign_key.columns.iter().zip(primary_key) {
+            let column = self.get_column(name)?;
+            if column.datatype != referred.datatype {
                 return Err(Error::Value(format!(
-                    "Table {} has no primary_key.",
-                    self.name
-                )))
+                    "Can't reference {} primary key of table {} from {} column {}",
+                    referred.datatype, target.name, column.datatype, column.name
+                )));
             }
-            n => {
+            if foreign_key.on_delete == ReferentialAction::SetNull && !column.nullable {
                 return Err(Error::Value(format!(
-                    "Table {} has {} primary key, should 1",
-                    self.name, n
-                )))
+                    "ON DELETE SET NULL needs nullable column {}",
+                    column.name
+                )));
             }
         }
-
-        for column in &self.columns {
-            column.validate(self, txn)?;
-        }
         Ok(())
     }
 }
@@ -24,8 +24,6 @@ pub struct TableColumn {
     pub default: Option<DataValue>,
     /// Whether the column should only take unique values
     pub unique: bool,
-    /// The table which is referenced by this foreign key, link other table's primary key
-    pub references: Option<String>,
     /// Whether the column should be indexed
     pub index: bool,
 }
@@ -39,7 +37,6 @@ impl TableColumn {
             nullable: false,
             default: None,
             unique: false,
-            references: None,
             index: false,
         };
 
@@ -54,9 +51,6 @@ impl TableColumn {
                     column.index = true;
                 }
                 ColumnOption::Unique { .. } => column.unique = true,
-                ColumnOption::ForeignKey { foreign_table, .. } => {
-                    column.references = Some(foreign_table.to_string())
-                }
                 _ => {}
             }
         }
@@ -67,7 +61,7 @@ impl TableColumn {
     pub fn validate_value(
         &self,
         table: &Table,
-        pk: &DataValue,
+        pk: &[DataValue],
         value: &DataValue,
         txn: &mut dyn SQLTransaction,
     ) -> Result<()> {
@@ -93,25 +87,12 @@ impl TableColumn {
             _ => Ok(()),
         }?;
 
-        if let Some(target) = &self.references {
-            match value {
-                DataValue::Null => Ok(()),
-                DataValue::Float(f) if f.is_nan() => Ok(()),
-                v if target == &table.name && v == pk => Ok(()),
-                v if txn.read(target, v)?.is_none() => Err(Error::Value(format!(
-                    "Reference primary key {} in table {} does not exist",
-                    v, target
-                ))),
-                _ => Ok(()),
-            }?;
-        }
-
         // validate uniquenes constrains
         if self.unique && !self.primary_key && value != &DataValue::Null {
             let index = table.get_column_index(&self.name)?;
             let mut scan = txn.scan(&table.name, None)?;
             while let Some(row) = scan.next().transpose()? {
-                if value == &row[index] && pk != &table.get_row_key(&row)? {
+                if value == &row[index] && pk != table.get_row_key(&row)? {
                     return Err(Error::Value(format!(
                         "Unique column {}, the value {} has exist",
                         self.name, value
@@ -124,20 +105,14 @@ impl TableColumn {
     }
 
     /// validate column schema
-    pub fn validate(&self, table: &Table, txn: &mut dyn SQLTransaction) -> Result<()> {
-        // validate primary key, the key should not be null and unique
+    pub fn validate(&self, table: &Table) -> Result<()> {
+        // validate primary key, the key should not be null
         if self.primary_key && self.nullable {
             return Err(Error::Value(format!(
                 "Table {}, column {} is primary key, it can't be nullable!!",
                 table.name, self.name
             )));
         }
-        if self.primary_key && !self.unique {
-            return Err(Error::Value(format!(
-                "Table {}, column {} is primary key, it should be unique!!",
-                table.name, self.name
-            )));
-        }
 
         // validate default value
         if let Some(default) = &self.default {
@@ -161,30 +136,6 @@ impl TableColumn {
             )));
         }
 
-        // validate reference
-        if let Some(reference) = &self.references {
-            let target = if reference == &table.name {
-                // reference self
-                table.clone()
-            } else if let Some(table) = txn.read_table(reference)? {
-                table
-            } else {
-                return Err(Error::Value(format!(
-                    "Table {} reference by column {} does not exist",
-                    reference, self.name
-                )));
-            };
-
-            if self.datatype != target.get_primary_key()?.datatype {
-                return Err(Error::Value(format!(
-                    "Can't reference {} primary key of table {} from {} column {}",
-                    target.get_primary_key()?.datatype,
-                    target.name,
-                    self.datatype,
-                    self.name
-                )));
-            }
-        }
         Ok(())
     }
 }
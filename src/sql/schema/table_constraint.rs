@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+use sqlparser::ast;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::plan::plan_expression::Expression;
+
+/// what happens to the referencing rows when a referenced row is deleted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReferentialAction {
+    /// the delete fails, this is the default
+    Restrict,
+    /// the referencing rows are deleted too
+    Cascade,
+    /// the referencing columns are set to NULL
+    SetNull,
+}
+
+impl ReferentialAction {
+    pub fn try_form(action: Option<&ast::ReferentialAction>) -> Result<Self> {
+        match action {
+            None | Some(ast::ReferentialAction::Restrict | ast::ReferentialAction::NoAction) => {
+                Ok(ReferentialAction::Restrict)
+            }
+            Some(ast::ReferentialAction::Cascade) => Ok(ReferentialAction::Cascade),
+            Some(ast::ReferentialAction::SetNull) => Ok(ReferentialAction::SetNull),
+            Some(action) => Err(Error::Value(format!(
+                "Referential action {} is not supported",
+                action
+            ))),
+        }
+    }
+}
+
+impl Display for ReferentialAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReferentialAction::Restrict => "RESTRICT",
+            ReferentialAction::Cascade => "CASCADE",
+            ReferentialAction::SetNull => "SET NULL",
+        })
+    }
+}
+
+/// a foreign key, the columns reference the primary key of a table
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    /// the referenced table
+    pub table: String,
+    /// the referenced columns as written, empty for the primary key
+    pub referred_columns: Vec<String>,
+    pub on_delete: ReferentialAction,
+}
+
+impl Display for ForeignKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "FOREIGN KEY ({}) REFERENCES {}",
+            self.columns.join(", "),
+            self.table
+        )
+    }
+}
+
+/// a CHECK constraint, a row is rejected if the expression evaluates to FALSE
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Check {
+    /// the SQL text of the expression, used in error messages
+    pub sql: String,
+    pub expr: Expression,
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::common::scram;
+use crate::error::Error;
+use crate::error::Result;
+
+pub type Users = Box<dyn DoubleEndedIterator<Item = User> + Send>;
+
+/// the table name of the privileges granted on every table
+pub const ALL_TABLES: &str = "*";
+
+/// a privilege on a table, DDL creates, drops and refreshes it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Privilege {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Ddl,
+}
+
+impl Privilege {
+    pub const ALL: [Privilege; 5] = [
+        Privilege::Select,
+        Privilege::Insert,
+        Privilege::Update,
+        Privilege::Delete,
+        Privilege::Ddl,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self> {
+        Ok(match name.to_uppercase().as_str() {
+            "SELECT" => Self::Select,
+            "INSERT" => Self::Insert,
+            "UPDATE" => Self::Update,
+            "DELETE" => Self::Delete,
+            "DDL" => Self::Ddl,
+            _ => return Err(Error::Parse(format!("Unknown privilege {}", name))),
+        })
+    }
+}
+
+impl Display for Privilege {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Select => "SELECT",
+            Self::Insert => "INSERT",
+            Self::Update => "UPDATE",
+            Self::Delete => "DELETE",
+            Self::Ddl => "DDL",
+        })
+    }
+}
+
+/// a user, its password is kept as the keys of SCRAM-SHA-256
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    /// a superuser holds every privilege, and manages the users
+    pub superuser: bool,
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+    /// the privileges granted by table, or on every table by *
+    pub privileges: BTreeMap<String, BTreeSet<Privilege>>,
+}
+
+impl User {
+    /// a user with a password salted at random, and no privileges
+    pub fn new(name: &str, password: &str, superuser: bool) -> Self {
+        let salt = scram::nonce();
+        let (stored_key, server_key) = scram::keys(password, &salt, scram::ITERATIONS);
+        Self {
+            name: name.to_string(),
+            superuser,
+            salt,
+            iterations: scram::ITERATIONS,
+            stored_key,
+            server_key,
+            privileges: BTreeMap::new(),
+        }
+    }
+
+    /// whether the user holds a privilege on a table
+    pub fn has_privilege(&self, privilege: Privilege, table: &str) -> bool {
+        self.superuser
+            || [table, ALL_TABLES].iter().any(|t| {
+                self.privileges
+                    .get(*t)
+                    .is_some_and(|privileges| privileges.contains(&privilege))
+            })
+    }
+
+    pub fn grant(&mut self, privileges: &[Privilege], table: &str) {
+        self.privileges
+            .entry(table.to_string())
+            .or_default()
+            .extend(privileges);
+    }
+
+    /// revokes privileges granted on a table, the ones granted on every
+    /// table are revoked by *
+    pub fn revoke(&mut self, privileges: &[Privilege], table: &str) {
+        if let Some(granted) = self.privileges.get_mut(table) {
+            granted.retain(|p| !privileges.contains(p));
+            if granted.is_empty() {
+                self.privileges.remove(table);
+            }
+        }
+    }
+}
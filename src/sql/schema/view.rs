@@ -0,0 +1,38 @@
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::sql_parser::KVParser;
+use crate::sql::sql_statement::KVStatement;
+
+pub type Views = Box<dyn DoubleEndedIterator<Item = View> + Send>;
+
+/// a view, i.e. a named query which is expanded wherever the view is read
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct View {
+    pub name: String,
+    /// the column names given with CREATE VIEW name (a, b, ...), if any
+    pub columns: Vec<String>,
+    /// the SQL text of the query
+    pub query: String,
+    /// the rows of a materialized view are stored in a table of the same name
+    pub materialized: bool,
+    /// the tables and views read by the query, they can not be dropped
+    /// while the view exists
+    pub dependencies: Vec<String>,
+}
+
+impl View {
+    /// parse the stored query of the view
+    pub fn parse_query(&self) -> Result<KVStatement> {
+        let mut statements = KVParser::parser_sql(&self.query)?;
+        match (statements.pop(), statements.is_empty()) {
+            (Some(statement), true) => Ok(statement),
+            _ => Err(Error::Internal(format!(
+                "View {} has an invalid query {}",
+                self.name, self.query
+            ))),
+        }
+    }
+}
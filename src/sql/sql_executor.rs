@@ -1,14 +1,28 @@
 use super::engine::SQLTransaction;
 use super::executors::CreateTableExec;
+use super::executors::CreateUserExec;
+use super::executors::CreateViewExec;
 use super::executors::DeleteExec;
 use super::executors::DropTableExec;
+use super::executors::DropUserExec;
+use super::executors::DropViewExec;
 use super::executors::FilterExec;
+use super::executors::GrantExec;
 use super::executors::GroupByExec;
 use super::executors::InsertExec;
+use super::executors::IntegrityCheckExec;
+use super::executors::NestedLoopJoinExec;
 use super::executors::NothingExec;
 use super::executors::ProjectionExec;
+use super::executors::RecursiveCteExec;
+use super::executors::RefreshViewExec;
 use super::executors::ScanExec;
+use super::executors::SetOperationExec;
+use super::executors::SetVariableExec;
 use super::executors::UpdateExec;
+use super::executors::ValuesExec;
+use super::executors::WindowExec;
+use super::executors::WorkTableExec;
 use super::plan::plan_node::PlanNode;
 use crate::common::result::ResultSet;
 use crate::error::Result;
@@ -31,6 +45,20 @@ impl<T: SQLTransaction + 'static> dyn KVExecutor<T> {
             PlanNode::GroupBy(plan) => GroupByExec::new(plan),
             PlanNode::Update(plan) => UpdateExec::new(plan),
             PlanNode::Delete(plan) => DeleteExec::new(plan),
+            PlanNode::SetOperation(plan) => SetOperationExec::new(plan),
+            PlanNode::Values(plan) => ValuesExec::new(plan),
+            PlanNode::NestedLoopJoin(plan) => NestedLoopJoinExec::new(plan),
+            PlanNode::RecursiveCte(plan) => RecursiveCteExec::new(plan),
+            PlanNode::WorkTable(plan) => WorkTableExec::new(plan),
+            PlanNode::Window(plan) => WindowExec::new(plan),
+            PlanNode::CreateView(plan) => CreateViewExec::new(plan),
+            PlanNode::DropView(plan) => DropViewExec::new(plan),
+            PlanNode::RefreshView(plan) => RefreshViewExec::new(plan),
+            PlanNode::IntegrityCheck => IntegrityCheckExec::new(),
+            PlanNode::CreateUser(plan) => CreateUserExec::new(plan),
+            PlanNode::DropUser(plan) => DropUserExec::new(plan),
+            PlanNode::Grant(plan) => GrantExec::new(plan),
+            PlanNode::SetVariable(plan) => SetVariableExec::new(plan),
         }
     }
 }
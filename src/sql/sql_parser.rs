@@ -1,16 +1,34 @@
 use sqlparser::ast::ObjectName;
 use sqlparser::ast::ObjectType;
 use sqlparser::ast::Query;
+use sqlparser::ast::SetExpr;
 use sqlparser::ast::Statement;
+use sqlparser::dialect::keywords::Keyword;
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+use sqlparser::tokenizer::Tokenizer;
+use sqlparser::tokenizer::Word;
 
 use super::sql_statement::KVStatement;
+use super::statements::KVCreateUserStatement;
+use super::statements::KVCreateViewStatement;
 use super::statements::KVDeleteStatement;
 use super::statements::KVDropTableStatement;
+use super::statements::KVDropUserStatement;
+use super::statements::KVDropViewStatement;
+use super::statements::KVGrantStatement;
+use super::statements::KVPragmaStatement;
 use super::statements::KVQueryStatement;
+use super::statements::KVRefreshViewStatement;
+use super::statements::KVSetOperationStatement;
+use super::statements::KVSetVariableStatement;
+use super::statements::KVValuesStatement;
+use super::statements::KVWithStatement;
 use crate::error::Error;
 use crate::error::Result;
+use crate::sql::schema::user::Privilege;
+use crate::sql::schema::user::ALL_TABLES;
 use crate::sql::statements::KVCreateTableStatement;
 use crate::sql::statements::KVInsertStatement;
 use crate::sql::statements::KVUpdateStatement;
@@ -30,6 +48,10 @@ macro_rules! internal_err {
     };
 }
 
+/// the highest parameter number of a statement, like postgres which counts
+/// the parameters of a Bind message in 16 bits
+pub const MAX_PARAMETER: usize = 65535;
+
 /// Newbee DB's Parser
 pub struct KVParser;
 
@@ -37,10 +59,185 @@ impl KVParser {
     /// parser sql
     pub fn parser_sql(sql: &str) -> Result<Vec<KVStatement>> {
         let dialect = &GenericDialect {};
-        Parser::parse_sql(dialect, sql)?
-            .into_iter()
-            .map(KVParser::parse_statement)
-            .collect::<Result<Vec<_>>>()
+        let tokens = KVParser::placeholders(Tokenizer::new(dialect, sql).tokenize()?)?;
+        let mut parser = Parser::new(tokens, dialect);
+        let mut statements = Vec::new();
+        let mut expecting_statement_delimiter = false;
+        loop {
+            // ignore empty statements (between successive statement delimiters)
+            while parser.consume_token(&Token::SemiColon) {
+                expecting_statement_delimiter = false;
+            }
+            match parser.peek_token() {
+                Token::EOF => break,
+                token if expecting_statement_delimiter => {
+                    return parser_err!(format!("Expected end of statement, found: {}", token))
+                }
+                _ => {}
+            }
+            statements.push(KVParser::parse_next_statement(&mut parser)?);
+            expecting_statement_delimiter = true;
+        }
+        Ok(statements)
+    }
+
+    /// replaces the $n and ? placeholders, which sqlparser does not know, by
+    /// the identifiers $n. the ? are numbered in order from $1, an unquoted
+    /// identifier can not start with $ so they can not be mistaken for columns.
+    /// a number above MAX_PARAMETER is refused
+    fn placeholders(tokens: Vec<Token>) -> Result<Vec<Token>> {
+        let placeholder = |n: usize| {
+            Token::Word(Word {
+                value: format!("${}", n),
+                quote_style: None,
+                keyword: Keyword::NoKeyword,
+            })
+        };
+        let mut result = Vec::with_capacity(tokens.len());
+        let mut questions = 0;
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Char('?') if questions == MAX_PARAMETER => {
+                    return Err(Error::Value(format!(
+                        "More than {} parameters",
+                        MAX_PARAMETER
+                    )))
+                }
+                Token::Char('?') => {
+                    questions += 1;
+                    result.push(placeholder(questions));
+                }
+                Token::Char('$') if matches!(tokens.peek(), Some(Token::Number(_, false))) => {
+                    let n = match tokens.next() {
+                        Some(Token::Number(n, _)) => n,
+                        _ => unreachable!(),
+                    };
+                    match n.parse::<usize>() {
+                        Ok(i) if i > 0 && i <= MAX_PARAMETER => result.push(placeholder(i)),
+                        Ok(0) => {
+                            return Err(Error::Value(format!(
+                                "Invalid parameter ${}, parameters are numbered from $1",
+                                n
+                            )))
+                        }
+                        // a positive integer, too large for a parameter
+                        _ if n.bytes().all(|b| b.is_ascii_digit())
+                            && !n.trim_start_matches('0').is_empty() =>
+                        {
+                            return Err(Error::Value(format!(
+                                "Parameter ${} exceeds the maximum ${}",
+                                n, MAX_PARAMETER
+                            )))
+                        }
+                        _ => return parser_err!(format!("Invalid parameter ${}", n)),
+                    }
+                }
+                token => result.push(token),
+            }
+        }
+        Ok(result)
+    }
+
+    /// parse the next statement, including the statements of materialized
+    /// views, users and PRAGMA which sqlparser does not know
+    fn parse_next_statement(parser: &mut Parser) -> Result<KVStatement> {
+        let is_word = |token: Token, word: &str| matches!(token, Token::Word(w) if w.value.eq_ignore_ascii_case(word));
+        if parser.parse_keywords(&[Keyword::CREATE, Keyword::USER]) {
+            let name = parser.parse_identifier()?;
+            let _ = parser.parse_keyword(Keyword::WITH);
+            if !is_word(parser.next_token(), "PASSWORD") {
+                return parser_err!("Expected PASSWORD after CREATE USER name");
+            }
+            let password = parser.parse_literal_string()?;
+            let superuser = is_word(parser.peek_token(), "SUPERUSER");
+            if superuser {
+                parser.next_token();
+            }
+            return Ok(KVStatement::CreateUser(KVCreateUserStatement {
+                name,
+                password,
+                superuser,
+            }));
+        }
+        if parser.parse_keywords(&[Keyword::DROP, Keyword::USER]) {
+            let if_exists = parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+            return Ok(KVStatement::DropUser(KVDropUserStatement {
+                if_exists,
+                names: parser.parse_comma_separated(Parser::parse_identifier)?,
+            }));
+        }
+        if let Some(keyword) = parser.parse_one_of_keywords(&[Keyword::GRANT, Keyword::REVOKE]) {
+            return KVParser::parse_grant(parser, keyword == Keyword::REVOKE);
+        }
+        if is_word(parser.peek_token(), "PRAGMA") {
+            parser.next_token();
+            return Ok(KVStatement::Pragma(KVPragmaStatement {
+                name: parser.parse_identifier()?,
+            }));
+        }
+        if is_word(parser.peek_token(), "REFRESH") {
+            parser.next_token();
+            parser.expect_keywords(&[Keyword::MATERIALIZED, Keyword::VIEW])?;
+            return Ok(KVStatement::RefreshView(KVRefreshViewStatement {
+                name: parser.parse_object_name()?,
+            }));
+        }
+        if parser.parse_keywords(&[Keyword::DROP, Keyword::MATERIALIZED]) {
+            return match parser.parse_drop()? {
+                Statement::Drop {
+                    object_type: ObjectType::View,
+                    if_exists,
+                    names,
+                    ..
+                } => Ok(KVStatement::DropView(KVDropViewStatement {
+                    if_exists,
+                    materialized: true,
+                    names,
+                })),
+                _ => parser_err!("Expected VIEW after DROP MATERIALIZED"),
+            };
+        }
+        KVParser::parse_statement(parser.parse_statement()?)
+    }
+
+    /// GRANT privileges ON [TABLE] table TO user, or REVOKE ... FROM user,
+    /// after the GRANT or REVOKE. the privileges are ALL [PRIVILEGES] or a
+    /// list of SELECT, INSERT, UPDATE, DELETE and DDL, the table * is every
+    /// table
+    fn parse_grant(parser: &mut Parser, revoke: bool) -> Result<KVStatement> {
+        let privileges = if parser.parse_keyword(Keyword::ALL) {
+            if matches!(parser.peek_token(), Token::Word(w) if w.value.eq_ignore_ascii_case("PRIVILEGES"))
+            {
+                parser.next_token();
+            }
+            Privilege::ALL.to_vec()
+        } else {
+            let mut privileges = Vec::new();
+            loop {
+                match parser.next_token() {
+                    Token::Word(w) => privileges.push(Privilege::parse(&w.value)?),
+                    token => return parser_err!(format!("Expected a privilege, found: {}", token)),
+                }
+                if !parser.consume_token(&Token::Comma) {
+                    break privileges;
+                }
+            }
+        };
+        parser.expect_keyword(Keyword::ON)?;
+        let _ = parser.parse_keyword(Keyword::TABLE);
+        let table = if parser.consume_token(&Token::Mul) {
+            ALL_TABLES.to_string()
+        } else {
+            parser.parse_object_name()?.to_string()
+        };
+        parser.expect_keyword(if revoke { Keyword::FROM } else { Keyword::TO })?;
+        Ok(KVStatement::Grant(KVGrantStatement {
+            revoke,
+            privileges,
+            table,
+            user: parser.parse_identifier()?,
+        }))
     }
 
     pub fn parse_statement(stmt: Statement) -> Result<KVStatement> {
@@ -51,10 +248,27 @@ impl KVParser {
             Statement::Delete { .. } => KVParser::parse_delete(stmt),
             Statement::CreateTable { .. } => KVParser::parse_create_table(stmt),
             Statement::Drop { .. } => KVParser::parse_drop(stmt),
+            Statement::CreateView { .. } => KVParser::parse_create_view(stmt),
+            Statement::SetVariable { .. } => KVParser::parse_set_variable(stmt),
             s => internal_err!("an SQL statement", s),
         }
     }
 
+    fn parse_set_variable(stmt: Statement) -> Result<KVStatement> {
+        match stmt {
+            Statement::SetVariable {
+                local: false,
+                hivevar: false,
+                variable,
+                mut value,
+            } if value.len() == 1 => Ok(KVStatement::SetVariable(KVSetVariableStatement {
+                name: variable,
+                value: value.remove(0),
+            })),
+            _ => parser_err!("Expect SET name = value statement"),
+        }
+    }
+
     fn parse_drop(stmt: Statement) -> Result<KVStatement> {
         match stmt {
             Statement::Drop {
@@ -64,6 +278,11 @@ impl KVParser {
                 ..
             } => match object_type {
                 ObjectType::Table => KVParser::parse_drop_table(if_exists, names),
+                ObjectType::View => Ok(KVStatement::DropView(KVDropViewStatement {
+                    if_exists,
+                    materialized: false,
+                    names,
+                })),
                 t => internal_err!("an SQL Drop Type", t),
             },
             _ => parser_err!("Expect set insert statement"),
@@ -83,7 +302,9 @@ impl KVParser {
                 if_not_exists,
                 name,
                 columns,
+                constraints,
                 table_properties,
+                with_options,
                 query,
                 like,
                 ..
@@ -91,7 +312,8 @@ impl KVParser {
                 if_not_exists,
                 name,
                 columns,
-                config: table_properties,
+                constraints,
+                config: [with_options, table_properties].concat(),
                 query,
                 like,
             })),
@@ -99,6 +321,26 @@ impl KVParser {
         }
     }
 
+    fn parse_create_view(stmt: Statement) -> Result<KVStatement> {
+        match stmt {
+            Statement::CreateView {
+                or_replace,
+                materialized,
+                name,
+                columns,
+                query,
+                ..
+            } => Ok(KVStatement::CreateView(KVCreateViewStatement {
+                or_replace,
+                materialized,
+                name,
+                columns,
+                query,
+            })),
+            _ => parser_err!("Expect set create view statement"),
+        }
+    }
+
     fn parse_delete(stmt: Statement) -> Result<KVStatement> {
         match stmt {
             Statement::Delete {
@@ -129,7 +371,19 @@ impl KVParser {
 
     /// parse query
     fn parse_query(query: Query) -> Result<KVStatement> {
-        Ok(KVStatement::Query(KVQueryStatement::try_from(query)?))
+        if query.with.is_some() {
+            return Ok(KVStatement::With(KVWithStatement::try_from(query)?));
+        }
+        match query.body {
+            SetExpr::Select(_) => Ok(KVStatement::Query(KVQueryStatement::try_from(query)?)),
+            SetExpr::SetOperation { .. } => Ok(KVStatement::SetOperation(
+                KVSetOperationStatement::try_from(query)?,
+            )),
+            SetExpr::Values(_) => Ok(KVStatement::Values(KVValuesStatement::try_from(query)?)),
+            // a parenthesized query, e.g. (SELECT ...) UNION (SELECT ...)
+            SetExpr::Query(query) => KVParser::parse_query(*query),
+            body => internal_err!("a SELECT, VALUES or set operation", body),
+        }
     }
 
     fn parse_insert(stmt: Statement) -> Result<KVStatement> {
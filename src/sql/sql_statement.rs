@@ -1,9 +1,20 @@
 use super::statements::KVCreateTableStatement;
+use super::statements::KVCreateUserStatement;
+use super::statements::KVCreateViewStatement;
 use super::statements::KVDeleteStatement;
 use super::statements::KVDropTableStatement;
+use super::statements::KVDropUserStatement;
+use super::statements::KVDropViewStatement;
+use super::statements::KVGrantStatement;
 use super::statements::KVInsertStatement;
+use super::statements::KVPragmaStatement;
 use super::statements::KVQueryStatement;
+use super::statements::KVRefreshViewStatement;
+use super::statements::KVSetOperationStatement;
+use super::statements::KVSetVariableStatement;
 use super::statements::KVUpdateStatement;
+use super::statements::KVValuesStatement;
+use super::statements::KVWithStatement;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum KVStatement {
@@ -13,4 +24,15 @@ pub enum KVStatement {
     CreateTable(KVCreateTableStatement),
     Delete(KVDeleteStatement),
     Update(KVUpdateStatement),
+    SetOperation(KVSetOperationStatement),
+    Values(KVValuesStatement),
+    With(KVWithStatement),
+    CreateView(KVCreateViewStatement),
+    DropView(KVDropViewStatement),
+    RefreshView(KVRefreshViewStatement),
+    Pragma(KVPragmaStatement),
+    CreateUser(KVCreateUserStatement),
+    DropUser(KVDropUserStatement),
+    Grant(KVGrantStatement),
+    SetVariable(KVSetVariableStatement),
 }
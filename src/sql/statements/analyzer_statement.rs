@@ -1,6 +1,13 @@
+use std::collections::HashMap;
+
+use crate::common::scope::Scope;
+use crate::error::Error;
 use crate::error::Result;
 use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_expression::Expression;
 use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::ProjectionPlan;
+use crate::sql::schema::view::View;
 use crate::sql::sql_statement::KVStatement;
 
 pub enum AnalyzerResult {
@@ -11,6 +18,69 @@ pub trait AnalyzerStatement {
     fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult>;
 }
 
+/// common table expressions visible to a query by name,
+/// with their plan and the scope of their output columns
+pub type CommonTables = HashMap<String, (PlanNode, Scope)>;
+
+/// statements which return rows, they can be the source of other queries
+pub trait AnalyzerQuery {
+    /// plan the query, also return the scope of its output columns
+    fn analyze_query<C: Catalog>(
+        &self,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)>;
+}
+
+/// names the output columns of a derived table (i.e. a common table expression
+/// or a view) after the column list of its definition, the unnamed columns are
+/// named column1, column2 ...
+pub(super) fn rename(
+    relation: &str,
+    columns: &[String],
+    (node, scope): (PlanNode, Scope),
+) -> Result<(PlanNode, Scope)> {
+    if !columns.is_empty() && columns.len() != scope.len() {
+        return Err(Error::Value(format!(
+            "{} has {} columns available but {} columns specified",
+            relation,
+            scope.len(),
+            columns.len()
+        )));
+    }
+    let mut renamed = Scope::new();
+    let mut expressions = Vec::new();
+    for i in 0..scope.len() {
+        let label = match columns.get(i) {
+            Some(column) => column.clone(),
+            None => scope
+                .get_column(i)?
+                .1
+                .unwrap_or_else(|| format!("column{}", i + 1)),
+        };
+        renamed.add_column(None, Some(label.clone()), scope.get_type(i));
+        expressions.push((Expression::Field(i, None), Some(label)));
+    }
+    Ok((
+        PlanNode::Projection(ProjectionPlan {
+            source: Box::new(node),
+            expressions,
+        }),
+        renamed,
+    ))
+}
+
+/// plan the query of a view, a view can not see the common table expressions
+/// of the query which reads it
+pub(super) fn analyze_view<C: Catalog>(view: &View, catalog: &mut C) -> Result<(PlanNode, Scope)> {
+    rename(
+        &format!("view {}", view.name),
+        &view.columns,
+        view.parse_query()?
+            .analyze_query(catalog, &CommonTables::new())?,
+    )
+}
+
 impl AnalyzerStatement for KVStatement {
     fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
         match self {
@@ -20,6 +90,35 @@ impl AnalyzerStatement for KVStatement {
             KVStatement::CreateTable(v) => v.analyze(catalog),
             KVStatement::Delete(v) => v.analyze(catalog),
             KVStatement::Update(v) => v.analyze(catalog),
+            KVStatement::SetOperation(v) => v.analyze(catalog),
+            KVStatement::Values(v) => v.analyze(catalog),
+            KVStatement::With(v) => v.analyze(catalog),
+            KVStatement::CreateView(v) => v.analyze(catalog),
+            KVStatement::DropView(v) => v.analyze(catalog),
+            KVStatement::RefreshView(v) => v.analyze(catalog),
+            KVStatement::Pragma(v) => v.analyze(catalog),
+            KVStatement::CreateUser(v) => v.analyze(catalog),
+            KVStatement::DropUser(v) => v.analyze(catalog),
+            KVStatement::Grant(v) => v.analyze(catalog),
+            KVStatement::SetVariable(v) => v.analyze(catalog),
+        }
+    }
+}
+
+impl AnalyzerQuery for KVStatement {
+    fn analyze_query<C: Catalog>(
+        &self,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
+        match self {
+            KVStatement::Query(v) => v.analyze_query(catalog, ctes),
+            KVStatement::SetOperation(v) => v.analyze_query(catalog, ctes),
+            KVStatement::Values(v) => v.analyze_query(catalog, ctes),
+            KVStatement::With(v) => v.analyze_query(catalog, ctes),
+            _ => Err(Error::Value(
+                "Only SELECT, VALUES and set operations can be used as a query".into(),
+            )),
         }
     }
 }
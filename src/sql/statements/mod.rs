@@ -1,16 +1,41 @@
 mod analyzer_statement;
 mod statement_create_table;
+mod statement_create_user;
+mod statement_create_view;
 mod statement_delete;
 mod statement_drop_table;
+mod statement_drop_user;
+mod statement_drop_view;
+mod statement_grant;
 mod statement_insert;
+mod statement_pragma;
 mod statement_query;
+mod statement_refresh_view;
+mod statement_set_operation;
+mod statement_set_variable;
 mod statement_update;
+mod statement_values;
+mod statement_with;
 
+pub use analyzer_statement::AnalyzerQuery;
 pub use analyzer_statement::AnalyzerResult;
 pub use analyzer_statement::AnalyzerStatement;
+pub use analyzer_statement::CommonTables;
 pub use statement_create_table::KVCreateTableStatement;
+pub use statement_create_user::KVCreateUserStatement;
+pub use statement_create_view::KVCreateViewStatement;
 pub use statement_delete::KVDeleteStatement;
 pub use statement_drop_table::KVDropTableStatement;
+pub use statement_drop_user::KVDropUserStatement;
+pub use statement_drop_view::KVDropViewStatement;
+pub use statement_grant::KVGrantStatement;
 pub use statement_insert::KVInsertStatement;
+pub use statement_pragma::KVPragmaStatement;
 pub use statement_query::KVQueryStatement;
+pub use statement_refresh_view::KVRefreshViewStatement;
+pub use statement_set_operation::KVSetOperationStatement;
+pub use statement_set_variable::KVSetVariableStatement;
 pub use statement_update::KVUpdateStatement;
+pub use statement_values::KVValuesStatement;
+pub use statement_with::KVCommonTable;
+pub use statement_with::KVWithStatement;
@@ -1,21 +1,33 @@
 use sqlparser::ast::ColumnDef;
+use sqlparser::ast::ColumnOption;
+use sqlparser::ast::Expr;
 use sqlparser::ast::ObjectName;
 use sqlparser::ast::Query;
 use sqlparser::ast::SqlOption;
+use sqlparser::ast::TableConstraint;
+use sqlparser::ast::Value;
 
 use super::AnalyzerResult;
 use super::AnalyzerStatement;
+use crate::common::scope::Scope;
+use crate::error::Error;
 use crate::error::Result;
 use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_expression::Expression;
 use crate::sql::plan::plan_node::PlanNode;
 use crate::sql::plan::planners::CreateTablePlan;
 use crate::sql::schema::table_column::TableColumn;
+use crate::sql::schema::table_constraint::Check;
+use crate::sql::schema::table_constraint::ForeignKey;
+use crate::sql::schema::table_constraint::ReferentialAction;
+use crate::storage::compression::Compression;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct KVCreateTableStatement {
     pub if_not_exists: bool,
     pub name: ObjectName,
     pub columns: Vec<ColumnDef>,
+    pub constraints: Vec<TableConstraint>,
     pub config: Vec<SqlOption>,
     pub query: Option<Box<Query>>,
     pub like: Option<ObjectName>,
@@ -23,16 +35,121 @@ pub struct KVCreateTableStatement {
 
 impl AnalyzerStatement for KVCreateTableStatement {
     fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
-        let columns = self
-            .columns
-            .iter()
-            .map(TableColumn::try_form)
-            .collect::<Vec<_>>();
+        let mut plan = CreateTablePlan {
+            name: self.name.to_string(),
+            columns: self
+                .columns
+                .iter()
+                .map(TableColumn::try_form)
+                .collect::<Vec<_>>(),
+            checks: vec![],
+            unique_keys: vec![],
+            foreign_keys: vec![],
+            compression: Compression::None,
+        };
+
+        for option in &self.config {
+            match option.name.value.to_lowercase().as_str() {
+                "compression" => {
+                    plan.compression = Compression::from_name(&match &option.value {
+                        Value::SingleQuotedString(name) => name.clone(),
+                        value => value.to_string(),
+                    })?
+                }
+                name => {
+                    return Err(Error::Value(format!(
+                        "Unknown option {} of table {}",
+                        name, plan.name
+                    )))
+                }
+            }
+        }
+
+        // CHECK and REFERENCES of a column are constraints of the table
+        let mut checks = Vec::new();
+        for column in &self.columns {
+            for option in &column.options {
+                match &option.option {
+                    ColumnOption::Check(expr) => checks.push(expr),
+                    ColumnOption::ForeignKey {
+                        foreign_table,
+                        referred_columns,
+                        on_delete,
+                        ..
+                    } => plan.foreign_keys.push(ForeignKey {
+                        columns: vec![column.name.to_string()],
+                        table: foreign_table.to_string(),
+                        referred_columns: referred_columns.iter().map(|c| c.to_string()).collect(),
+                        on_delete: ReferentialAction::try_form(on_delete.as_ref())?,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+
+        for constraint in &self.constraints {
+            match constraint {
+                TableConstraint::Unique {
+                    columns,
+                    is_primary: true,
+                    ..
+                } => {
+                    if plan.columns.iter().any(|c| c.primary_key) {
+                        return Err(Error::Value(format!(
+                            "Table {} has more than one primary key",
+                            plan.name
+                        )));
+                    }
+                    for name in columns {
+                        let column = column_mut(&mut plan, &name.to_string())?;
+                        column.primary_key = true;
+                        column.index = true;
+                        column.unique = columns.len() == 1;
+                    }
+                }
+                TableConstraint::Unique { columns, .. } if columns.len() == 1 => {
+                    column_mut(&mut plan, &columns[0].to_string())?.unique = true;
+                }
+                TableConstraint::Unique { columns, .. } => plan
+                    .unique_keys
+                    .push(columns.iter().map(|c| c.to_string()).collect()),
+                TableConstraint::ForeignKey {
+                    columns,
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    ..
+                } => plan.foreign_keys.push(ForeignKey {
+                    columns: columns.iter().map(|c| c.to_string()).collect(),
+                    table: foreign_table.to_string(),
+                    referred_columns: referred_columns.iter().map(|c| c.to_string()).collect(),
+                    on_delete: ReferentialAction::try_form(on_delete.as_ref())?,
+                }),
+                TableConstraint::Check { expr, .. } => checks.push(expr),
+            }
+        }
+
+        // the expressions are resolved against the columns of the new table
+        let mut scope = Scope::from_table(plan.clone().to_table())?;
+        plan.checks = checks
+            .into_iter()
+            .map(|expr: &Expr| {
+                Ok(Check {
+                    sql: expr.to_string(),
+                    expr: Expression::from_expr(expr, &mut scope)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
         Ok(AnalyzerResult::SimpleQuery(Box::new(
-            PlanNode::CreateTable(CreateTablePlan {
-                name: self.name.to_string(),
-                columns,
-            }),
+            PlanNode::CreateTable(plan),
         )))
     }
 }
+
+fn column_mut<'a>(plan: &'a mut CreateTablePlan, name: &str) -> Result<&'a mut TableColumn> {
+    plan.columns
+        .iter_mut()
+        .find(|c| c.name == name)
+        .ok_or_else(|| Error::Value(format!("Column {} not found in table {}", name, plan.name)))
+}
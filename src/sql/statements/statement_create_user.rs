@@ -0,0 +1,28 @@
+use sqlparser::ast::Ident;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::CreateUserPlan;
+use crate::sql::schema::user::User;
+
+/// CREATE USER name [WITH] PASSWORD 'password' [SUPERUSER]
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVCreateUserStatement {
+    pub name: Ident,
+    pub password: String,
+    pub superuser: bool,
+}
+
+impl AnalyzerStatement for KVCreateUserStatement {
+    fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
+        // the password is salted by the planning, it is not kept in the plan
+        Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::CreateUser(
+            CreateUserPlan {
+                user: User::new(&self.name.value, &self.password, self.superuser),
+            },
+        ))))
+    }
+}
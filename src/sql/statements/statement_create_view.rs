@@ -0,0 +1,99 @@
+use sqlparser::ast::Ident;
+use sqlparser::ast::ObjectName;
+use sqlparser::ast::Query;
+use sqlparser::ast::SetExpr;
+use sqlparser::ast::TableFactor;
+use sqlparser::ast::TableWithJoins;
+
+use super::analyzer_statement::analyze_view;
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::CreateViewPlan;
+use crate::sql::schema::view::View;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVCreateViewStatement {
+    pub or_replace: bool,
+    pub materialized: bool,
+    pub name: ObjectName,
+    pub columns: Vec<Ident>,
+    pub query: Box<Query>,
+}
+
+impl AnalyzerStatement for KVCreateViewStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        if self.or_replace {
+            return Err(Error::Value(
+                "CREATE OR REPLACE VIEW is not supported".into(),
+            ));
+        }
+        let view = View {
+            name: self.name.to_string(),
+            columns: self.columns.iter().map(|c| c.value.clone()).collect(),
+            query: self.query.to_string(),
+            materialized: self.materialized,
+            dependencies: relations(&self.query, &[]),
+        };
+        // the query is planned now, so a view can only read existing tables and views
+        let (source, _) = analyze_view(&view, catalog)?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::CreateView(
+            CreateViewPlan {
+                source: view.materialized.then(|| Box::new(source)),
+                view,
+            },
+        ))))
+    }
+}
+
+/// the tables and views read by a query, without the common table expressions
+/// which hide them
+fn relations(query: &Query, hidden: &[String]) -> Vec<String> {
+    let mut hidden = hidden.to_vec();
+    let mut names = Vec::new();
+    if let Some(with) = &query.with {
+        hidden.extend(with.cte_tables.iter().map(|cte| cte.alias.name.to_string()));
+        for cte in &with.cte_tables {
+            names.extend(relations(&cte.query, &hidden));
+        }
+    }
+    set_expr_relations(&query.body, &hidden, &mut names);
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn set_expr_relations(body: &SetExpr, hidden: &[String], names: &mut Vec<String>) {
+    match body {
+        SetExpr::Select(select) => {
+            for from in &select.from {
+                from_relations(from, hidden, names);
+            }
+        }
+        SetExpr::Query(query) => names.extend(relations(query, hidden)),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_relations(left, hidden, names);
+            set_expr_relations(right, hidden, names);
+        }
+        SetExpr::Values(_) | SetExpr::Insert(_) => {}
+    }
+}
+
+fn from_relations(from: &TableWithJoins, hidden: &[String], names: &mut Vec<String>) {
+    for relation in std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation)) {
+        match relation {
+            TableFactor::Table { name, .. } => {
+                let name = name.to_string();
+                if !hidden.contains(&name) {
+                    names.push(name);
+                }
+            }
+            TableFactor::Derived { subquery, .. } => names.extend(relations(subquery, hidden)),
+            TableFactor::NestedJoin(from) => from_relations(from, hidden, names),
+            TableFactor::TableFunction { .. } => {}
+        }
+    }
+}
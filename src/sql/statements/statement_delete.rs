@@ -21,6 +21,7 @@ impl AnalyzerStatement for KVDeleteStatement {
     fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
         let table_name = self.table_name.to_string();
         let table = catalog.must_read_table(&table_name)?;
+        table.check_writable()?;
         let mut scope = Scope::from_table(table)?;
         let filter = self
             .selection
@@ -0,0 +1,26 @@
+use sqlparser::ast::Ident;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::DropUserPlan;
+
+/// DROP USER [IF EXISTS] name, ...
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVDropUserStatement {
+    pub if_exists: bool,
+    pub names: Vec<Ident>,
+}
+
+impl AnalyzerStatement for KVDropUserStatement {
+    fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
+        Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::DropUser(
+            DropUserPlan {
+                names: self.names.iter().map(|n| n.value.clone()).collect(),
+                if_exists: self.if_exists,
+            },
+        ))))
+    }
+}
@@ -0,0 +1,27 @@
+use sqlparser::ast::ObjectName;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::DropViewPlan;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVDropViewStatement {
+    pub if_exists: bool,
+    pub materialized: bool,
+    pub names: Vec<ObjectName>,
+}
+
+impl AnalyzerStatement for KVDropViewStatement {
+    fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
+        Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::DropView(
+            DropViewPlan {
+                names: self.names.iter().map(|n| n.to_string()).collect(),
+                if_exists: self.if_exists,
+                materialized: self.materialized,
+            },
+        ))))
+    }
+}
@@ -0,0 +1,46 @@
+use sqlparser::ast::Ident;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::GrantPlan;
+use crate::sql::schema::user::Privilege;
+use crate::sql::schema::user::ALL_TABLES;
+
+/// GRANT privileges ON [TABLE] table TO user, or
+/// REVOKE privileges ON [TABLE] table FROM user. the table * is every table
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVGrantStatement {
+    pub revoke: bool,
+    pub privileges: Vec<Privilege>,
+    pub table: String,
+    pub user: Ident,
+}
+
+impl AnalyzerStatement for KVGrantStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        // a table may be dropped with privileges left on it, they can be
+        // revoked
+        if !self.revoke
+            && self.table != ALL_TABLES
+            && catalog.read_table(&self.table)?.is_none()
+            && catalog.read_view(&self.table)?.is_none()
+        {
+            return Err(Error::Value(format!(
+                "Table {} does not exist.",
+                self.table
+            )));
+        }
+        Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::Grant(
+            GrantPlan {
+                revoke: self.revoke,
+                privileges: self.privileges.clone(),
+                table: self.table.clone(),
+                user: self.user.value.clone(),
+            },
+        ))))
+    }
+}
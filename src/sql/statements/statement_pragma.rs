@@ -0,0 +1,25 @@
+use sqlparser::ast::Ident;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+
+/// PRAGMA name
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVPragmaStatement {
+    pub name: Ident,
+}
+
+impl AnalyzerStatement for KVPragmaStatement {
+    fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
+        match self.name.value.to_lowercase().as_str() {
+            "integrity_check" => Ok(AnalyzerResult::SimpleQuery(Box::new(
+                PlanNode::IntegrityCheck,
+            ))),
+            _ => Err(Error::Value(format!("Unknown pragma {}", self.name))),
+        }
+    }
+}
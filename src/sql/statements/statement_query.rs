@@ -1,4 +1,6 @@
 use sqlparser::ast::Expr;
+use sqlparser::ast::JoinConstraint;
+use sqlparser::ast::JoinOperator;
 use sqlparser::ast::Offset;
 use sqlparser::ast::OrderByExpr;
 use sqlparser::ast::Query;
@@ -7,8 +9,11 @@ use sqlparser::ast::SetExpr;
 use sqlparser::ast::TableFactor;
 use sqlparser::ast::TableWithJoins;
 
+use super::analyzer_statement::analyze_view;
+use super::AnalyzerQuery;
 use super::AnalyzerResult;
 use super::AnalyzerStatement;
+use super::CommonTables;
 use crate::common::scope::Scope;
 use crate::error::Error;
 use crate::error::Result;
@@ -17,8 +22,11 @@ use crate::sql::plan::plan_expression::Expression;
 use crate::sql::plan::plan_node::PlanNode;
 use crate::sql::plan::planners::FilterPlan;
 use crate::sql::plan::planners::GroupByPlan;
+use crate::sql::plan::planners::NestedLoopJoinPlan;
 use crate::sql::plan::planners::ProjectionPlan;
 use crate::sql::plan::planners::ScanPlan;
+use crate::sql::plan::planners::WindowExpression;
+use crate::sql::plan::planners::WindowPlan;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct KVQueryStatement {
@@ -53,42 +61,131 @@ impl KVQueryStatement {
 
 impl AnalyzerStatement for KVQueryStatement {
     fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
-        if self.from.is_empty() {
-            return Ok(AnalyzerResult::SimpleQuery(Box::new(PlanNode::Nothing)));
-        }
+        let (node, _) = self.analyze_query(catalog, &CommonTables::new())?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(node)))
+    }
+}
+
+impl AnalyzerQuery for KVQueryStatement {
+    fn analyze_query<C: Catalog>(
+        &self,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
         let mut scope = Scope::new();
 
-        let mut node = self.plan_node_from(&mut scope, catalog)?;
+        // a SELECT without FROM evaluates its projection on a single empty row
+        let mut node = if self.from.is_empty() {
+            PlanNode::Nothing
+        } else {
+            self.plan_node_from(&mut scope, catalog, ctes)?
+        };
         node = self.plan_node_selection(node, &mut scope)?;
-        node = self.plan_node_projection(node, &mut scope)?;
+        // `*` expands to the columns of FROM only, not to the window functions
+        let width = scope.len();
+        node = self.plan_node_window(node, &mut scope)?;
+        node = self.plan_node_projection(node, &mut scope, width)?;
         node = self.plan_node_group_by(node, &mut scope)?;
 
-        Ok(AnalyzerResult::SimpleQuery(Box::new(node)))
+        Ok((node, scope))
     }
 }
 
 impl KVQueryStatement {
     // FROM
-    fn plan_node_from<C: Catalog>(&self, scope: &mut Scope, ctx: &mut C) -> Result<PlanNode> {
-        if self.from.len() != 1 {
-            return Err(Error::Internal("unsupport two or than SELECT".into()));
+    fn plan_node_from<C: Catalog>(
+        &self,
+        scope: &mut Scope,
+        ctx: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<PlanNode> {
+        let mut node = None;
+        // FROM a, b is the cross join of a and b
+        for from in &self.from {
+            let right = self.plan_node_join(from, scope, ctx, ctes)?;
+            node = Some(match node {
+                None => right,
+                Some(left) => PlanNode::NestedLoopJoin(NestedLoopJoinPlan {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    predicate: None,
+                    outer: false,
+                }),
+            });
+        }
+        node.ok_or_else(|| Error::Internal("FROM without any table".into()))
+    }
+
+    // JOIN
+    fn plan_node_join<C: Catalog>(
+        &self,
+        from: &TableWithJoins,
+        scope: &mut Scope,
+        ctx: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<PlanNode> {
+        let mut node = self.plan_node_table(&from.relation, scope, ctx, ctes)?;
+        for join in &from.joins {
+            let right = self.plan_node_table(&join.relation, scope, ctx, ctes)?;
+            let (constraint, outer) = match &join.join_operator {
+                JoinOperator::Inner(constraint) => (Some(constraint), false),
+                JoinOperator::LeftOuter(constraint) => (Some(constraint), true),
+                JoinOperator::CrossJoin => (None, false),
+                o => return Err(Error::Internal(format!("unsupport join {:?}", o))),
+            };
+            // the predicate is resolved against the columns of both sides
+            let predicate = match constraint {
+                Some(JoinConstraint::On(expr)) => Some(Expression::from_expr(expr, scope)?),
+                Some(JoinConstraint::None) | None => None,
+                Some(c) => {
+                    return Err(Error::Internal(format!(
+                        "unsupport join constraint {:?}",
+                        c
+                    )))
+                }
+            };
+            node = PlanNode::NestedLoopJoin(NestedLoopJoinPlan {
+                left: Box::new(node),
+                right: Box::new(right),
+                predicate,
+                outer,
+            });
         }
-        let from = &self.from[0];
-        // FIXME: should support JOIN
-        match &from.relation {
+        Ok(node)
+    }
+
+    // a single table of FROM or JOIN
+    fn plan_node_table<C: Catalog>(
+        &self,
+        relation: &TableFactor,
+        scope: &mut Scope,
+        ctx: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<PlanNode> {
+        match relation {
             TableFactor::Table { name, alias, .. } => {
                 let table_name = name.to_string();
-                let alias_name = alias.as_ref().map(|a| a.to_string());
-                scope.add_table(
-                    alias
-                        .as_ref()
-                        .map(|a| a.name.value.clone())
-                        .unwrap_or_else(|| table_name.clone()),
-                    ctx.must_read_table(&table_name)?,
-                )?;
+                let label = alias
+                    .as_ref()
+                    .map(|a| a.name.value.clone())
+                    .unwrap_or_else(|| table_name.clone());
+                // a common table expression hides the table with the same name
+                if let Some((node, cte_scope)) = ctes.get(&table_name) {
+                    scope.add_derived(label, cte_scope)?;
+                    return Ok(node.clone());
+                }
+                // a view is expanded inline, a materialized view is read as a table
+                if let Some(view) = ctx.read_view(&table_name)? {
+                    if !view.materialized {
+                        let (node, view_scope) = analyze_view(&view, ctx)?;
+                        scope.add_derived(label, &view_scope)?;
+                        return Ok(node);
+                    }
+                }
+                scope.add_table(label, ctx.must_read_table(&table_name)?)?;
                 Ok(PlanNode::Scan(ScanPlan {
                     table_name,
-                    alias: alias_name,
+                    alias: alias.as_ref().map(|a| a.to_string()),
                     filter: None,
                 }))
             }
@@ -107,16 +204,58 @@ impl KVQueryStatement {
         }
     }
 
+    // OVER
+    fn plan_node_window(&self, node: PlanNode, scope: &mut Scope) -> Result<PlanNode> {
+        let mut calls = Vec::new();
+        for select in &self.projection {
+            match select {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                    WindowExpression::collect(expr, &mut calls)
+                }
+                _ => {}
+            }
+        }
+        if calls.is_empty() {
+            return Ok(node);
+        }
+
+        let functions = calls
+            .iter()
+            .map(|call| WindowExpression::from_function(call, scope))
+            .collect::<Result<_>>()?;
+        // the results are labeled by the SQL of the call, so that the
+        // projection can resolve them like any other column
+        for call in calls {
+            scope.add_column(None, Some(Expr::Function(call).to_string()), None);
+        }
+        Ok(PlanNode::Window(WindowPlan {
+            source: Box::new(node),
+            functions,
+        }))
+    }
+
     // Column
-    fn plan_node_projection(&self, node: PlanNode, scope: &mut Scope) -> Result<PlanNode> {
+    fn plan_node_projection(
+        &self,
+        node: PlanNode,
+        scope: &mut Scope,
+        width: usize,
+    ) -> Result<PlanNode> {
         if self.projection.is_empty() {
             return Ok(node);
         }
-        let projections = self
-            .projection
-            .iter()
-            .flat_map(|select| Expression::from_select_item(select, scope).transpose())
-            .collect::<Result<Vec<_>>>()?;
+        let mut projections = Vec::new();
+        for select in &self.projection {
+            match select {
+                // expand * into every column of FROM
+                SelectItem::Wildcard => {
+                    for i in 0..width {
+                        projections.push((Expression::Field(i, scope.get_label(i)?), None));
+                    }
+                }
+                select => projections.extend(Expression::from_select_item(select, scope)?),
+            }
+        }
 
         let p = &projections[..];
         scope.project(p)?;
@@ -0,0 +1,34 @@
+use sqlparser::ast::ObjectName;
+
+use super::analyzer_statement::analyze_view;
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::RefreshViewPlan;
+
+/// REFRESH MATERIALIZED VIEW name
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVRefreshViewStatement {
+    pub name: ObjectName,
+}
+
+impl AnalyzerStatement for KVRefreshViewStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        let name = self.name.to_string();
+        let view = match catalog.read_view(&name)? {
+            Some(view) if view.materialized => view,
+            Some(_) => return Err(Error::Value(format!("{} is not a materialized view", name))),
+            None => return Err(Error::Value(format!("View {} does not exist.", name))),
+        };
+        let (source, _) = analyze_view(&view, catalog)?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(
+            PlanNode::RefreshView(RefreshViewPlan {
+                name,
+                source: Box::new(source),
+            }),
+        )))
+    }
+}
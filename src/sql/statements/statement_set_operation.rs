@@ -0,0 +1,210 @@
+use sqlparser::ast::Expr;
+use sqlparser::ast::Offset;
+use sqlparser::ast::OrderByExpr;
+use sqlparser::ast::Query;
+use sqlparser::ast::SetExpr;
+use sqlparser::ast::SetOperator;
+use sqlparser::ast::Statement;
+use sqlparser::ast::Value;
+
+use super::AnalyzerQuery;
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use super::CommonTables;
+use crate::common::scope::Scope;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::SetOperationPlan;
+use crate::sql::plan::planners::SetOperator as PlanSetOperator;
+use crate::sql::plan::planners::WindowOrder;
+use crate::sql::schema::data_type::DataType;
+use crate::sql::schema::data_value::DataValue;
+use crate::sql::sql_parser::KVParser;
+use crate::sql::sql_statement::KVStatement;
+
+/// UNION/INTERSECT/EXCEPT of two queries
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVSetOperationStatement {
+    pub op: SetOperator,
+    pub all: bool,
+    pub left: Box<KVStatement>,
+    pub right: Box<KVStatement>,
+    /// ORDER BY, LIMIT and OFFSET apply to the result of the set operation
+    pub order_by: Vec<OrderByExpr>,
+    pub limit: Option<Expr>,
+    pub offset: Option<Offset>,
+}
+
+impl KVSetOperationStatement {
+    pub fn try_from(stmt: Query) -> Result<Self> {
+        if stmt.fetch.is_some() {
+            return Err(Error::Value(
+                "FETCH is not supported on set operations".into(),
+            ));
+        }
+        if let SetExpr::SetOperation {
+            op,
+            all,
+            left,
+            right,
+        } = stmt.body
+        {
+            return Ok(KVSetOperationStatement {
+                op,
+                all,
+                left: Box::new(Self::parse_operand(*left)?),
+                right: Box::new(Self::parse_operand(*right)?),
+                order_by: stmt.order_by,
+                limit: stmt.limit,
+                offset: stmt.offset,
+            });
+        }
+        Err(Error::Internal(format!("unsupport Query type {}", stmt)))
+    }
+
+    /// an operand is a bare query body, parse it as a query of its own
+    fn parse_operand(body: SetExpr) -> Result<KVStatement> {
+        KVParser::parse_statement(Statement::Query(Box::new(Query {
+            with: None,
+            body,
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            fetch: None,
+        })))
+    }
+}
+
+impl KVSetOperationStatement {
+    /// the columns of the result, they take the names of the left query, and
+    /// the columns whose integers are converted to floats to match the other
+    /// query. both queries must have as many columns, of the same datatypes,
+    /// a column of unknown datatype, e.g. NULL, matches any datatype
+    pub(super) fn result_scope(
+        op: &SetOperator,
+        left: &Scope,
+        right: &Scope,
+    ) -> Result<(Scope, Vec<usize>)> {
+        if left.len() != right.len() {
+            return Err(Error::Value(format!(
+                "each {} query must have the same number of columns",
+                op
+            )));
+        }
+        let mut scope = Scope::new();
+        let mut floats = Vec::new();
+        for i in 0..left.len() {
+            let (_, label) = left.get_column(i)?;
+            let datatype = match (left.get_type(i), right.get_type(i)) {
+                (Some(DataType::Integer), Some(DataType::Float))
+                | (Some(DataType::Float), Some(DataType::Integer)) => {
+                    floats.push(i);
+                    Some(DataType::Float)
+                }
+                (Some(expected), Some(datatype)) if expected != datatype => {
+                    return Err(Error::Value(format!(
+                        "{} types {} and {} cannot be matched in column {}",
+                        op,
+                        expected,
+                        datatype,
+                        i + 1
+                    )))
+                }
+                (left, right) => left.or(right),
+            };
+            scope.add_column(None, label, datatype);
+        }
+        Ok((scope, floats))
+    }
+
+    /// ORDER BY a column of the result, by its name or its position
+    fn order_by(&self, scope: &mut Scope) -> Result<Vec<WindowOrder>> {
+        self.order_by
+            .iter()
+            .map(|order| {
+                let expr = match &order.expr {
+                    Expr::Value(Value::Number(n, _)) => match n.parse::<usize>() {
+                        Ok(i) if i > 0 && i <= scope.len() => {
+                            Expression::Field(i - 1, scope.get_label(i - 1)?)
+                        }
+                        _ => {
+                            return Err(Error::Value(format!(
+                                "ORDER BY position {} is not in select list",
+                                n
+                            )))
+                        }
+                    },
+                    expr => Expression::from_expr(expr, scope)?,
+                };
+                let asc = order.asc.unwrap_or(true);
+                Ok(WindowOrder {
+                    expr,
+                    asc,
+                    // NULL is the largest value by default
+                    nulls_first: order.nulls_first.unwrap_or(!asc),
+                })
+            })
+            .collect()
+    }
+}
+
+/// the number of rows of LIMIT or OFFSET, a constant
+fn row_count(clause: &str, expr: &Expr) -> Result<usize> {
+    match Expression::from_expr(expr, &mut Scope::constant())?.evaluate(None)? {
+        DataValue::Integer(n) if n >= 0 => Ok(n as usize),
+        value => Err(Error::Value(format!(
+            "{} must be a non-negative integer, found {}",
+            clause, value
+        ))),
+    }
+}
+
+impl AnalyzerStatement for KVSetOperationStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        let (node, _) = self.analyze_query(catalog, &CommonTables::new())?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(node)))
+    }
+}
+
+impl AnalyzerQuery for KVSetOperationStatement {
+    fn analyze_query<C: Catalog>(
+        &self,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
+        let (left, left_scope) = self.left.analyze_query(catalog, ctes)?;
+        let (right, right_scope) = self.right.analyze_query(catalog, ctes)?;
+        let (mut scope, floats) = Self::result_scope(&self.op, &left_scope, &right_scope)?;
+        let order_by = self.order_by(&mut scope)?;
+
+        Ok((
+            PlanNode::SetOperation(SetOperationPlan {
+                op: match self.op {
+                    SetOperator::Union => PlanSetOperator::Union,
+                    SetOperator::Intersect => PlanSetOperator::Intersect,
+                    SetOperator::Except => PlanSetOperator::Except,
+                },
+                all: self.all,
+                left: Box::new(left),
+                right: Box::new(right),
+                floats,
+                order_by,
+                limit: self
+                    .limit
+                    .as_ref()
+                    .map(|limit| row_count("LIMIT", limit))
+                    .transpose()?,
+                offset: self
+                    .offset
+                    .as_ref()
+                    .map(|offset| row_count("OFFSET", &offset.value))
+                    .transpose()?
+                    .unwrap_or(0),
+            }),
+            scope,
+        ))
+    }
+}
@@ -0,0 +1,88 @@
+use sqlparser::ast::Ident;
+use sqlparser::ast::SetVariableValue;
+use sqlparser::ast::Value;
+
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::SetVariablePlan;
+use crate::sql::plan::planners::DEFAULT_MAX_RECURSIVE_ROWS;
+use crate::sql::plan::planners::MAX_RECURSIVE_ROWS;
+use crate::sql::plan::planners::STATEMENT_TIMEOUT;
+use crate::sql::schema::data_value::DataValue;
+
+/// SET name = value, a setting of the session
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVSetVariableStatement {
+    pub name: Ident,
+    pub value: SetVariableValue,
+}
+
+impl AnalyzerStatement for KVSetVariableStatement {
+    fn analyze<C: Catalog>(&self, _catalog: &mut C) -> Result<AnalyzerResult> {
+        let (name, value) = match self.name.value.to_lowercase().as_str() {
+            STATEMENT_TIMEOUT => (STATEMENT_TIMEOUT, DataValue::Integer(self.milliseconds()?)),
+            MAX_RECURSIVE_ROWS => (MAX_RECURSIVE_ROWS, DataValue::Integer(self.rows()?)),
+            _ => return Err(Error::Value(format!("Unknown setting {}", self.name))),
+        };
+        Ok(AnalyzerResult::SimpleQuery(Box::new(
+            PlanNode::SetVariable(SetVariablePlan {
+                name: name.to_string(),
+                value,
+            }),
+        )))
+    }
+}
+
+impl KVSetVariableStatement {
+    /// a number of rows, 0 for no limit and DEFAULT for the default limit
+    fn rows(&self) -> Result<i64> {
+        match &self.value {
+            SetVariableValue::Ident(ident) if ident.value.eq_ignore_ascii_case("default") => {
+                Ok(DEFAULT_MAX_RECURSIVE_ROWS as i64)
+            }
+            SetVariableValue::Literal(Value::Number(n, _)) => n
+                .parse::<i64>()
+                .ok()
+                .filter(|rows| *rows >= 0)
+                .ok_or_else(|| self.invalid()),
+            _ => Err(self.invalid()),
+        }
+    }
+
+    fn invalid(&self) -> Error {
+        Error::Value(format!(
+            "Invalid value {} for setting {}",
+            self.value, self.name
+        ))
+    }
+
+    /// a duration in milliseconds, given as a number or as a string with a
+    /// unit of ms, s or min. 0 and DEFAULT disable the timeout
+    fn milliseconds(&self) -> Result<i64> {
+        let invalid = || self.invalid();
+        let (number, unit) = match &self.value {
+            SetVariableValue::Ident(ident) if ident.value.eq_ignore_ascii_case("default") => {
+                return Ok(0)
+            }
+            SetVariableValue::Literal(Value::Number(n, _)) => (n.as_str(), ""),
+            SetVariableValue::Literal(Value::SingleQuotedString(s)) => {
+                let s = s.trim();
+                let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+                (&s[..split], s[split..].trim())
+            }
+            _ => return Err(invalid()),
+        };
+        let number = number.parse::<i64>().map_err(|_| invalid())?;
+        let scale = match unit.to_lowercase().as_str() {
+            "" | "ms" => 1,
+            "s" => 1000,
+            "min" => 60 * 1000,
+            _ => return Err(invalid()),
+        };
+        number.checked_mul(scale).ok_or_else(invalid)
+    }
+}
@@ -23,6 +23,7 @@ impl AnalyzerStatement for KVUpdateStatement {
     fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
         let table_name = self.table_name.to_string();
         let table = catalog.must_read_table(&table_name)?;
+        table.check_writable()?;
         let mut scope = Scope::from_table(table)?;
         let set = self.assignment_to_set(&self.assignments, &mut scope)?;
         let filter = self
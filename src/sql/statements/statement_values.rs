@@ -0,0 +1,76 @@
+use sqlparser::ast::Expr;
+use sqlparser::ast::Query;
+use sqlparser::ast::SetExpr;
+
+use super::AnalyzerQuery;
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use super::CommonTables;
+use crate::common::scope::Scope;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_expression::Expression;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::ValuesPlan;
+
+/// a standalone VALUES list, e.g. VALUES (1, 'a'), (2, 'b')
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVValuesStatement {
+    pub rows: Vec<Vec<Expr>>,
+}
+
+impl KVValuesStatement {
+    pub fn try_from(stmt: Query) -> Result<Self> {
+        if let SetExpr::Values(values) = stmt.body {
+            return Ok(KVValuesStatement { rows: values.0 });
+        }
+        Err(Error::Internal(format!("unsupport Query type {}", stmt)))
+    }
+}
+
+impl AnalyzerStatement for KVValuesStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        let (node, _) = self.analyze_query(catalog, &CommonTables::new())?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(node)))
+    }
+}
+
+impl AnalyzerQuery for KVValuesStatement {
+    fn analyze_query<C: Catalog>(
+        &self,
+        _catalog: &mut C,
+        _ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
+        let width = self.rows.first().map(|row| row.len()).unwrap_or(0);
+        if self.rows.iter().any(|row| row.len() != width) {
+            return Err(Error::Value(
+                "VALUES lists must all be the same length".into(),
+            ));
+        }
+
+        // like postgres, the columns are named column1, column2 ...
+        let columns = (1..=width)
+            .map(|i| format!("column{}", i))
+            .collect::<Vec<_>>();
+        let mut constant = Scope::constant();
+        let rows: Vec<Vec<Expression>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|expr| Expression::from_expr(expr, &mut constant))
+                    .collect::<Result<_>>()
+            })
+            .collect::<Result<_>>()?;
+
+        // a column has a datatype if every value of a known datatype has it
+        let mut scope = Scope::new();
+        for (i, column) in columns.iter().enumerate() {
+            let mut types = rows.iter().filter_map(|row| row[i].data_type(&constant));
+            let datatype = types.next().filter(|first| types.all(|t| &t == first));
+            scope.add_column(None, Some(column.clone()), datatype);
+        }
+        Ok((PlanNode::Values(ValuesPlan { columns, rows }), scope))
+    }
+}
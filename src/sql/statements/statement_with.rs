@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use sqlparser::ast::Query;
+use sqlparser::ast::SetOperator;
+use sqlparser::ast::Statement;
+
+use super::analyzer_statement::rename;
+use super::AnalyzerQuery;
+use super::AnalyzerResult;
+use super::AnalyzerStatement;
+use super::CommonTables;
+use super::KVSetOperationStatement;
+use crate::common::scope::Scope;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sql::engine::Catalog;
+use crate::sql::plan::plan_node::PlanNode;
+use crate::sql::plan::planners::RecursiveCtePlan;
+use crate::sql::plan::planners::SetOperationPlan;
+use crate::sql::plan::planners::SetOperator as PlanSetOperator;
+use crate::sql::plan::planners::WorkTablePlan;
+use crate::sql::sql_parser::KVParser;
+use crate::sql::sql_statement::KVStatement;
+
+/// a common table expression, name (columns) AS (query)
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVCommonTable {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub query: KVStatement,
+}
+
+/// a query with common table expressions, WITH [RECURSIVE] ... query
+#[derive(Debug, PartialEq, Eq)]
+pub struct KVWithStatement {
+    pub recursive: bool,
+    pub ctes: Vec<KVCommonTable>,
+    pub body: Box<KVStatement>,
+}
+
+impl KVWithStatement {
+    pub fn try_from(mut stmt: Query) -> Result<Self> {
+        let with = match stmt.with.take() {
+            Some(with) => with,
+            None => return Err(Error::Internal(format!("unsupport Query type {}", stmt))),
+        };
+        let ctes = with
+            .cte_tables
+            .into_iter()
+            .map(|cte| {
+                Ok(KVCommonTable {
+                    name: cte.alias.name.to_string(),
+                    columns: cte.alias.columns.iter().map(|c| c.to_string()).collect(),
+                    query: KVParser::parse_statement(Statement::Query(Box::new(cte.query)))?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(KVWithStatement {
+            recursive: with.recursive,
+            ctes,
+            body: Box::new(KVParser::parse_statement(Statement::Query(Box::new(stmt)))?),
+        })
+    }
+
+    /// plans a recursive common table expression, anchor UNION [ALL] recursive,
+    /// where the recursive query reads the rows of the previous iteration
+    fn analyze_recursive<C: Catalog>(
+        cte: &KVCommonTable,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
+        let (all, anchor, recursive) = match &cte.query {
+            KVStatement::SetOperation(KVSetOperationStatement {
+                op: SetOperator::Union,
+                all,
+                left,
+                right,
+                order_by,
+                limit: None,
+                offset: None,
+            }) if order_by.is_empty() => (*all, left, right),
+            // without UNION the query can not refer to itself
+            query => {
+                return rename(
+                    &format!("WITH query {}", cte.name),
+                    &cte.columns,
+                    query.analyze_query(catalog, ctes)?,
+                )
+            }
+        };
+        let (anchor, scope) = rename(
+            &format!("WITH query {}", cte.name),
+            &cte.columns,
+            anchor.analyze_query(catalog, ctes)?,
+        )?;
+
+        let columns = (0..scope.len())
+            .map(|i| Ok(scope.get_column(i)?.1.unwrap_or_default()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut work_ctes = ctes.clone();
+        work_ctes.insert(
+            cte.name.clone(),
+            (
+                PlanNode::WorkTable(WorkTablePlan {
+                    name: cte.name.clone(),
+                    columns,
+                }),
+                scope.clone(),
+            ),
+        );
+        let (recursive, recursive_scope) = recursive.analyze_query(catalog, &work_ctes)?;
+        let (_, floats) =
+            KVSetOperationStatement::result_scope(&SetOperator::Union, &scope, &recursive_scope)?;
+
+        let node = if recursive.reads_work_table(&cte.name) {
+            // the rows of every iteration are read with the columns of the
+            // anchor, their datatypes can not be changed
+            if let Some(&i) = floats.first() {
+                let datatype = |scope: &Scope| scope.get_type(i).map(|t| t.to_string());
+                return Err(Error::Value(format!(
+                    "recursive query {} column {} has type {} in the non-recursive term and {} in the recursive term",
+                    cte.name,
+                    i + 1,
+                    datatype(&scope).unwrap_or_default(),
+                    datatype(&recursive_scope).unwrap_or_default()
+                )));
+            }
+            PlanNode::RecursiveCte(RecursiveCtePlan {
+                name: cte.name.clone(),
+                all,
+                anchor: Box::new(anchor),
+                recursive: Box::new(recursive),
+            })
+        } else {
+            PlanNode::SetOperation(SetOperationPlan {
+                op: PlanSetOperator::Union,
+                all,
+                left: Box::new(anchor),
+                right: Box::new(recursive),
+                floats,
+                order_by: vec![],
+                limit: None,
+                offset: 0,
+            })
+        };
+        Ok((node, scope))
+    }
+}
+
+impl AnalyzerStatement for KVWithStatement {
+    fn analyze<C: Catalog>(&self, catalog: &mut C) -> Result<AnalyzerResult> {
+        let (node, _) = self.analyze_query(catalog, &CommonTables::new())?;
+        Ok(AnalyzerResult::SimpleQuery(Box::new(node)))
+    }
+}
+
+impl AnalyzerQuery for KVWithStatement {
+    fn analyze_query<C: Catalog>(
+        &self,
+        catalog: &mut C,
+        ctes: &CommonTables,
+    ) -> Result<(PlanNode, Scope)> {
+        // every common table expression can refer to the ones defined before it
+        let mut ctes = ctes.clone();
+        let mut names = HashSet::new();
+        for cte in &self.ctes {
+            if !names.insert(&cte.name) {
+                return Err(Error::Value(format!(
+                    "WITH query name {} specified more than once",
+                    cte.name
+                )));
+            }
+            let table = if self.recursive {
+                Self::analyze_recursive(cte, catalog, &ctes)?
+            } else {
+                rename(
+                    &format!("WITH query {}", cte.name),
+                    &cte.columns,
+                    cte.query.analyze_query(catalog, &ctes)?,
+                )?
+            };
+            ctes.insert(cte.name.clone(), table);
+        }
+        self.body.analyze_query(catalog, &ctes)
+    }
+}
@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io::Read;
+
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::CompressionLevel;
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// values smaller than this are stored uncompressed, they hardly shrink
+pub const COMPRESSION_THRESHOLD: usize = 64;
+
+/// the tag before a stored value, it names the codec the value was
+/// compressed with
+const TAG_RAW: u8 = 0;
+const TAG_LZ4: u8 = 1;
+const TAG_ZSTD: u8 = 2;
+
+/// the codec compressing the stored values of a table
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(Error::Value(format!(
+                "Unknown compression {}, excepted none, lz4 or zstd",
+                name
+            ))),
+        }
+    }
+
+    /// the stored form of a value: a tag, then the value compressed by this
+    /// codec. a value below COMPRESSION_THRESHOLD or one that does not
+    /// shrink is stored as it is.
+    pub fn compress(&self, value: &[u8]) -> Vec<u8> {
+        let compressed = match self {
+            _ if value.len() < COMPRESSION_THRESHOLD => None,
+            Compression::None => None,
+            Compression::Lz4 => Some((TAG_LZ4, lz4_flex::compress_prepend_size(value))),
+            Compression::Zstd => Some((
+                TAG_ZSTD,
+                ruzstd::encoding::compress_to_vec(value, CompressionLevel::Fastest),
+            )),
+        };
+        match compressed {
+            Some((tag, data)) if data.len() < value.len() => [&[tag], &data[..]].concat(),
+            _ => [&[TAG_RAW], value].concat(),
+        }
+    }
+
+    /// the value of a stored form, whatever codec it was written with
+    pub fn decompress(data: &[u8]) -> Result<Cow<'_, [u8]>> {
+        match data.split_first() {
+            Some((&TAG_RAW, value)) => Ok(Cow::Borrowed(value)),
+            Some((&TAG_LZ4, data)) => lz4_flex::decompress_size_prepended(data)
+                .map(Cow::Owned)
+                .map_err(|e| Error::Internal(format!("Invalid lz4 value: {}", e))),
+            Some((&TAG_ZSTD, mut data)) => {
+                let mut value = Vec::new();
+                StreamingDecoder::new(&mut data)
+                    .map_err(|e| Error::Internal(format!("Invalid zstd value: {}", e)))?
+                    .read_to_end(&mut value)?;
+                Ok(Cow::Owned(value))
+            }
+            _ => Err(Error::Internal("Invalid stored value".into())),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::aead::KeyInit;
+use aes_gcm::aead::OsRng;
+use aes_gcm::AeadCore;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::Nonce;
+use aes_gcm::Tag;
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// the size of an AES-256 key
+pub const KEY_SIZE: usize = 32;
+
+/// a new random nonce is stored with every encrypted page
+pub const NONCE_SIZE: usize = 12;
+
+/// the authentication tag, it detects a wrong key and a changed ciphertext
+pub const TAG_SIZE: usize = 16;
+
+/// the bytes an encrypted page needs besides its data
+pub const ENCRYPTION_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+/// the PBKDF2-HMAC-SHA256 rounds deriving a key from a passphrase
+const KDF_ROUNDS: u32 = 100_000;
+
+/// where the key of an encrypted database comes from
+#[derive(Clone, PartialEq, Eq)]
+pub enum EncryptionKey {
+    /// the key is derived from the passphrase and a salt stored with the data
+    Passphrase(String),
+    /// the file holds the key, as 32 bytes or 64 hex digits
+    KeyFile(PathBuf),
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionKey::Passphrase(_) => write!(f, "Passphrase(..)"),
+            EncryptionKey::KeyFile(path) => f.debug_tuple("KeyFile").field(path).finish(),
+        }
+    }
+}
+
+impl EncryptionKey {
+    /// the key, the salt is used by a passphrase only
+    pub fn derive(&self, salt: &[u8]) -> Result<[u8; KEY_SIZE]> {
+        let mut key = [0u8; KEY_SIZE];
+        match self {
+            EncryptionKey::Passphrase(passphrase) => {
+                pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KDF_ROUNDS, &mut key);
+            }
+            EncryptionKey::KeyFile(path) => {
+                let data = fs::read(path)?;
+                let text = String::from_utf8_lossy(&data);
+                let hex = text.trim();
+                if data.len() == KEY_SIZE {
+                    key.copy_from_slice(&data);
+                } else if hex.len() == 2 * KEY_SIZE && hex.is_ascii() {
+                    for (i, byte) in key.iter_mut().enumerate() {
+                        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+                    }
+                } else {
+                    return Err(Error::Config(format!(
+                        "Key file {} does not hold {} bytes or {} hex digits",
+                        path.display(),
+                        KEY_SIZE,
+                        2 * KEY_SIZE
+                    )));
+                }
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// AES-256-GCM encryption of the pages of the pager, its journal and its
+/// write-ahead log. an encrypted buffer ends with ENCRYPTION_OVERHEAD bytes
+/// holding its nonce and tag.
+pub struct Cipher {
+    aead: Aes256Gcm,
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cipher").finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    pub fn new(key: &[u8; KEY_SIZE]) -> Self {
+        Self {
+            aead: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// the cipher of the key, the salt is used by a passphrase only
+    pub fn from_key(key: &EncryptionKey, salt: &[u8]) -> Result<Self> {
+        Ok(Self::new(&key.derive(salt)?))
+    }
+
+    /// encrypt a buffer in place, its last ENCRYPTION_OVERHEAD bytes receive
+    /// the nonce and the tag. the associated data is authenticated, not
+    /// encrypted: a buffer is only decrypted with the same associated data,
+    /// e.g. the page number of a page.
+    pub fn encrypt_in_place(&self, aad: &[u8], data: &mut [u8]) -> Result<()> {
+        let len = data
+            .len()
+            .checked_sub(ENCRYPTION_OVERHEAD)
+            .ok_or_else(|| Error::Internal("Buffer too small to encrypt".into()))?;
+        let (data, overhead) = data.split_at_mut(len);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let tag = self
+            .aead
+            .encrypt_in_place_detached(&nonce, aad, data)
+            .map_err(|_| Error::Internal("Encryption failed".into()))?;
+        overhead[..NONCE_SIZE].copy_from_slice(&nonce);
+        overhead[NONCE_SIZE..].copy_from_slice(&tag);
+        Ok(())
+    }
+
+    /// decrypt a buffer encrypted by encrypt_in_place, false if the key or
+    /// the associated data are wrong or the buffer was changed
+    pub fn decrypt_in_place(&self, aad: &[u8], data: &mut [u8]) -> Result<bool> {
+        let len = match data.len().checked_sub(ENCRYPTION_OVERHEAD) {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+        let (data, overhead) = data.split_at_mut(len);
+        let (nonce, tag) = overhead.split_at(NONCE_SIZE);
+        Ok(self
+            .aead
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), aad, data, Tag::from_slice(tag))
+            .is_ok())
+    }
+}
@@ -2,6 +2,8 @@ mod sql_storage;
 pub mod sqlite;
 
 pub mod b_tree;
+pub mod compression;
+pub mod encryption;
 pub mod mvcc;
 
 pub use sql_storage::Store;
@@ -11,6 +11,7 @@ use super::transaction::TransactionMode;
 use crate::common::keys::TransactionKey;
 use crate::common::range::Range;
 use crate::error::Result;
+use crate::storage::sqlite::page::PagerStats;
 use crate::storage::Store;
 
 /// MVCC Status
@@ -19,6 +20,7 @@ pub struct Status {
     pub txns: u64,
     pub txns_active: u64,
     pub storage: String,
+    pub cache: Option<PagerStats>,
 }
 
 pub struct MVCC {
@@ -78,6 +80,7 @@ impl MVCC {
             txns,
             txns_active,
             storage: store.to_string(),
+            cache: store.cache_stats()?,
         })
     }
 
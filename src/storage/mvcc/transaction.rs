@@ -102,7 +102,7 @@ impl Snapshot {
 
     /// check whether the given version is visible in this snapshot
     pub fn is_visable(&self, version: u64) -> bool {
-        version < self.version && !self.invisible.contains(&version)
+        version <= self.version && !self.invisible.contains(&version)
     }
 }
 
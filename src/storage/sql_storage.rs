@@ -3,6 +3,7 @@ use std::fmt::Display;
 use crate::common::range::Range;
 use crate::common::range::Scan;
 use crate::error::Result;
+use crate::storage::sqlite::page::PagerStats;
 
 /// A key/value store.
 pub trait Store: Display + Send + Sync {
@@ -20,4 +21,9 @@ pub trait Store: Display + Send + Sync {
 
     /// Sets a value for a key, replacing the existing value if any.
     fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<()>;
+
+    /// Returns the statistics of the page cache, if the store has one.
+    fn cache_stats(&self) -> Result<Option<PagerStats>> {
+        Ok(None)
+    }
 }
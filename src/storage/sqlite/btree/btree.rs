@@ -9,19 +9,26 @@ use std::sync::Mutex;
 use std::sync::RwLock;
 
 use super::page::MemPage;
+use super::page::PageLayout;
 use super::page::PageOne;
 use crate::common;
+use crate::common::options::JournalMode;
 use crate::common::options::PagerOption;
 use crate::error::Result;
+use crate::storage::encryption::EncryptionKey;
 use crate::storage::sqlite::page::DiskData;
+use crate::storage::sqlite::page::FileHeader;
 use crate::storage::sqlite::page::Pager;
-use crate::storage::sqlite::page::PAGE_SIZE;
+use crate::storage::sqlite::page::PagerStats;
+use crate::storage::sqlite::page::BUSY_TIMEOUT;
 use crate::storage::Store;
 
 pub struct Btree {
     pager: Arc<Mutex<Pager>>,
     cursor: Option<BtCursor>,
     page1: Option<Arc<RwLock<DiskData>>>,
+    // the limits of a page, from the page size of the database
+    layout: PageLayout,
     in_trans: u8,
     in_ckpt: u8,
     read_only: bool,
@@ -43,20 +50,36 @@ pub struct BtCursor {
 }
 
 impl Btree {
-    /// open pager and set destructor(maybe)
-    pub fn open(filename: &'static str, n_cache: usize) -> Result<Btree> {
+    /// open pager and set destructor(maybe).
+    /// page_size and checksums are used by a new database only, a new
+    /// database is encrypted if a key is given.
+    pub fn open(
+        filename: &'static str,
+        n_cache: usize,
+        page_size: usize,
+        checksums: bool,
+        encryption: Option<EncryptionKey>,
+    ) -> Result<Btree> {
         let pager_option = PagerOption {
             path: Some(filename),
             max_page: if n_cache < 10 { 10 } else { n_cache as u32 },
             n_extra: 0,
             read_only: false,
+            journal_mode: JournalMode::Rollback,
+            page_size,
+            checksums,
+            busy_timeout: BUSY_TIMEOUT,
+            mmap_size: 0,
+            encryption,
         };
         let pager = Pager::open(pager_option)?;
         let read_only = pager.read_only();
+        let layout = PageLayout::new(pager.page_size(), pager.page_size() - pager.usable_size());
         let mut btree = Self {
             pager: Arc::new(Mutex::new(pager)),
             cursor: None,
             page1: None,
+            layout,
             in_trans: 0,
             in_ckpt: 0,
             read_only,
@@ -113,8 +136,17 @@ impl Btree {
         Ok(())
     }
 
+    /// write the file header into page 1 of a new database
     fn new_database(&mut self) -> Result<()> {
-        todo!()
+        let pager_arc = Arc::clone(&self.pager);
+        let header = pager_arc.lock()?.new_file_header()?;
+        let page = pager_arc.lock()?.get_page(1, Arc::clone(&pager_arc))?;
+        let mut pg = page.lock()?;
+        if FileHeader::read(&pg.get_data())?.is_some() {
+            return Ok(());
+        }
+        pg.write(&header.to_bytes(), 0)?;
+        Ok(())
     }
 }
 
@@ -144,4 +176,8 @@ impl Store for Btree {
     fn set(&mut self, key: &[u8], value: Vec<u8>) -> crate::error::Result<()> {
         todo!()
     }
+
+    fn cache_stats(&self) -> crate::error::Result<Option<PagerStats>> {
+        Ok(Some(self.pager.lock()?.stats()?))
+    }
 }
@@ -1,9 +1,8 @@
-use super::MX_LOCAL_PAYLOAD;
-
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Cell {
     h: CellHdr,
-    a_payload: [u8; MX_LOCAL_PAYLOAD],
+    // at most PageLayout.mx_local_payload bytes
+    a_payload: Vec<u8>,
     ovfl: u32,
 }
 
@@ -1,7 +1,3 @@
-use std::mem::size_of;
-
-use super::OVERFLOW_SIZE;
-
 pub struct FreeBlk {
     i_size: u16,
     i_next: u16,
@@ -9,10 +5,12 @@ pub struct FreeBlk {
 
 pub struct OverflowPage {
     i_next: u32,
-    a_payload: [u8; OVERFLOW_SIZE],
+    // PageLayout.overflow_size bytes
+    a_payload: Vec<u8>,
 }
 
 pub struct FreelistInfo {
     n_free: i32,
-    a_free: [u32; (OVERFLOW_SIZE - size_of::<i32>()) / size_of::<u32>()],
+    // the page numbers fill the rest of the overflow page
+    a_free: Vec<u32>,
 }
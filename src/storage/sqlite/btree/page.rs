@@ -1,25 +1,26 @@
+use std::mem::size_of;
 use std::sync::Arc;
 
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 
 use super::cell::Cell;
-use super::MAGIC_SIZE;
-use super::MX_CELL;
+use super::cell::CellHdr;
+use super::MIN_CELL_SIZE;
 use super::SQLITE_N_BTREE_META;
 use crate::error::Error;
 use crate::error::Result;
-use crate::storage::sqlite::page::PAGE_SIZE;
+use crate::storage::sqlite::page::check_page_size;
+use crate::storage::sqlite::page::FileHeader;
+use crate::storage::sqlite::page::FILE_HEADER_SIZE;
 
-/// the first page of the database file contains a magic header string to identify the file
-/// as an SQLITE database file.
+/// the first page of the database file starts with the file header, it
+/// identifies the file and records the page size and the format version.
 #[repr(C)]
 #[derive(Debug)]
 pub struct PageOne {
-    // String that identifies the file as a database
-    z_magic: [u8; MAGIC_SIZE],
-    // Integer to verify correct byte order
-    i_magic: i32,
+    // the encoded FileHeader
+    header: [u8; FILE_HEADER_SIZE],
     // a pointer to the first free page of the file.
     // BY ME: how the save a pointer?
     free_list: u32,
@@ -29,6 +30,14 @@ pub struct PageOne {
 }
 
 impl PageOne {
+    pub fn get_header(&self) -> Result<Option<FileHeader>> {
+        FileHeader::read(&self.header)
+    }
+
+    pub fn set_header(&mut self, header: &FileHeader) {
+        self.header = header.to_bytes();
+    }
+
     pub fn set_free_list(&mut self, free_list: u32) {
         self.free_list = free_list;
     }
@@ -38,6 +47,36 @@ impl PageOne {
     }
 }
 
+/// the limits of a b-tree page, they depend on the page size of the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLayout {
+    pub page_size: usize,
+    /// the bytes at the end of a page reserved for its checksum
+    pub reserved: usize,
+    /// the bytes of a page available for cells
+    pub usable_space: usize,
+    /// the max number of cells on a page
+    pub mx_cell: usize,
+    /// the max payload stored in a cell, the rest goes to overflow pages
+    pub mx_local_payload: usize,
+    /// the payload stored in an overflow page
+    pub overflow_size: usize,
+}
+
+impl PageLayout {
+    pub fn new(page_size: usize, reserved: usize) -> Self {
+        let usable_space = page_size - reserved - size_of::<PageHdr>();
+        Self {
+            page_size,
+            reserved,
+            usable_space,
+            mx_cell: usable_space / MIN_CELL_SIZE,
+            mx_local_payload: usable_space / 4 - (size_of::<CellHdr>() + size_of::<u32>()),
+            overflow_size: page_size - reserved - size_of::<u32>(),
+        }
+    }
+}
+
 /// each database page has a header that is an instance of this structure.
 #[derive(Copy)]
 pub struct PageHdr {
@@ -58,40 +97,35 @@ impl Clone for PageHdr {
     }
 }
 
-#[repr(C)]
-pub union MemPageHdr {
-    pub disk: [u8; PAGE_SIZE],
-    pub hdr: PageHdr,
-}
-
 pub struct MemPage {
-    n: MemPageHdr,
+    // the page data, it starts with a PageHdr
+    disk: Vec<u8>,
     is_init: i32,
     p_parent: Option<Arc<MemPage>>,
     n_free: i32,
     n_cell: i32,
     is_overfull: i32,
-    ap_cell: [usize; MX_CELL + 2],
+    // PageLayout.mx_cell + 2 entries
+    ap_cell: Vec<usize>,
 }
 
 impl MemPage {
     pub fn from_data(data: &[u8]) -> Result<MemPage> {
-        if data.len() != PAGE_SIZE {
+        if check_page_size(data.len()).is_err() {
             return Err(Error::Serialization);
         }
         // FIXME: this means that the data in MemPage and PageHdr is
         // no longer the same piece of memory
-        let mut disk_data = [0u8; PAGE_SIZE];
-        disk_data.copy_from_slice(data);
+        let layout = PageLayout::new(data.len(), 0);
 
         Ok(Self {
-            n: MemPageHdr { disk: disk_data },
+            disk: data.to_vec(),
             is_init: 1,
             p_parent: None,
             n_free: 1,
             n_cell: 2,
             is_overfull: 11,
-            ap_cell: [0; MX_CELL + 2],
+            ap_cell: vec![0; layout.mx_cell + 2],
         })
     }
 }
@@ -1,53 +1,102 @@
+use std::sync::Arc;
+
 use derivative::Derivative;
+use memmap2::Mmap;
 
-use super::PAGE_SIZE;
 use crate::common;
 use crate::error::Result;
 use crate::storage::sqlite::btree::page::PageOne;
 
+/// the bytes of a page: a copy, or borrowed from a read-only mapping of the
+/// database file until the page is written
+#[derive(Derivative)]
+#[derivative(Debug)]
+enum PageBytes {
+    Owned(Vec<u8>),
+    Mapped {
+        #[derivative(Debug = "ignore")]
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+    },
+}
+
 /// a separate disk data objct
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct DiskData {
-    data: [u8; PAGE_SIZE],
+    data: PageBytes,
     is_dirty: bool,
 }
 
 impl DiskData {
-    pub fn new() -> DiskData {
+    pub fn new(page_size: usize) -> DiskData {
         Self {
-            data: [0u8; PAGE_SIZE],
+            data: PageBytes::Owned(vec![0u8; page_size]),
             is_dirty: false,
         }
     }
 
+    /// borrow the page from a mapping of the database file, the page starts
+    /// at offset and must lie inside the mapping
+    pub fn map(&mut self, map: Arc<Mmap>, offset: usize) {
+        let len = self.as_slice().len();
+        assert!(offset + len <= map.len());
+        self.data = PageBytes::Mapped { map, offset, len };
+    }
+
+    /// true while the page is borrowed from a mapping of the database file
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.data, PageBytes::Mapped { .. })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match &self.data {
+            PageBytes::Owned(data) => data,
+            PageBytes::Mapped { map, offset, len } => &map[*offset..*offset + *len],
+        }
+    }
+
+    /// the bytes of the page to change, a mapped page is copied first
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        if let PageBytes::Mapped { .. } = self.data {
+            self.data = PageBytes::Owned(self.as_slice().to_vec());
+        }
+        match &mut self.data {
+            PageBytes::Owned(data) => data,
+            PageBytes::Mapped { .. } => unreachable!(),
+        }
+    }
+
     pub fn to_page1(&self) -> Result<Option<&PageOne>> {
-        common::ptr_util::deserialize(&self.data)
+        common::ptr_util::deserialize(self.as_slice())
     }
 
     pub fn to_page1_mut(&mut self) -> Result<Option<&mut PageOne>> {
-        common::ptr_util::deserialize_mut(&mut self.data)
+        common::ptr_util::deserialize_mut(self.as_mut_slice())
     }
 
     pub fn read(&self, buf: &mut [u8], offset: usize) -> Result<usize> {
-        if offset >= self.data.len() {
+        let data = self.as_slice();
+        if offset >= data.len() {
             return Ok(0);
         }
-        let end = if buf.len() + offset > self.data.len() {
-            self.data.len()
+        let end = if buf.len() + offset > data.len() {
+            data.len()
         } else {
             buf.len() + offset
         };
-        buf.copy_from_slice(&self.data[offset..end]);
+        buf.copy_from_slice(&data[offset..end]);
         Ok(end - offset)
     }
 
     pub fn write(&mut self, buf: &[u8], offset: usize) -> Result<usize> {
-        if offset >= self.data.len() {
+        let size = self.as_slice().len();
+        if offset >= size {
             return Ok(0);
         }
-        let len = if buf.len() + offset > self.data.len() {
-            self.data.len() - offset
+        let len = if buf.len() + offset > size {
+            size - offset
         } else {
             buf.len()
         };
@@ -56,7 +105,7 @@ impl DiskData {
             return Ok(0);
         }
 
-        let write_slice = &mut self.data[offset..offset + len];
+        let write_slice = &mut self.as_mut_slice()[offset..offset + len];
         write_slice.copy_from_slice(&buf[..len]);
         self.is_dirty = true;
         Ok(len)
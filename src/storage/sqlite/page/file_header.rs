@@ -0,0 +1,109 @@
+use super::page_error::error_values;
+use super::page_error::SQLExecValue;
+use crate::error::Error;
+use crate::error::Result;
+
+/// the page size of a new database, unless another one is chosen
+pub const PAGE_SIZE: usize = 1024;
+
+/// the smallest page size
+pub const MIN_PAGE_SIZE: usize = 512;
+
+/// the largest page size
+pub const MAX_PAGE_SIZE: usize = 65536;
+
+/// the version of the file format written by this build, a file of a newer
+/// version is refused
+pub const FORMAT_VERSION: u16 = 1;
+
+/// every page ends with a checksum of its content
+pub const FEATURE_CHECKSUMS: u32 = 0x01;
+
+/// every page is encrypted, except the file header. the nonce and the
+/// authentication tag are stored at the end of the page.
+pub const FEATURE_ENCRYPTED: u32 = 0x02;
+
+/// the feature flags understood by this build, a file using any other flag
+/// is refused
+pub const KNOWN_FEATURES: u32 = FEATURE_CHECKSUMS | FEATURE_ENCRYPTED;
+
+/// the bytes at the end of a page reserved for its checksum
+pub const CHECKSUM_SIZE: usize = 4;
+
+/// identifies a kvdb database file
+const HEADER_MAGIC: [u8; 16] = *b"kvdb format\0\0\0\0\0";
+
+/// the file header is stored at the start of page 1, format:
+/// [HEADER_MAGIC][page size u32][format version u16][reserved u16][features u32][salt u32]
+pub const FILE_HEADER_SIZE: usize = 32;
+
+/// the header of a database file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub page_size: usize,
+    pub version: u16,
+    pub features: u32,
+    /// the salt of a key derived from a passphrase, 0 if not encrypted
+    pub salt: u32,
+}
+
+impl FileHeader {
+    /// the header of a new database with the given page size
+    pub fn new(page_size: usize) -> Result<Self> {
+        check_page_size(page_size)?;
+        Ok(Self {
+            page_size,
+            version: FORMAT_VERSION,
+            features: 0,
+            salt: 0,
+        })
+    }
+
+    /// read the header at the start of page 1. return None if the data has
+    /// no header, e.g. a new database, and an error if the file was written
+    /// in a format this build does not understand.
+    pub fn read(data: &[u8]) -> Result<Option<Self>> {
+        if data.len() < FILE_HEADER_SIZE || data[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+            return Ok(None);
+        }
+        let header = Self {
+            page_size: u32::from_be_bytes(data[16..20].try_into()?) as usize,
+            version: u16::from_be_bytes(data[20..22].try_into()?),
+            features: u32::from_be_bytes(data[24..28].try_into()?),
+            salt: u32::from_be_bytes(data[28..32].try_into()?),
+        };
+        if header.version > FORMAT_VERSION
+            || header.features & !KNOWN_FEATURES != 0
+            || check_page_size(header.page_size).is_err()
+        {
+            return Err(error_values(SQLExecValue::NOTADB));
+        }
+        Ok(Some(header))
+    }
+
+    pub fn has_feature(&self, feature: u32) -> bool {
+        self.features & feature != 0
+    }
+
+    /// the encoded header, to be written at the start of page 1
+    pub fn to_bytes(&self) -> [u8; FILE_HEADER_SIZE] {
+        let mut data = [0u8; FILE_HEADER_SIZE];
+        data[..16].copy_from_slice(&HEADER_MAGIC);
+        data[16..20].copy_from_slice(&(self.page_size as u32).to_be_bytes());
+        data[20..22].copy_from_slice(&self.version.to_be_bytes());
+        data[24..28].copy_from_slice(&self.features.to_be_bytes());
+        data[28..32].copy_from_slice(&self.salt.to_be_bytes());
+        data
+    }
+}
+
+/// a page size is a power of two between MIN_PAGE_SIZE and MAX_PAGE_SIZE
+pub fn check_page_size(page_size: usize) -> Result<()> {
+    if !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size) || !page_size.is_power_of_two() {
+        return Err(Error::Value(format!(
+            "Page size {} is not a power of two between {} and {}",
+            page_size, MIN_PAGE_SIZE, MAX_PAGE_SIZE
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::page_error::error_values;
+use super::page_error::SQLExecValue;
+use crate::error::Result;
+
+/// the locks are taken on bytes far beyond the pages of any database, the
+/// same bytes as SQLite: readers are kept out while a writer waits for the
+/// exclusive lock by the pending byte, the writer holds the reserved byte,
+/// readers share the shared range
+const PENDING_BYTE: u64 = 0x4000_0000;
+const RESERVED_BYTE: u64 = PENDING_BYTE + 1;
+const SHARED_FIRST: u64 = PENDING_BYTE + 2;
+const SHARED_SIZE: u64 = 510;
+
+/// how long a connection waits for a lock unless another timeout is chosen
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// the first wait of the busy handler, doubled on every retry
+const BUSY_WAIT: Duration = Duration::from_millis(1);
+
+/// the longest wait of the busy handler between two retries
+const BUSY_WAIT_MAX: Duration = Duration::from_millis(50);
+
+/// open file description locks belong to the opened file, not to the process,
+/// so two pagers of one process exclude each other too
+#[cfg(target_os = "linux")]
+const F_SETLK: libc::c_int = libc::F_OFD_SETLK;
+#[cfg(target_os = "linux")]
+const F_GETLK: libc::c_int = libc::F_OFD_GETLK;
+#[cfg(not(target_os = "linux"))]
+const F_SETLK: libc::c_int = libc::F_SETLK;
+#[cfg(not(target_os = "linux"))]
+const F_GETLK: libc::c_int = libc::F_GETLK;
+
+/// the lock held on a database file, shared by every process that opens it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockLevel {
+    /// the file is not used
+    None,
+    /// the file is read, any number of connections hold it
+    Shared,
+    /// the file will be written, the changed pages are in the cache, one
+    /// connection holds it together with the readers
+    Reserved,
+    /// the file is written, no other connection holds any lock
+    Exclusive,
+}
+
+/// advisory byte-range locks on the database file, implementing the
+/// shared/reserved/exclusive protocol between processes
+#[derive(Debug)]
+pub struct FileLock {
+    level: LockLevel,
+    // how long to retry a lock held by another connection before BUSY
+    busy_timeout: Duration,
+}
+
+impl FileLock {
+    pub fn new(busy_timeout: Duration) -> Self {
+        Self {
+            level: LockLevel::None,
+            busy_timeout,
+        }
+    }
+
+    pub fn level(&self) -> LockLevel {
+        self.level
+    }
+
+    pub fn busy_timeout(&self) -> Duration {
+        self.busy_timeout
+    }
+
+    /// raise the lock to the given level, retrying while another connection
+    /// holds a conflicting lock. BUSY once the busy timeout has passed.
+    pub fn lock(&mut self, fd: &File, level: LockLevel) -> Result<()> {
+        self.lock_timeout(fd, level, self.busy_timeout)
+    }
+
+    /// raise the lock to the given level, retrying until the timeout
+    pub fn lock_timeout(&mut self, fd: &File, level: LockLevel, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        let mut wait = BUSY_WAIT;
+        while self.level < level {
+            if self.try_lock(fd, level)? {
+                continue;
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                if self.level == LockLevel::Reserved {
+                    // stop keeping the readers out
+                    set_lock(fd, libc::F_UNLCK, PENDING_BYTE, 1)?;
+                }
+                return Err(error_values(SQLExecValue::BUSY));
+            }
+            thread::sleep(wait.min(timeout - elapsed));
+            wait = (wait * 2).min(BUSY_WAIT_MAX);
+        }
+        Ok(())
+    }
+
+    /// raise the lock one level toward the given level, false if another
+    /// connection holds a conflicting lock
+    fn try_lock(&mut self, fd: &File, level: LockLevel) -> Result<bool> {
+        match self.level {
+            LockLevel::None => {
+                // a writer waiting for the exclusive lock holds the pending byte
+                if !set_lock(fd, libc::F_RDLCK, PENDING_BYTE, 1)? {
+                    return Ok(false);
+                }
+                let shared = set_lock(fd, libc::F_RDLCK, SHARED_FIRST, SHARED_SIZE)?;
+                set_lock(fd, libc::F_UNLCK, PENDING_BYTE, 1)?;
+                if !shared {
+                    return Ok(false);
+                }
+                self.level = LockLevel::Shared;
+            }
+            LockLevel::Shared => {
+                if !set_lock(fd, libc::F_WRLCK, RESERVED_BYTE, 1)? {
+                    return Ok(false);
+                }
+                self.level = LockLevel::Reserved;
+            }
+            LockLevel::Reserved if level == LockLevel::Exclusive => {
+                // the pending byte keeps new readers out until the readers
+                // holding the shared range are done
+                if !set_lock(fd, libc::F_WRLCK, PENDING_BYTE, 1)?
+                    || !set_lock(fd, libc::F_WRLCK, SHARED_FIRST, SHARED_SIZE)?
+                {
+                    return Ok(false);
+                }
+                self.level = LockLevel::Exclusive;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// lower the lock to the given level, Shared or None
+    pub fn unlock(&mut self, fd: &File, level: LockLevel) -> Result<()> {
+        if self.level <= level {
+            return Ok(());
+        }
+        match level {
+            LockLevel::Shared => {
+                set_lock(fd, libc::F_RDLCK, SHARED_FIRST, SHARED_SIZE)?;
+                set_lock(fd, libc::F_UNLCK, PENDING_BYTE, 2)?;
+            }
+            _ => {
+                set_lock(fd, libc::F_UNLCK, PENDING_BYTE, 2 + SHARED_SIZE)?;
+            }
+        }
+        self.level = level;
+        Ok(())
+    }
+
+    /// true if another connection holds the reserved lock, e.g. it is
+    /// writing a journal that must not be played back
+    pub fn check_reserved(&self, fd: &File) -> Result<bool> {
+        if self.level >= LockLevel::Reserved {
+            return Ok(false);
+        }
+        let mut flock = new_flock(libc::F_WRLCK, RESERVED_BYTE, 1);
+        if unsafe { libc::fcntl(fd.as_raw_fd(), F_GETLK, &mut flock) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(flock.l_type != libc::F_UNLCK as libc::c_short)
+    }
+}
+
+fn new_flock(lock_type: libc::c_int, start: u64, len: u64) -> libc::flock {
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_type = lock_type as libc::c_short;
+    flock.l_whence = libc::SEEK_SET as libc::c_short;
+    flock.l_start = start as libc::off_t;
+    flock.l_len = len as libc::off_t;
+    flock
+}
+
+/// set or release a lock on a range of bytes without waiting, false if
+/// another connection holds a conflicting lock
+fn set_lock(fd: &File, lock_type: libc::c_int, start: u64, len: u64) -> Result<bool> {
+    let flock = new_flock(lock_type, start, len);
+    if unsafe { libc::fcntl(fd.as_raw_fd(), F_SETLK, &flock) } == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EAGAIN) | Some(libc::EACCES) => Ok(false),
+        _ => Err(err.into()),
+    }
+}
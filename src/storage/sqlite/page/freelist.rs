@@ -0,0 +1,87 @@
+use std::mem::size_of;
+
+use super::file_header::FILE_HEADER_SIZE;
+use super::page_error::error_values;
+use super::page_error::SQLExecValue;
+use crate::error::Result;
+
+/// the head of the freelist is stored in page 1 after the file header, it
+/// is the `free_list` and `n_free` of PageOne:
+/// [first trunk page u32][number of free pages u32]
+pub const FREELIST_OFFSET: usize = FILE_HEADER_SIZE;
+
+/// the head of the freelist of the database file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FreelistHead {
+    /// the first trunk page, 0 if no page is free
+    pub first_trunk: u32,
+    /// the free pages, the trunk pages included
+    pub n_free: u32,
+}
+
+impl FreelistHead {
+    /// read the head of the freelist from the data of page 1
+    pub fn read(page1: &[u8]) -> Result<Self> {
+        let data = &page1[FREELIST_OFFSET..FREELIST_OFFSET + 2 * size_of::<u32>()];
+        Ok(Self {
+            first_trunk: u32::from_ne_bytes(data[0..4].try_into()?),
+            n_free: u32::from_ne_bytes(data[4..8].try_into()?),
+        })
+    }
+
+    /// the encoded head, to be written into page 1 at FREELIST_OFFSET
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut data = [0u8; 8];
+        data[0..4].copy_from_slice(&self.first_trunk.to_ne_bytes());
+        data[4..8].copy_from_slice(&self.n_free.to_ne_bytes());
+        data
+    }
+}
+
+/// a trunk page of the freelist, an OverflowPage holding a FreelistInfo:
+/// [next trunk page u32][number of leaves u32][leaf page u32]...
+/// a leaf page holds nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FreelistTrunk {
+    /// the next trunk page, 0 for the last one
+    pub next: u32,
+    /// the free pages listed by this trunk
+    pub leaves: Vec<u32>,
+}
+
+impl FreelistTrunk {
+    /// the most leaves a trunk page lists, the page holds usable_size bytes
+    pub fn capacity(usable_size: usize) -> usize {
+        usable_size / size_of::<u32>() - 2
+    }
+
+    /// read a trunk page, it is corrupt if it lists more leaves than it can
+    /// hold
+    pub fn read(data: &[u8], usable_size: usize) -> Result<Self> {
+        let n_leaves = u32::from_ne_bytes(data[4..8].try_into()?) as usize;
+        if n_leaves > Self::capacity(usable_size) {
+            return Err(error_values(SQLExecValue::CORRUPT));
+        }
+        let leaves = data[8..8 + n_leaves * size_of::<u32>()]
+            .chunks_exact(size_of::<u32>())
+            .map(|leaf| u32::from_ne_bytes(leaf.try_into().unwrap()))
+            .collect();
+        Ok(Self {
+            next: u32::from_ne_bytes(data[0..4].try_into()?),
+            leaves,
+        })
+    }
+
+    /// the encoded trunk, usable_size bytes to be written at the start of
+    /// the page
+    pub fn to_bytes(&self, usable_size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; usable_size];
+        data[0..4].copy_from_slice(&self.next.to_ne_bytes());
+        data[4..8].copy_from_slice(&(self.leaves.len() as u32).to_ne_bytes());
+        for (i, leaf) in self.leaves.iter().enumerate() {
+            let offset = 8 + i * size_of::<u32>();
+            data[offset..offset + 4].copy_from_slice(&leaf.to_ne_bytes());
+        }
+        data
+    }
+}
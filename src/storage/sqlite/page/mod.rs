@@ -1,10 +1,29 @@
 mod disk_data;
+mod file_header;
+mod file_lock;
+mod freelist;
 mod page_error;
 mod page_record;
 mod pager;
 mod pg_hdr;
+mod wal;
 
 pub use disk_data::DiskData;
+pub use file_header::check_page_size;
+pub use file_header::FileHeader;
+pub use file_header::CHECKSUM_SIZE;
+pub use file_header::FEATURE_CHECKSUMS;
+pub use file_header::FEATURE_ENCRYPTED;
+pub use file_header::FILE_HEADER_SIZE;
+pub use file_header::FORMAT_VERSION;
+pub use file_header::MAX_PAGE_SIZE;
+pub use file_header::MIN_PAGE_SIZE;
+pub use file_header::PAGE_SIZE;
+pub use file_lock::LockLevel;
+pub use file_lock::BUSY_TIMEOUT;
+pub use freelist::FreelistHead;
+pub use freelist::FreelistTrunk;
+pub use freelist::FREELIST_OFFSET;
 pub use pager::Pager;
-pub use pager::PAGE_SIZE;
+pub use pager::PagerStats;
 pub use pg_hdr::PgHdr;
@@ -31,6 +31,9 @@ pub enum SQLExecValue {
     CONSIRAINT,
     MISMATCH,
     MISUSE,
+    /// named after SQLITE_NOTADB like the other values
+    #[allow(clippy::upper_case_acronyms)]
+    NOTADB,
 }
 
 impl SQLExecValue {
@@ -59,6 +62,7 @@ impl SQLExecValue {
             CONSIRAINT => 19,
             MISMATCH => 20,
             MISUSE => 21,
+            NOTADB => 22,
         }
     }
 
@@ -87,6 +91,7 @@ impl SQLExecValue {
             19 => CONSIRAINT,
             20 => MISMATCH,
             21 => MISUSE,
+            22 => NOTADB,
             _ => OK,
         }
     }
@@ -142,6 +147,7 @@ pub(super) fn error_values(value: SQLExecValue) -> Error {
             CONSIRAINT => "Abort due to contraint violation".into(),
             MISMATCH => "Data type mismatch".into(),
             MISUSE => "Library used incorrectly".into(),
+            NOTADB => "File opened that is not a database file".into(),
             _ => todo!(),
         }),
     }
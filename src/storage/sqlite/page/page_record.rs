@@ -1,7 +1,36 @@
-use super::pager::PAGE_SIZE;
+use std::mem::size_of;
 
-#[repr(C)]
+use crate::error::Result;
+
+/// a page image in the journal file, format: [pgno][data][checksum]
 pub struct PageRecord {
     pub pgno: u32,
-    pub data: [u8; PAGE_SIZE],
+    pub data: Vec<u8>,
+    /// guards against replaying a torn record, see `record_checksum`
+    pub checksum: u32,
+}
+
+impl PageRecord {
+    /// the size of an encoded record
+    pub fn size(page_size: usize) -> usize {
+        page_size + 2 * size_of::<u32>()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::size(self.data.len()));
+        data.extend_from_slice(&self.pgno.to_be_bytes());
+        data.extend_from_slice(&self.data);
+        data.extend_from_slice(&self.checksum.to_be_bytes());
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let (pgno, data) = data.split_at(size_of::<u32>());
+        let (data, checksum) = data.split_at(data.len() - size_of::<u32>());
+        Ok(Self {
+            pgno: u32::from_be_bytes(pgno.try_into()?),
+            data: data.to_vec(),
+            checksum: u32::from_be_bytes(checksum.try_into()?),
+        })
+    }
 }
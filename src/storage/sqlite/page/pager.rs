@@ -2,23 +2,34 @@ use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::io::Seek;
-use std::io::SeekFrom;
 use std::io::Write;
 use std::mem::size_of;
 use std::os::unix::prelude::FileExt;
 use std::path::PathBuf;
-use std::ptr::addr_of;
-use std::slice;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
 use std::sync::RwLock;
-use std::sync::RwLockReadGuard;
-use std::sync::RwLockWriteGuard;
+use std::time::Duration;
 
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
 use derivative::Derivative;
-
+use memmap2::Mmap;
+use memmap2::MmapOptions;
+use serde_derive::Deserialize;
+use serde_derive::Serialize;
+
+use super::file_header::check_page_size;
+use super::file_header::FileHeader;
+use super::file_header::CHECKSUM_SIZE;
+use super::file_header::FEATURE_CHECKSUMS;
+use super::file_header::FEATURE_ENCRYPTED;
+use super::file_header::FILE_HEADER_SIZE;
+use super::file_lock::FileLock;
+use super::file_lock::LockLevel;
+use super::freelist::FreelistHead;
+use super::freelist::FreelistTrunk;
 use super::page_error::error_values;
 use super::page_error::page_errorcode;
 use super::page_error::SQLExecValue;
@@ -26,13 +37,18 @@ use super::page_error::ERR_CORRUPT;
 use super::page_error::ERR_FULL;
 use super::page_error::ERR_MEM;
 use super::page_record::PageRecord;
+use super::wal::Wal;
 use super::PgHdr;
+use crate::common::options::JournalMode;
 use crate::common::options::PagerOption;
 use crate::error::Error;
 use crate::error::Result;
+use crate::storage::encryption::Cipher;
+use crate::storage::encryption::EncryptionKey;
+use crate::storage::encryption::ENCRYPTION_OVERHEAD;
 
-/// page size
-pub const PAGE_SIZE: usize = 1024;
+/// checkpoint the write-ahead log on commit once it holds this many frames
+const WAL_AUTOCHECKPOINT: u32 = 1000;
 
 /// How big to make the hash table used for locating in-memory pages
 /// by page number.
@@ -41,6 +57,9 @@ const PG_HASH: usize = 2003;
 /// jfd magic number
 pub(super) const JOURNAL_MAGIC: [u8; 8] = [0xca, 0xfe, 0xba, 0xbe, 0xa1, 0xb2, 0xc3, 0xd4];
 
+/// journal header: [JOURNAL_MAGIC][db_size before write][checksum nonce][page size]
+const JOURNAL_HEADER_SIZE: u64 = JOURNAL_MAGIC.len() as u64 + 3 * size_of::<u32>() as u64;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PageLockState {
     UNLOCK,
@@ -48,6 +67,29 @@ pub enum PageLockState {
     WRITELOCK,
 }
 
+/// the statistics of the page cache
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagerStats {
+    /// pages held in the cache
+    pub pages: u32,
+    /// the number of pages the cache holds before it recycles pages
+    pub max_pages: u32,
+    /// pages in use, they can not be recycled
+    pub referenced: u32,
+    /// pages changed by the current transaction
+    pub dirty: u32,
+    /// page requests served from the cache
+    pub hits: u32,
+    /// page requests read from the database file or the log
+    pub misses: u32,
+    /// pages recycled for another page number
+    pub evictions: u32,
+    /// dirty pages written to the database file to make room in the cache
+    pub spills: u32,
+    /// pages borrowed from the mapping of the database file
+    pub mapped: u32,
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Pager {
@@ -59,6 +101,18 @@ pub struct Pager {
     fd: RwLock<File>,
     // file descriptor for journal
     jfd: Option<RwLock<File>>,
+    // the write-ahead log, replaces the journal in WAL mode
+    wal: Option<Wal>,
+    // the size of a page, read from the file header of an existing database
+    page_size: usize,
+    // true if every page ends with a checksum, read from the file header of
+    // an existing database
+    checksums: bool,
+    // encrypts the pages written to the database file, the log and the
+    // journal, None if the database is not encrypted
+    cipher: Option<Cipher>,
+    // the salt of the key, recorded in the file header
+    salt: u32,
     // number of pages in the database filename
     db_size: u32,
     // db_size before the current change
@@ -75,14 +129,20 @@ pub struct Pager {
     n_hit: u32,
     // cache miss
     n_miss: u32,
-    // LRU overflows
+    // LRU overflows, the pages recycled for another page number
     n_ovfl: u32,
+    // dirty pages written to the database file to make room in the cache
+    n_spill: u32,
     // true if journal file descriptor is valid
     journal_open: bool,
+    // salt of the record checksums of the current journal
+    journal_nonce: u32,
     // true if write the database and flush disk
     no_sync: bool,
     // the lock state
     state: PageLockState,
+    // the lock on the database file, shared with the other connections
+    lock: FileLock,
     // one of several kinds of errors, error msg
     err_mask: u8,
     // true if the z_filename is a temporary file
@@ -93,12 +153,20 @@ pub struct Pager {
     need_sync: bool,
     // true if database file has changed in any way
     dirty_file: bool,
+    // true if the database file is truncated to db_size by the commit
+    truncated: bool,
+    // the most bytes of the database file to map, 0 if the pages are read
+    mmap_size: u64,
+    // the read-only mapping of the database file, the clean pages inside it
+    // borrow their data from it
+    #[derivative(Debug = "ignore")]
+    mmap: Option<Arc<Mmap>>,
     // one bit for each page in the database file
     // record the writing of multiple Pages during a transaction execution
     a_in_journal: Option<Vec<u8>>,
-    // list of free page
+    // list of free page, the least recently used first
     p_first: Option<Arc<Mutex<PgHdr>>>,
-    // list of free page
+    // list of free page, the most recently used last
     p_last: Option<Arc<Mutex<PgHdr>>>,
     // list of all pages
     p_all: Option<Arc<Mutex<PgHdr>>>,
@@ -109,22 +177,28 @@ pub struct Pager {
 
 impl Pager {
     pub fn open(option: PagerOption) -> Result<Self> {
+        check_page_size(option.page_size)?;
         let (z_filename, z_journal) = option.get_paths()?;
+        // only a temporary database starts empty
         let fd = RwLock::new(
             OpenOptions::new()
                 .write(true)
                 .create(true)
-                .truncate(true)
+                .truncate(option.is_temp())
                 .read(true)
                 .open(z_filename.as_path())?,
         );
 
-        // let jfd = RwLock::new(File::create(z_journal.as_path())?);
-        let pager = Pager {
+        let mut pager = Pager {
             z_filename,
             z_journal,
             fd,
             jfd: None,
+            wal: None,
+            page_size: option.page_size,
+            checksums: option.checksums,
+            cipher: None,
+            salt: 0,
             db_size: 0,
             orig_db_size: 0,
             n_extra: option.n_extra,
@@ -134,23 +208,456 @@ impl Pager {
             n_hit: 0,
             n_miss: 0,
             n_ovfl: 0,
+            n_spill: 0,
             journal_open: true,
+            journal_nonce: 0,
             no_sync: false,
             state: PageLockState::UNLOCK,
+            lock: FileLock::new(option.busy_timeout),
             err_mask: 0,
             temp_file: option.is_temp(),
             read_only: option.read_only,
             need_sync: false,
             dirty_file: false,
+            truncated: false,
+            mmap_size: option.mmap_size,
+            mmap: None,
             a_in_journal: None,
             p_first: None,
             p_last: None,
             p_all: None,
             a_hash: HashMap::new(),
         };
+        pager.lock_db(LockLevel::Shared)?;
+        let result = pager.open_database(&option);
+        pager.unlock_db(LockLevel::None)?;
+        result?;
         Ok(pager)
     }
 
+    /// recover the database, and read its file header. called with the
+    /// shared lock held.
+    fn open_database(&mut self, option: &PagerOption) -> Result<()> {
+        self.playback_hot_journal()?;
+
+        let wal_path = self.z_filename.with_extension("wal");
+        if option.journal_mode == JournalMode::Rollback && wal_path.exists() {
+            // a log left behind in WAL mode holds committed pages
+            self.lock_db(LockLevel::Exclusive)?;
+            if !option.is_temp() {
+                let mut wal = Wal::open(&wal_path, false, self.page_size)?;
+                wal.checkpoint(&*self.fd.write()?, !self.no_sync)?;
+            }
+            fs::remove_file(&wal_path)?;
+            self.unlock_db(LockLevel::Shared)?;
+        }
+
+        // an existing database keeps the page size of its file header
+        let mut header = self.read_file_header()?;
+        if let Some(header) = header {
+            self.page_size = header.page_size;
+            self.checksums = header.has_feature(FEATURE_CHECKSUMS);
+        }
+        if option.journal_mode == JournalMode::Wal {
+            let wal = Wal::open(&wal_path, option.is_temp(), self.page_size)?;
+            if header.is_some_and(|h| h.page_size != wal.page_size()) {
+                return Err(error_values(SQLExecValue::CORRUPT));
+            }
+            self.page_size = wal.page_size();
+            self.wal = Some(wal);
+            // the latest page 1 may be in the log
+            if let Some(latest) = self.read_file_header()? {
+                self.checksums = latest.has_feature(FEATURE_CHECKSUMS);
+                header = Some(latest);
+            }
+        }
+        self.open_cipher(option.encryption.as_ref(), header)
+    }
+
+    /// derive the key of an encrypted database from the salt of its file
+    /// header. a new database is encrypted with a new salt, it is recorded
+    /// by the header written into page 1, see `new_file_header`.
+    fn open_cipher(
+        &mut self,
+        key: Option<&EncryptionKey>,
+        header: Option<FileHeader>,
+    ) -> Result<()> {
+        let encrypted = header.map(|h| h.has_feature(FEATURE_ENCRYPTED));
+        let key = match (key, encrypted) {
+            (Some(key), Some(true) | None) => key,
+            (None, Some(true)) => {
+                return Err(Error::Config(
+                    "The database is encrypted, it can not be opened without its key".into(),
+                ))
+            }
+            (Some(_), Some(false)) => {
+                return Err(Error::Config(
+                    "The database is not encrypted, it can not be opened with a key".into(),
+                ))
+            }
+            (None, _) => return Ok(()),
+        };
+        self.salt = match header {
+            Some(header) => header.salt,
+            None => new_salt(),
+        };
+        self.cipher = Some(Cipher::from_key(key, &self.salt.to_be_bytes())?);
+        Ok(())
+    }
+
+    /// the file header to write into page 1 of a new database, with the
+    /// features of this pager
+    pub fn new_file_header(&self) -> Result<FileHeader> {
+        let mut header = FileHeader::new(self.page_size)?;
+        if self.checksums {
+            header.features |= FEATURE_CHECKSUMS;
+        }
+        if self.cipher.is_some() {
+            header.features |= FEATURE_ENCRYPTED;
+            header.salt = self.salt;
+        }
+        Ok(header)
+    }
+
+    /// true if the pages are encrypted
+    pub fn encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// read the file header at the start of page 1, None for a database
+    /// without a header
+    fn read_file_header(&self) -> Result<Option<FileHeader>> {
+        let mut data = vec![0u8; FILE_HEADER_SIZE];
+        match self.wal.as_ref().and_then(|wal| wal.find(1)) {
+            Some(frame) => data.copy_from_slice(&self.wal_frame(frame)?[..FILE_HEADER_SIZE]),
+            None => {
+                if self.fd.read()?.read_exact_at(&mut data, 0).is_err() {
+                    return Ok(None);
+                }
+            }
+        }
+        FileHeader::read(&data)
+    }
+
+    /// the size of a page of this database
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// true if every page ends with a checksum
+    pub fn checksums(&self) -> bool {
+        self.checksums
+    }
+
+    /// the bytes of a page available to the user, the checksum is stored
+    /// after them
+    pub fn usable_size(&self) -> usize {
+        let mut usable_size = self.page_size;
+        if self.checksums {
+            usable_size -= CHECKSUM_SIZE;
+        }
+        if self.cipher.is_some() {
+            usable_size -= ENCRYPTION_OVERHEAD;
+        }
+        usable_size
+    }
+
+    /// the image of a page as written to the database file, the log or the
+    /// journal, with its checksum, encrypted
+    fn page_image(&self, pgno: u32, data: Vec<u8>) -> Result<Vec<u8>> {
+        self.page_image_with(self.cipher.as_ref(), pgno, data)
+    }
+
+    /// the image of a page encrypted with the given cipher
+    fn page_image_with(
+        &self,
+        cipher: Option<&Cipher>,
+        pgno: u32,
+        mut data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        if self.checksums {
+            let usable_size = self.usable_size();
+            let checksum = page_checksum(pgno, &data[..usable_size]);
+            data[usable_size..usable_size + CHECKSUM_SIZE].copy_from_slice(&checksum.to_be_bytes());
+        }
+        if let Some(cipher) = cipher {
+            encrypt_page(cipher, pgno, &mut data)?;
+        }
+        Ok(data)
+    }
+
+    /// the data of a page image read from the database file or the log, None
+    /// if it can not be decrypted
+    fn decrypt_page(&self, pgno: u32, mut image: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        match self.cipher.as_ref() {
+            Some(cipher) if !decrypt_page(cipher, pgno, &mut image)? => Ok(None),
+            _ => Ok(Some(image)),
+        }
+    }
+
+    /// false if the checksum of a page read from the database file or the
+    /// log does not match. a page that was never written is all zeros.
+    fn verify_page(&self, pgno: u32, data: &[u8]) -> bool {
+        if !self.checksums {
+            return true;
+        }
+        let (data, checksum) = data.split_at(self.usable_size());
+        let checksum = &checksum[..CHECKSUM_SIZE];
+        checksum == page_checksum(pgno, data).to_be_bytes()
+            || (checksum.iter().all(|b| *b == 0) && data.iter().all(|b| *b == 0))
+    }
+
+    /// check every page of the database, and describe each problem found.
+    /// the pages are read from the database file and the log, not from the
+    /// cache.
+    pub fn integrity_check(&mut self) -> Result<Vec<String>> {
+        // the database file must not change while it is checked
+        let level = self.lock.level();
+        self.lock_db(LockLevel::Shared)?;
+        let problems = self.check_pages();
+        self.unlock_db(level)?;
+        problems
+    }
+
+    fn check_pages(&mut self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        if let Some(wal) = self.wal.as_mut() {
+            wal.refresh()?;
+        }
+        let db_len = self.fd.read()?.metadata()?.len();
+        if db_len % self.page_size as u64 != 0 {
+            problems.push(format!(
+                "File size {} is not a multiple of the page size {}",
+                db_len, self.page_size
+            ));
+        }
+        let db_size = match self.wal.as_ref().map(|wal| wal.db_size()) {
+            Some(db_size) if db_size != 0 => db_size,
+            _ => (db_len / self.page_size as u64) as u32,
+        };
+        for pgno in 1..=db_size {
+            let data = match self.decrypt_page(pgno, self.read_image(pgno)?)? {
+                Some(data) => data,
+                None => {
+                    problems.push(format!("Page {} can not be decrypted", pgno));
+                    continue;
+                }
+            };
+            if !self.verify_page(pgno, &data) {
+                problems.push(format!("Page {} has a wrong checksum", pgno));
+            }
+        }
+        self.check_freelist(db_size, &mut problems)?;
+        Ok(problems)
+    }
+
+    /// walk the trunk and leaf pages of the freelist, a page must lie inside
+    /// the database and be referenced once. the free pages counted must
+    /// match the count of page 1.
+    fn check_freelist(&self, db_size: u32, problems: &mut Vec<String>) -> Result<()> {
+        // only a database with a file header has a freelist
+        let page1 = match db_size {
+            0 => return Ok(()),
+            _ => match self.decrypt_page(1, self.read_image(1)?)? {
+                Some(data) if FileHeader::read(&data)?.is_some() => data,
+                _ => return Ok(()),
+            },
+        };
+        let head = FreelistHead::read(&page1)?;
+        let mut referenced = vec![false; db_size as usize + 1];
+        referenced[1] = true;
+        let mut reference = |pgno: u32, problems: &mut Vec<String>| -> bool {
+            if pgno < 2 || pgno > db_size {
+                problems.push(format!(
+                    "Freelist page {} is outside the database of {} pages",
+                    pgno, db_size
+                ));
+                return false;
+            }
+            if referenced[pgno as usize] {
+                problems.push(format!("Page {} is referenced twice", pgno));
+                return false;
+            }
+            referenced[pgno as usize] = true;
+            true
+        };
+
+        let mut n_free = 0;
+        let mut trunk = head.first_trunk;
+        // a trunk referenced twice is a cycle, the walk ends there
+        while trunk != 0 && reference(trunk, problems) {
+            n_free += 1;
+            let data = match self.decrypt_page(trunk, self.read_image(trunk)?)? {
+                Some(data) => data,
+                None => break,
+            };
+            let info = match FreelistTrunk::read(&data, self.usable_size()) {
+                Ok(info) => info,
+                Err(_) => {
+                    problems.push(format!(
+                        "Freelist trunk page {} lists more leaves than it holds",
+                        trunk
+                    ));
+                    break;
+                }
+            };
+            for leaf in info.leaves {
+                if reference(leaf, problems) {
+                    n_free += 1;
+                }
+            }
+            trunk = info.next;
+        }
+        if n_free != head.n_free {
+            problems.push(format!(
+                "Freelist holds {} pages, page 1 counts {}",
+                n_free, head.n_free
+            ));
+        }
+        Ok(())
+    }
+
+    /// encrypt every page again with a new key and a new salt, e.g. to rotate
+    /// the key. no page may be in use. the log is checkpointed first, and the
+    /// pages encrypted with the old key are journaled: a crash during the
+    /// pass leaves the database encrypted with the old key.
+    pub fn rekey(&mut self, key: &EncryptionKey) -> Result<()> {
+        if self.cipher.is_none() {
+            return Err(Error::Value("The database is not encrypted".into()));
+        }
+        if self.state != PageLockState::UNLOCK {
+            return Err(error_values(SQLExecValue::MISUSE));
+        }
+        if self.read_only {
+            return Err(error_values(SQLExecValue::PERM));
+        }
+        self.lock_db(LockLevel::Shared)?;
+        let result = self.rekey_pages(key);
+        self.unlock_db(LockLevel::None)?;
+        result
+    }
+
+    fn rekey_pages(&mut self, key: &EncryptionKey) -> Result<()> {
+        self.begin_read()?;
+        self.lock_db(LockLevel::Exclusive)?;
+        if self.wal.is_some() {
+            self.checkpoint_wal()?;
+        }
+        let db_size = (self.fd.read()?.metadata()?.len() / self.page_size as u64) as u32;
+
+        // every page is read before the database file changes
+        let nonce = nonce();
+        let mut journal = JOURNAL_MAGIC.to_vec();
+        journal.extend_from_slice(&db_size.to_be_bytes());
+        journal.extend_from_slice(&nonce.to_be_bytes());
+        journal.extend_from_slice(&(self.page_size as u32).to_be_bytes());
+        for pgno in 1..=db_size {
+            let data = self.read_image(pgno)?;
+            if self.decrypt_page(pgno, data.clone())?.is_none() {
+                return Err(error_values(SQLExecValue::CORRUPT));
+            }
+            let record = PageRecord {
+                pgno,
+                checksum: record_checksum(nonce, pgno, &data),
+                data,
+            };
+            journal.extend_from_slice(&record.encode());
+        }
+        let mut jfd = File::create(self.z_journal.as_path())?;
+        jfd.write_all(&journal)?;
+        if !self.no_sync {
+            jfd.sync_all()?;
+        }
+
+        let salt = new_salt();
+        let cipher = Cipher::from_key(key, &salt.to_be_bytes())?;
+        for pgno in 1..=db_size {
+            let mut data = self.read_page_data(pgno)?;
+            if pgno == 1 {
+                if let Some(mut header) = FileHeader::read(&data)? {
+                    header.salt = salt;
+                    data[..FILE_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+                }
+            }
+            if data.iter().any(|b| *b != 0) {
+                data = self.page_image_with(Some(&cipher), pgno, data)?;
+            }
+            self.fd
+                .write()?
+                .write_all_at(&data, (pgno - 1) as u64 * self.page_size as u64)?;
+        }
+        if !self.no_sync {
+            self.fd.write()?.sync_all()?;
+        }
+        fs::remove_file(self.z_journal.as_path())?;
+        self.cipher = Some(cipher);
+        self.salt = salt;
+        Ok(())
+    }
+
+    /// a journal left behind by a crash is hot: the database file may hold
+    /// part of a commit that never finished. every journaled page is written
+    /// back, the file is truncated to its original size and the journal is
+    /// deleted. a journal without a complete header was never used, and is
+    /// deleted without a playback.
+    fn playback_hot_journal(&mut self) -> Result<()> {
+        if !self.z_journal.exists() {
+            return Ok(());
+        }
+        // the journal of a connection still writing is not hot
+        if !self.temp_file && self.lock.check_reserved(&*self.fd.read()?)? {
+            return Ok(());
+        }
+        self.lock_db(LockLevel::Exclusive)?;
+        let jfd = match File::open(self.z_journal.as_path()) {
+            Ok(jfd) => jfd,
+            Err(_) => return Err(error_values(SQLExecValue::CANTOPEN)),
+        };
+        let fd = self.fd.write()?;
+        if pager_playback(self, &fd, &jfd)?.is_some() && !self.no_sync {
+            fd.sync_all()?;
+        }
+        drop(fd);
+        fs::remove_file(self.z_journal.as_path())?;
+        self.unlock_db(LockLevel::Shared)
+    }
+
+    /// raise the lock on the database file, waiting for the other
+    /// connections up to the busy timeout. a temporary database is private.
+    fn lock_db(&mut self, level: LockLevel) -> Result<()> {
+        if self.temp_file {
+            return Ok(());
+        }
+        self.lock.lock(&*self.fd.read()?, level)
+    }
+
+    /// lower the lock on the database file
+    fn unlock_db(&mut self, level: LockLevel) -> Result<()> {
+        if self.temp_file {
+            return Ok(());
+        }
+        self.lock.unlock(&*self.fd.read()?, level)
+    }
+
+    /// the lock held on the database file
+    pub fn lock_level(&self) -> LockLevel {
+        self.lock.level()
+    }
+
+    /// close the pager without a commit or a rollback: the cached pages are
+    /// dropped and the lock on the database file is released, the pages must
+    /// not be used anymore. the journal of a transaction in progress is left
+    /// behind, and played back by the next connection.
+    pub fn close(&mut self) -> Result<()> {
+        self.reset()?;
+        self.n_ref = 0;
+        self.journal_open = false;
+        self.jfd = None;
+        self.a_in_journal = None;
+        self.state = PageLockState::UNLOCK;
+        self.unlock_db(LockLevel::None)
+    }
+
     pub fn add_ref(&mut self) {
         self.n_ref += 1;
     }
@@ -252,6 +759,14 @@ impl Pager {
             }
         }
 
+        if self.wal.is_some() {
+            return self.commit_wal();
+        }
+
+        // wait for the readers of the database file, the transaction goes on
+        // if they are busy
+        self.lock_db(LockLevel::Exclusive)?;
+
         // hloding write lock
         let fd_writer = self.fd.write()?;
 
@@ -264,8 +779,9 @@ impl Pager {
                 p_all = next_all;
                 continue;
             }
-            let offset = (node.get_pgno() - 1) as u64 * PAGE_SIZE as u64;
-            if let Err(_) = fd_writer.write_all_at(&node.get_data(), offset) {
+            let offset = (node.get_pgno() - 1) as u64 * self.page_size as u64;
+            let data = self.page_image(node.get_pgno(), node.get_data())?;
+            if let Err(_) = fd_writer.write_all_at(&data, offset) {
                 drop(fd_writer);
                 self.rollback()?;
                 return Err(error_values(SQLExecValue::FULL));
@@ -275,6 +791,19 @@ impl Pager {
             drop(node);
             p_all = next_all;
         }
+        if self.truncated {
+            // the mapping must not reach beyond the end of the file
+            self.mmap = None;
+        }
+        if self.truncated
+            && fd_writer
+                .set_len(self.db_size as u64 * self.page_size as u64)
+                .is_err()
+        {
+            drop(fd_writer);
+            self.rollback()?;
+            return Err(error_values(SQLExecValue::FULL));
+        }
 
         if !self.no_sync {
             if let Err(_) = fd_writer.sync_all() {
@@ -291,6 +820,102 @@ impl Pager {
         Ok(())
     }
 
+    /// append the dirty pages to the write-ahead log, the database file is
+    /// not changed until a checkpoint
+    fn commit_wal(&mut self) -> Result<()> {
+        let mut pages = Vec::new();
+        let mut p_all = self.p_all.as_ref().map(Arc::clone);
+        while let Some(all) = p_all.as_ref() {
+            let node = all.lock()?;
+            if node.is_dirty() {
+                pages.push((
+                    node.get_pgno(),
+                    self.page_image(node.get_pgno(), node.get_data())?,
+                ));
+            }
+            let next_all = node.get_next_all();
+            drop(node);
+            p_all = next_all;
+        }
+        pages.sort_by_key(|(pgno, _)| *pgno);
+        if pages.is_empty() && self.truncated {
+            // a commit frame records the new size of the database
+            pages.push((1, self.page_image(1, self.read_page_data(1)?)?));
+        }
+
+        let db_size = pages
+            .iter()
+            .map(|(pgno, _)| *pgno)
+            .fold(self.db_size, u32::max);
+        let sync = !self.no_sync;
+        if let Some(wal) = self.wal.as_mut() {
+            if !pages.is_empty() && wal.append(&pages, db_size, sync).is_err() {
+                self.rollback()?;
+                return Err(error_values(SQLExecValue::FULL));
+            }
+        }
+        self.unwritelock()?;
+        self.db_size = 0;
+
+        if self.wal.as_ref().map(|wal| wal.frames()).unwrap_or(0) >= WAL_AUTOCHECKPOINT {
+            // the checkpoint is tried again by the next commit if the log
+            // has readers
+            match self.checkpoint_timeout(Duration::ZERO) {
+                Err(err) if err != error_values(SQLExecValue::BUSY) => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// copy the write-ahead log into the database file, and empty the log.
+    /// return the number of pages copied, always 0 in rollback journal mode.
+    pub fn checkpoint(&mut self) -> Result<u32> {
+        let busy_timeout = self.lock.busy_timeout();
+        self.checkpoint_timeout(busy_timeout)
+    }
+
+    /// checkpoint, waiting up to the timeout for the readers of the log
+    fn checkpoint_timeout(&mut self, timeout: Duration) -> Result<u32> {
+        if self.state == PageLockState::WRITELOCK {
+            return Err(error_values(SQLExecValue::BUSY));
+        }
+        if self.wal.is_none() {
+            return Ok(0);
+        }
+        // the log is emptied, nobody may read it
+        let level = self.lock.level();
+        if !self.temp_file {
+            self.lock
+                .lock_timeout(&*self.fd.read()?, LockLevel::Exclusive, timeout)?;
+        }
+        let result = self.checkpoint_wal();
+        self.unlock_db(level)?;
+        result
+    }
+
+    /// copy the log into the database file, with the exclusive lock held
+    fn checkpoint_wal(&mut self) -> Result<u32> {
+        let sync = !self.no_sync;
+        let n_ref = self.n_ref;
+        match self.wal.as_mut() {
+            Some(wal) => {
+                // the commits of other connections are copied too, unless
+                // the pages in use were read before them
+                if wal.changed()? {
+                    if n_ref > 0 {
+                        return Err(error_values(SQLExecValue::BUSY));
+                    }
+                    wal.refresh()?;
+                }
+                // the checkpoint may truncate the database file
+                self.mmap = None;
+                wal.checkpoint(&*self.fd.write()?, sync)
+            }
+            None => Ok(0),
+        }
+    }
+
     /// Acquire a write-lock on the database. The lock is removed when
     /// the any of the following happen:
     ///     1. commit
@@ -304,23 +929,35 @@ impl Pager {
             // only the upgrade of the lock is performed to acquire
             // the write lock during the transaction
             assert_eq!(self.a_in_journal, None);
-            let _ = self.fd.write()?;
+            drop(self.fd.write()?);
+            self.lock_db(LockLevel::Reserved)?;
+            // in WAL mode a write must start from the latest commit, the
+            // pages read before it are out of date
+            if self.wal.as_ref().map_or(Ok(false), |wal| wal.changed())? {
+                self.unlock_db(LockLevel::Shared)?;
+                return Err(error_values(SQLExecValue::BUSY));
+            }
 
             self.a_in_journal = Some(vec![0u8; self.db_size as usize / 8 + 1]);
 
-            match OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .read(true)
-                .open(self.z_journal.as_path())
-            {
-                Ok(jfd) => {
-                    self.jfd = Some(RwLock::new(jfd));
-                }
-                Err(_) => {
-                    self.a_in_journal = None;
-                    return Err(error_values(SQLExecValue::CANTOPEN));
+            // in WAL mode the database file is not changed until a
+            // checkpoint, so nothing needs to be journaled
+            if self.wal.is_none() {
+                match OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .read(true)
+                    .open(self.z_journal.as_path())
+                {
+                    Ok(jfd) => {
+                        self.jfd = Some(RwLock::new(jfd));
+                    }
+                    Err(_) => {
+                        self.a_in_journal = None;
+                        self.unlock_db(LockLevel::Shared)?;
+                        return Err(error_values(SQLExecValue::CANTOPEN));
+                    }
                 }
             }
 
@@ -332,12 +969,15 @@ impl Pager {
             self.orig_db_size = self.db_size;
 
             // init journal file
-            // format: [JOURNAL_MAGIC][db_size before write]...
-            if let Some(jfd) = self.jfd.as_ref() {
+            // format: [JOURNAL_MAGIC][db_size before write][nonce][page size][PageRecord]...
+            self.journal_nonce = nonce();
+            if let Some(jfd) = self.jfd.as_ref().filter(|_| self.wal.is_none()) {
                 let mut write_jfd = jfd.write()?;
                 match write_jfd
                     .write_all(&JOURNAL_MAGIC)
                     .and_then(|_| write_jfd.write_all(&self.db_size.to_be_bytes()))
+                    .and_then(|_| write_jfd.write_all(&self.journal_nonce.to_be_bytes()))
+                    .and_then(|_| write_jfd.write_all(&(self.page_size as u32).to_be_bytes()))
                 {
                     Err(_) => {
                         drop(write_jfd);
@@ -357,18 +997,14 @@ impl Pager {
             let writer = jfd.write()?;
             let len = writer.metadata()?.len();
 
-            let mut record_data = [0u8; PAGE_SIZE];
-            record_data.copy_from_slice(pg_data);
+            // the journal holds the page as stored in the database file
+            let data = self.page_image(pgno, pg_data.to_vec())?;
             let record = PageRecord {
                 pgno,
-                data: record_data,
+                checksum: record_checksum(self.journal_nonce, pgno, &data),
+                data,
             };
-            let record_ptr = addr_of!(record);
-            let byte_ptr = record_ptr as *const u8;
-            let record_serialize =
-                unsafe { slice::from_raw_parts(byte_ptr, size_of::<PageRecord>()) };
-
-            writer.write_all_at(record_serialize, len)?;
+            writer.write_all_at(&record.encode(), len)?;
         }
         Ok(())
     }
@@ -383,53 +1019,42 @@ impl Pager {
             return Err(error_values(SQLExecValue::from_bit(self.err_mask)));
         }
 
-        let mut p_pg = None;
-
-        if self.n_ref == 0 {
-            // this is a new pager or not used, should try to playback journal
-            let read_lock = match self.fd.read() {
-                Err(_) => return Err(error_values(SQLExecValue::BUSY)),
-                Ok(rl) => {
-                    self.state = PageLockState::READLOCK;
-                    rl
-                }
-            };
-
-            if self.z_journal.exists() && read_lock.metadata()?.len() > 0 {
-                // if journal file exists. playback it
-                drop(read_lock);
-                let mut write_fd = match self.fd.write() {
-                    Err(_) => {
-                        return Err(error_values(SQLExecValue::BUSY));
-                    }
-                    Ok(wl) => {
-                        self.state = PageLockState::WRITELOCK;
-                        wl
-                    }
-                };
-                self.journal_open = true;
-                if let Some(jfd) = self.jfd.as_ref() {
-                    let mut read_jfd = jfd.read()?;
-                    let db_size = pager_playback(self, &mut write_fd, &mut read_jfd)?;
-                    self.db_size = db_size;
-                }
-                drop(write_fd);
+        if self.state == PageLockState::UNLOCK {
+            // this is a new pager or not used, another connection may have
+            // changed the database since the pages were cached
+            self.lock_db(LockLevel::Shared)?;
+            self.state = PageLockState::READLOCK;
+            if let Err(err) = self.begin_read() {
+                self.state = PageLockState::UNLOCK;
+                self.unlock_db(LockLevel::None)?;
+                return Err(err);
             }
-        } else {
-            p_pg = self.lookup(pgno)?;
         }
 
-        if let Some(p_pg) = p_pg.as_ref() {
+        if let Some(p_pg) = self.lookup(pgno)? {
+            // cache hit
+            self.n_hit += 1;
             let mut pg = p_pg.lock()?;
-            pg.page_ref()?;
+            if pg.get_ref() == 0 {
+                self.unlink_free(&mut pg)?;
+                self.n_ref += 1;
+            }
+            pg.pg_ref();
+            drop(pg);
+            return Ok(p_pg);
+        }
+
+        // cache miss
+        self.n_miss += 1;
+        let victim = if self.n_page < self.mx_page {
+            None
         } else {
-            // cache miss
-            self.n_miss += 1;
-            if self.n_page < self.mx_page || self.p_first.is_none() {
+            self.find_victim()?
+        };
+        let p_pg = match victim {
+            None => {
                 // create a new page
-
-                // create a blank pg_hdr
-                let pg_hdr = match PgHdr::new(pg_ref, pgno) {
+                let pg_hdr = match PgHdr::new(pg_ref, pgno, self.page_size, self.usable_size()) {
                     Ok(p) => p,
                     Err(_) => {
                         self.unwritelock()?;
@@ -440,10 +1065,7 @@ impl Pager {
                 let pg_arc = Arc::new(Mutex::new(pg_hdr));
 
                 // put it in the head node of p_all
-                let p_pg_ref = Arc::clone(&pg_arc);
-                p_pg = Some(Arc::clone(&pg_arc));
-
-                let mut p_hdr = p_pg_ref.as_ref().lock()?;
+                let mut p_hdr = pg_arc.lock()?;
                 if let Some(p_all) = self.p_all.as_ref() {
                     // if has other pgHdr
                     p_hdr.set_next_all(Arc::clone(p_all));
@@ -451,120 +1073,273 @@ impl Pager {
                     let mut prev_head = p_all.lock()?;
                     prev_head.set_prev_all(Arc::clone(&pg_arc));
                 }
+                drop(p_hdr);
 
                 self.p_all = Some(Arc::clone(&pg_arc));
                 self.n_page += 1;
-            } else {
-                // recycle pages
-                let mut p_first = self.p_first.as_ref().map(Arc::clone);
-                while let Some(free_node) = p_first.as_ref().map(Arc::clone) {
-                    let node = free_node.as_ref().lock()?;
-                    // try to find a free page that is not being used
-                    if node.is_dirty() {
-                        p_first = node.get_next_free();
-                    } else {
-                        break;
-                    }
-                }
+                pg_arc
+            }
+            Some(victim) => {
+                // recycle the least recently used page
+                let mut free_node = victim.lock()?;
+                self.unlink_free(&mut free_node)?;
+                self.unlink_hash(&mut free_node)?;
+                free_node.recycle(pgno);
+                drop(free_node);
+                self.n_ovfl += 1;
+                victim
+            }
+        };
 
-                if p_first.is_none() {
-                    // all page was dirty, sync all page
-                    match self.sync_all_pages() {
-                        Ok(_) => {}
-                        Err(_) => {
-                            self.rollback()?;
-                            return Err(error_values(SQLExecValue::IOERR));
-                        }
-                    }
+        let mut pg = p_pg.lock()?;
 
-                    p_first = self.p_first.as_ref().map(Arc::clone);
+        // set write pgno in a_in_journal
+        match self.a_in_journal.as_ref() {
+            Some(in_journal) if pgno <= self.orig_db_size => {
+                if let Some(index) = in_journal.get(pgno as usize / 8) {
+                    pg.set_journal((*index & (1 << (pgno & 7))) != 0);
                 }
+            }
+            _ => { /* do not anything */ }
+        }
 
-                // remove p_first in free list
-                if let Some(free_first) = p_first {
-                    let mut free_node = free_first.as_ref().lock()?;
+        pg.pg_ref();
+        self.n_ref += 1;
 
-                    // remove it with free list
-                    if let Some(free_prev) = free_node.get_prev_free() {
-                        let mut prev_node = free_prev.as_ref().lock()?;
-                        prev_node.set_next_free(free_node.get_next_free());
-                    } else {
-                        self.p_first = free_node.get_next_free();
-                    }
-                    if let Some(free_next) = free_node.get_next_free() {
-                        let mut next_node = free_next.as_ref().lock()?;
-                        next_node.set_prev_free(free_node.get_prev_free());
-                    } else {
-                        self.p_last = free_node.get_prev_free();
-                    }
-                    free_node.set_next_free(None);
-                    free_node.set_prev_free(None);
+        // append hash list
+        let hash_key = pgno_hash(pgno);
+        pg.set_next_hash(self.a_hash.get(&hash_key).map(Arc::clone));
+        self.a_hash.insert(hash_key, Arc::clone(&p_pg));
+        if let Some(next_hash) = pg.get_next_hash() {
+            let mut next = next_hash.as_ref().lock()?;
+            next.set_prev_hash(Some(Arc::clone(&p_pg)));
+        }
 
-                    // remove it with hash list
-                    if let Some(hash_next) = free_node.get_next_hash() {
-                        let mut next_node = hash_next.as_ref().lock()?;
-                        next_node.set_prev_hash(free_node.get_prev_hash());
-                    }
-                    if let Some(hash_prev) = free_node.get_prev_hash() {
-                        let mut prev_node = hash_prev.as_ref().lock()?;
-                        prev_node.set_next_hash(free_node.get_next_hash());
-                    } else {
-                        // in the hash list head
-                        let hash_key = pgno_hash(free_node.get_pgno());
-                        match free_node.get_next_hash() {
-                            Some(node) => self.a_hash.insert(hash_key, node),
-                            None => self.a_hash.remove(&hash_key),
-                        };
-                    }
-                    free_node.set_next_hash(None);
-                    free_node.set_prev_hash(None);
+        if self.db_size == 0 {
+            self.pagecount()?;
+        }
 
-                    drop(free_node);
-                    p_pg = Some(free_first);
-                } else {
-                    return Err(error_values(SQLExecValue::IOERR));
+        if self.db_size >= pgno {
+            // if pgni exist, try to read data from the latest
+            // committed frame of the log, or from fd
+            match self.wal.as_ref().and_then(|wal| wal.find(pgno)) {
+                // an encrypted page is decrypted into the cache, it is
+                // never mapped
+                _ if self.cipher.is_some() => {
+                    match self.decrypt_page(pgno, self.read_image(pgno)?)? {
+                        Some(data) => pg.set_data(&data),
+                        None => {
+                            self.err_mask |= ERR_CORRUPT;
+                            return Err(error_values(SQLExecValue::CORRUPT));
+                        }
+                    }
                 }
-                self.n_ovfl += 1;
+                Some(frame) => pg.set_data(&self.wal_frame(frame)?),
+                None => match self.mapping(pgno)? {
+                    Some(map) => pg.map_data(map)?,
+                    None => {
+                        let fd_read = self.fd.read()?;
+                        pg.read_fd(fd_read)?;
+                    }
+                },
+            }
+            let disk_data = pg.get_disk_data();
+            if !self.verify_page(pgno, disk_data.read()?.as_slice()) {
+                self.err_mask |= ERR_CORRUPT;
+                return Err(error_values(SQLExecValue::CORRUPT));
             }
+        }
+        drop(pg);
 
-            if let Some(pg_hdr) = p_pg.as_ref() {
-                let mut pg = pg_hdr.lock()?;
+        Ok(p_pg)
+    }
 
-                // set write pgno in a_in_journal
-                match self.a_in_journal.as_ref() {
-                    Some(in_journal) if pgno < self.orig_db_size => {
-                        if let Some(index) = in_journal.get(pgno as usize / 8) {
-                            pg.set_journal((*index & (1 << (pgno % 7))) != 0);
-                        }
-                    }
-                    _ => { /* do not anything */ }
-                }
+    /// the mapping of the database file holding the page, the file is mapped
+    /// again when it grew. None if the page lies beyond mmap_size or the end
+    /// of the file, it is read then.
+    fn mapping(&mut self, pgno: u32) -> Result<Option<Arc<Mmap>>> {
+        let end = pgno as u64 * self.page_size as u64;
+        if end > self.mmap_size {
+            return Ok(None);
+        }
+        if let Some(map) = self.mmap.as_ref().filter(|map| map.len() as u64 >= end) {
+            return Ok(Some(Arc::clone(map)));
+        }
+        let fd = self.fd.read()?;
+        let len = fd.metadata()?.len().min(self.mmap_size);
+        if len < end {
+            return Ok(None);
+        }
+        // the pages of the file only shrink with the exclusive lock held,
+        // the mapping is dropped before
+        let map = unsafe { MmapOptions::new().len(len as usize).map(&*fd)? };
+        drop(fd);
+        let map = Arc::new(map);
+        self.mmap = Some(Arc::clone(&map));
+        Ok(Some(map))
+    }
 
-                pg.pg_ref();
-                self.n_ref += 1;
+    /// start reading with the shared lock held: play back the journal of a
+    /// crashed writer, and drop the pages that may be out of date. a read
+    /// sees the commits appended to the log until now.
+    fn begin_read(&mut self) -> Result<()> {
+        self.playback_hot_journal()?;
+        if let Some(wal) = self.wal.as_mut() {
+            wal.refresh()?;
+        }
+        self.reset()?;
+        self.db_size = 0;
+        // another connection may have truncated the database file
+        let db_len = self.fd.read()?.metadata()?.len();
+        if self
+            .mmap
+            .as_ref()
+            .is_some_and(|map| map.len() as u64 > db_len)
+        {
+            self.mmap = None;
+        }
+        Ok(())
+    }
 
-                // append hash list
-                let hash_key = pgno_hash(pgno);
-                pg.set_next_hash(self.a_hash.get(&hash_key).map(Arc::clone));
-                self.a_hash.insert(hash_key, Arc::clone(pg_hdr));
-                if let Some(next_hash) = pg.get_next_hash() {
-                    let mut next = next_hash.as_ref().lock()?;
-                    next.set_prev_hash(Some(Arc::clone(pg_hdr)));
-                }
+    /// release a page returned by get_page. a page nobody uses is put at the
+    /// end of the free list, it is recycled when the cache is full and every
+    /// page used less recently has been recycled.
+    pub fn unref(&mut self, p_pg: &Arc<Mutex<PgHdr>>) -> Result<()> {
+        let mut pg = p_pg.lock()?;
+        if pg.get_ref() == 0 {
+            return Err(error_values(SQLExecValue::MISUSE));
+        }
+        pg.pg_unref();
+        if pg.get_ref() > 0 {
+            return Ok(());
+        }
 
-                if self.db_size == 0 {
-                    self.pagecount()?;
-                }
+        pg.set_prev_free(self.p_last.as_ref().map(Arc::clone));
+        pg.set_next_free(None);
+        match self.p_last.as_ref() {
+            Some(last) => last.lock()?.set_next_free(Some(Arc::clone(p_pg))),
+            None => self.p_first = Some(Arc::clone(p_pg)),
+        }
+        self.p_last = Some(Arc::clone(p_pg));
+        self.n_ref -= 1;
+        drop(pg);
+
+        // the last page is released, so are the readers of the database
+        if self.n_ref == 0 && self.state == PageLockState::READLOCK {
+            self.state = PageLockState::UNLOCK;
+            self.unlock_db(LockLevel::None)?;
+        }
+        Ok(())
+    }
 
-                if self.db_size >= pgno {
-                    // if pgni exist, try to read data from fd
-                    let fd_read = self.fd.read()?;
-                    pg.read_fd(fd_read)?;
-                }
+    /// the statistics of the page cache
+    pub fn stats(&self) -> Result<PagerStats> {
+        let mut dirty = 0;
+        let mut mapped = 0;
+        let mut p_all = self.p_all.as_ref().map(Arc::clone);
+        while let Some(all) = p_all {
+            let node = all.lock()?;
+            if node.is_dirty() {
+                dirty += 1;
+            }
+            if node.get_disk_data().read()?.is_mapped() {
+                mapped += 1;
+            }
+            p_all = node.get_next_all();
+        }
+        Ok(PagerStats {
+            pages: self.n_page,
+            max_pages: self.mx_page,
+            referenced: self.n_ref,
+            dirty,
+            hits: self.n_hit,
+            misses: self.n_miss,
+            evictions: self.n_ovfl,
+            spills: self.n_spill,
+            mapped,
+        })
+    }
+
+    /// find the page to recycle when the cache is full: the least recently
+    /// used clean page. when every free page is dirty they are spilled to
+    /// the database file, their original content is in the journal. None if
+    /// no page can be recycled, the cache grows beyond mx_page then.
+    fn find_victim(&mut self) -> Result<Option<Arc<Mutex<PgHdr>>>> {
+        let mut p_first = self.p_first.as_ref().map(Arc::clone);
+        while let Some(free_node) = p_first {
+            let node = free_node.lock()?;
+            if !node.is_dirty() {
+                drop(node);
+                return Ok(Some(free_node));
+            }
+            p_first = node.get_next_free();
+        }
+
+        // the log must not hold uncommitted pages, so nothing is spilled in
+        // WAL mode
+        if self.p_first.is_none() || self.wal.is_some() {
+            return Ok(None);
+        }
+        // nor while other connections read the database file
+        if self.lock_db(LockLevel::Exclusive).is_err() {
+            return Ok(None);
+        }
+        if self.sync_all_pages().is_err() {
+            self.rollback()?;
+            return Err(error_values(SQLExecValue::IOERR));
+        }
+        Ok(self.p_first.as_ref().map(Arc::clone))
+    }
+
+    /// remove an unused page from the free list
+    fn unlink_free(&mut self, pg: &mut PgHdr) -> Result<()> {
+        match pg.get_prev_free() {
+            Some(prev) => prev.lock()?.set_next_free(pg.get_next_free()),
+            None => self.p_first = pg.get_next_free(),
+        }
+        match pg.get_next_free() {
+            Some(next) => next.lock()?.set_prev_free(pg.get_prev_free()),
+            None => self.p_last = pg.get_prev_free(),
+        }
+        pg.set_next_free(None);
+        pg.set_prev_free(None);
+        Ok(())
+    }
+
+    /// remove a page from the hash table
+    fn unlink_hash(&mut self, pg: &mut PgHdr) -> Result<()> {
+        if let Some(hash_next) = pg.get_next_hash() {
+            hash_next.lock()?.set_prev_hash(pg.get_prev_hash());
+        }
+        match pg.get_prev_hash() {
+            Some(hash_prev) => hash_prev.lock()?.set_next_hash(pg.get_next_hash()),
+            None => {
+                // in the hash list head
+                let hash_key = pgno_hash(pg.get_pgno());
+                match pg.get_next_hash() {
+                    Some(node) => self.a_hash.insert(hash_key, node),
+                    None => self.a_hash.remove(&hash_key),
+                };
             }
         }
+        pg.set_next_hash(None);
+        pg.set_prev_hash(None);
+        Ok(())
+    }
 
-        p_pg.ok_or_else(|| error_values(SQLExecValue::IOERR))
+    /// drop every cached page
+    fn reset(&mut self) -> Result<()> {
+        let mut p_all = self.p_all.take();
+        while let Some(all) = p_all {
+            let mut node = all.lock()?;
+            node.detach();
+            p_all = node.take_next_all();
+        }
+        self.p_first = None;
+        self.p_last = None;
+        self.a_hash.clear();
+        self.n_page = 0;
+        Ok(())
     }
 
     pub fn pagecount(&mut self) -> Result<u32> {
@@ -573,7 +1348,10 @@ impl Pager {
         }
         let db_len = self.fd.read()?.metadata()?.len();
         if self.state != PageLockState::UNLOCK {
-            self.db_size = (db_len / PAGE_SIZE as u64) as u32;
+            self.db_size = match self.wal.as_ref().map(|wal| wal.db_size()) {
+                Some(db_size) if db_size != 0 => db_size,
+                _ => (db_len / self.page_size as u64) as u32,
+            };
         }
         Ok(self.db_size)
     }
@@ -582,10 +1360,10 @@ impl Pager {
     pub fn rollback(&mut self) -> Result<()> {
         if self.err_mask != 0 && self.err_mask != ERR_FULL {
             if self.state == PageLockState::WRITELOCK {
-                let mut fd = self.fd.write()?;
+                let fd = self.fd.write()?;
                 if let Some(jfd) = self.jfd.as_ref() {
                     let jfd = jfd.read()?;
-                    pager_playback(self, &mut fd, &jfd)?;
+                    pager_playback(self, &fd, &jfd)?;
                 }
             }
             return page_errorcode(self.err_mask);
@@ -594,23 +1372,142 @@ impl Pager {
         if self.state != PageLockState::WRITELOCK {
             return Ok(());
         }
-        let mut fd = self.fd.write()?;
-        if let Some(jfd) = self.jfd.as_ref() {
-            let jfd = jfd.read()?;
-            match pager_playback(self, &mut fd, &jfd) {
-                Ok(_) => {}
-                Err(_) => {
-                    self.err_mask |= ERR_CORRUPT;
-                    return Err(error_values(SQLExecValue::CORRUPT));
+        if self.wal.is_some() {
+            // nothing has been written in WAL mode, the write transaction ends
+            self.reload_pages()?;
+            self.dirty_file = false;
+            self.unwritelock()?;
+            self.db_size = 0;
+            return Ok(());
+        }
+        // the database file was written only with the exclusive lock held
+        if self.temp_file || self.lock.level() == LockLevel::Exclusive {
+            let fd = self.fd.write()?;
+            if let Some(jfd) = self.jfd.as_ref() {
+                let jfd = jfd.read()?;
+                match pager_playback(self, &fd, &jfd) {
+                    Ok(_) => {}
+                    Err(_) => {
+                        self.err_mask |= ERR_CORRUPT;
+                        return Err(error_values(SQLExecValue::CORRUPT));
+                    }
                 }
             }
         }
+        self.reload_pages()?;
+        self.truncated = false;
         self.db_size = 0;
         Ok(())
     }
 
+    /// read the cached pages again from the log or the database file, a page
+    /// beyond the end of the file is zeroed
+    fn reload_pages(&mut self) -> Result<()> {
+        // the playback may have truncated the database file
+        self.mmap = None;
+        let mut p_all = self.p_all.as_ref().map(Arc::clone);
+        while let Some(all) = p_all.as_ref() {
+            let mut node = all.lock()?;
+            let data = self.read_page_data(node.get_pgno())?;
+            node.set_data(&data);
+            let next_all = node.get_next_all();
+            drop(node);
+            p_all = next_all;
+        }
+        Ok(())
+    }
+
+    /// read the committed image of a page from the log or the database file,
+    /// a page beyond the end of the file is zeroed
+    fn read_page_data(&self, pgno: u32) -> Result<Vec<u8>> {
+        self.decrypt_page(pgno, self.read_image(pgno)?)?
+            .ok_or_else(|| error_values(SQLExecValue::CORRUPT))
+    }
+
+    /// read the committed image of a page from the log or the database file,
+    /// as it is stored
+    fn read_image(&self, pgno: u32) -> Result<Vec<u8>> {
+        if let Some(frame) = self.wal.as_ref().and_then(|wal| wal.find(pgno)) {
+            return self.wal_frame(frame);
+        }
+        let mut data = vec![0u8; self.page_size];
+        self.fd
+            .read()?
+            .read_at(&mut data, (pgno - 1) as u64 * self.page_size as u64)?;
+        Ok(data)
+    }
+
+    /// shrink the database to its first n_pages pages, the file is truncated
+    /// by the commit. the pages cut off are journaled, a rollback restores
+    /// them. a write to a page beyond the new end grows the database again.
+    pub fn truncate(&mut self, n_pages: u32) -> Result<()> {
+        // page 1 holds the file header
+        if n_pages == 0 || self.state == PageLockState::UNLOCK {
+            return Err(error_values(SQLExecValue::MISUSE));
+        }
+        if self.read_only {
+            return Err(error_values(SQLExecValue::PERM));
+        }
+        self.page_begin()?;
+        if self.db_size == 0 {
+            self.pagecount()?;
+        }
+        if n_pages >= self.db_size {
+            return Ok(());
+        }
+
+        if self.wal.is_none() {
+            for pgno in n_pages + 1..=self.orig_db_size {
+                let in_journal = self
+                    .a_in_journal
+                    .as_ref()
+                    .and_then(|j| j.get(pgno as usize / 8))
+                    .is_some_and(|index| index & (1 << (pgno & 7)) != 0);
+                if in_journal {
+                    continue;
+                }
+                let data = self.read_page_data(pgno)?;
+                if self.write_journal(pgno, &data).is_err() {
+                    self.rollback()?;
+                    self.err_mask |= ERR_FULL;
+                    return Err(error_values(SQLExecValue::FULL));
+                }
+                self.put_a_journal(pgno);
+                self.need_sync = !self.no_sync;
+            }
+        }
+
+        // the cached pages cut off are empty pages now
+        let mut p_all = self.p_all.as_ref().map(Arc::clone);
+        while let Some(all) = p_all {
+            let mut node = all.lock()?;
+            let pgno = node.get_pgno();
+            if pgno > n_pages {
+                node.set_data(&vec![0u8; self.page_size]);
+                node.set_dirty(false);
+                node.set_journal(pgno <= self.orig_db_size);
+            }
+            p_all = node.get_next_all();
+        }
+        self.db_size = n_pages;
+        self.truncated = true;
+        self.dirty_file = true;
+        Ok(())
+    }
+
+    fn wal_frame(&self, frame: u32) -> Result<Vec<u8>> {
+        match self.wal.as_ref() {
+            Some(wal) => wal.read_frame(frame),
+            None => Err(error_values(SQLExecValue::INTERNAL)),
+        }
+    }
+
     /// Sync the journal and then write all free dirty pages to the database file.
     fn sync_all_pages(&mut self) -> Result<()> {
+        // in WAL mode the database file must not change before a checkpoint
+        if self.wal.is_some() {
+            return Err(error_values(SQLExecValue::FULL));
+        }
         if self.need_sync {
             if !self.temp_file {
                 if let Some(jfd) = self.jfd.as_ref() {
@@ -625,10 +1522,12 @@ impl Pager {
             let mut all_node = node.lock()?;
             if all_node.is_dirty() {
                 let fd_write = self.fd.write()?;
-                let offset = (all_node.get_pgno() - 1) as u64 * PAGE_SIZE as u64;
-                fd_write.write_all_at(&all_node.get_data(), offset)?;
+                let offset = (all_node.get_pgno() - 1) as u64 * self.page_size as u64;
+                let data = self.page_image(all_node.get_pgno(), all_node.get_data())?;
+                fd_write.write_all_at(&data, offset)?;
                 drop(fd_write);
                 all_node.set_dirty(false);
+                self.n_spill += 1;
             }
 
             let next_node = all_node.get_next_free();
@@ -649,8 +1548,11 @@ impl Pager {
         }
 
         self.journal_open = false;
-        fs::remove_file(self.z_journal.as_path())?;
+        if self.wal.is_none() {
+            fs::remove_file(self.z_journal.as_path())?;
+        }
         self.a_in_journal = None;
+        self.truncated = false;
 
         let mut p_all = self.p_all.as_ref().map(Arc::clone);
         while let Some(all) = p_all.as_ref() {
@@ -663,8 +1565,15 @@ impl Pager {
             p_all = next_all;
         }
 
-        self.state = PageLockState::READLOCK;
-        Ok(())
+        // the readers are let in again, the lock is released with the last
+        // page in use
+        if self.n_ref == 0 {
+            self.state = PageLockState::UNLOCK;
+            self.unlock_db(LockLevel::None)
+        } else {
+            self.state = PageLockState::READLOCK;
+            self.unlock_db(LockLevel::Shared)
+        }
     }
 
     /// find a page in the hash table given its page number.
@@ -689,81 +1598,137 @@ impl Pager {
     }
 }
 
-fn pager_playback(
-    pager: &Pager,
-    fd: &mut RwLockWriteGuard<File>,
-    jfd: &RwLockReadGuard<File>,
-) -> Result<u32> {
-    let mut n_rec = match jfd.metadata() {
+/// play back the journal jfd into the database file fd, and return the
+/// original number of pages, or None if the journal has no valid header.
+/// the records are replayed in order, a record with a wrong checksum is the
+/// torn tail of the journal and ends the playback.
+fn pager_playback(pager: &Pager, fd: &File, jfd: &File) -> Result<Option<u32>> {
+    let len = match jfd.metadata() {
         Err(_) => return Err(error_values(SQLExecValue::CORRUPT)),
         Ok(metadata) => metadata.len(),
     };
+    if len < JOURNAL_HEADER_SIZE {
+        return Ok(None);
+    }
 
-    n_rec = (n_rec - JOURNAL_MAGIC.len() as u64 - size_of::<u32>() as u64)
-        / size_of::<PageRecord>() as u64;
-    if n_rec == 0 {
+    let mut header = [0u8; JOURNAL_HEADER_SIZE as usize];
+    if jfd.read_exact_at(&mut header, 0).is_err() {
         return Err(error_values(SQLExecValue::CORRUPT));
     }
+    let (magic, header) = header.split_at(JOURNAL_MAGIC.len());
+    if magic != JOURNAL_MAGIC {
+        return Ok(None);
+    }
+    let mx_pg = u32::from_be_bytes(header[0..4].try_into()?);
+    let nonce = u32::from_be_bytes(header[4..8].try_into()?);
+    // the journal records the page size, it may be played back before the
+    // file header is read
+    let page_size = u32::from_be_bytes(header[8..12].try_into()?) as usize;
+    if check_page_size(page_size).is_err() {
+        return Ok(None);
+    }
 
-    let mut magic = [0u8; JOURNAL_MAGIC.len()];
-    match jfd.read_exact_at(&mut magic, 0) {
-        Err(e) => {
-            println!("{:?}", e);
-            return Err(error_values(SQLExecValue::CORRUPT));
-        }
-        Ok(_) => {}
-    };
-
-    let mut mx_pg_data = [0u8; size_of::<u32>()];
-    match jfd.read_exact_at(&mut mx_pg_data, JOURNAL_MAGIC.len() as u64) {
-        Err(_) => return Err(error_values(SQLExecValue::CORRUPT)),
-        Ok(_) => {}
-    };
-    let mx_pg = u32::from_be_bytes(mx_pg_data);
-
-    match fd.set_len(mx_pg as u64 * PAGE_SIZE as u64) {
-        Err(_) => return Err(error_values(SQLExecValue::CORRUPT)),
-        Ok(_) => {}
+    if fd.set_len(mx_pg as u64 * page_size as u64).is_err() {
+        return Err(error_values(SQLExecValue::CORRUPT));
     }
 
-    let record_size = size_of::<PageRecord>() as u64;
-    let used_offset = JOURNAL_MAGIC.len() as u64 + size_of::<u32>() as u64;
-    for i in 1..n_rec + 1 {
-        pager_playback_one_page(pager, fd, jfd, used_offset + (i - 1) * record_size)?;
+    let record_size = PageRecord::size(page_size) as u64;
+    let n_rec = (len - JOURNAL_HEADER_SIZE) / record_size;
+    for i in 0..n_rec {
+        let offset = JOURNAL_HEADER_SIZE + i * record_size;
+        if !pager_playback_one_page(pager, fd, jfd, nonce, page_size, offset)? {
+            break;
+        }
     }
 
-    Ok(mx_pg)
+    Ok(Some(mx_pg))
 }
 
 /// read a single page from the journal file opened file descripter jfd.
-/// playback this one page.
+/// playback this one page, unless its checksum does not match.
 fn pager_playback_one_page(
     pager: &Pager,
-    fd: &mut RwLockWriteGuard<File>,
-    jfd: &RwLockReadGuard<File>,
+    fd: &File,
+    jfd: &File,
+    nonce: u32,
+    page_size: usize,
     offset: u64,
-) -> Result<()> {
-    const RECORD_SIZE: usize = size_of::<PageRecord>();
-    let mut record_data = [0u8; RECORD_SIZE];
+) -> Result<bool> {
+    let mut record_data = vec![0u8; PageRecord::size(page_size)];
     jfd.read_exact_at(&mut record_data, offset)?;
 
-    let byte_ptr: *const u8 = record_data.as_ptr();
-    let record = unsafe {
-        byte_ptr
-            .cast::<PageRecord>()
-            .as_ref()
-            .ok_or_else(|| Error::Internal("read journal fail".into()))?
-    };
+    let record = PageRecord::decode(&record_data)?;
+    if record.pgno == 0 || record.checksum != record_checksum(nonce, record.pgno, &record.data) {
+        return Ok(false);
+    }
 
     if let Some(pghdr) = pager.lookup(record.pgno)? {
-        let mut pg = pghdr.as_ref().lock()?;
-        pg.set_data(&record.data);
+        if let Some(data) = pager.decrypt_page(record.pgno, record.data.clone())? {
+            pghdr.as_ref().lock()?.set_data(&data);
+        }
     }
 
-    fd.seek(SeekFrom::Start((record.pgno - 1) as u64 * PAGE_SIZE as u64))?;
-    fd.write_all(&record.data)?;
+    fd.write_all_at(&record.data, (record.pgno - 1) as u64 * page_size as u64)?;
+
+    Ok(true)
+}
+
+/// the checksum (FNV-1a) of the concatenated parts, seeded so that the
+/// checksums of an earlier journal or log never match
+pub(super) fn checksum(seed: u32, parts: &[&[u8]]) -> u32 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0x811c_9dc5 ^ seed, |hash, b| {
+            (hash ^ *b as u32).wrapping_mul(0x0100_0193)
+        })
+}
+
+/// the checksum of a journal record, salted with the nonce of the journal so
+/// that a record left over from an earlier journal never matches
+fn record_checksum(nonce: u32, pgno: u32, data: &[u8]) -> u32 {
+    checksum(nonce, &[&pgno.to_be_bytes(), data])
+}
+
+/// the checksum stored at the end of a page, the page number is included to
+/// detect a page written at the wrong place
+fn page_checksum(pgno: u32, data: &[u8]) -> u32 {
+    checksum(0, &[&pgno.to_be_bytes(), data])
+}
+
+/// the associated data of an encrypted page: its page number, so that a page
+/// written at the wrong place is not decrypted, and the file header of page 1
+fn page_aad(pgno: u32, header: &[u8]) -> Vec<u8> {
+    let mut aad = pgno.to_be_bytes().to_vec();
+    aad.extend_from_slice(header);
+    aad
+}
+
+/// encrypt a page in place, the file header of page 1 stays readable
+fn encrypt_page(cipher: &Cipher, pgno: u32, data: &mut [u8]) -> Result<()> {
+    let (header, data) = data.split_at_mut(if pgno == 1 { FILE_HEADER_SIZE } else { 0 });
+    cipher.encrypt_in_place(&page_aad(pgno, header), data)
+}
+
+/// decrypt a page in place, false if it can not be authenticated. a page
+/// that was never written is all zeros.
+fn decrypt_page(cipher: &Cipher, pgno: u32, data: &mut [u8]) -> Result<bool> {
+    if data.iter().all(|b| *b == 0) {
+        return Ok(true);
+    }
+    let (header, data) = data.split_at_mut(if pgno == 1 { FILE_HEADER_SIZE } else { 0 });
+    cipher.decrypt_in_place(&page_aad(pgno, header), data)
+}
+
+/// a new random salt for a key derived from a passphrase, never 0
+fn new_salt() -> u32 {
+    OsRng.next_u32().max(1)
+}
 
-    Ok(())
+/// a new random checksum salt for every journal and write-ahead log, so
+/// that the records of an earlier one can not be forged to match
+pub(super) fn nonce() -> u32 {
+    OsRng.next_u32()
 }
 
 fn pgno_hash(pgno: u32) -> u32 {
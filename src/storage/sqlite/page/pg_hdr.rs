@@ -6,10 +6,10 @@ use std::sync::RwLock;
 use std::sync::RwLockReadGuard;
 
 use derivative::Derivative;
+use memmap2::Mmap;
 
 use super::page_error::error_values;
 use super::page_error::SQLExecValue;
-use super::pager::PAGE_SIZE;
 use super::DiskData;
 use super::Pager;
 use crate::error::Result;
@@ -30,12 +30,19 @@ pub struct PgHdr {
     p_prev_all: Option<Arc<Mutex<PgHdr>>>,  // a list of all pages
     in_journal: bool,                       // true if has been written to journal
     dirty: bool,                            // true if we need write back change
-    data: Arc<RwLock<DiskData>>,            // PAGE_SIZE bytes of page data follow this header
+    page_size: usize,                       // the size of the page data
+    usable_size: usize,                     // the bytes of the page data before its checksum
+    data: Arc<RwLock<DiskData>>,            // page_size bytes of page data follow this header
 }
 
 impl PgHdr {
-    pub fn new(pager: Arc<Mutex<Pager>>, pgno: u32) -> Result<PgHdr> {
-        let disk_data = DiskData::new();
+    pub fn new(
+        pager: Arc<Mutex<Pager>>,
+        pgno: u32,
+        page_size: usize,
+        usable_size: usize,
+    ) -> Result<PgHdr> {
+        let disk_data = DiskData::new(page_size);
         Ok(Self {
             pager,
             pgno,
@@ -48,6 +55,8 @@ impl PgHdr {
             p_prev_all: None,
             in_journal: false,
             dirty: false,
+            page_size,
+            usable_size,
             data: Arc::new(RwLock::new(disk_data)),
         })
     }
@@ -58,7 +67,7 @@ impl PgHdr {
 
     /// write data to page
     pub fn write(&mut self, data: &[u8], offset: u64) -> Result<()> {
-        if PAGE_SIZE < offset as usize || offset as usize + data.len() > PAGE_SIZE {
+        if self.usable_size < offset as usize || offset as usize + data.len() > self.usable_size {
             return Err(error_values(SQLExecValue::NOMEM));
         }
         self.write_begin()?;
@@ -100,7 +109,7 @@ impl PgHdr {
             // the main database file.
             // write the current page to the transaction journal
             // if it is not there already.
-            let mut record_data = [0u8; PAGE_SIZE];
+            let mut record_data = vec![0u8; self.page_size];
             let disk_data_lock = Arc::clone(&self.data);
             let disk_data = disk_data_lock.write()?;
             disk_data.read(&mut record_data, 0)?;
@@ -154,6 +163,32 @@ impl PgHdr {
         self.n_ref += 1;
     }
 
+    pub fn pg_unref(&mut self) {
+        self.n_ref -= 1;
+    }
+
+    /// reuse this unused page for another page number, the data is zeroed
+    pub fn recycle(&mut self, pgno: u32) {
+        self.pgno = pgno;
+        self.n_ref = 0;
+        self.in_journal = false;
+        self.dirty = false;
+        self.set_data(&vec![0u8; self.page_size]);
+    }
+
+    /// drop the links to the other pages of the cache
+    pub fn detach(&mut self) {
+        self.p_next_hash = None;
+        self.p_prev_hash = None;
+        self.p_next_free = None;
+        self.p_prev_free = None;
+        self.p_prev_all = None;
+    }
+
+    pub fn take_next_all(&mut self) -> Option<Arc<Mutex<PgHdr>>> {
+        self.p_next_all.take()
+    }
+
     pub fn set_journal(&mut self, journal: bool) {
         self.in_journal = journal;
     }
@@ -180,8 +215,11 @@ impl PgHdr {
 
     /// read this data with pgno from fd
     pub fn read_fd(&mut self, fd: RwLockReadGuard<File>) -> Result<()> {
-        let mut read_buf = [0u8; PAGE_SIZE];
-        match fd.read_exact_at(&mut read_buf, (self.pgno - 1) as u64 * PAGE_SIZE as u64) {
+        let mut read_buf = vec![0u8; self.page_size];
+        match fd.read_exact_at(
+            &mut read_buf,
+            (self.pgno - 1) as u64 * self.page_size as u64,
+        ) {
             Ok(_) => {
                 let disk_data_lock = Arc::clone(&self.data);
                 let mut disk_data = disk_data_lock.write()?;
@@ -192,8 +230,16 @@ impl PgHdr {
         }
     }
 
-    pub fn get_data(&self) -> [u8; PAGE_SIZE] {
-        let mut read_data = [0u8; PAGE_SIZE];
+    /// borrow the data of this page from a mapping of the database file,
+    /// the page is copied when it is written
+    pub fn map_data(&mut self, map: Arc<Mmap>) -> Result<()> {
+        let offset = (self.pgno - 1) as usize * self.page_size;
+        self.data.write()?.map(map, offset);
+        Ok(())
+    }
+
+    pub fn get_data(&self) -> Vec<u8> {
+        let mut read_data = vec![0u8; self.page_size];
         let disk_data_lock = Arc::clone(&self.data);
         let disk_data = disk_data_lock.read().unwrap();
         disk_data.read(&mut read_data, 0).unwrap();
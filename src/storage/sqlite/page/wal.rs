@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::os::unix::prelude::FileExt;
+use std::path::Path;
+
+use super::file_header::check_page_size;
+use super::page_error::error_values;
+use super::page_error::SQLExecValue;
+use super::pager::checksum;
+use super::pager::nonce;
+use crate::error::Result;
+
+/// wal magic number
+const WAL_MAGIC: [u8; 8] = [0xca, 0xfe, 0xba, 0xbe, 0x57, 0x41, 0x4c, 0x01];
+
+/// wal header: [WAL_MAGIC][page size][salt]
+const WAL_HEADER_SIZE: u64 = WAL_MAGIC.len() as u64 + 8;
+
+/// frame header: [pgno][db size after commit, 0 if not a commit frame][checksum]
+const FRAME_HEADER_SIZE: u64 = 12;
+
+/// the frames of a commit as (pgno, frame) pairs, the database size after
+/// the commit and the checksum of its last frame
+type Commit = (Vec<(u32, u32)>, u32, u32);
+
+/// A write-ahead log. A commit appends the image of every changed page as a
+/// frame, the last frame of a commit records the database size. Readers look
+/// up the latest committed frame of a page before reading the database file,
+/// a checkpoint copies the frames back into the database file.
+#[derive(Debug)]
+pub struct Wal {
+    file: File,
+    page_size: usize,
+    /// a new salt is chosen whenever the log is reset, frames written with
+    /// an older salt fail their checksum
+    salt: u32,
+    /// the latest committed frame of each page
+    index: HashMap<u32, u32>,
+    /// number of committed frames
+    mx_frame: u32,
+    /// number of pages in the database after the last commit, 0 if the log
+    /// has no commit
+    db_size: u32,
+    /// checksum of the last committed frame, the checksums form a chain
+    checksum: u32,
+}
+
+impl Wal {
+    /// open the log, and rebuild the index of its committed frames. a log
+    /// holding commits keeps its own page size, an empty log is reset to the
+    /// given page size.
+    pub fn open(path: &Path, truncate: bool, page_size: usize) -> Result<Self> {
+        let file = match OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(truncate)
+            .read(true)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(_) => return Err(error_values(SQLExecValue::CANTOPEN)),
+        };
+        let mut wal = Self {
+            file,
+            page_size,
+            salt: 0,
+            index: HashMap::new(),
+            mx_frame: 0,
+            db_size: 0,
+            checksum: 0,
+        };
+        wal.refresh()?;
+        if wal.mx_frame == 0 && wal.page_size != page_size {
+            wal.page_size = page_size;
+            wal.reset()?;
+        }
+        Ok(wal)
+    }
+
+    /// read the frames committed since the index was built, e.g. by another
+    /// connection. a log without a valid header is reset.
+    pub fn refresh(&mut self) -> Result<()> {
+        let mut header = [0u8; WAL_HEADER_SIZE as usize];
+        if self.file.read_exact_at(&mut header, 0).is_err() || header[..8] != WAL_MAGIC {
+            return self.reset();
+        }
+        let page_size = u32::from_be_bytes(header[8..12].try_into()?) as usize;
+        let salt = u32::from_be_bytes(header[12..16].try_into()?);
+        if salt != self.salt {
+            // the log was reset by a checkpoint
+            if check_page_size(page_size).is_err() {
+                return Err(error_values(SQLExecValue::CORRUPT));
+            }
+            self.page_size = page_size;
+            self.salt = salt;
+            self.index.clear();
+            self.mx_frame = 0;
+            self.db_size = 0;
+            self.checksum = salt;
+        } else if page_size != self.page_size {
+            return Err(error_values(SQLExecValue::CORRUPT));
+        }
+
+        while let Some((frames, db_size, checksum)) = self.next_commit()? {
+            self.mx_frame += frames.len() as u32;
+            self.index.extend(frames);
+            self.db_size = db_size;
+            self.checksum = checksum;
+        }
+        Ok(())
+    }
+
+    /// true if another connection committed to the log, or reset it, since
+    /// the index was built
+    pub fn changed(&self) -> Result<bool> {
+        let mut header = [0u8; WAL_HEADER_SIZE as usize];
+        if self.file.read_exact_at(&mut header, 0).is_err()
+            || header[..8] != WAL_MAGIC
+            || u32::from_be_bytes(header[12..16].try_into()?) != self.salt
+        {
+            return Ok(true);
+        }
+        Ok(self.next_commit()?.is_some())
+    }
+
+    /// read the commit following the last committed frame, return the
+    /// frames of each page, the database size and the checksum of its last
+    /// frame. None if there is no commit, or only the torn tail of one.
+    fn next_commit(&self) -> Result<Option<Commit>> {
+        let len = self.file.metadata()?.len();
+        let mut checksum = self.checksum;
+        let mut pending = Vec::new();
+        let mut frame = self.mx_frame;
+        let mut buf = vec![0u8; self.frame_size() as usize];
+        while self.frame_offset(frame + 1) <= len {
+            self.file
+                .read_exact_at(&mut buf, self.frame_offset(frame))?;
+            let pgno = u32::from_be_bytes(buf[0..4].try_into()?);
+            let db_size = u32::from_be_bytes(buf[4..8].try_into()?);
+            let frame_checksum = u32::from_be_bytes(buf[8..12].try_into()?);
+            checksum = frame_checksum_of(checksum, pgno, db_size, &buf[12..]);
+            if pgno == 0 || checksum != frame_checksum {
+                // the torn tail of an unfinished commit
+                break;
+            }
+            pending.push((pgno, frame));
+            frame += 1;
+            if db_size != 0 {
+                return Ok(Some((pending, db_size, checksum)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// the latest committed frame of a page, if any
+    pub fn find(&self, pgno: u32) -> Option<u32> {
+        self.index.get(&pgno).copied()
+    }
+
+    /// the number of pages in the database, 0 if the log has no commit
+    pub fn db_size(&self) -> u32 {
+        self.db_size
+    }
+
+    /// the page size of the frames
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// the number of committed frames
+    pub fn frames(&self) -> u32 {
+        self.mx_frame
+    }
+
+    /// read the page image of a frame
+    pub fn read_frame(&self, frame: u32) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; self.page_size];
+        match self
+            .file
+            .read_exact_at(&mut data, self.frame_offset(frame) + FRAME_HEADER_SIZE)
+        {
+            Ok(_) => Ok(data),
+            Err(_) => Err(error_values(SQLExecValue::IOERR)),
+        }
+    }
+
+    /// append the pages of a commit, the commit is durable once the log is
+    /// synced. the frames overwrite any torn tail after the last commit.
+    pub fn append(&mut self, pages: &[(u32, Vec<u8>)], db_size: u32, sync: bool) -> Result<()> {
+        let mut checksum = self.checksum;
+        let mut frames = Vec::with_capacity(pages.len() * self.frame_size() as usize);
+        for (i, (pgno, data)) in pages.iter().enumerate() {
+            let commit = if i + 1 == pages.len() { db_size } else { 0 };
+            checksum = frame_checksum_of(checksum, *pgno, commit, data);
+            frames.extend_from_slice(&pgno.to_be_bytes());
+            frames.extend_from_slice(&commit.to_be_bytes());
+            frames.extend_from_slice(&checksum.to_be_bytes());
+            frames.extend_from_slice(data);
+        }
+        self.file
+            .write_all_at(&frames, self.frame_offset(self.mx_frame))?;
+        if sync {
+            self.file.sync_all()?;
+        }
+
+        for (pgno, _) in pages {
+            self.index.insert(*pgno, self.mx_frame);
+            self.mx_frame += 1;
+        }
+        self.db_size = db_size;
+        self.checksum = checksum;
+        Ok(())
+    }
+
+    /// copy the latest frame of every page into the database file fd, then
+    /// reset the log. return the number of pages copied.
+    pub fn checkpoint(&mut self, fd: &File, sync: bool) -> Result<u32> {
+        if self.mx_frame == 0 {
+            return Ok(0);
+        }
+        let mut pages = self.index.iter().collect::<Vec<_>>();
+        pages.sort();
+        for (pgno, frame) in &pages {
+            if **pgno <= self.db_size {
+                let data = self.read_frame(**frame)?;
+                fd.write_all_at(&data, (**pgno - 1) as u64 * self.page_size as u64)?;
+            }
+        }
+        fd.set_len(self.db_size as u64 * self.page_size as u64)?;
+        if sync {
+            fd.sync_all()?;
+        }
+        let copied = pages.len() as u32;
+        self.reset()?;
+        Ok(copied)
+    }
+
+    /// empty the log, and start it with a new random salt
+    fn reset(&mut self) -> Result<()> {
+        let mut salt = nonce();
+        if salt == self.salt {
+            salt = salt.wrapping_add(1);
+        }
+        let mut header = Vec::with_capacity(WAL_HEADER_SIZE as usize);
+        header.extend_from_slice(&WAL_MAGIC);
+        header.extend_from_slice(&(self.page_size as u32).to_be_bytes());
+        header.extend_from_slice(&salt.to_be_bytes());
+        self.file.set_len(0)?;
+        self.file.write_all_at(&header, 0)?;
+        self.file.sync_all()?;
+
+        self.salt = salt;
+        self.index.clear();
+        self.mx_frame = 0;
+        self.db_size = 0;
+        self.checksum = salt;
+        Ok(())
+    }
+
+    fn frame_size(&self) -> u64 {
+        FRAME_HEADER_SIZE + self.page_size as u64
+    }
+
+    fn frame_offset(&self, frame: u32) -> u64 {
+        WAL_HEADER_SIZE + frame as u64 * self.frame_size()
+    }
+}
+
+/// the checksum of a frame, chained to the checksum of the frame before it,
+/// the first frame is chained to the random salt of the log
+fn frame_checksum_of(prev: u32, pgno: u32, db_size: u32, data: &[u8]) -> u32 {
+    checksum(prev, &[&pgno.to_be_bytes(), &db_size.to_be_bytes(), data])
+}
@@ -0,0 +1,626 @@
+use std::path::Path;
+use std::time::Duration;
+
+use kvdb::client::Client;
+use kvdb::client::Credentials;
+use kvdb::client_pool::ClientPool;
+use kvdb::client_pool::PoolConfig;
+use kvdb::common::result::ResultSet;
+use kvdb::common::tls::TlsOptions;
+use kvdb::error::Error;
+use kvdb::error::Result;
+use kvdb::server::tcp_server::Server;
+use kvdb::sql::schema::data_type::DataType;
+use kvdb::sql::schema::data_value::DataValue;
+use kvdb::sql::schema::table::TableKind;
+use kvdb::storage::b_tree::Memory;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19607")
+        .await?;
+    tokio::spawn(server.server());
+
+    let client = Client::new("127.0.0.1:19607").await?;
+    client
+        .execute("CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING)")
+        .await?;
+    client
+        .execute("CREATE VIEW titles AS SELECT title FROM movies")
+        .await?;
+
+    assert_eq!(
+        client.list_tables().await?,
+        vec![
+            ("movies".into(), TableKind::Table),
+            ("titles".into(), TableKind::View)
+        ]
+    );
+    let table = client.get_table("movies").await?;
+    assert_eq!(table.name, "movies");
+    assert_eq!(
+        table
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["id", "title"]
+    );
+    assert!(matches!(
+        client.get_table("nope").await,
+        Err(Error::Value(_))
+    ));
+
+    // a second client, both are listed with no transaction
+    let other = Client::new("127.0.0.1:19607").await?;
+    let status = other.status().await?;
+    assert_eq!(status.id, "client");
+    assert_eq!((status.connections, status.connections_accepted), (2, 2));
+    assert!(status
+        .sessions
+        .iter()
+        .all(|s| s.protocol == "sql" && s.txn.is_none()));
+    assert!(status.mvcc.txns >= 2);
+    assert_eq!(status.mvcc.storage, "memory");
+
+    drop(other);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(client.status().await?.connections, 1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_prepared_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19608")
+        .await?;
+    tokio::spawn(server.server());
+
+    let client = Client::new("127.0.0.1:19608").await?;
+    client
+        .execute("CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING)")
+        .await?;
+    let (insert, parameters) = client.prepare("INSERT INTO movies VALUES (?, ?)").await?;
+    assert_eq!(
+        parameters,
+        vec![Some(DataType::Integer), Some(DataType::String)]
+    );
+    for (id, title) in [(1, "Sicario"), (2, "Stalker"), (3, "Heat")] {
+        client
+            .execute_prepared(
+                insert,
+                vec![DataValue::Integer(id), DataValue::String(title.into())],
+            )
+            .await?;
+    }
+
+    let (select, _) = client
+        .prepare("SELECT title FROM movies WHERE id >= $1")
+        .await?;
+    match client
+        .execute_prepared(select, vec![DataValue::Integer(2)])
+        .await?
+    {
+        ResultSet::Query { rows, .. } => assert_eq!(
+            rows.collect::<Result<Vec<_>>>()?,
+            vec![
+                vec![DataValue::String("Stalker".into())],
+                vec![DataValue::String("Heat".into())]
+            ]
+        ),
+        r => panic!("query result error: {}", r),
+    }
+
+    client.deallocate(select).await?;
+    assert!(matches!(
+        client
+            .execute_prepared(select, vec![DataValue::Integer(2)])
+            .await,
+        Err(Error::Value(_))
+    ));
+    assert!(client.deallocate(select).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_cursor_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19609")
+        .await?;
+    tokio::spawn(server.server());
+
+    let client = Client::new("127.0.0.1:19609").await?;
+    client
+        .execute("CREATE TABLE numbers (n INTEGER PRIMARY KEY)")
+        .await?;
+    let values = (1..=250)
+        .map(|n| format!("({})", n))
+        .collect::<Vec<_>>()
+        .join(", ");
+    client
+        .execute(&format!("INSERT INTO numbers VALUES {}", values))
+        .await?;
+
+    // the rows are fetched ten at a time
+    let mut cursor = client
+        .query("SELECT n FROM numbers WHERE n > 5")
+        .await?
+        .fetch_size(10);
+    assert_eq!(cursor.columns()[0].name, Some("n".into()));
+    let mut n = 5;
+    while let Some(row) = cursor.next().await {
+        n += 1;
+        assert_eq!(row?, vec![DataValue::Integer(n)]);
+    }
+    assert_eq!(n, 250);
+
+    // a cursor dropped or closed early, the client goes on
+    let mut cursor = client.query("SELECT n FROM numbers").await?.fetch_size(7);
+    for _ in 0..12 {
+        cursor.next().await.unwrap()?;
+    }
+    drop(cursor);
+    let (select, _) = client
+        .prepare("SELECT n FROM numbers WHERE n <= $1")
+        .await?;
+    let cursor = client
+        .query_prepared(select, vec![DataValue::Integer(100)])
+        .await?;
+    cursor.close().await?;
+    match client
+        .execute_prepared(select, vec![DataValue::Integer(3)])
+        .await?
+    {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 3),
+        r => panic!("query result error: {}", r),
+    }
+
+    // an error ends the rows
+    let mut cursor = client
+        .query("SELECT n, n * 4611686018427387904 FROM numbers")
+        .await?
+        .fetch_size(5);
+    assert!(cursor.next().await.unwrap().is_ok());
+    assert!(cursor.next().await.unwrap().is_err());
+    assert!(cursor.next().await.is_none());
+    assert!(client.query("DELETE FROM numbers").await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_pool_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19610")
+        .await?;
+    tokio::spawn(server.server());
+
+    let config = PoolConfig {
+        min_connections: 2,
+        max_connections: 4,
+        acquire_timeout: Duration::from_millis(100),
+        idle_timeout: Duration::from_millis(200),
+        health_check_interval: Duration::from_millis(50),
+        ..PoolConfig::default()
+    };
+    let pool = ClientPool::new("127.0.0.1:19610", config).await?;
+    assert_eq!(pool.open_connections()?, 2);
+    pool.execute("CREATE TABLE numbers (n INTEGER PRIMARY KEY)")
+        .await?;
+
+    // concurrent statements share at most four connections
+    let tasks = (1..=50)
+        .map(|n| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                pool.execute(&format!("INSERT INTO numbers VALUES ({})", n))
+                    .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await??;
+    }
+    assert!(pool.open_connections()? <= 4);
+    match pool.execute("SELECT n FROM numbers").await? {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 50),
+        r => panic!("query result error: {}", r),
+    }
+
+    // every connection checked out, the next checkout times out
+    let mut clients = Vec::new();
+    for _ in 0..4 {
+        clients.push(pool.get().await?);
+    }
+    assert_eq!(clients[0].status().await?.connections, 4);
+    assert!(pool.get().await.is_err());
+    drop(clients);
+    assert_eq!(pool.idle_connections()?, 4);
+
+    // a cursor keeps its client checked out after the client is dropped, the
+    // other borrowers do not share its connection
+    let client = pool.get().await?;
+    let mut cursor = client.query("SELECT n FROM numbers").await?.fetch_size(10);
+    drop(client);
+    assert_eq!(pool.idle_connections()?, 3);
+    let other = pool.get().await?;
+    other.execute("INSERT INTO numbers VALUES (51)").await?;
+    let mut count = 0;
+    while let Some(row) = cursor.next().await {
+        row?;
+        count += 1;
+    }
+    assert_eq!(count, 50);
+    other.execute("DELETE FROM numbers WHERE n = 51").await?;
+    drop((cursor, other));
+    assert_eq!(pool.idle_connections()?, 4);
+
+    // the idle connections above the minimum are closed
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(pool.open_connections()?, 2);
+    assert_eq!(pool.get().await?.status().await?.connections, 2);
+
+    // a pool larger than its maximum is refused
+    let config = PoolConfig {
+        min_connections: 5,
+        max_connections: 4,
+        ..PoolConfig::default()
+    };
+    assert!(matches!(
+        ClientPool::new("127.0.0.1:19610", config).await,
+        Err(Error::Config(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_authentication_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19611")
+        .await?
+        .authenticate("root", "secret")
+        .await?;
+    tokio::spawn(server.server());
+    let credentials = |user: &str, password: &str| Credentials {
+        user: user.into(),
+        password: password.into(),
+    };
+
+    // a client must authenticate with the password of a known user
+    let client = Client::new("127.0.0.1:19611").await?;
+    assert!(matches!(
+        client.execute("SELECT 1").await,
+        Err(Error::Unauthorized(_))
+    ));
+    for (user, password) in [("root", "wrong"), ("nobody", "secret")] {
+        assert!(matches!(
+            Client::with_credentials("127.0.0.1:19611", &credentials(user, password)).await,
+            Err(Error::Unauthorized(_))
+        ));
+    }
+
+    let root = Client::with_credentials("127.0.0.1:19611", &credentials("root", "secret")).await?;
+    root.execute("CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING)")
+        .await?;
+    root.execute("INSERT INTO movies VALUES (1, 'Sicario')")
+        .await?;
+    root.execute("CREATE USER alice PASSWORD 'wonderland'")
+        .await?;
+    root.execute("GRANT SELECT ON movies TO alice").await?;
+    root.execute("CREATE TABLE secret (id INTEGER PRIMARY KEY)")
+        .await?;
+
+    // the schemas are described to the users holding a privilege on them,
+    // the status to the superusers
+    let alice =
+        Client::with_credentials("127.0.0.1:19611", &credentials("alice", "wonderland")).await?;
+    let tables = alice.list_tables().await?;
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].0, "movies");
+    assert_eq!(root.list_tables().await?.len(), 2);
+    assert_eq!(alice.get_table("movies").await?.name, "movies");
+    assert!(root.get_table("secret").await.is_ok());
+    assert!(matches!(
+        alice.get_table("secret").await,
+        Err(Error::Unauthorized(_))
+    ));
+    assert!(root.status().await.is_ok());
+    assert!(matches!(alice.status().await, Err(Error::Unauthorized(_))));
+
+    // the pooled connections authenticate too
+    let config = PoolConfig {
+        credentials: Some(credentials("alice", "wonderland")),
+        ..PoolConfig::default()
+    };
+    let pool = ClientPool::new("127.0.0.1:19611", config).await?;
+    match pool.execute("SELECT title FROM movies").await? {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 1),
+        r => panic!("query result error: {}", r),
+    }
+    assert!(matches!(
+        pool.execute("DELETE FROM movies").await,
+        Err(Error::Unauthorized(_))
+    ));
+
+    // the MySQL protocol has no authentication
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19612")
+        .await?
+        .listen_mysql("127.0.0.1:19613")
+        .await?
+        .authenticate("root", "secret")
+        .await?;
+    assert!(matches!(server.server().await, Err(Error::Config(_))));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_tls_test() -> Result<()> {
+    let dir = std::env::temp_dir().join("kvdb_client_tls_test");
+    std::fs::create_dir_all(&dir)?;
+    let ca = certificate(&dir, "ca", vec![], None)?;
+    certificate(&dir, "server", vec!["localhost".into()], Some(&ca))?;
+    certificate(&dir, "client", vec!["alice".into()], Some(&ca))?;
+    certificate(&dir, "other_ca", vec![], None)?;
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19614")
+        .await?
+        .tls(
+            &path("server.pem"),
+            &path("server.key"),
+            Some(&path("ca.pem")),
+        )?;
+    tokio::spawn(server.server());
+    let tls = TlsOptions {
+        ca_file: path("ca.pem"),
+        server_name: "localhost".into(),
+        identity: Some((path("client.pem"), path("client.key"))),
+    };
+
+    // a client verifies the server, and presents its certificate
+    let client = Client::connect("127.0.0.1:19614", Some(&tls), None).await?;
+    client
+        .execute("CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING)")
+        .await?;
+    client
+        .execute("INSERT INTO movies VALUES (1, 'Sicario')")
+        .await?;
+    let config = PoolConfig {
+        tls: Some(tls.clone()),
+        ..PoolConfig::default()
+    };
+    let pool = ClientPool::new("127.0.0.1:19614", config).await?;
+    match pool.execute("SELECT title FROM movies").await? {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 1),
+        r => panic!("query result error: {}", r),
+    }
+
+    // the plaintext clients, the clients without a certificate, and the
+    // servers of another name or authority are refused
+    async fn refused(tls: Option<&TlsOptions>) -> bool {
+        match Client::connect("127.0.0.1:19614", tls, None).await {
+            Ok(client) => client.execute("SELECT 1").await.is_err(),
+            Err(_) => true,
+        }
+    }
+    assert!(refused(None).await);
+    let anonymous = TlsOptions {
+        identity: None,
+        ..tls.clone()
+    };
+    assert!(refused(Some(&anonymous)).await);
+    let other_name = TlsOptions {
+        server_name: "example.com".into(),
+        ..tls.clone()
+    };
+    assert!(refused(Some(&other_name)).await);
+    let other_authority = TlsOptions {
+        ca_file: path("other_ca.pem"),
+        ..tls.clone()
+    };
+    assert!(refused(Some(&other_authority)).await);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_tls_test() -> Result<()> {
+    let dir = std::env::temp_dir().join("kvdb_server_tls_test");
+    std::fs::create_dir_all(&dir)?;
+    let ca = certificate(&dir, "ca", vec![], None)?;
+    certificate(&dir, "server", vec!["localhost".into()], Some(&ca))?;
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+
+    // the other protocols are encrypted with the same certificate
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19615")
+        .await?
+        .listen_pg("127.0.0.1:19616")
+        .await?
+        .listen_mysql("127.0.0.1:19622")
+        .await?
+        .listen_http("127.0.0.1:19623")
+        .await?
+        .tls(&path("server.pem"), &path("server.key"), None)?;
+    tokio::spawn(server.server());
+    let tls = TlsOptions {
+        ca_file: path("ca.pem"),
+        server_name: "localhost".into(),
+        identity: None,
+    };
+    let (connector, server_name) = tls.connector()?;
+
+    // PostgreSQL asks for TLS with an SSLRequest before the startup
+    let startup = [&196608i32.to_be_bytes()[..], b"user\0test\0\0"].concat();
+    let startup = [&(startup.len() as i32 + 4).to_be_bytes()[..], &startup].concat();
+    let mut socket = TcpStream::connect("127.0.0.1:19616").await?;
+    socket.write_all(&[0, 0, 0, 8, 4, 210, 22, 47]).await?;
+    assert_eq!(socket.read_u8().await?, b'S');
+    let mut conn = connector.connect(server_name.clone(), socket).await?;
+    conn.write_all(&startup).await?;
+    assert_eq!(pg_read_until_ready(&mut conn).await?.last(), Some(&b'Z'));
+    let query = b"SELECT 1\0";
+    conn.write_all(&[&[b'Q'][..], &(query.len() as i32 + 4).to_be_bytes(), query].concat())
+        .await?;
+    assert_eq!(pg_read_until_ready(&mut conn).await?, b"TDCZ");
+    let mut conn = TcpStream::connect("127.0.0.1:19616").await?;
+    conn.write_all(&startup).await?;
+    assert_eq!(conn.read_u8().await?, b'E');
+
+    // MySQL asks for TLS with an SSLRequest before the handshake response:
+    // protocol 4.1, secure connection, plugin auth and SSL
+    let mut request = 0x0008_8a00u32.to_le_bytes().to_vec();
+    request.extend((1u32 << 24).to_le_bytes());
+    request.push(33);
+    request.extend([0; 23]);
+    let mut response = request.clone();
+    response.extend(b"root\0\0");
+    let mut socket = TcpStream::connect("127.0.0.1:19622").await?;
+    mysql_read(&mut socket).await?;
+    mysql_write(&mut socket, 1, &request).await?;
+    let mut conn = connector.connect(server_name.clone(), socket).await?;
+    mysql_write(&mut conn, 2, &response).await?;
+    assert_eq!(mysql_read(&mut conn).await?[0], 0x00);
+    mysql_write(&mut conn, 0, b"\x03SELECT 1").await?;
+    assert_eq!(mysql_read(&mut conn).await?, [1]);
+    let mut conn = TcpStream::connect("127.0.0.1:19622").await?;
+    mysql_read(&mut conn).await?;
+    mysql_write(&mut conn, 1, &response).await?;
+    assert_eq!(mysql_read(&mut conn).await?[0], 0xff);
+
+    // HTTP is served as HTTPS
+    let body = r#"{"sql": "SELECT 1"}"#;
+    let socket = TcpStream::connect("127.0.0.1:19623").await?;
+    let mut conn = connector.connect(server_name, socket).await?;
+    conn.write_all(
+        format!(
+            "POST /query HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    )
+    .await?;
+    let mut response = String::new();
+    conn.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    Ok(())
+}
+
+/// the tags of the PostgreSQL messages up to ReadyForQuery
+async fn pg_read_until_ready<S: AsyncRead + Unpin>(conn: &mut S) -> Result<Vec<u8>> {
+    let mut tags = Vec::new();
+    loop {
+        let tag = conn.read_u8().await?;
+        let len = conn.read_i32().await? as usize;
+        conn.read_exact(&mut vec![0; len - 4]).await?;
+        tags.push(tag);
+        if tag == b'Z' {
+            return Ok(tags);
+        }
+    }
+}
+
+/// the payload of a MySQL packet
+async fn mysql_read<S: AsyncRead + Unpin>(conn: &mut S) -> Result<Vec<u8>> {
+    let mut header = [0; 4];
+    conn.read_exact(&mut header).await?;
+    let mut payload = vec![0; u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize];
+    conn.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// sends a MySQL packet
+async fn mysql_write<S: AsyncWrite + Unpin>(conn: &mut S, seq: u8, payload: &[u8]) -> Result<()> {
+    let header = (payload.len() as u32 | (seq as u32) << 24).to_le_bytes();
+    conn.write_all(&[&header[..], payload].concat()).await?;
+    Ok(())
+}
+
+/// write a certificate and its key as name.pem and name.key, a certificate
+/// authority if it is not signed by another
+fn certificate(
+    dir: &Path,
+    name: &str,
+    names: Vec<String>,
+    ca: Option<&rcgen::Certificate>,
+) -> Result<rcgen::Certificate> {
+    let err = |e: rcgen::RcgenError| Error::Internal(e.to_string());
+    let mut params = rcgen::CertificateParams::new(names);
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, name);
+    if ca.is_none() {
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    }
+    let cert = rcgen::Certificate::from_params(params).map_err(err)?;
+    let pem = match ca {
+        Some(ca) => cert.serialize_pem_with_signer(ca).map_err(err)?,
+        None => cert.serialize_pem().map_err(err)?,
+    };
+    std::fs::write(dir.join(format!("{}.pem", name)), pem)?;
+    std::fs::write(
+        dir.join(format!("{}.key", name)),
+        cert.serialize_private_key_pem(),
+    )?;
+    Ok(cert)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_cancel_test() -> Result<()> {
+    let server = Server::new("client", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19617")
+        .await?
+        .authenticate("root", "secret")
+        .await?;
+    tokio::spawn(server.server());
+    let credentials = Credentials {
+        user: "root".into(),
+        password: "secret".into(),
+    };
+    let client = Client::with_credentials("127.0.0.1:19617", &credentials).await?;
+    let endless = "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT n FROM t";
+
+    // a statement is cancelled from another connection, which needs no
+    // authentication but the key of the session
+    let token = client.cancel_token().await?;
+    let running = {
+        let client = client.clone();
+        tokio::spawn(async move { client.execute(endless).await })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    token.cancel().await?;
+    assert!(matches!(running.await?, Err(Error::Cancelled(_))));
+    assert_eq!(
+        client.execute("SELECT 1").await?.into_value()?,
+        DataValue::Integer(1)
+    );
+
+    // a statement running longer than the timeout of its session is
+    // interrupted
+    client.execute("SET statement_timeout = '100ms'").await?;
+    assert!(matches!(
+        client.execute(endless).await,
+        Err(Error::Cancelled(_))
+    ));
+    client.execute("SET statement_timeout = 0").await?;
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ use kvdb::storage::sqlite::page::PAGE_SIZE;
 
 #[test]
 fn disk_data_test() -> Result<()> {
-    let mut disk_data = DiskData::new();
+    let mut disk_data = DiskData::new(PAGE_SIZE);
     let write_data = [19u8; 10];
     let write_len = disk_data.write(&write_data, 10)?;
     assert_eq!(write_len, write_data.len());
@@ -26,7 +26,7 @@ fn disk_data_test() -> Result<()> {
 
 #[test]
 fn disk_data_page1_test() -> Result<()> {
-    let disk_data_lock = Arc::new(RwLock::new(DiskData::new()));
+    let disk_data_lock = Arc::new(RwLock::new(DiskData::new(PAGE_SIZE)));
     let mut disk_data = disk_data_lock.write()?;
     if let Some(page1) = disk_data.to_page1_mut()? {
         page1.set_free_list(23);
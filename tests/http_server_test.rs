@@ -0,0 +1,276 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use kvdb::error::Result;
+use kvdb::server::tcp_server::Server;
+use kvdb::storage::b_tree::Memory;
+use serde_json::json;
+use serde_json::Value;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+const ADDR: &str = "127.0.0.1:19606";
+
+/// sends a request on a new connection, returns the status and the body
+async fn request(method: &str, path: &str, accept: &str, body: &str) -> Result<(u16, String)> {
+    let mut conn = TcpStream::connect(ADDR).await?;
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nAccept: {}\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        accept,
+        body.len(),
+        body
+    );
+    conn.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    conn.read_to_string(&mut response).await?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head[9..12].parse().unwrap();
+    if !head.to_lowercase().contains("transfer-encoding: chunked") {
+        return Ok((status, body.to_string()));
+    }
+    // the chunks of a streamed body
+    let mut chunks = String::new();
+    let mut rest = body;
+    loop {
+        let (size, data) = rest.split_once("\r\n").unwrap();
+        let size = usize::from_str_radix(size, 16).unwrap();
+        if size == 0 {
+            return Ok((status, chunks));
+        }
+        chunks.push_str(&data[..size]);
+        rest = &data[size + 2..];
+    }
+}
+
+async fn json(method: &str, path: &str, body: Value) -> Result<(u16, Value)> {
+    let (status, body) = request(method, path, "application/json", &body.to_string()).await?;
+    Ok((status, serde_json::from_str(&body).unwrap()))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http_api_test() -> Result<()> {
+    let server = Server::new("http", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19605")
+        .await?
+        .listen_http(ADDR)
+        .await?;
+    tokio::spawn(server.server());
+
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({ "sql": "CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING, rating FLOAT NULL DEFAULT NULL)" }),
+    )
+    .await?;
+    assert_eq!(
+        (status, body),
+        (200, json!({ "CreateTable": { "name": "movies" } }))
+    );
+
+    // the parameters are bound in order, a quote in a string is escaped
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({
+            "sql": "INSERT INTO movies VALUES ($1, $2, $3), (2, 'Stalker', 8.1), (3, $4, NULL)",
+            "params": [1, "Sicario's", 7.6, "Heat"],
+        }),
+    )
+    .await?;
+    assert_eq!((status, body), (200, json!({ "Create": { "count": 3 } })));
+
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({ "sql": "SELECT id, title, rating FROM movies WHERE id < $1", "params": [3] }),
+    )
+    .await?;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body["Query"]["rows"],
+        json!([[1, "Sicario's", 7.6], [2, "Stalker", 8.1]])
+    );
+    assert_eq!(body["Query"]["columns"][1], json!({ "name": "title" }));
+
+    // a parameter is a value, never SQL, and an integer given for a float
+    // column is a float
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({
+            "sql": "INSERT INTO movies VALUES ($1, $2, $3)",
+            "params": [4, "x'); DROP TABLE movies; --", 8],
+        }),
+    )
+    .await?;
+    assert_eq!((status, body), (200, json!({ "Create": { "count": 1 } })));
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({ "sql": "SELECT title, rating FROM movies WHERE id = $1", "params": [4] }),
+    )
+    .await?;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body["Query"]["rows"],
+        json!([["x'); DROP TABLE movies; --", 8.0]])
+    );
+    json(
+        "POST",
+        "/query",
+        json!({ "sql": "DELETE FROM movies WHERE id = $1", "params": [4] }),
+    )
+    .await?;
+
+    // the rows streamed one per line after the result set
+    let (status, body) = request(
+        "POST",
+        "/query",
+        "application/x-ndjson",
+        &json!({ "sql": "SELECT title, rating FROM movies" }).to_string(),
+    )
+    .await?;
+    assert_eq!(status, 200);
+    let lines = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect::<Vec<Value>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0]["Query"]["columns"].is_array());
+    assert_eq!(
+        lines[1..],
+        [
+            json!(["Sicario's", 7.6]),
+            json!(["Stalker", 8.1]),
+            json!(["Heat", null])
+        ]
+    );
+
+    // the schema
+    let (status, body) = json("GET", "/tables", json!(null)).await?;
+    assert_eq!(status, 200);
+    assert_eq!(body, json!([{ "name": "movies", "kind": "table" }]));
+    let (status, body) = json("GET", "/tables/movies", json!(null)).await?;
+    assert_eq!(status, 200);
+    assert_eq!(body["name"], "movies");
+    assert_eq!(body["columns"].as_array().unwrap().len(), 3);
+    let (status, _) = json("GET", "/tables/nope", json!(null)).await?;
+    assert_eq!(status, 404);
+
+    let (status, body) = json("GET", "/status", json!(null)).await?;
+    assert_eq!(status, 200);
+    assert_eq!(body["id"], "http");
+    assert!(body["mvcc"]["txns"].as_u64().unwrap() > 0);
+    assert_eq!(body["sessions"][0]["protocol"], "http");
+
+    // errors
+    let (status, body) = json("POST", "/query", json!({ "sql": "SELECT $1" })).await?;
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("$1"));
+    let (status, body) = json(
+        "POST",
+        "/query",
+        json!({ "sql": "SELECT $1", "params": [18446744073709551615u64] }),
+    )
+    .await?;
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("out of range"));
+    let (status, _) = json("POST", "/query", json!({ "query": "SELECT 1" })).await?;
+    assert_eq!(status, 400);
+    let (status, _) = json("GET", "/query", json!(null)).await?;
+    assert_eq!(status, 405);
+    let (status, _) = json("GET", "/nope", json!(null)).await?;
+    assert_eq!(status, 404);
+
+    // a body above the limit is refused before it is read
+    let mut conn = TcpStream::connect(ADDR).await?;
+    conn.write_all(
+        b"POST /query HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+          Content-Length: 1000000000\r\n\r\n",
+    )
+    .await?;
+    let mut response = String::new();
+    conn.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http_authentication_test() -> Result<()> {
+    let server = Server::new("http", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19620")
+        .await?
+        .listen_http("127.0.0.1:19621")
+        .await?
+        .authenticate("root", "secret")
+        .await?;
+    tokio::spawn(server.server());
+
+    // the credentials are sent with the basic authentication scheme
+    for (credentials, status) in [
+        (None, "401"),
+        (Some("root:wrong"), "401"),
+        (Some("nobody:secret"), "401"),
+        (Some("root:secret"), "200"),
+    ] {
+        let response = basic("POST", "/query", credentials, r#"{"sql": "SELECT 1"}"#).await?;
+        assert_eq!(&response[9..12], status, "{}", response);
+        if status == "401" {
+            assert!(response.contains("www-authenticate: Basic realm=\"kvdb\""));
+        }
+    }
+
+    // the schemas are described to the users holding a privilege on them,
+    // the status to the superusers
+    for sql in [
+        "CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING)",
+        "CREATE TABLE secret (id INTEGER PRIMARY KEY)",
+        "CREATE USER alice PASSWORD 'wonderland'",
+        "GRANT SELECT ON movies TO alice",
+    ] {
+        let body = json!({ "sql": sql }).to_string();
+        let response = basic("POST", "/query", Some("root:secret"), &body).await?;
+        assert_eq!(&response[9..12], "200", "{}", response);
+    }
+    for (path, root, alice) in [
+        ("/tables/movies", "200", "200"),
+        ("/tables/secret", "200", "403"),
+        ("/status", "200", "403"),
+    ] {
+        let response = basic("GET", path, Some("root:secret"), "").await?;
+        assert_eq!(&response[9..12], root, "{}", response);
+        let response = basic("GET", path, Some("alice:wonderland"), "").await?;
+        assert_eq!(&response[9..12], alice, "{}", response);
+    }
+    let response = basic("GET", "/tables", Some("alice:wonderland"), "").await?;
+    assert!(response.contains("movies") && !response.contains("secret"));
+    Ok(())
+}
+
+/// sends a request with basic credentials to the authenticating server,
+/// returns the whole response
+async fn basic(method: &str, path: &str, credentials: Option<&str>, body: &str) -> Result<String> {
+    let authorization = credentials
+        .map(|credentials| format!("Authorization: Basic {}\r\n", BASE64.encode(credentials)))
+        .unwrap_or_default();
+    let mut conn = TcpStream::connect("127.0.0.1:19621").await?;
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        authorization,
+        body.len(),
+        body
+    );
+    conn.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    conn.read_to_string(&mut response).await?;
+    Ok(response)
+}
@@ -0,0 +1,72 @@
+use kvdb::error::Error;
+use kvdb::error::Result;
+use kvdb::storage::b_tree::Memory;
+use kvdb::storage::mvcc::TransactionMode;
+use kvdb::storage::mvcc::MVCC;
+
+#[test]
+fn test_read_own_writes() -> Result<()> {
+    let mvcc = MVCC::new(Box::new(Memory::new()));
+    let mut txn = mvcc.begin()?;
+    txn.set(b"a", b"1".to_vec())?;
+    assert_eq!(txn.get(b"a")?, Some(b"1".to_vec()));
+    let scan = txn.scan_prefix(b"a")?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(scan, vec![(b"a".to_vec(), b"1".to_vec())]);
+    txn.delete(b"a")?;
+    assert_eq!(txn.get(b"a")?, None);
+    txn.commit()?;
+
+    // uncommitted writes stay invisible to other transactions
+    let mut txn = mvcc.begin()?;
+    txn.set(b"b", b"1".to_vec())?;
+    let other = mvcc.begin_with_mode(TransactionMode::ReadOnly)?;
+    assert_eq!(other.get(b"b")?, None);
+    Ok(())
+}
+
+#[test]
+fn test_write_key_twice() -> Result<()> {
+    let mvcc = MVCC::new(Box::new(Memory::new()));
+    let mut txn = mvcc.begin()?;
+    txn.set(b"a", b"1".to_vec())?;
+    txn.set(b"a", b"2".to_vec())?;
+    assert_eq!(txn.get(b"a")?, Some(b"2".to_vec()));
+    txn.commit()?;
+    assert_eq!(mvcc.begin()?.get(b"a")?, Some(b"2".to_vec()));
+
+    // a key written by a concurrent transaction is still a conflict
+    let mut first = mvcc.begin()?;
+    let mut second = mvcc.begin()?;
+    first.set(b"a", b"3".to_vec())?;
+    assert_eq!(second.set(b"a", b"4".to_vec()), Err(Error::Serialization));
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_reads() -> Result<()> {
+    let mvcc = MVCC::new(Box::new(Memory::new()));
+    let mut txn = mvcc.begin()?;
+    txn.set(b"a", b"1".to_vec())?;
+    txn.commit()?;
+
+    // b is written by a transaction concurrent with the snapshot version
+    let mut concurrent = mvcc.begin()?;
+    concurrent.set(b"b", b"1".to_vec())?;
+    let mut txn = mvcc.begin()?;
+    let version = txn.id();
+    txn.set(b"a", b"2".to_vec())?;
+    txn.commit()?;
+    concurrent.commit()?;
+
+    let mut txn = mvcc.begin()?;
+    txn.set(b"a", b"3".to_vec())?;
+    txn.commit()?;
+
+    // the snapshot sees the writes of the version itself, not the writes of
+    // transactions active at its start or begun after it
+    let snapshot = mvcc.begin_with_mode(TransactionMode::Snapshot { version })?;
+    assert_eq!(snapshot.get(b"a")?, Some(b"2".to_vec()));
+    assert_eq!(snapshot.get(b"b")?, None);
+    assert_eq!(mvcc.begin()?.get(b"a")?, Some(b"3".to_vec()));
+    Ok(())
+}
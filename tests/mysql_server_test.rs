@@ -0,0 +1,161 @@
+use kvdb::error::Result;
+use kvdb::server::tcp_server::Server;
+use kvdb::storage::b_tree::Memory;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+/// a packet of the MySQL protocol, its sequence id and payload
+type Packet = (u8, Vec<u8>);
+
+async fn write_packet(conn: &mut TcpStream, seq: u8, payload: &[u8]) -> Result<()> {
+    let mut packet = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+    packet.push(seq);
+    packet.extend_from_slice(payload);
+    conn.write_all(&packet).await?;
+    Ok(())
+}
+
+async fn read_packet(conn: &mut TcpStream) -> Result<Packet> {
+    let mut header = [0u8; 4];
+    conn.read_exact(&mut header).await?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+    let mut payload = vec![0; len];
+    conn.read_exact(&mut payload).await?;
+    Ok((header[3], payload))
+}
+
+/// sends a query and reads its reply: one OK or ERR packet, or the packets of
+/// a result set up to its second EOF
+async fn query(conn: &mut TcpStream, sql: &str) -> Result<Vec<Packet>> {
+    write_packet(conn, 0, &[&[0x03], sql.as_bytes()].concat()).await?;
+    let mut packets = vec![read_packet(conn).await?];
+    if matches!(packets[0].1[0], 0x00 | 0xff) {
+        return Ok(packets);
+    }
+    let mut eofs = 0;
+    while eofs < 2 {
+        let packet = read_packet(conn).await?;
+        match packet.1[0] {
+            0xfe if packet.1.len() < 9 => eofs += 1,
+            0xff => eofs = 2,
+            _ => {}
+        }
+        packets.push(packet);
+    }
+    Ok(packets)
+}
+
+/// the text values of a row packet
+fn row(payload: &[u8]) -> Vec<Option<String>> {
+    let mut values = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        if payload[pos] == 0xfb {
+            values.push(None);
+            pos += 1;
+            continue;
+        }
+        let len = payload[pos] as usize;
+        values.push(Some(
+            String::from_utf8(payload[pos + 1..pos + 1 + len].to_vec()).unwrap(),
+        ));
+        pos += 1 + len;
+    }
+    values
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mysql_protocol_test() -> Result<()> {
+    let server = Server::new("mysql", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19603")
+        .await?
+        .listen_mysql("127.0.0.1:19604")
+        .await?;
+    tokio::spawn(server.server());
+
+    let mut conn = TcpStream::connect("127.0.0.1:19604").await?;
+    let (seq, handshake) = read_packet(&mut conn).await?;
+    assert_eq!((seq, handshake[0]), (0, 10));
+
+    // protocol 4.1, secure connection, plugin auth and a database
+    let mut response = (0x0200u32 | 0x8000 | 0x0008_0000 | 0x0008)
+        .to_le_bytes()
+        .to_vec();
+    response.extend((1u32 << 24).to_le_bytes());
+    response.push(33);
+    response.extend([0; 23]);
+    response.extend(b"test\0");
+    response.push(0);
+    response.extend(b"kvdb\0mysql_native_password\0");
+    write_packet(&mut conn, 1, &response).await?;
+    assert_eq!(
+        read_packet(&mut conn).await?,
+        (2, vec![0, 0, 0, 2, 0, 0, 0])
+    );
+
+    // a ping and the selection of the database
+    write_packet(&mut conn, 0, &[0x0e]).await?;
+    assert_eq!(read_packet(&mut conn).await?.1[0], 0x00);
+    write_packet(&mut conn, 0, &[&[0x02], &b"kvdb"[..]].concat()).await?;
+    assert_eq!(read_packet(&mut conn).await?.1[0], 0x00);
+
+    let packets = query(
+        &mut conn,
+        "CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING, rating FLOAT)",
+    )
+    .await?;
+    assert_eq!(packets, vec![(1, vec![0, 0, 0, 2, 0, 0, 0])]);
+    let packets = query(
+        &mut conn,
+        "INSERT INTO movies VALUES (1, 'Sicario', 7.6), (2, 'Stalker', 8.1), (3, 'Heat', 8.3)",
+    )
+    .await?;
+    assert_eq!(packets, vec![(1, vec![0, 3, 0, 2, 0, 0, 0])]);
+    let packets = query(&mut conn, "UPDATE movies SET rating = 8.0 WHERE id = 1").await?;
+    assert_eq!(packets[0].1[..2], [0, 1]);
+
+    // a result set: the column count, the columns, EOF, the rows and EOF,
+    // numbered in sequence
+    let packets = query(
+        &mut conn,
+        "SELECT id, title, rating FROM movies WHERE id > 1",
+    )
+    .await?;
+    assert_eq!(
+        packets.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(),
+        (1..=8).collect::<Vec<_>>()
+    );
+    assert_eq!(packets[0].1, vec![3]);
+    // the type of the columns: longlong, var string and double
+    assert_eq!(packets[1].1[packets[1].1.len() - 6], 0x08);
+    assert_eq!(packets[2].1[packets[2].1.len() - 6], 0xfd);
+    assert_eq!(packets[3].1[packets[3].1.len() - 6], 0x05);
+    assert_eq!(packets[4].1[0], 0xfe);
+    assert_eq!(
+        packets[5..7]
+            .iter()
+            .map(|(_, p)| row(p))
+            .collect::<Vec<_>>(),
+        vec![
+            vec![Some("2".into()), Some("Stalker".into()), Some("8.1".into())],
+            vec![Some("3".into()), Some("Heat".into()), Some("8.3".into())],
+        ]
+    );
+    assert_eq!(packets[7].1[0], 0xfe);
+
+    // errors, the session goes on
+    let packets = query(&mut conn, "SELEC 1").await?;
+    assert_eq!(packets[0].1[..4], [0xff, 0x51, 0x04, b'#']);
+    assert_eq!(&packets[0].1[4..9], b"HY000");
+    let packets = query(&mut conn, "SELECT nope FROM movies").await?;
+    assert_eq!(packets[0].1[0], 0xff);
+    write_packet(&mut conn, 0, &[0x11]).await?;
+    assert_eq!(read_packet(&mut conn).await?.1[0], 0xff);
+    let packets = query(&mut conn, "DELETE FROM movies").await?;
+    assert_eq!(packets[0].1[..2], [0, 3]);
+
+    write_packet(&mut conn, 0, &[0x01]).await?;
+    Ok(())
+}
@@ -1,14 +1,30 @@
-use core::slice;
-use std::mem::size_of;
-use std::ptr::addr_of;
+use std::fs;
+use std::fs::OpenOptions;
+use std::os::unix::prelude::FileExt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
-use bincode::serialize;
 use kvdb::common;
+use kvdb::common::options::JournalMode;
 use kvdb::common::options::PagerOption;
 use kvdb::error::Result;
+use kvdb::storage::encryption::EncryptionKey;
+use kvdb::storage::encryption::ENCRYPTION_OVERHEAD;
+use kvdb::storage::sqlite::page::FileHeader;
+use kvdb::storage::sqlite::page::FreelistHead;
+use kvdb::storage::sqlite::page::FreelistTrunk;
+use kvdb::storage::sqlite::page::LockLevel;
 use kvdb::storage::sqlite::page::Pager;
+use kvdb::storage::sqlite::page::BUSY_TIMEOUT;
+use kvdb::storage::sqlite::page::CHECKSUM_SIZE;
+use kvdb::storage::sqlite::page::FEATURE_CHECKSUMS;
+use kvdb::storage::sqlite::page::FEATURE_ENCRYPTED;
+use kvdb::storage::sqlite::page::FREELIST_OFFSET;
+use kvdb::storage::sqlite::page::PAGE_SIZE;
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Eq)]
@@ -24,6 +40,12 @@ fn page_test() -> Result<()> {
         max_page: 10,
         n_extra: 0,
         read_only: false,
+        journal_mode: JournalMode::Rollback,
+        page_size: PAGE_SIZE,
+        checksums: false,
+        busy_timeout: BUSY_TIMEOUT,
+        mmap_size: 0,
+        encryption: None,
     };
     let pager_arc = Arc::new(Mutex::new(Pager::open(pager_option)?));
     {
@@ -135,3 +157,760 @@ fn pager_serialize_test() -> Result<()> {
 
     Ok(())
 }
+
+/// the steps of `Pager::commit` a crash can interrupt
+#[derive(Debug, Clone, Copy)]
+enum Crash {
+    /// the journal is written, the database file is untouched
+    BeforeWrite,
+    /// some dirty pages are written to the database file
+    DuringWrite,
+    /// all dirty pages are written and synced, the journal still exists
+    BeforeJournalDelete,
+    /// the journal is deleted, the commit is done
+    AfterJournalDelete,
+}
+
+/// an empty database directory for a test
+fn database_dir(name: &str) -> Result<&'static str> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    Ok(Box::leak(
+        dir.to_string_lossy().into_owned().into_boxed_str(),
+    ))
+}
+
+fn open(path: &'static str) -> Result<Arc<Mutex<Pager>>> {
+    open_in(path, JournalMode::Rollback, PAGE_SIZE)
+}
+
+fn open_in(
+    path: &'static str,
+    journal_mode: JournalMode,
+    page_size: usize,
+) -> Result<Arc<Mutex<Pager>>> {
+    let option = PagerOption {
+        path: Some(path),
+        max_page: 10,
+        n_extra: 0,
+        read_only: false,
+        journal_mode,
+        page_size,
+        checksums: false,
+        busy_timeout: BUSY_TIMEOUT,
+        mmap_size: 0,
+        encryption: None,
+    };
+    Ok(Arc::new(Mutex::new(Pager::open(option)?)))
+}
+
+fn write_page(pager: &Arc<Mutex<Pager>>, pgno: u32, data: &str) -> Result<()> {
+    let pghdr = pager.lock()?.get_page(pgno, Arc::clone(pager))?;
+    pghdr.lock()?.write(data.as_bytes(), 0)?;
+    pager.lock()?.unref(&pghdr)
+}
+
+fn read_page(pager: &Arc<Mutex<Pager>>, pgno: u32) -> Result<Vec<u8>> {
+    let pghdr = pager.lock()?.get_page(pgno, Arc::clone(pager))?;
+    let data = pghdr.lock()?.get_data();
+    pager.lock()?.unref(&pghdr)?;
+    Ok(data)
+}
+
+fn page(data: &str) -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    page[..data.len()].copy_from_slice(data.as_bytes());
+    page
+}
+
+/// commits three pages, then changes two of them and adds a fourth one, and
+/// crashes at the given step of the commit of the changes
+fn crash(path: &'static str, step: Crash) -> Result<()> {
+    let pager = open(path)?;
+    write_page(&pager, 1, "one")?;
+    write_page(&pager, 2, "two")?;
+    write_page(&pager, 3, "three")?;
+    pager.lock()?.commit()?;
+
+    write_page(&pager, 1, "ONE")?;
+    write_page(&pager, 3, "THREE")?;
+    write_page(&pager, 4, "FOUR")?;
+
+    let dir = PathBuf::from(path);
+    let journal = dir.join("kvdb.journal");
+    assert!(journal.exists());
+    match step {
+        Crash::BeforeWrite => {}
+        Crash::DuringWrite => {
+            let db = OpenOptions::new().write(true).open(dir.join("kvdb.db"))?;
+            db.write_all_at(&page("ONE"), 0)?;
+            db.write_all_at(&page("FOUR"), 3 * PAGE_SIZE as u64)?;
+        }
+        Crash::BeforeJournalDelete => {
+            let saved = fs::read(&journal)?;
+            pager.lock()?.commit()?;
+            fs::write(&journal, saved)?;
+        }
+        Crash::AfterJournalDelete => pager.lock()?.commit()?,
+    }
+    // the pager is never used again, like after a crash
+    pager.lock()?.close()?;
+    Ok(())
+}
+
+#[test]
+fn pager_recovery_test() -> Result<()> {
+    for step in [
+        Crash::BeforeWrite,
+        Crash::DuringWrite,
+        Crash::BeforeJournalDelete,
+        Crash::AfterJournalDelete,
+    ] {
+        let path = database_dir(&format!("recovery_{:?}", step))?;
+        crash(path, step)?;
+
+        let pager = open(path)?;
+        assert!(
+            !PathBuf::from(path).join("kvdb.journal").exists(),
+            "{:?}",
+            step
+        );
+        let (pages, expect) = match step {
+            Crash::AfterJournalDelete => (4, ["ONE", "two", "THREE", "FOUR"]),
+            _ => (3, ["one", "two", "three", ""]),
+        };
+        let len = fs::metadata(PathBuf::from(path).join("kvdb.db"))?.len();
+        assert_eq!(len, pages * PAGE_SIZE as u64, "{:?}", step);
+        for (i, data) in expect.iter().enumerate().take(pages as usize) {
+            assert_eq!(read_page(&pager, i as u32 + 1)?, page(data), "{:?}", step);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn pager_torn_journal_test() -> Result<()> {
+    // the record of page 3 was being written when the crash happened, so
+    // page 3 of the database file is unchanged and must not be replayed
+    let path = database_dir("torn_record")?;
+    crash(path, Crash::BeforeWrite)?;
+    let journal = PathBuf::from(path).join("kvdb.journal");
+    let mut data = fs::read(&journal)?;
+    let len = data.len();
+    data[len - 10] ^= 0xff;
+    fs::write(&journal, data)?;
+
+    let pager = open(path)?;
+    assert_eq!(read_page(&pager, 1)?, page("one"));
+    assert_eq!(read_page(&pager, 3)?, page("three"));
+
+    // a partial record at the end of the journal is ignored
+    let path = database_dir("torn_tail")?;
+    crash(path, Crash::DuringWrite)?;
+    let journal = PathBuf::from(path).join("kvdb.journal");
+    let mut data = fs::read(&journal)?;
+    data.extend_from_slice(&[0xab; 100]);
+    fs::write(&journal, data)?;
+
+    let pager = open(path)?;
+    assert_eq!(read_page(&pager, 1)?, page("one"));
+    let len = fs::metadata(PathBuf::from(path).join("kvdb.db"))?.len();
+    assert_eq!(len, 3 * PAGE_SIZE as u64);
+
+    // a journal without a valid header was never used, it is deleted
+    let path = database_dir("torn_header")?;
+    crash(path, Crash::BeforeWrite)?;
+    let journal = PathBuf::from(path).join("kvdb.journal");
+    fs::write(&journal, b"kvdb")?;
+
+    let pager = open(path)?;
+    assert!(!journal.exists());
+    assert_eq!(read_page(&pager, 3)?, page("three"));
+    Ok(())
+}
+
+#[test]
+fn pager_wal_test() -> Result<()> {
+    let path = database_dir("wal")?;
+    let dir = PathBuf::from(path);
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    write_page(&pager, 1, "one")?;
+    write_page(&pager, 2, "two")?;
+    pager.lock()?.commit()?;
+    write_page(&pager, 2, "TWO")?;
+    write_page(&pager, 3, "three")?;
+    pager.lock()?.commit()?;
+
+    // the commits are in the log, the database file is untouched
+    assert!(!dir.join("kvdb.journal").exists());
+    assert_eq!(fs::metadata(dir.join("kvdb.db"))?.len(), 0);
+    assert!(fs::metadata(dir.join("kvdb.wal"))?.len() > 0);
+
+    // a rolled back change is never written
+    write_page(&pager, 1, "rollback")?;
+    pager.lock()?.rollback()?;
+    assert_eq!(read_page(&pager, 1)?, page("one"));
+
+    // an uncommitted change is not seen by another pager
+    write_page(&pager, 3, "uncommitted")?;
+    let reader = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    for (i, data) in ["one", "TWO", "three"].iter().enumerate() {
+        assert_eq!(read_page(&reader, i as u32 + 1)?, page(data));
+    }
+    pager.lock()?.rollback()?;
+
+    // a checkpoint copies the pages into the database file, and empties the log
+    let wal_len = fs::metadata(dir.join("kvdb.wal"))?.len();
+    assert_eq!(pager.lock()?.checkpoint()?, 3);
+    assert_eq!(
+        fs::metadata(dir.join("kvdb.db"))?.len(),
+        3 * PAGE_SIZE as u64
+    );
+    assert!(fs::metadata(dir.join("kvdb.wal"))?.len() < wal_len);
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    for (i, data) in ["one", "TWO", "three"].iter().enumerate() {
+        assert_eq!(read_page(&pager, i as u32 + 1)?, page(data));
+    }
+    Ok(())
+}
+
+#[test]
+fn pager_wal_recovery_test() -> Result<()> {
+    let path = database_dir("wal_recovery")?;
+    let dir = PathBuf::from(path);
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    write_page(&pager, 1, "one")?;
+    pager.lock()?.commit()?;
+    write_page(&pager, 1, "ONE")?;
+    write_page(&pager, 2, "two")?;
+    pager.lock()?.commit()?;
+    drop(pager);
+
+    // the last frame of the second commit is torn, so the commit is ignored
+    let wal = dir.join("kvdb.wal");
+    let mut data = fs::read(&wal)?;
+    let len = data.len();
+    data.truncate(len - 100);
+    fs::write(&wal, data)?;
+
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    assert_eq!(read_page(&pager, 1)?, page("one"));
+    assert_eq!(read_page(&pager, 2)?, page(""));
+    drop(pager);
+
+    // opened in rollback journal mode, the log is copied into the database file
+    let pager = open(path)?;
+    assert!(!wal.exists());
+    assert_eq!(fs::metadata(dir.join("kvdb.db"))?.len(), PAGE_SIZE as u64);
+    assert_eq!(read_page(&pager, 1)?, page("one"));
+    Ok(())
+}
+
+#[test]
+fn pager_page_size_test() -> Result<()> {
+    let path = database_dir("page_size")?;
+    let db = PathBuf::from(path).join("kvdb.db");
+    let pager = open_in(path, JournalMode::Rollback, 4096)?;
+    {
+        let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+        pghdr.lock()?.write(&FileHeader::new(4096)?.to_bytes(), 0)?;
+        pager.lock()?.unref(&pghdr)?;
+    }
+    write_page(&pager, 2, "two")?;
+    pager.lock()?.commit()?;
+    assert_eq!(fs::metadata(&db)?.len(), 2 * 4096);
+
+    // the page size of the file header wins over the one of the option
+    let pager = open_in(path, JournalMode::Rollback, PAGE_SIZE)?;
+    assert_eq!(pager.lock()?.page_size(), 4096);
+    let data = read_page(&pager, 2)?;
+    assert_eq!(data.len(), 4096);
+    assert_eq!(&data[..3], b"two");
+    write_page(&pager, 3, "three")?;
+    pager.lock()?.commit()?;
+    assert_eq!(fs::metadata(&db)?.len(), 3 * 4096);
+
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    assert_eq!(pager.lock()?.page_size(), 4096);
+    assert_eq!(&read_page(&pager, 3)?[..5], b"three");
+    drop(pager);
+
+    // a page size that is not a power of two is refused
+    assert!(open_in(path, JournalMode::Rollback, 1000).is_err());
+    assert!(open_in(path, JournalMode::Rollback, 256).is_err());
+
+    // a file of a newer format version, or with an unknown feature, is refused
+    let file = OpenOptions::new().read(true).write(true).open(&db)?;
+    let mut header = [0u8; 32];
+    file.read_exact_at(&mut header, 0)?;
+    let mut newer = header;
+    newer[21] += 1;
+    file.write_all_at(&newer, 0)?;
+    assert!(open(path).is_err());
+    let mut feature = header;
+    feature[27] = 0x80;
+    file.write_all_at(&feature, 0)?;
+    assert!(open(path).is_err());
+    file.write_all_at(&header, 0)?;
+    assert!(open(path).is_ok());
+    Ok(())
+}
+
+#[test]
+fn pager_checksum_test() -> Result<()> {
+    let path = database_dir("checksum")?;
+    let db = PathBuf::from(path).join("kvdb.db");
+    let open_checksums = |checksums: bool| -> Result<Arc<Mutex<Pager>>> {
+        let option = PagerOption {
+            path: Some(path),
+            max_page: 10,
+            n_extra: 0,
+            read_only: false,
+            journal_mode: JournalMode::Rollback,
+            page_size: PAGE_SIZE,
+            checksums,
+            busy_timeout: BUSY_TIMEOUT,
+            mmap_size: 0,
+            encryption: None,
+        };
+        Ok(Arc::new(Mutex::new(Pager::open(option)?)))
+    };
+
+    let pager = open_checksums(true)?;
+    let usable_size = pager.lock()?.usable_size();
+    assert_eq!(usable_size, PAGE_SIZE - CHECKSUM_SIZE);
+    let mut header = FileHeader::new(PAGE_SIZE)?;
+    header.features |= FEATURE_CHECKSUMS;
+    {
+        let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+        let mut pg = pghdr.lock()?;
+        pg.write(&header.to_bytes(), 0)?;
+        // the checksum can't be written by the user
+        assert!(pg.write(b"abcd", usable_size as u64 - 2).is_err());
+        drop(pg);
+        pager.lock()?.unref(&pghdr)?;
+    }
+    write_page(&pager, 2, "two")?;
+    write_page(&pager, 3, "three")?;
+    pager.lock()?.commit()?;
+    assert!(pager.lock()?.integrity_check()?.is_empty());
+
+    // the file header turns the checksums on
+    let file = OpenOptions::new().read(true).write(true).open(&db)?;
+    let mut byte = [0u8];
+    file.read_exact_at(&mut byte, PAGE_SIZE as u64 + 1)?;
+    file.write_all_at(&[byte[0] ^ 0x01], PAGE_SIZE as u64 + 1)?;
+    let pager = open_checksums(false)?;
+    assert!(pager.lock()?.checksums());
+    assert_eq!(
+        pager.lock()?.integrity_check()?,
+        vec!["Page 2 has a wrong checksum".to_string()]
+    );
+    assert_eq!(&read_page(&pager, 3)?[..5], b"three");
+    assert!(read_page(&pager, 2).is_err());
+
+    // a page written at the wrong place is detected too
+    let mut page3 = vec![0u8; PAGE_SIZE];
+    file.read_exact_at(&mut page3, 2 * PAGE_SIZE as u64)?;
+    file.write_all_at(&page3, PAGE_SIZE as u64)?;
+    let pager = open_checksums(true)?;
+    assert_eq!(
+        pager.lock()?.integrity_check()?,
+        vec!["Page 2 has a wrong checksum".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn pager_freelist_check_test() -> Result<()> {
+    let path = database_dir("freelist_check")?;
+    let db = PathBuf::from(path).join("kvdb.db");
+    let pager = open(path)?;
+    let usable_size = pager.lock()?.usable_size();
+    {
+        let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+        let mut pg = pghdr.lock()?;
+        pg.write(&FileHeader::new(PAGE_SIZE)?.to_bytes(), 0)?;
+        let head = FreelistHead {
+            first_trunk: 3,
+            n_free: 3,
+        };
+        pg.write(&head.to_bytes(), FREELIST_OFFSET as u64)?;
+        drop(pg);
+        pager.lock()?.unref(&pghdr)?;
+    }
+    let trunk = |next: u32, leaves: Vec<u32>| FreelistTrunk { next, leaves }.to_bytes(usable_size);
+    for pgno in 2..=6 {
+        let pghdr = pager.lock()?.get_page(pgno, Arc::clone(&pager))?;
+        match pgno {
+            3 => pghdr.lock()?.write(&trunk(0, vec![5, 6]), 0)?,
+            _ => pghdr.lock()?.write(format!("page {}", pgno).as_bytes(), 0)?,
+        }
+        pager.lock()?.unref(&pghdr)?;
+    }
+    pager.lock()?.commit()?;
+    assert!(pager.lock()?.integrity_check()?.is_empty());
+
+    // a trunk pointing back to itself is a cycle, a leaf beyond the end of
+    // the file is reported, and so is the count of page 1
+    let file = OpenOptions::new().read(true).write(true).open(&db)?;
+    file.write_all_at(&trunk(3, vec![5, 9]), 2 * PAGE_SIZE as u64)?;
+    assert_eq!(
+        pager.lock()?.integrity_check()?,
+        vec![
+            "Freelist page 9 is outside the database of 6 pages".to_string(),
+            "Page 3 is referenced twice".to_string(),
+            "Freelist holds 2 pages, page 1 counts 3".to_string(),
+        ]
+    );
+
+    // a page listed by two trunks
+    file.write_all_at(&trunk(4, vec![5]), 2 * PAGE_SIZE as u64)?;
+    file.write_all_at(&trunk(0, vec![5]), 3 * PAGE_SIZE as u64)?;
+    assert_eq!(
+        pager.lock()?.integrity_check()?,
+        vec!["Page 5 is referenced twice".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn pager_cache_test() -> Result<()> {
+    let path = database_dir("cache")?;
+    let pager = open(path)?;
+    let write = |pgno: u32, data: &str| -> Result<()> {
+        let pghdr = pager.lock()?.get_page(pgno, Arc::clone(&pager))?;
+        pghdr.lock()?.write(data.as_bytes(), 0)?;
+        pager.lock()?.unref(&pghdr)
+    };
+    let read = |pgno: u32| -> Result<Vec<u8>> {
+        let pghdr = pager.lock()?.get_page(pgno, Arc::clone(&pager))?;
+        let data = pghdr.lock()?.get_data();
+        pager.lock()?.unref(&pghdr)?;
+        Ok(data)
+    };
+
+    // twice as many dirty pages as the cache holds are spilled
+    for pgno in 1..=20 {
+        write(pgno, &format!("first {}", pgno))?;
+    }
+    let stats = pager.lock()?.stats()?;
+    assert_eq!(stats.pages, 10);
+    assert_eq!(stats.referenced, 0);
+    assert_eq!(stats.evictions, 10);
+    assert!(stats.spills >= 10);
+    pager.lock()?.commit()?;
+
+    // a page in use is never recycled
+    let pinned = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    for pgno in 2..=20 {
+        assert_eq!(read(pgno)?, page(&format!("first {}", pgno)));
+    }
+    assert_eq!(pinned.lock()?.get_data(), page("first 1"));
+    assert_eq!(pager.lock()?.stats()?.referenced, 1);
+
+    // the most recently used page stays in the cache
+    let stats = pager.lock()?.stats()?;
+    read(20)?;
+    let after = pager.lock()?.stats()?;
+    assert_eq!(after.hits, stats.hits + 1);
+    assert_eq!(after.misses, stats.misses);
+    assert_eq!(after.dirty, 0);
+
+    // once no page is in use, another connection may change the database,
+    // so the cache is dropped
+    pager.lock()?.unref(&pinned)?;
+    read(20)?;
+    assert_eq!(pager.lock()?.stats()?.misses, after.misses + 1);
+
+    // the journal restores the spilled pages on rollback
+    for pgno in 1..=20 {
+        write(pgno, &format!("second {}", pgno))?;
+    }
+    pager.lock()?.rollback()?;
+    for pgno in 1..=20 {
+        assert_eq!(read(pgno)?, page(&format!("first {}", pgno)));
+    }
+    Ok(())
+}
+
+#[test]
+fn pager_lock_test() -> Result<()> {
+    let path = database_dir("lock")?;
+    let journal = PathBuf::from(path).join("kvdb.journal");
+    let open_busy = |busy_timeout: Duration| -> Result<Arc<Mutex<Pager>>> {
+        let option = PagerOption {
+            path: Some(path),
+            max_page: 10,
+            n_extra: 0,
+            read_only: false,
+            journal_mode: JournalMode::Rollback,
+            page_size: PAGE_SIZE,
+            checksums: false,
+            busy_timeout,
+            mmap_size: 0,
+            encryption: None,
+        };
+        Ok(Arc::new(Mutex::new(Pager::open(option)?)))
+    };
+    let a = open_busy(Duration::from_millis(100))?;
+    let b = open_busy(Duration::from_millis(100))?;
+    write_page(&a, 1, "one")?;
+    a.lock()?.commit()?;
+    assert_eq!(a.lock()?.lock_level(), LockLevel::None);
+
+    // a reader keeps the writer from writing the database file
+    let pghdr = b.lock()?.get_page(1, Arc::clone(&b))?;
+    assert_eq!(b.lock()?.lock_level(), LockLevel::Shared);
+    write_page(&a, 1, "ONE")?;
+    assert_eq!(a.lock()?.lock_level(), LockLevel::Reserved);
+    assert!(a.lock()?.commit().is_err());
+    assert_eq!(pghdr.lock()?.get_data(), page("one"));
+
+    // there is one writer at a time
+    assert!(pghdr.lock()?.write(b"two", 0).is_err());
+    b.lock()?.unref(&pghdr)?;
+    assert_eq!(b.lock()?.lock_level(), LockLevel::None);
+    a.lock()?.commit()?;
+    assert_eq!(read_page(&b, 1)?, page("ONE"));
+
+    // the journal of a writer that is still alive is not played back
+    write_page(&a, 2, "two")?;
+    let c = open_busy(Duration::from_millis(100))?;
+    assert!(journal.exists());
+    assert_eq!(read_page(&c, 1)?, page("ONE"));
+
+    // the busy handler waits for the reader to finish
+    let pghdr = c.lock()?.get_page(1, Arc::clone(&c))?;
+    let reader = {
+        let c = Arc::clone(&c);
+        thread::spawn(move || -> Result<()> {
+            thread::sleep(Duration::from_millis(50));
+            c.lock()?.unref(&pghdr)
+        })
+    };
+    let writer = open(path)?;
+    let start = Instant::now();
+    a.lock()?.close()?;
+    write_page(&writer, 3, "three")?;
+    writer.lock()?.commit()?;
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    reader.join().unwrap()?;
+
+    // the journal of the closed writer was played back
+    assert!(!journal.exists());
+    assert_eq!(read_page(&b, 2)?, page(""));
+    assert_eq!(read_page(&b, 3)?, page("three"));
+    Ok(())
+}
+
+#[test]
+fn pager_truncate_test() -> Result<()> {
+    let path = database_dir("truncate")?;
+    let db = PathBuf::from(path).join("kvdb.db");
+    let pager = open(path)?;
+    for pgno in 1..=6 {
+        write_page(&pager, pgno, &format!("page {}", pgno))?;
+    }
+    pager.lock()?.commit()?;
+    assert_eq!(fs::metadata(&db)?.len(), 6 * PAGE_SIZE as u64);
+
+    // the pages cut off are restored by a rollback
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    pager.lock()?.truncate(2)?;
+    assert_eq!(read_page(&pager, 5)?, page(""));
+    pager.lock()?.rollback()?;
+    assert_eq!(fs::metadata(&db)?.len(), 6 * PAGE_SIZE as u64);
+    assert_eq!(read_page(&pager, 5)?, page("page 5"));
+    pager.lock()?.commit()?;
+
+    // the commit shrinks the file
+    pager.lock()?.truncate(3)?;
+    write_page(&pager, 2, "PAGE")?;
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+    assert_eq!(fs::metadata(&db)?.len(), 3 * PAGE_SIZE as u64);
+    assert_eq!(read_page(&pager, 2)?, page("PAGE 2"));
+    assert_eq!(read_page(&pager, 3)?, page("page 3"));
+    assert_eq!(read_page(&pager, 4)?, page(""));
+
+    // in WAL mode the file shrinks with the checkpoint
+    pager.lock()?.close()?;
+    let pager = open_in(path, JournalMode::Wal, PAGE_SIZE)?;
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    pager.lock()?.truncate(1)?;
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+    assert_eq!(fs::metadata(&db)?.len(), 3 * PAGE_SIZE as u64);
+    assert_eq!(read_page(&pager, 2)?, page(""));
+    assert_eq!(pager.lock()?.checkpoint()?, 1);
+    assert_eq!(fs::metadata(&db)?.len(), PAGE_SIZE as u64);
+    assert_eq!(read_page(&pager, 1)?, page("page 1"));
+    Ok(())
+}
+
+#[test]
+fn pager_mmap_test() -> Result<()> {
+    let path = database_dir("mmap")?;
+    let pager = Arc::new(Mutex::new(Pager::open(PagerOption {
+        path: Some(path),
+        max_page: 10,
+        n_extra: 0,
+        read_only: false,
+        journal_mode: JournalMode::Rollback,
+        page_size: PAGE_SIZE,
+        checksums: true,
+        busy_timeout: BUSY_TIMEOUT,
+        mmap_size: 4 * PAGE_SIZE as u64,
+        encryption: None,
+    })?));
+    let usable_page = |data: &str| {
+        let mut page = page(data);
+        page.truncate(PAGE_SIZE - CHECKSUM_SIZE);
+        page
+    };
+    let read = |pgno: u32| -> Result<Vec<u8>> {
+        let mut data = read_page(&pager, pgno)?;
+        data.truncate(PAGE_SIZE - CHECKSUM_SIZE);
+        Ok(data)
+    };
+    for pgno in 1..=2 {
+        write_page(&pager, pgno, &format!("page {}", pgno))?;
+    }
+    pager.lock()?.commit()?;
+
+    // the pages read are borrowed from the mapping
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    assert_eq!(read(2)?, usable_page("page 2"));
+    assert_eq!(pager.lock()?.stats()?.mapped, 2);
+
+    // a page written is copied
+    write_page(&pager, 2, "PAGE")?;
+    assert_eq!(pager.lock()?.stats()?.mapped, 1);
+    assert_eq!(read(2)?, usable_page("PAGE 2"));
+    for pgno in 3..=6 {
+        write_page(&pager, pgno, &format!("page {}", pgno))?;
+    }
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+
+    // the file grew, it is mapped again up to mmap_size
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    assert_eq!(read(2)?, usable_page("PAGE 2"));
+    assert_eq!(read(4)?, usable_page("page 4"));
+    assert_eq!(read(6)?, usable_page("page 6"));
+    assert_eq!(pager.lock()?.stats()?.mapped, 3);
+    assert_eq!(pager.lock()?.stats()?.pages, 4);
+
+    // a rollback restores the copied page from the mapping
+    write_page(&pager, 3, "PAGE")?;
+    pager.lock()?.rollback()?;
+    assert_eq!(read(3)?, usable_page("page 3"));
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+
+    // the pages of a truncated file are read again
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    pager.lock()?.truncate(3)?;
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+    assert_eq!(read(3)?, usable_page("page 3"));
+    assert_eq!(read(4)?, usable_page(""));
+    assert!(pager.lock()?.integrity_check()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn pager_encryption_test() -> Result<()> {
+    let path = database_dir("encryption")?;
+    let dir = PathBuf::from(path);
+    let key_file = dir.join("kvdb.key");
+    fs::write(
+        &key_file,
+        "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff\n",
+    )?;
+    let open_key = |journal_mode: JournalMode, key: Option<EncryptionKey>| {
+        Pager::open(PagerOption {
+            path: Some(path),
+            max_page: 10,
+            n_extra: 0,
+            read_only: false,
+            journal_mode,
+            page_size: PAGE_SIZE,
+            checksums: true,
+            busy_timeout: BUSY_TIMEOUT,
+            mmap_size: 4 * PAGE_SIZE as u64,
+            encryption: key,
+        })
+        .map(|pager| Arc::new(Mutex::new(pager)))
+    };
+    let contains = |file: &str, data: &[u8]| -> Result<bool> {
+        let file = fs::read(dir.join(file))?;
+        Ok(file.windows(data.len()).any(|w| w == data))
+    };
+    let key = EncryptionKey::KeyFile(key_file.clone());
+
+    let pager = open_key(JournalMode::Rollback, Some(key.clone()))?;
+    assert!(pager.lock()?.encrypted());
+    let header = pager.lock()?.new_file_header()?;
+    assert!(header.has_feature(FEATURE_ENCRYPTED));
+    let pghdr = pager.lock()?.get_page(1, Arc::clone(&pager))?;
+    pghdr.lock()?.write(&header.to_bytes(), 0)?;
+    pager.lock()?.unref(&pghdr)?;
+    write_page(&pager, 2, "secret page")?;
+    pager.lock()?.commit()?;
+
+    // only the file header is stored in the clear
+    assert!(!contains("kvdb.db", b"secret")?);
+    let page1 = fs::read(dir.join("kvdb.db"))?;
+    assert_eq!(FileHeader::read(&page1)?, Some(header));
+
+    // so is the journal
+    let pghdr = pager.lock()?.get_page(2, Arc::clone(&pager))?;
+    pghdr.lock()?.write(b"public", 0)?;
+    assert!(!contains("kvdb.journal", b"secret")?);
+    pager.lock()?.rollback()?;
+    assert_eq!(&pghdr.lock()?.get_data()[..11], b"secret page");
+    pager.lock()?.commit()?;
+    pager.lock()?.unref(&pghdr)?;
+    pager.lock()?.close()?;
+
+    // the key is needed to read the pages
+    assert!(open_key(JournalMode::Rollback, None).is_err());
+    let wrong = dir.join("wrong.key");
+    fs::write(&wrong, [7u8; 32])?;
+    let pager = open_key(JournalMode::Rollback, Some(EncryptionKey::KeyFile(wrong)))?;
+    assert!(read_page(&pager, 2).is_err());
+    pager.lock()?.close()?;
+
+    // the log is encrypted too
+    let pager = open_key(JournalMode::Wal, Some(key.clone()))?;
+    assert_eq!(
+        pager.lock()?.usable_size(),
+        PAGE_SIZE - CHECKSUM_SIZE - ENCRYPTION_OVERHEAD
+    );
+    write_page(&pager, 3, "secret log")?;
+    pager.lock()?.commit()?;
+    assert!(!contains("kvdb.wal", b"secret")?);
+    assert_eq!(&read_page(&pager, 3)?[..10], b"secret log");
+    assert!(pager.lock()?.integrity_check()?.is_empty());
+
+    // the key is rotated by encrypting every page again
+    let passphrase = EncryptionKey::Passphrase("correct horse battery staple".into());
+    pager.lock()?.rekey(&passphrase)?;
+    assert!(!contains("kvdb.db", b"secret")?);
+    assert!(!dir.join("kvdb.journal").exists());
+    assert_eq!(&read_page(&pager, 2)?[..11], b"secret page");
+    pager.lock()?.close()?;
+    let pager = open_key(JournalMode::Rollback, Some(passphrase))?;
+    assert_eq!(&read_page(&pager, 2)?[..11], b"secret page");
+    // an encrypted page is never mapped
+    assert_eq!(pager.lock()?.stats()?.mapped, 0);
+    assert_eq!(&read_page(&pager, 3)?[..10], b"secret log");
+    assert!(pager.lock()?.integrity_check()?.is_empty());
+    pager.lock()?.close()?;
+    let pager = open_key(JournalMode::Rollback, Some(key))?;
+    assert!(read_page(&pager, 2).is_err());
+    Ok(())
+}
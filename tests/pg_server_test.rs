@@ -0,0 +1,424 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use kvdb::common::scram;
+use kvdb::error::Result;
+use kvdb::server::tcp_server::Server;
+use kvdb::storage::b_tree::Memory;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+/// a message of the PostgreSQL protocol, its type and body
+type Message = (u8, Vec<u8>);
+
+fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut m = vec![tag];
+    m.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+    m.extend_from_slice(body);
+    m
+}
+
+fn cstr(s: &str) -> Vec<u8> {
+    [s.as_bytes(), &[0]].concat()
+}
+
+/// reads a message
+async fn read_message(conn: &mut TcpStream) -> Result<Message> {
+    let tag = conn.read_u8().await?;
+    let len = conn.read_i32().await? as usize;
+    let mut body = vec![0; len - 4];
+    conn.read_exact(&mut body).await?;
+    Ok((tag, body))
+}
+
+/// reads the messages up to ReadyForQuery
+async fn read_until_ready(conn: &mut TcpStream) -> Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    loop {
+        let message = read_message(conn).await?;
+        let ready = message.0 == b'Z';
+        messages.push(message);
+        if ready {
+            return Ok(messages);
+        }
+    }
+}
+
+/// sends the startup message of a user
+async fn startup(conn: &mut TcpStream, user: &str) -> Result<()> {
+    let startup = [
+        &196608i32.to_be_bytes()[..],
+        &cstr("user"),
+        &cstr(user),
+        &[0],
+    ]
+    .concat();
+    conn.write_all(&[&(startup.len() as i32 + 4).to_be_bytes()[..], &startup].concat())
+        .await?;
+    Ok(())
+}
+
+/// the text values of a DataRow
+fn row(body: &[u8]) -> Vec<Option<String>> {
+    let n = i16::from_be_bytes([body[0], body[1]]);
+    let mut pos = 2;
+    (0..n)
+        .map(|_| {
+            let len = i32::from_be_bytes(body[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            if len < 0 {
+                return None;
+            }
+            let value = String::from_utf8(body[pos..pos + len as usize].to_vec()).unwrap();
+            pos += len as usize;
+            Some(value)
+        })
+        .collect()
+}
+
+fn tags(messages: &[Message]) -> Vec<u8> {
+    messages.iter().map(|(tag, _)| *tag).collect()
+}
+
+fn rows(messages: &[Message]) -> Vec<Vec<Option<String>>> {
+    messages
+        .iter()
+        .filter(|(tag, _)| *tag == b'D')
+        .map(|(_, body)| row(body))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pg_protocol_test() -> Result<()> {
+    let server = Server::new("pg", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19601")
+        .await?
+        .listen_pg("127.0.0.1:19602")
+        .await?;
+    tokio::spawn(server.server());
+
+    let mut conn = TcpStream::connect("127.0.0.1:19602").await?;
+    // an SSL request is refused, the startup follows in plain text
+    conn.write_all(&[0, 0, 0, 8, 4, 210, 22, 47]).await?;
+    assert_eq!(conn.read_u8().await?, b'N');
+    startup(&mut conn, "test").await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(messages[0], (b'R', vec![0, 0, 0, 0]));
+    assert_eq!(messages.last().unwrap(), &(b'Z', vec![b'I']));
+
+    // simple queries, several statements in one
+    let query = "CREATE TABLE movies (id INTEGER PRIMARY KEY, title STRING, rating FLOAT);
+        INSERT INTO movies VALUES (1, 'Sicario', 7.6), (2, 'Stalker', 8.1), (3, 'Heat', 8.3);
+        SELECT id, title FROM movies WHERE rating > 8.0";
+    conn.write_all(&message(b'Q', &cstr(query))).await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"CCTDDCZ");
+    assert_eq!(messages[1].1, cstr("INSERT 0 3"));
+    assert_eq!(
+        rows(&messages),
+        vec![
+            vec![Some("2".into()), Some("Stalker".into())],
+            vec![Some("3".into()), Some("Heat".into())],
+        ]
+    );
+    assert_eq!(messages[5].1, cstr("SELECT 2"));
+
+    // an error ends the query, the session goes on
+    conn.write_all(&message(b'Q', &cstr("SELECT nope FROM movies; SELECT 1")))
+        .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"EZ");
+    conn.write_all(&message(b'Q', &cstr(" ; "))).await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"IZ");
+
+    // the extended protocol, the rows are fetched two at a time
+    let mut parse = cstr("s1");
+    parse.extend(cstr(
+        "SELECT title FROM movies WHERE rating > $1 OR title = $2",
+    ));
+    parse.extend([0, 2, 0, 0, 2, 189, 0, 0, 0, 0]);
+    let mut describe = vec![b'S'];
+    describe.extend(cstr("s1"));
+    let mut bind = cstr("p1");
+    bind.extend(cstr("s1"));
+    bind.extend([0, 0, 0, 2, 0, 0, 0, 3]);
+    bind.extend(b"8.0");
+    bind.extend([0, 0, 0, 7]);
+    bind.extend(b"Sicario");
+    bind.extend([0, 0]);
+    let mut execute = cstr("p1");
+    execute.extend(2i32.to_be_bytes());
+    conn.write_all(
+        &[
+            message(b'P', &parse),
+            message(b'D', &describe),
+            message(b'B', &bind),
+            message(b'E', &execute),
+            message(b'E', &execute),
+            message(b'S', &[]),
+        ]
+        .concat(),
+    )
+    .await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"1tT2DDsDCZ");
+    assert_eq!(
+        rows(&messages),
+        vec![
+            vec![Some("Sicario".into())],
+            vec![Some("Stalker".into())],
+            vec![Some("Heat".into())],
+        ]
+    );
+    assert_eq!(messages[8].1, cstr("SELECT 1"));
+
+    // Describe plans the statement without running it, an endless query
+    // is described at once and its execution is ended by the timeout
+    let mut parse = cstr("endless");
+    parse.extend(cstr(
+        "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT n FROM t",
+    ));
+    parse.extend([0, 0]);
+    let mut describe = vec![b'S'];
+    describe.extend(cstr("endless"));
+    let mut bind = cstr("");
+    bind.extend(cstr("endless"));
+    bind.extend([0, 0, 0, 0, 0, 0]);
+    let mut execute = cstr("");
+    execute.extend(0i32.to_be_bytes());
+    conn.write_all(&message(b'Q', &cstr("SET statement_timeout = 50")))
+        .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"CZ");
+    conn.write_all(
+        &[
+            message(b'P', &parse),
+            message(b'D', &describe),
+            message(b'S', &[]),
+        ]
+        .concat(),
+    )
+    .await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"1tTZ");
+    assert_eq!(&messages[2].1[..4], [0, 1, b'n', 0]);
+    conn.write_all(
+        &[
+            message(b'B', &bind),
+            message(b'E', &execute),
+            message(b'S', &[]),
+        ]
+        .concat(),
+    )
+    .await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"2EZ");
+    assert!(String::from_utf8_lossy(&messages[1].1).contains("statement timeout"));
+    conn.write_all(&message(b'Q', &cstr("SET statement_timeout = DEFAULT")))
+        .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"CZ");
+
+    // an error skips the messages up to Sync
+    let mut bind = cstr("");
+    bind.extend(cstr("missing"));
+    bind.extend([0, 0, 0, 0, 0, 0]);
+    conn.write_all(
+        &[
+            message(b'B', &bind),
+            message(b'E', &execute),
+            message(b'S', &[]),
+        ]
+        .concat(),
+    )
+    .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"EZ");
+
+    // a parameter number above the maximum is refused by Parse
+    let mut parse = cstr("s2");
+    parse.extend(cstr("SELECT title FROM movies WHERE id = $4000000000"));
+    parse.extend([0, 0]);
+    let mut describe = vec![b'S'];
+    describe.extend(cstr("s2"));
+    conn.write_all(
+        &[
+            message(b'P', &parse),
+            message(b'D', &describe),
+            message(b'S', &[]),
+        ]
+        .concat(),
+    )
+    .await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"EZ");
+
+    // the counts of Parse and Bind have to match the parameters of the query
+    let mut parse = cstr("s3");
+    parse.extend(cstr("SELECT title FROM movies WHERE id = $1"));
+    parse.extend([0, 2, 0, 0, 0, 20, 0, 0, 0, 20]);
+    conn.write_all(&[message(b'P', &parse), message(b'S', &[])].concat())
+        .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"EZ");
+    let mut bind = cstr("");
+    bind.extend(cstr("s1"));
+    bind.extend([0, 0, 0, 1, 0, 0, 0, 3]);
+    bind.extend(b"8.0");
+    bind.extend([0, 0]);
+    conn.write_all(&[message(b'B', &bind), message(b'S', &[])].concat())
+        .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"EZ");
+
+    // a count is unsigned, 65535 parameters do not fit in the message and
+    // the connection is closed
+    let mut bind = cstr("");
+    bind.extend(cstr("s1"));
+    bind.extend([0, 0, 255, 255, 0, 0, 0, 0]);
+    conn.write_all(&message(b'B', &bind)).await?;
+    assert!(conn.read_u8().await.is_err());
+    Ok(())
+}
+
+/// the start of the SCRAM-SHA-256 exchange of a client, returns the
+/// server-first-message
+async fn scram_start(conn: &mut TcpStream, user: &str, bare: &str) -> Result<String> {
+    startup(conn, user).await?;
+    assert_eq!(
+        read_message(conn).await?,
+        (
+            b'R',
+            [&10i32.to_be_bytes()[..], b"SCRAM-SHA-256\0\0"].concat()
+        )
+    );
+    let mut initial = cstr("SCRAM-SHA-256");
+    initial.extend((bare.len() as i32 + 3).to_be_bytes());
+    initial.extend(format!("n,,{}", bare).as_bytes());
+    conn.write_all(&message(b'p', &initial)).await?;
+
+    let (tag, body) = read_message(conn).await?;
+    assert_eq!((tag, &body[..4]), (b'R', &11i32.to_be_bytes()[..]));
+    Ok(String::from_utf8(body[4..].to_vec()).unwrap())
+}
+
+/// the SCRAM-SHA-256 exchange of a client, the messages following the
+/// client-final-message are returned up to ReadyForQuery or an error
+async fn scram_exchange(conn: &mut TcpStream, user: &str, password: &str) -> Result<Vec<Message>> {
+    let bare = "n=,r=rOprNGfwEbeRWgbNEkqO";
+    let server_first = scram_start(conn, user, bare).await?;
+    let attribute = |name: &str| {
+        server_first
+            .split(',')
+            .find_map(|a| a.strip_prefix(name))
+            .unwrap()
+            .to_string()
+    };
+    let (nonce, salt, iterations) = (attribute("r="), attribute("s="), attribute("i="));
+    assert!(nonce.starts_with("rOprNGfwEbeRWgbNEkqO"));
+    let salt = BASE64.decode(salt).unwrap();
+    let iterations = iterations.parse().unwrap();
+    let without_proof = format!("c=biws,r={}", nonce);
+    let auth_message = format!("{},{},{}", bare, server_first, without_proof);
+    let proof = scram::client_proof(password, &salt, iterations, auth_message.as_bytes());
+    let client_final = format!("{},p={}", without_proof, BASE64.encode(proof));
+    conn.write_all(&message(b'p', client_final.as_bytes()))
+        .await?;
+
+    let mut messages = Vec::new();
+    loop {
+        let message = read_message(conn).await?;
+        let last = matches!(message.0, b'Z' | b'E');
+        if message.0 == b'R' && message.1[..4] == 12i32.to_be_bytes() {
+            let signature = std::str::from_utf8(&message.1[4..])
+                .unwrap()
+                .strip_prefix("v=")
+                .unwrap();
+            assert!(scram::verify_server_signature(
+                password,
+                &salt,
+                iterations,
+                auth_message.as_bytes(),
+                &BASE64.decode(signature).unwrap(),
+            ));
+        }
+        messages.push(message);
+        if last {
+            return Ok(messages);
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pg_authentication_test() -> Result<()> {
+    let server = Server::new("pg", Box::new(Memory::new()))
+        .await?
+        .listen("127.0.0.1:19618")
+        .await?
+        .listen_pg("127.0.0.1:19619")
+        .await?
+        .authenticate("root", "secret")
+        .await?;
+    tokio::spawn(server.server());
+
+    // the user of the startup message authenticates with SCRAM-SHA-256
+    let mut conn = TcpStream::connect("127.0.0.1:19619").await?;
+    let messages = scram_exchange(&mut conn, "root", "secret").await?;
+    assert_eq!(messages[0].0, b'R');
+    assert_eq!(messages[1], (b'R', vec![0, 0, 0, 0]));
+    assert_eq!(messages.last().unwrap(), &(b'Z', vec![b'I']));
+    conn.write_all(&message(b'Q', &cstr("SELECT 1"))).await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"TDCZ");
+    conn.write_all(&message(
+        b'Q',
+        &cstr("CREATE TABLE secret (id INTEGER PRIMARY KEY, code STRING); CREATE USER alice PASSWORD 'wonderland'"),
+    ))
+    .await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"CCZ");
+
+    // Describe checks the privileges of the user on the tables of a query
+    let mut parse = cstr("s1");
+    parse.extend(cstr("SELECT * FROM secret"));
+    parse.extend([0, 0]);
+    let mut describe = vec![b'S'];
+    describe.extend(cstr("s1"));
+    let describe = [
+        message(b'P', &parse),
+        message(b'D', &describe),
+        message(b'S', &[]),
+    ]
+    .concat();
+    conn.write_all(&describe).await?;
+    assert_eq!(tags(&read_until_ready(&mut conn).await?), b"1tTZ");
+    let mut conn = TcpStream::connect("127.0.0.1:19619").await?;
+    let messages = scram_exchange(&mut conn, "alice", "wonderland").await?;
+    assert_eq!(messages.last().unwrap(), &(b'Z', vec![b'I']));
+    conn.write_all(&describe).await?;
+    let messages = read_until_ready(&mut conn).await?;
+    assert_eq!(tags(&messages), b"1EZ");
+    assert!(String::from_utf8_lossy(&messages[1].1).contains("no SELECT privilege"));
+
+    // a wrong password or an unknown user closes the connection
+    for (user, password) in [("root", "wrong"), ("nobody", "secret")] {
+        let mut conn = TcpStream::connect("127.0.0.1:19619").await?;
+        let messages = scram_exchange(&mut conn, user, password).await?;
+        assert_eq!(tags(&messages), b"E");
+        assert!(conn.read_u8().await.is_err());
+    }
+
+    // an unknown user is always sent the same salt, like a user
+    let mut salts = Vec::new();
+    for user in ["nobody", "nobody", "root", "root", "somebody"] {
+        let mut conn = TcpStream::connect("127.0.0.1:19619").await?;
+        let server_first = scram_start(&mut conn, user, "n=,r=abc").await?;
+        salts.push(server_first.split(',').nth(1).unwrap().to_string());
+    }
+    assert_eq!(salts[0], salts[1]);
+    assert_eq!(salts[2], salts[3]);
+    assert_ne!(salts[0], salts[4]);
+    assert_eq!(salts[0].len(), salts[2].len());
+
+    // a query is refused before the authentication
+    let mut conn = TcpStream::connect("127.0.0.1:19619").await?;
+    startup(&mut conn, "root").await?;
+    assert_eq!(read_message(&mut conn).await?.0, b'R');
+    conn.write_all(&message(b'Q', &cstr("SELECT 1"))).await?;
+    assert_eq!(read_message(&mut conn).await?.0, b'E');
+    assert!(conn.read_u8().await.is_err());
+    Ok(())
+}
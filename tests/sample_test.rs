@@ -7,7 +7,6 @@ use bincode::deserialize;
 use bincode::serialize;
 use kvdb::error::Result;
 use kvdb::storage::sqlite::btree::page::MemPage;
-use kvdb::storage::sqlite::btree::page::MemPageHdr;
 use kvdb::storage::sqlite::btree::page::PageHdr;
 use kvdb::storage::sqlite::btree::page::PageOne;
 use kvdb::storage::sqlite::page::PAGE_SIZE;
@@ -15,7 +14,6 @@ use kvdb::storage::sqlite::page::PAGE_SIZE;
 #[test]
 fn size_of_test() -> Result<()> {
     println!("size of PageOne: {}", size_of::<PageOne>());
-    println!("size of MemPage.n: {}", size_of::<MemPageHdr>());
     println!("size of MemPage: {}", size_of::<MemPage>());
     Ok(())
 }
@@ -1,13 +1,30 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+
+use bincode::serialize;
+use kvdb::common::keys::SQLKey;
 use kvdb::common::result::DataColumn;
 use kvdb::common::result::DataRow;
 use kvdb::common::result::ResultSet;
+use kvdb::common::scope::Scope;
+use kvdb::error::Error;
 use kvdb::error::Result;
+use kvdb::sql::engine::Catalog;
 use kvdb::sql::engine::KVEngine;
 use kvdb::sql::engine::SQLEngine;
+use kvdb::sql::engine::SQLTransaction;
+use kvdb::sql::plan::plan_expression::Expression;
+use kvdb::sql::schema::data_type::DataType;
 use kvdb::sql::schema::data_value::DataValue;
+use kvdb::sql::schema::table::TableKind;
 use kvdb::storage::b_tree::Memory;
+use kvdb::storage::compression::Compression;
+use kvdb::storage::mvcc::TransactionMode;
 use kvdb::storage::mvcc::MVCC;
 use kvdb::storage::Store;
+use sqlparser::ast::Expr;
+use sqlparser::ast::Ident;
 
 struct QueryTest {
     sql: &'static str,
@@ -136,6 +153,894 @@ fn query_test() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn set_operation_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+
+    let id = || DataColumn {
+        name: Some("id".into()),
+    };
+    let tests = [
+        QueryTest {
+            sql: "SELECT id FROM countries UNION SELECT 'fr'",
+            columns: vec![id()],
+            rows: vec![
+                vec![DataValue::String("fr".into())],
+                vec![DataValue::String("ru".into())],
+                vec![DataValue::String("us".into())],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id FROM countries WHERE id = 'fr' UNION ALL SELECT 'fr'",
+            columns: vec![id()],
+            rows: vec![
+                vec![DataValue::String("fr".into())],
+                vec![DataValue::String("fr".into())],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id FROM countries INTERSECT VALUES ('us'), ('de')",
+            columns: vec![id()],
+            rows: vec![vec![DataValue::String("us".into())]],
+        },
+        QueryTest {
+            sql: "SELECT id FROM countries EXCEPT SELECT id FROM countries WHERE id = 'ru'",
+            columns: vec![id()],
+            rows: vec![
+                vec![DataValue::String("fr".into())],
+                vec![DataValue::String("us".into())],
+            ],
+        },
+        QueryTest {
+            sql: "VALUES (1), (1), (2) EXCEPT ALL VALUES (1)",
+            columns: vec![DataColumn {
+                name: Some("column1".into()),
+            }],
+            rows: vec![vec![DataValue::Integer(1)], vec![DataValue::Integer(2)]],
+        },
+        QueryTest {
+            sql: "VALUES (1, 'a'), (2, 'b')",
+            columns: vec![
+                DataColumn {
+                    name: Some("column1".into()),
+                },
+                DataColumn {
+                    name: Some("column2".into()),
+                },
+            ],
+            rows: vec![
+                vec![DataValue::Integer(1), DataValue::String("a".into())],
+                vec![DataValue::Integer(2), DataValue::String("b".into())],
+            ],
+        },
+        // ORDER BY, LIMIT and OFFSET apply to the result
+        QueryTest {
+            sql: "SELECT id FROM countries UNION SELECT 'fr' ORDER BY 1 DESC LIMIT 2",
+            columns: vec![id()],
+            rows: vec![
+                vec![DataValue::String("us".into())],
+                vec![DataValue::String("ru".into())],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id FROM countries UNION ALL SELECT 'de' ORDER BY id LIMIT 2 OFFSET 1",
+            columns: vec![id()],
+            rows: vec![
+                vec![DataValue::String("fr".into())],
+                vec![DataValue::String("ru".into())],
+            ],
+        },
+        // integers matched to floats are converted to floats
+        QueryTest {
+            sql: "VALUES (2), (1) UNION VALUES (1.5), (1.0) ORDER BY column1",
+            columns: vec![DataColumn {
+                name: Some("column1".into()),
+            }],
+            rows: vec![
+                vec![DataValue::Float(1.0)],
+                vec![DataValue::Float(1.5)],
+                vec![DataValue::Float(2.0)],
+            ],
+        },
+    ];
+    query_check_test(&tests, &mut engine)?;
+
+    let session = engine.session()?;
+    for sql in [
+        "SELECT id FROM countries UNION SELECT 'fr' ORDER BY 2",
+        "SELECT id FROM countries UNION SELECT 'fr' LIMIT 1.5",
+        "SELECT id FROM countries UNION SELECT 'fr' OFFSET 1.5",
+        "WITH RECURSIVE t (n) AS (SELECT 1 UNION SELECT n + 0.5 FROM t WHERE n < 3) SELECT n FROM t",
+    ] {
+        assert!(
+            matches!(session.execute(sql), Err(Error::Value(_))),
+            "{}",
+            sql
+        );
+    }
+    assert!(session
+        .execute("SELECT * FROM countries UNION SELECT id FROM countries")
+        .is_err());
+    // the column types are checked when planning, before any row is read
+    assert!(matches!(
+        session.execute("SELECT id FROM genres UNION SELECT id FROM countries"),
+        Err(Error::Value(_))
+    ));
+    assert!(matches!(
+        session.execute("SELECT id FROM genres WHERE id < 0 EXCEPT SELECT 'a'"),
+        Err(Error::Value(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn common_table_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+    for sql in [
+        "CREATE TABLE employees (
+            id INTEGER PRIMARY KEY,
+            name STRING NOT NULL,
+            manager_id INTEGER NULL DEFAULT NULL REFERENCES employees
+        )",
+        "INSERT INTO employees VALUES (1, 'Alice', NULL)",
+        "INSERT INTO employees VALUES (2, 'Bob', 1)",
+        "INSERT INTO employees VALUES (3, 'Carol', 2)",
+        "INSERT INTO employees VALUES (4, 'Dave', 1)",
+    ] {
+        session.execute(sql)?;
+    }
+
+    let tests = [
+        QueryTest {
+            sql: "WITH f AS (SELECT id, name FROM countries WHERE id = 'fr') SELECT name FROM f",
+            columns: vec![DataColumn {
+                name: Some("name".into()),
+            }],
+            rows: vec![vec![DataValue::String("France".into())]],
+        },
+        QueryTest {
+            sql: "WITH a(x) AS (VALUES (1), (2)), b AS (SELECT x FROM a WHERE x > 1) \
+                  SELECT b.x FROM b",
+            columns: vec![DataColumn {
+                name: Some("x".into()),
+            }],
+            rows: vec![vec![DataValue::Integer(2)]],
+        },
+        QueryTest {
+            sql: "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 4) \
+                  SELECT n FROM t",
+            columns: vec![DataColumn {
+                name: Some("n".into()),
+            }],
+            rows: vec![
+                vec![DataValue::Integer(1)],
+                vec![DataValue::Integer(2)],
+                vec![DataValue::Integer(3)],
+                vec![DataValue::Integer(4)],
+            ],
+        },
+        QueryTest {
+            sql: "WITH RECURSIVE chain(id, name) AS ( \
+                    SELECT id, name FROM employees WHERE id = 3 \
+                    UNION \
+                    SELECT e.id, e.name FROM employees e \
+                    JOIN employees c ON c.manager_id = e.id \
+                    JOIN chain ON chain.id = c.id \
+                  ) SELECT name FROM chain",
+            columns: vec![DataColumn {
+                name: Some("name".into()),
+            }],
+            rows: vec![
+                vec![DataValue::String("Carol".into())],
+                vec![DataValue::String("Bob".into())],
+                vec![DataValue::String("Alice".into())],
+            ],
+        },
+        QueryTest {
+            sql: "WITH RECURSIVE reports(id, depth) AS ( \
+                    SELECT id, 0 FROM employees WHERE manager_id IS NULL \
+                    UNION ALL \
+                    SELECT e.id, r.depth + 1 FROM employees e, reports r WHERE e.manager_id = r.id \
+                  ) SELECT id, depth FROM reports",
+            columns: vec![
+                DataColumn {
+                    name: Some("id".into()),
+                },
+                DataColumn {
+                    name: Some("depth".into()),
+                },
+            ],
+            rows: vec![
+                vec![DataValue::Integer(1), DataValue::Integer(0)],
+                vec![DataValue::Integer(2), DataValue::Integer(1)],
+                vec![DataValue::Integer(4), DataValue::Integer(1)],
+                vec![DataValue::Integer(3), DataValue::Integer(2)],
+            ],
+        },
+    ];
+    query_check_test(&tests, &mut engine)?;
+
+    assert!(session
+        .execute("WITH a AS (SELECT 1), a AS (SELECT 2) SELECT * FROM a")
+        .is_err());
+    assert!(session
+        .execute("WITH a(x, y) AS (SELECT 1) SELECT * FROM a")
+        .is_err());
+
+    // a cyclic hierarchy, UNION stops at the rows already seen and UNION ALL
+    // is stopped by max_recursive_rows
+    session.execute("CREATE TABLE cycle (id INTEGER PRIMARY KEY, next INTEGER)")?;
+    session.execute("INSERT INTO cycle VALUES (1, 2), (2, 3), (3, 1)")?;
+    let walk = |union: &str| {
+        format!(
+            "WITH RECURSIVE walk(id) AS ( \
+               SELECT 1 {} SELECT c.next FROM cycle c, walk w WHERE c.id = w.id \
+             ) SELECT id FROM walk",
+            union
+        )
+    };
+    match session.execute(&walk("UNION"))? {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 3),
+        r => panic!("query result error: {}", r),
+    }
+    assert_eq!(
+        session.execute("SET max_recursive_rows = 100")?,
+        ResultSet::SetVariable {
+            name: "max_recursive_rows".into(),
+            value: DataValue::Integer(100),
+        }
+    );
+    match session.execute(&walk("UNION ALL")) {
+        Err(Error::Value(e)) => assert!(e.contains("max_recursive_rows of 100"), "{}", e),
+        r => panic!("query result error: {:?}", r.map(|r| r.to_string())),
+    }
+    session.execute("SET max_recursive_rows = DEFAULT")?;
+    assert_eq!(
+        session.max_recursive_rows.load(Ordering::Relaxed),
+        1_000_000
+    );
+    assert!(session.execute("SET max_recursive_rows = -1").is_err());
+    Ok(())
+}
+
+#[test]
+fn window_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+    session.execute(
+        "CREATE TABLE scores (id INTEGER PRIMARY KEY, team STRING NOT NULL, points INTEGER)",
+    )?;
+    session.execute(
+        "INSERT INTO scores VALUES (1, 'a', 10), (2, 'b', 30), (3, 'a', 30), \
+         (4, 'a', 20), (5, 'b', 30), (6, 'a', 20)",
+    )?;
+
+    let column = |name: &str| DataColumn {
+        name: Some(name.into()),
+    };
+    let int = |values: &[i64]| values.iter().map(|v| DataValue::Integer(*v)).collect();
+    let tests = [
+        QueryTest {
+            sql: "SELECT id, \
+                  ROW_NUMBER() OVER (PARTITION BY team ORDER BY points DESC), \
+                  RANK() OVER (PARTITION BY team ORDER BY points DESC), \
+                  DENSE_RANK() OVER (PARTITION BY team ORDER BY points DESC) \
+                  FROM scores",
+            columns: vec![
+                column("id"),
+                column("row_number"),
+                column("rank"),
+                column("dense_rank"),
+            ],
+            rows: vec![
+                int(&[1, 4, 4, 3]),
+                int(&[2, 1, 1, 1]),
+                int(&[3, 1, 1, 1]),
+                int(&[4, 2, 2, 2]),
+                int(&[5, 2, 1, 1]),
+                int(&[6, 3, 2, 2]),
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id, LAG(points) OVER (ORDER BY id), \
+                  LEAD(points, 2, 0) OVER (ORDER BY id) FROM scores",
+            columns: vec![column("id"), column("lag"), column("lead")],
+            rows: vec![
+                vec![
+                    DataValue::Integer(1),
+                    DataValue::Null,
+                    DataValue::Integer(30),
+                ],
+                int(&[2, 10, 20]),
+                int(&[3, 30, 30]),
+                int(&[4, 30, 20]),
+                int(&[5, 20, 0]),
+                int(&[6, 30, 0]),
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id, SUM(points) OVER (PARTITION BY team ORDER BY id) AS total, \
+                  COUNT(*) OVER (PARTITION BY team) FROM scores",
+            columns: vec![column("id"), column("total"), column("count")],
+            rows: vec![
+                int(&[1, 10, 4]),
+                int(&[2, 30, 2]),
+                int(&[3, 40, 4]),
+                int(&[4, 60, 4]),
+                int(&[5, 60, 2]),
+                int(&[6, 80, 4]),
+            ],
+        },
+        QueryTest {
+            sql: "SELECT id, \
+                  SUM(points) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING), \
+                  MIN(points) OVER (ORDER BY id ROWS BETWEEN 2 FOLLOWING AND UNBOUNDED FOLLOWING), \
+                  SUM(points) OVER (ORDER BY points) \
+                  FROM scores",
+            columns: vec![column("id"), column("sum"), column("min"), column("sum")],
+            rows: vec![
+                int(&[1, 40, 20, 10]),
+                int(&[2, 70, 20, 140]),
+                int(&[3, 80, 20, 140]),
+                vec![
+                    DataValue::Integer(4),
+                    DataValue::Integer(80),
+                    DataValue::Integer(20),
+                    DataValue::Integer(50),
+                ],
+                vec![
+                    DataValue::Integer(5),
+                    DataValue::Integer(70),
+                    DataValue::Null,
+                    DataValue::Integer(140),
+                ],
+                vec![
+                    DataValue::Integer(6),
+                    DataValue::Integer(50),
+                    DataValue::Null,
+                    DataValue::Integer(50),
+                ],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT team, AVG(points) OVER (PARTITION BY team), \
+                  FIRST_VALUE(id) OVER (PARTITION BY team ORDER BY points) \
+                  FROM scores WHERE id < 3",
+            columns: vec![column("team"), column("avg"), column("first_value")],
+            rows: vec![
+                vec![
+                    DataValue::String("a".into()),
+                    DataValue::Float(10.0),
+                    DataValue::Integer(1),
+                ],
+                vec![
+                    DataValue::String("b".into()),
+                    DataValue::Float(30.0),
+                    DataValue::Integer(2),
+                ],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT *, ROW_NUMBER() OVER (ORDER BY id) + 1 AS n FROM scores WHERE team = 'b'",
+            columns: vec![column("id"), column("team"), column("points"), column("n")],
+            rows: vec![
+                vec![
+                    DataValue::Integer(2),
+                    DataValue::String("b".into()),
+                    DataValue::Integer(30),
+                    DataValue::Integer(2),
+                ],
+                vec![
+                    DataValue::Integer(5),
+                    DataValue::String("b".into()),
+                    DataValue::Integer(30),
+                    DataValue::Integer(3),
+                ],
+            ],
+        },
+    ];
+    query_check_test(&tests, &mut engine)?;
+
+    for sql in [
+        "SELECT id FROM scores WHERE ROW_NUMBER() OVER () > 1",
+        "SELECT NTILE(2) OVER () FROM scores",
+        "SELECT RANK(id) OVER () FROM scores",
+        "SELECT SUM(points) OVER (ORDER BY id GROUPS 1 PRECEDING) FROM scores",
+    ] {
+        assert!(session.execute(sql).is_err(), "{}", sql);
+    }
+    Ok(())
+}
+
+#[test]
+fn view_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let mut session = engine.session()?;
+
+    assert_eq!(
+        session.execute(
+            "CREATE VIEW big_genres (gid, gname) AS SELECT id, name FROM genres WHERE id > 1"
+        )?,
+        ResultSet::CreateView {
+            name: "big_genres".into()
+        }
+    );
+    session
+        .execute("CREATE MATERIALIZED VIEW genre_names AS SELECT name FROM genres WHERE id < 3")?;
+    session.execute("INSERT INTO genres VALUES (4, 'Drama')")?;
+    session.execute("DELETE FROM genres WHERE id = 2")?;
+
+    let column = |name: &str| DataColumn {
+        name: Some(name.into()),
+    };
+    let string = |s: &str| vec![DataValue::String(s.into())];
+    let tests = [
+        QueryTest {
+            sql: "SELECT gname FROM big_genres",
+            columns: vec![column("gname")],
+            rows: vec![string("Comedy"), string("Drama")],
+        },
+        QueryTest {
+            sql: "SELECT v.gid, g.name FROM big_genres v JOIN genres g ON v.gid = g.id WHERE v.gid = 3",
+            columns: vec![column("gid"), column("name")],
+            rows: vec![vec![DataValue::Integer(3), DataValue::String("Comedy".into())]],
+        },
+        // the rows of a materialized view are only changed by a refresh
+        QueryTest {
+            sql: "SELECT * FROM genre_names",
+            columns: vec![column("name")],
+            rows: vec![string("Science Fiction"), string("Action")],
+        },
+    ];
+    query_check_test(&tests, &mut engine)?;
+
+    assert_eq!(
+        session.execute("REFRESH MATERIALIZED VIEW genre_names")?,
+        ResultSet::RefreshView {
+            name: "genre_names".into(),
+            count: 1
+        }
+    );
+    query_check_test(
+        &[QueryTest {
+            sql: "SELECT name FROM genre_names",
+            columns: vec![column("name")],
+            rows: vec![string("Science Fiction")],
+        }],
+        &mut engine,
+    )?;
+
+    let tables = session.with_txn(TransactionMode::ReadOnly, |txn| {
+        Ok(txn
+            .scan_table()?
+            .map(|t| (t.name, t.kind))
+            .collect::<Vec<_>>())
+    })?;
+    assert_eq!(
+        tables,
+        vec![
+            ("big_genres".into(), TableKind::View),
+            ("countries".into(), TableKind::Table),
+            ("genre_names".into(), TableKind::MaterializedView),
+            ("genres".into(), TableKind::Table),
+        ]
+    );
+
+    for sql in [
+        "INSERT INTO genre_names VALUES ('Horror')",
+        "DELETE FROM genre_names",
+        "DROP TABLE genre_names",
+        "DROP VIEW genre_names",
+        "DROP MATERIALIZED VIEW big_genres",
+        "REFRESH MATERIALIZED VIEW big_genres",
+        "CREATE VIEW genres AS SELECT 1",
+        "CREATE TABLE big_genres (id INTEGER PRIMARY KEY)",
+        "CREATE VIEW pair (a, b) AS SELECT id FROM genres",
+        "CREATE VIEW missing AS SELECT id FROM nothing",
+        "DROP VIEW big_genres, nothing",
+    ] {
+        assert!(session.execute(sql).is_err(), "{}", sql);
+    }
+
+    // a table or a view can not be dropped while a view reads it
+    session.execute("CREATE VIEW genre_list AS SELECT gname FROM big_genres")?;
+    for sql in ["DROP TABLE genres", "DROP VIEW big_genres"] {
+        assert!(
+            matches!(session.execute(sql), Err(Error::Value(_))),
+            "{}",
+            sql
+        );
+    }
+    session.execute("DROP VIEW genre_list")?;
+    session.execute("DROP VIEW big_genres")?;
+    session.execute("DROP MATERIALIZED VIEW genre_names")?;
+    assert_eq!(
+        session.execute("DROP VIEW IF EXISTS big_genres")?,
+        ResultSet::DropView { names: vec![] }
+    );
+    assert!(session.execute("SELECT * FROM big_genres").is_err());
+    assert!(session.execute("SELECT * FROM genre_names").is_err());
+    session.execute("DROP TABLE genres")?;
+    Ok(())
+}
+
+#[test]
+fn constraint_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+
+    for sql in [
+        "CREATE TABLE stock (
+            genre_id INTEGER REFERENCES genres ON DELETE CASCADE,
+            country_id STRING,
+            amount INTEGER NULL DEFAULT 0 CHECK (amount >= 0),
+            label STRING,
+            PRIMARY KEY (genre_id, country_id),
+            UNIQUE (country_id, label),
+            FOREIGN KEY (country_id) REFERENCES countries (id),
+            CHECK (label <> country_id)
+        )",
+        "CREATE TABLE orders (
+            id INTEGER PRIMARY KEY,
+            genre_id INTEGER NULL DEFAULT NULL,
+            country_id STRING NULL DEFAULT NULL,
+            FOREIGN KEY (genre_id, country_id) REFERENCES stock (genre_id, country_id)
+                ON DELETE SET NULL
+        )",
+        "INSERT INTO stock VALUES (1, 'fr', 10, 'a'), (1, 'us', 5, 'b'), (2, 'fr', 0, 'c')",
+        "INSERT INTO orders VALUES (1, 1, 'fr'), (2, 2, 'fr'), (3, NULL, NULL)",
+    ] {
+        session.execute(sql)?;
+    }
+
+    for sql in [
+        // composite primary key
+        "INSERT INTO stock VALUES (1, 'fr', 1, 'd')",
+        // composite unique key
+        "INSERT INTO stock VALUES (3, 'fr', 1, 'a')",
+        // CHECK constraints of a column and of the table
+        "INSERT INTO stock VALUES (3, 'fr', -1, 'd')",
+        "UPDATE stock SET label = 'us' WHERE country_id = 'us'",
+        // foreign keys
+        "INSERT INTO stock VALUES (9, 'fr', 1, 'd')",
+        "INSERT INTO stock VALUES (3, 'de', 1, 'd')",
+        "INSERT INTO orders VALUES (4, 2, 'us')",
+        // ON DELETE RESTRICT is the default
+        "DELETE FROM countries WHERE id = 'us'",
+        "UPDATE genres SET id = 5 WHERE id = 1",
+        "CREATE TABLE bad (id INTEGER PRIMARY KEY, r STRING REFERENCES stock)",
+        "CREATE TABLE bad (id INTEGER PRIMARY KEY, r INTEGER REFERENCES genres ON DELETE SET NULL)",
+        "CREATE TABLE bad (id INTEGER PRIMARY KEY, PRIMARY KEY (id))",
+    ] {
+        assert!(session.execute(sql).is_err(), "{}", sql);
+    }
+    // a CHECK evaluating to NULL passes
+    session.execute("INSERT INTO stock VALUES (3, 'ru', NULL, 'd')")?;
+
+    // the stock of genre 1 is deleted, and the orders of it are kept
+    session.execute("DELETE FROM genres WHERE id = 1")?;
+    let column = |name: &str| DataColumn {
+        name: Some(name.into()),
+    };
+    let tests = [
+        QueryTest {
+            sql: "SELECT genre_id, country_id FROM stock",
+            columns: vec![column("genre_id"), column("country_id")],
+            rows: vec![
+                vec![DataValue::Integer(2), DataValue::String("fr".into())],
+                vec![DataValue::Integer(3), DataValue::String("ru".into())],
+            ],
+        },
+        QueryTest {
+            sql: "SELECT * FROM orders",
+            columns: vec![column("id"), column("genre_id"), column("country_id")],
+            rows: vec![
+                vec![DataValue::Integer(1), DataValue::Null, DataValue::Null],
+                vec![
+                    DataValue::Integer(2),
+                    DataValue::Integer(2),
+                    DataValue::String("fr".into()),
+                ],
+                vec![DataValue::Integer(3), DataValue::Null, DataValue::Null],
+            ],
+        },
+    ];
+    query_check_test(&tests, &mut engine)?;
+    Ok(())
+}
+
+#[test]
+fn compression_test() -> Result<()> {
+    let engine = get_engine();
+    // the plot is the primary key, so the row and its index entry are long
+    let plot = "A hacker learns about the true nature of reality. ".repeat(4);
+    for codec in ["lz4", "zstd", "none"] {
+        let table = format!("movies_{}", codec);
+        let mut session = engine.session()?;
+        session.execute(&format!(
+            "CREATE TABLE {} (plot STRING PRIMARY KEY, year INTEGER) WITH (compression = '{}')",
+            table, codec
+        ))?;
+        session.execute(&format!(
+            "INSERT INTO {} VALUES ('{}', 1999), ('short', 2000)",
+            table, plot
+        ))?;
+        let compression = session.with_txn(TransactionMode::ReadOnly, |txn| {
+            Ok(txn.must_read_table(&table)?.compression)
+        })?;
+        assert_eq!(compression, Compression::from_name(codec)?);
+
+        // the long row is stored compressed, the short one as it is
+        let id = [DataValue::String(plot.clone())];
+        let key = SQLKey::Row(table.as_str().into(), Some(id[..].into()));
+        let stored = engine.mvcc.begin()?.get(&key.encode())?.unwrap();
+        assert_eq!(stored.len() < plot.len(), codec != "none", "{}", codec);
+
+        let sql = format!("SELECT year FROM {} WHERE plot = '{}'", table, plot);
+        match session.execute(&sql)? {
+            ResultSet::Query { rows, .. } => assert_eq!(
+                rows.collect::<Result<Vec<_>>>()?,
+                vec![vec![DataValue::Integer(1999)]]
+            ),
+            r => panic!("query result error: {}", r),
+        }
+        assert_eq!(
+            session.with_txn(TransactionMode::ReadOnly, |txn| {
+                txn.read_index(&table, "plot", &DataValue::String(plot.clone()))
+            })?,
+            HashSet::from([id.to_vec()])
+        );
+    }
+
+    let session = engine.session()?;
+    assert!(session
+        .execute("CREATE TABLE a (id INTEGER PRIMARY KEY) WITH (compression = 'gzip')")
+        .is_err());
+    assert!(session
+        .execute("CREATE TABLE b (id INTEGER PRIMARY KEY) WITH (fillfactor = 70)")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn integrity_check_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let check = |rows: &[&str]| QueryTest {
+        sql: "PRAGMA integrity_check",
+        columns: vec![DataColumn {
+            name: Some("integrity_check".into()),
+        }],
+        rows: rows
+            .iter()
+            .map(|r| vec![DataValue::String(r.to_string())])
+            .collect(),
+    };
+    query_check_test(&[check(&["ok"])], &mut engine)?;
+
+    // an index entry without its row, and a row without its index entry
+    let mut txn = engine.mvcc.begin()?;
+    let orphan = HashSet::from([vec![DataValue::Integer(9)]]);
+    let value = DataValue::Integer(9);
+    let key = SQLKey::Index("genres".into(), "id".into(), Some(Cow::Borrowed(&value)));
+    txn.set(
+        &key.encode(),
+        Compression::None.compress(&serialize(&orphan)?),
+    )?;
+    let id = [DataValue::Integer(7)];
+    let row = vec![DataValue::Integer(7), DataValue::String("Drama".into())];
+    txn.set(
+        &SQLKey::Row("genres".into(), Some(id[..].into())).encode(),
+        Compression::None.compress(&serialize(&row)?),
+    )?;
+    txn.commit()?;
+    query_check_test(
+        &[check(&[
+            "Index genres.id references the missing row 9",
+            "Row 7 of table genres is missing from index genres.id",
+        ])],
+        &mut engine,
+    )?;
+
+    assert!(engine.session()?.execute("PRAGMA foo").is_err());
+    Ok(())
+}
+
+#[test]
+fn prepared_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+    let rows = |result: ResultSet| match result {
+        ResultSet::Query { rows, .. } => rows.collect::<Result<Vec<_>>>(),
+        r => panic!("query result error: {}", r),
+    };
+
+    // the values of the parameters take the type of their column
+    let mut insert = session.prepare("INSERT INTO genres (name, id) VALUES ($2, ?)")?;
+    assert_eq!(
+        insert.parameters(),
+        &[Some(DataType::Integer), Some(DataType::String)]
+    );
+    for (id, name) in [(4, "Drama"), (5, "Western")] {
+        session.execute_prepared(
+            &mut insert,
+            &[DataValue::Integer(id), DataValue::String(name.into())],
+        )?;
+    }
+    assert!(session
+        .execute_prepared(
+            &mut insert,
+            &[DataValue::String("6".into()), DataValue::Null]
+        )
+        .is_err());
+    assert!(session
+        .execute_prepared(&mut insert, &[DataValue::Integer(6)])
+        .is_err());
+
+    // a value is never read as SQL
+    let mut select = session.prepare("SELECT id FROM genres WHERE name = $1 OR id > $2")?;
+    assert_eq!(select.parameters(), &[None, None]);
+    let params = [
+        DataValue::String("x' OR 'a' = 'a".into()),
+        DataValue::Integer(3),
+    ];
+    assert_eq!(
+        rows(session.execute_prepared(&mut select, &params)?)?,
+        vec![vec![DataValue::Integer(4)], vec![DataValue::Integer(5)]]
+    );
+    let params = [DataValue::String("Action".into()), DataValue::Integer(4)];
+    assert_eq!(
+        rows(session.execute_prepared(&mut select, &params)?)?,
+        vec![vec![DataValue::Integer(2)], vec![DataValue::Integer(5)]]
+    );
+
+    // the plan is built again when the table changes
+    session.execute("DROP TABLE genres")?;
+    session.execute("CREATE TABLE genres (name STRING PRIMARY KEY, id INTEGER)")?;
+    session.execute("INSERT INTO genres VALUES ('Drama', 9)")?;
+    let params = [DataValue::String("Drama".into()), DataValue::Integer(100)];
+    assert_eq!(
+        rows(session.execute_prepared(&mut select, &params)?)?,
+        vec![vec![DataValue::Integer(9)]]
+    );
+
+    // a parameter without a value
+    assert!(rows(session.execute("SELECT * FROM genres WHERE id = $1")?).is_err());
+    for sql in ["SELECT $0", "SELECT $00"] {
+        assert!(matches!(session.prepare(sql), Err(Error::Value(_))));
+        assert!(matches!(session.execute(sql), Err(Error::Value(_))));
+    }
+
+    // the parameter numbers are capped, they size the parameters of a plan
+    for sql in [
+        "SELECT id FROM genres WHERE id = $18446744073709551615",
+        "SELECT id FROM genres WHERE id = $4000000000000",
+        "SELECT id FROM genres WHERE id = $4000000000",
+        "SELECT id FROM genres WHERE id = $65536",
+    ] {
+        assert!(matches!(session.prepare(sql), Err(Error::Value(_))));
+        assert!(matches!(session.execute(sql), Err(Error::Value(_))));
+    }
+    // the same for a placeholder identifier which did not come from the
+    // parser
+    for name in ["$0", "$4000000000000", "$x"] {
+        assert!(matches!(
+            Expression::from_expr(&Expr::Identifier(Ident::new(name)), &mut Scope::new()),
+            Err(Error::Value(_))
+        ));
+    }
+    let select = session.prepare("SELECT id FROM genres WHERE id = $65535")?;
+    assert_eq!(select.parameters().len(), 65535);
+    Ok(())
+}
+
+#[test]
+fn privileges_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+    session.execute("CREATE USER root PASSWORD 'secret' SUPERUSER")?;
+    session.execute("CREATE USER alice WITH PASSWORD 'wonderland'")?;
+    assert!(session
+        .execute("CREATE USER alice PASSWORD 'again'")
+        .is_err());
+
+    let mut root = engine.session()?;
+    root.user = Some("root".into());
+    let mut alice = engine.session()?;
+    alice.user = Some("alice".into());
+    let denied = |result: Result<ResultSet>| matches!(result, Err(Error::Unauthorized(_)));
+    let count = |result: Result<ResultSet>| -> Result<usize> {
+        match result? {
+            ResultSet::Query { rows, .. } => Ok(rows.count()),
+            r => panic!("query result error: {}", r),
+        }
+    };
+
+    // a new user holds no privilege
+    assert!(denied(alice.execute("SELECT * FROM genres")));
+    assert!(denied(alice.execute("DROP TABLE genres")));
+    assert!(denied(alice.execute("CREATE USER bob PASSWORD 'bob'")));
+    assert!(denied(alice.execute("PRAGMA integrity_check")));
+
+    // privileges on a table
+    root.execute("GRANT SELECT, INSERT ON genres TO alice")?;
+    assert_eq!(count(alice.execute("SELECT * FROM genres"))?, 3);
+    alice.execute("INSERT INTO genres VALUES (4, 'Drama')")?;
+    assert!(denied(alice.execute("DELETE FROM genres WHERE id = 4")));
+    assert!(denied(alice.execute("UPDATE genres SET name = 'x'")));
+    assert!(denied(alice.execute("SELECT * FROM countries")));
+    assert!(denied(alice.execute(
+        "SELECT * FROM genres JOIN countries ON genres.name = countries.name"
+    )));
+
+    // privileges on every table
+    root.execute("GRANT ALL PRIVILEGES ON * TO alice")?;
+    alice.execute("DELETE FROM countries WHERE id = 'fr'")?;
+    alice.execute("CREATE TABLE movies (id INTEGER PRIMARY KEY)")?;
+    assert!(denied(alice.execute("DROP USER root")));
+    root.execute("REVOKE ALL ON * FROM alice")?;
+    assert_eq!(count(alice.execute("SELECT * FROM genres"))?, 4);
+    assert!(denied(alice.execute("SELECT * FROM movies")));
+    assert_eq!(
+        root.execute("REVOKE SELECT ON TABLE genres FROM alice")?,
+        ResultSet::Revoke {
+            user: "alice".into()
+        }
+    );
+    assert!(denied(alice.execute("SELECT * FROM genres")));
+    // UPDATE and DELETE read their rows under their own privilege
+    root.execute("GRANT UPDATE, DELETE ON genres TO alice")?;
+    assert_eq!(
+        alice.execute("UPDATE genres SET name = 'Noir' WHERE id = 4")?,
+        ResultSet::Update { count: 1 }
+    );
+    assert_eq!(
+        alice.execute("DELETE FROM genres WHERE id = 4")?,
+        ResultSet::Delete { count: 1 }
+    );
+    assert!(denied(alice.execute("SELECT * FROM genres")));
+    assert!(root.execute("GRANT SELECT ON nope TO alice").is_err());
+    assert!(root.execute("GRANT DROP ON genres TO alice").is_err());
+
+    // the password is kept salted
+    let user = engine
+        .begin(TransactionMode::ReadOnly)?
+        .must_read_user("alice")?;
+    assert!(!user.superuser);
+    assert_ne!(user.stored_key, b"wonderland".to_vec());
+
+    assert!(root.execute("DROP USER root").is_err());
+    root.execute("DROP USER alice")?;
+    assert!(alice.execute("SELECT * FROM genres").is_err());
+    assert_eq!(
+        root.execute("DROP USER IF EXISTS alice")?,
+        ResultSet::DropUser { names: vec![] }
+    );
+    Ok(())
+}
+
+fn query_check_test(tests: &[QueryTest], engine: &mut KVEngine) -> Result<()> {
+    for test in tests {
+        let session = engine.session()?;
+        match session.execute(test.sql)? {
+            ResultSet::Query { columns, rows } => {
+                assert_eq!(columns, test.columns, "{}", test.sql);
+                assert_eq!(rows.collect::<Result<Vec<_>>>()?, test.rows, "{}", test.sql);
+            }
+            r => panic!("query result error: {}", r),
+        }
+    }
+    Ok(())
+}
+
 struct UpdateTest {
     update_sql: &'static str,
     update_count: u64,
@@ -302,3 +1207,63 @@ fn init_db(engine: &mut KVEngine) -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn interrupt_test() -> Result<()> {
+    let mut engine = get_engine();
+    init_db(&mut engine)?;
+    let session = engine.session()?;
+    let cancelled = |result: Result<ResultSet>| matches!(result, Err(Error::Cancelled(_)));
+    let endless = "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t) SELECT n FROM t";
+
+    // a statement running longer than the timeout is interrupted
+    assert_eq!(
+        session.execute("SET statement_timeout = 50")?,
+        ResultSet::SetVariable {
+            name: "statement_timeout".into(),
+            value: DataValue::Integer(50),
+        }
+    );
+    assert!(cancelled(session.execute(endless)));
+    session.execute("SET statement_timeout TO '2s'")?;
+    assert_eq!(session.statement_timeout.load(Ordering::Relaxed), 2000);
+    session.execute("SET statement_timeout = DEFAULT")?;
+    assert_eq!(session.statement_timeout.load(Ordering::Relaxed), 0);
+    assert!(session.execute("SET statement_timeout = '5 days'").is_err());
+    assert!(session.execute("SET search_path = public").is_err());
+
+    // a cancel interrupts the rows of a query being fetched
+    let mut rows = match session.execute("SELECT * FROM genres")? {
+        ResultSet::Query { rows, .. } => rows,
+        r => panic!("query result error: {}", r),
+    };
+    assert!(rows.next().transpose()?.is_some());
+    session.cancel.store(true, Ordering::Relaxed);
+    assert!(matches!(rows.next(), Some(Err(Error::Cancelled(_)))));
+
+    // a cancel is cleared when a statement starts, an interrupted statement
+    // is rolled back
+    session.execute("SELECT * FROM genres")?;
+    session.execute("CREATE TABLE numbers (n INTEGER PRIMARY KEY)")?;
+    let values = (1..=5000)
+        .map(|n| format!("({})", n))
+        .collect::<Vec<_>>()
+        .join(", ");
+    session.execute(&format!("INSERT INTO numbers VALUES {}", values))?;
+    session.execute("SET statement_timeout = 1")?;
+    assert!(cancelled(session.execute("DELETE FROM numbers")));
+    session.execute("SET statement_timeout = 0")?;
+    match session.execute("SELECT n FROM numbers")? {
+        ResultSet::Query { rows, .. } => assert_eq!(rows.count(), 5000),
+        r => panic!("query result error: {}", r),
+    }
+
+    // a window function whose frame spans the partition is interrupted
+    // between its rows
+    let window =
+        "SELECT SUM(n) OVER (ORDER BY n ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) \
+                  FROM numbers";
+    session.execute("SET statement_timeout = 200")?;
+    assert!(cancelled(session.execute(window)));
+    Ok(())
+}